@@ -13,4 +13,73 @@ pub struct Position {
 pub struct Rotation {
     pub yaw: f32,
     pub pitch: f32,
-} 
\ No newline at end of file
+}
+
+/// Game mode of a player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+}
+
+impl GameMode {
+    /// ID used for the game mode on the wire
+    pub fn id(self) -> u8 {
+        match self {
+            GameMode::Survival => 0,
+            GameMode::Creative => 1,
+            GameMode::Adventure => 2,
+            GameMode::Spectator => 3,
+        }
+    }
+
+    /// Looks up a game mode by its wire ID
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(GameMode::Survival),
+            1 => Some(GameMode::Creative),
+            2 => Some(GameMode::Adventure),
+            3 => Some(GameMode::Spectator),
+            _ => None,
+        }
+    }
+
+    /// Looks up a game mode by the name commands use for it, such as `creative`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "survival" => Some(GameMode::Survival),
+            "creative" => Some(GameMode::Creative),
+            "adventure" => Some(GameMode::Adventure),
+            "spectator" => Some(GameMode::Spectator),
+            _ => None,
+        }
+    }
+}
+
+/// Horizontal direction a player or block can face
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// Towards negative Z
+    North,
+    /// Towards positive Z
+    South,
+    /// Towards positive X
+    East,
+    /// Towards negative X
+    West,
+}
+
+impl Direction {
+    /// Direction closest to a yaw in degrees, where 0 faces south and 90 faces west
+    pub fn from_yaw(yaw: f32) -> Self {
+        match ((yaw / 90.0).round() as i32).rem_euclid(4) {
+            0 => Direction::South,
+            1 => Direction::West,
+            2 => Direction::North,
+            _ => Direction::East,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod log;
+pub mod panic;
 pub mod severity;
 pub mod systime;
-pub mod log;
\ No newline at end of file
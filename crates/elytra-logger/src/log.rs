@@ -1,20 +1,157 @@
-﻿use crate::severity::LogSeverity;
+﻿use crate::severity::{LogSeverity, ANSI_RESET};
 use crate::systime::now;
+use once_cell::sync::Lazy;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// Least severe messages still logged, as a `LogSeverity` discriminant
+static MIN_SEVERITY: AtomicU8 = AtomicU8::new(LogSeverity::Debug as u8);
+/// File every logged line is appended to, besides the console
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+/// Whether severity tags printed to the console are colored. Only on by default when stdout is
+/// a terminal, so piped output stays free of escape codes.
+static COLOR_ENABLED: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(io::stdout().is_terminal()));
+
+/// Drops every message less severe than `severity` from then on. Everything is logged until
+/// this is called.
+pub fn set_min_severity(severity: LogSeverity) {
+    MIN_SEVERITY.store(severity as u8, Ordering::Relaxed);
+}
+
+/// Forces colored severity tags on or off, whether or not stdout is a terminal
+pub fn set_color_enabled(enabled: bool) {
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether messages of this severity are currently logged
+pub fn is_logged(severity: LogSeverity) -> bool {
+    severity as u8 >= MIN_SEVERITY.load(Ordering::Relaxed)
+}
 
 /// Logs a message to the console
 pub fn log(message: String, severity: LogSeverity) {
-    println!("[{}] {}: {}", now(), severity, message);
+    if !is_logged(severity) {
+        return;
+    }
+    write_line(&message, severity, None);
+}
+
+/// Logs a message to the console, tagged with the context it happened in
+pub fn log_ctx(message: String, severity: LogSeverity, ctx: &str) {
+    if !is_logged(severity) {
+        return;
+    }
+    write_line(&message, severity, Some(ctx));
+}
+
+/// Appends every line logged from then on to the file at `path` as well as the console,
+/// creating the file and its directory if needed
+pub fn log_to_file(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *LOG_FILE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(file);
+    Ok(())
+}
+
+/// Prints a formatted line, and appends it to the log file if there is one. Each line is
+/// written straight to the file so it survives a crash. The file never gets colors.
+fn write_line(message: &str, severity: LogSeverity, ctx: Option<&str>) {
+    let time = now();
+    let line = compose_line(&time, message, &severity, ctx);
+    if COLOR_ENABLED.load(Ordering::Relaxed) {
+        println!(
+            "{}",
+            compose_line(&time, message, &ColoredSeverity(severity), ctx)
+        );
+    } else {
+        println!("{}", line);
+    }
+
+    let mut log_file = LOG_FILE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(file) = log_file.as_mut() {
+        if let Err(write_error) = writeln!(file, "{}", line) {
+            eprintln!("Failed to write to the log file: {}", write_error);
+        }
+    }
+}
+
+/// Formats a log line, putting the context (if any) in brackets before the message
+pub fn format_line(message: &str, severity: &LogSeverity, ctx: Option<&str>) -> String {
+    compose_line(&now(), message, severity, ctx)
+}
+
+fn compose_line(time: &str, message: &str, severity: &dyn Display, ctx: Option<&str>) -> String {
+    match ctx {
+        Some(ctx) => format!("[{}] {}: [{}] {}", time, severity, ctx, message),
+        None => format!("[{}] {}: {}", time, severity, message),
+    }
+}
+
+/// Severity tag wrapped in its ANSI color
+struct ColoredSeverity(LogSeverity);
+
+impl Display for ColoredSeverity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.0.ansi_color(), self.0, ANSI_RESET)
+    }
+}
+
+/// Logger that tags every line with the connection it belongs to, so lines from concurrent
+/// connections can be told apart.
+#[derive(Debug, Clone)]
+pub struct ContextLogger {
+    connection_id: u64,
+    context: String,
+}
+
+impl ContextLogger {
+    /// Creates a logger for a newly accepted connection, identified by its remote address
+    pub fn for_connection(connection_id: u64, remote_addr: impl Display) -> Self {
+        Self {
+            connection_id,
+            context: format!("conn #{} / {}", connection_id, remote_addr),
+        }
+    }
+
+    /// Returns a logger for the same connection, identified by the player's name instead
+    pub fn with_player(&self, username: &str) -> Self {
+        Self {
+            connection_id: self.connection_id,
+            context: format!("conn #{} / {}", self.connection_id, username),
+        }
+    }
+
+    pub fn connection_id(&self) -> u64 {
+        self.connection_id
+    }
+
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// Logs a message tagged with this logger's context
+    pub fn log(&self, message: String, severity: LogSeverity) {
+        log_ctx(message, severity, &self.context);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::sync::Mutex;
-    use std::sync::Once;
 
     // Use a mutex to capture output in tests
     static TEST_MUTEX: Mutex<()> = Mutex::new(());
-    static INIT: Once = Once::new();
 
     #[test]
     fn test_log_output_format() {
@@ -46,6 +183,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_format_line_with_context() {
+        let logger = ContextLogger::for_connection(12, "127.0.0.1:51234");
+        assert_eq!(logger.context(), "conn #12 / 127.0.0.1:51234");
+
+        let logger = logger.with_player("Steve");
+        assert_eq!(logger.connection_id(), 12);
+        let line = format_line("joined", &LogSeverity::Info, Some(logger.context()));
+        assert!(line.ends_with("INFO: [conn #12 / Steve] joined"));
+        assert!(line.starts_with('['));
+
+        let line = format_line("joined", &LogSeverity::Info, None);
+        assert!(line.ends_with("INFO: joined"));
+    }
+
+    #[test]
+    fn test_min_severity_filters_less_severe_messages() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        set_min_severity(LogSeverity::Warning);
+        assert!(!is_logged(LogSeverity::Debug));
+        assert!(!is_logged(LogSeverity::Info));
+        assert!(is_logged(LogSeverity::Warning));
+        assert!(is_logged(LogSeverity::Fatal));
+
+        set_min_severity(LogSeverity::Debug);
+        assert!(is_logged(LogSeverity::Debug));
+    }
+
+    #[test]
+    fn test_log_to_file_appends_lines() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        let dir = std::env::temp_dir().join(format!("elytra-log-{}", std::process::id()));
+        let path = dir.join("logs").join("latest.log");
+
+        log_to_file(&path).unwrap();
+        log("First line".to_owned(), LogSeverity::Info);
+        ContextLogger::for_connection(3, "127.0.0.1:25566")
+            .log("Second line".to_owned(), LogSeverity::Warning);
+        *LOG_FILE.lock().unwrap() = None;
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("INFO: First line"));
+        assert!(lines[1].ends_with("WARNING: [conn #3 / 127.0.0.1:25566] Second line"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_colored_severity() {
+        assert_eq!(
+            ColoredSeverity(LogSeverity::Warning).to_string(),
+            "\x1b[33mWARNING\x1b[0m"
+        );
+        let line = compose_line(
+            "12:00:00",
+            "joined",
+            &ColoredSeverity(LogSeverity::Fatal),
+            Some("conn #1 / Steve"),
+        );
+        assert_eq!(
+            line,
+            "[12:00:00] \x1b[1;31mFATAL\x1b[0m: [conn #1 / Steve] joined"
+        );
+    }
+
     #[test]
     fn test_log_empty_message() {
         let _lock = TEST_MUTEX.lock().unwrap();
@@ -0,0 +1,46 @@
+use crate::log::log;
+use crate::severity::LogSeverity;
+use std::panic::PanicHookInfo;
+
+/// Panic hook, as accepted by `std::panic::set_hook`
+pub type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static>;
+
+/// Routes panics through the logger at Fatal severity instead of printing them to stderr.
+/// Should be called once at startup.
+pub fn install_panic_hook() {
+    std::panic::set_hook(panic_hook(log));
+}
+
+/// Builds a panic hook that describes the panic, including the thread and location it happened
+/// at, and hands it to `sink` at Fatal severity
+pub fn panic_hook<F>(sink: F) -> PanicHook
+where
+    F: Fn(String, LogSeverity) + Send + Sync + 'static,
+{
+    Box::new(move |info| sink(describe_panic(info), LogSeverity::Fatal))
+}
+
+/// Formats a panic as "Thread 'name' panicked at file:line:column: message"
+fn describe_panic(info: &PanicHookInfo<'_>) -> String {
+    let thread = std::thread::current();
+    let thread_name = thread.name().unwrap_or("<unnamed>");
+
+    let location = info
+        .location()
+        .map(|location| location.to_string())
+        .unwrap_or_else(|| "<unknown location>".to_owned());
+
+    let payload = info.payload();
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+
+    format!(
+        "Thread '{}' panicked at {}: {}",
+        thread_name, location, message
+    )
+}
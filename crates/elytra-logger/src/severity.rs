@@ -1,7 +1,11 @@
 ﻿use std::fmt;
 use std::fmt::{Display, Formatter};
 
-/// Log severity
+/// ANSI escape that clears any color set before it
+pub const ANSI_RESET: &str = "\x1b[0m";
+
+/// Log severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogSeverity {
     Debug,
     Info,
@@ -10,6 +14,31 @@ pub enum LogSeverity {
     Fatal,
 }
 
+impl LogSeverity {
+    /// Looks up a severity by name, such as `info` or `WARNING`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "debug" => Some(LogSeverity::Debug),
+            "info" => Some(LogSeverity::Info),
+            "warning" => Some(LogSeverity::Warning),
+            "error" => Some(LogSeverity::Error),
+            "fatal" => Some(LogSeverity::Fatal),
+            _ => None,
+        }
+    }
+
+    /// ANSI escape that colors the severity tag in a terminal
+    pub fn ansi_color(self) -> &'static str {
+        match self {
+            LogSeverity::Debug => "\x1b[90m",
+            LogSeverity::Info => "\x1b[32m",
+            LogSeverity::Warning => "\x1b[33m",
+            LogSeverity::Error => "\x1b[31m",
+            LogSeverity::Fatal => "\x1b[1;31m",
+        }
+    }
+}
+
 /// Display impl for LogSeverity
 impl Display for LogSeverity {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -35,4 +64,17 @@ mod tests {
         assert_eq!(format!("{}", LogSeverity::Error), "ERROR");
         assert_eq!(format!("{}", LogSeverity::Fatal), "FATAL");
     }
+
+    #[test]
+    fn test_log_severity_order() {
+        assert!(LogSeverity::Debug < LogSeverity::Info);
+        assert!(LogSeverity::Info < LogSeverity::Warning);
+        assert!(LogSeverity::Warning < LogSeverity::Error);
+        assert!(LogSeverity::Error < LogSeverity::Fatal);
+        assert_eq!(
+            LogSeverity::from_name("WARNING"),
+            Some(LogSeverity::Warning)
+        );
+        assert_eq!(LogSeverity::from_name("verbose"), None);
+    }
 }
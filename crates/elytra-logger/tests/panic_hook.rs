@@ -0,0 +1,34 @@
+//! The panic hook is process-wide, so this test runs in its own binary where no other test can
+//! panic while it is replaced
+
+use elytra_logger::panic::panic_hook;
+use elytra_logger::severity::LogSeverity;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_panic_hook_reports_fatal_with_thread_and_location() {
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let sink_captured = captured.clone();
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(panic_hook(move |message, severity| {
+        sink_captured.lock().unwrap().push((message, severity));
+    }));
+
+    let result = std::thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(|| panic!("Unexpected value: {}", 3))
+        .unwrap()
+        .join();
+    std::panic::set_hook(previous_hook);
+    assert!(result.is_err());
+
+    let captured = captured.lock().unwrap();
+    let (message, severity) = captured
+        .iter()
+        .find(|(message, _)| message.contains("worker"))
+        .unwrap();
+    assert!(matches!(severity, LogSeverity::Fatal));
+    assert!(message.starts_with("Thread 'worker' panicked at"));
+    assert!(message.contains("panic_hook.rs"));
+    assert!(message.ends_with("Unexpected value: 3"));
+}
@@ -1,9 +1,22 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use std::collections::HashMap;
-use std::io::{self, Read, Write};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use indexmap::IndexMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Compression level for written NBT data. Level 1 is the fastest and level 9 the smallest; the
+/// default of 6 is a middle ground.
+pub use flate2::Compression;
+
+/// Entries of a compound tag. Entries keep the order they were inserted or read in, so a tag is
+/// always written back with its keys in the same order.
+pub type Compound = IndexMap<String, Tag>;
+
+pub mod modified_utf8;
+mod snbt;
+pub use snbt::SnbtError;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Tag {
@@ -17,7 +30,7 @@ pub enum Tag {
     ByteArray(Vec<i8>),
     String(String),
     List(Vec<Tag>),
-    Compound(HashMap<String, Tag>),
+    Compound(Compound),
     IntArray(Vec<i32>),
     LongArray(Vec<i64>),
 }
@@ -41,60 +54,119 @@ impl Tag {
         }
     }
 
+    /// Reads a named tag, nested at most [`DEFAULT_MAX_DEPTH`] lists and compounds deep
     pub fn read<R: Read>(reader: &mut R) -> io::Result<(String, Tag)> {
-        let type_id = reader.read_u8()?;
+        Self::read_limited(reader, DEFAULT_MAX_DEPTH, u64::MAX)
+    }
+
+    /// Reads a named tag from data that can't be trusted. Tags nested more than `max_depth`
+    /// lists and compounds deep, or taking more than `max_bytes`, are `InvalidData` errors, so
+    /// crafted data can neither overflow the stack nor make the reader consume endless input.
+    pub fn read_limited<R: Read>(
+        reader: &mut R,
+        max_depth: usize,
+        max_bytes: u64,
+    ) -> io::Result<(String, Tag)> {
+        Self::read_named(&mut OffsetReader::new(reader, max_depth, max_bytes), 0)
+    }
+
+    /// Reads a tag in the network form sent since protocol 764 (1.20.2), where the root has a
+    /// type but no name
+    pub fn read_network<R: Read>(reader: &mut R) -> io::Result<Tag> {
+        Self::read_network_limited(reader, DEFAULT_MAX_DEPTH, u64::MAX)
+    }
+
+    /// Reads a nameless root tag from data that can't be trusted, with the same limits as
+    /// [`Tag::read_limited`]
+    pub fn read_network_limited<R: Read>(
+        reader: &mut R,
+        max_depth: usize,
+        max_bytes: u64,
+    ) -> io::Result<Tag> {
+        let mut reader = OffsetReader::new(reader, max_depth, max_bytes);
+        let type_id = reader.context("tag type", |r| r.read_u8())?;
+        Tag::read_payload(&mut reader, type_id, 0)
+    }
+
+    fn read_named<R: Read>(
+        reader: &mut OffsetReader<R>,
+        depth: usize,
+    ) -> io::Result<(String, Tag)> {
+        let type_id = reader.context("tag type", |r| r.read_u8())?;
         if type_id == 0 {
             return Ok(("".to_owned(), Tag::End));
         }
 
-        let name_length = reader.read_u16::<BigEndian>()?;
-        let mut name_bytes = vec![0u8; name_length as usize];
-        reader.read_exact(&mut name_bytes)?;
-        let name = String::from_utf8(name_bytes)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-        let tag = Tag::read_payload(reader, type_id)?;
+        let name = reader.context("tag name", read_string)?;
+        let tag = Tag::read_payload(reader, type_id, depth)?;
         Ok((name, tag))
     }
 
-    fn read_payload<R: Read>(reader: &mut R, type_id: u8) -> io::Result<Tag> {
+    /// Reads the payload of a tag `depth` lists and compounds below the root
+    fn read_payload<R: Read>(
+        reader: &mut OffsetReader<R>,
+        type_id: u8,
+        depth: usize,
+    ) -> io::Result<Tag> {
+        if matches!(type_id, 9 | 10) && depth >= reader.max_depth {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Tag nested deeper than {} levels at byte {}",
+                    reader.max_depth, reader.offset
+                ),
+            ));
+        }
         match type_id {
             0 => Ok(Tag::End),
-            1 => Ok(Tag::Byte(reader.read_i8()?)),
-            2 => Ok(Tag::Short(reader.read_i16::<BigEndian>()?)),
-            3 => Ok(Tag::Int(reader.read_i32::<BigEndian>()?)),
-            4 => Ok(Tag::Long(reader.read_i64::<BigEndian>()?)),
-            5 => Ok(Tag::Float(reader.read_f32::<BigEndian>()?)),
-            6 => Ok(Tag::Double(reader.read_f64::<BigEndian>()?)),
+            1 => reader.context("Byte", |r| r.read_i8()).map(Tag::Byte),
+            2 => reader
+                .context("Short", |r| r.read_i16::<BigEndian>())
+                .map(Tag::Short),
+            3 => reader
+                .context("Int", |r| r.read_i32::<BigEndian>())
+                .map(Tag::Int),
+            4 => reader
+                .context("Long", |r| r.read_i64::<BigEndian>())
+                .map(Tag::Long),
+            5 => reader
+                .context("Float", |r| r.read_f32::<BigEndian>())
+                .map(Tag::Float),
+            6 => reader
+                .context("Double", |r| r.read_f64::<BigEndian>())
+                .map(Tag::Double),
             7 => {
-                let length = reader.read_i32::<BigEndian>()?;
-                let mut bytes = Vec::with_capacity(length as usize);
+                let length = reader.context("ByteArray length", read_length)?;
+                let mut bytes = Vec::with_capacity(length.min(MAX_PREALLOCATED_ELEMENTS));
                 for _ in 0..length {
-                    bytes.push(reader.read_i8()?);
+                    bytes.push(reader.context("ByteArray element", |r| r.read_i8())?);
                 }
                 Ok(Tag::ByteArray(bytes))
             }
-            8 => {
-                let length = reader.read_u16::<BigEndian>()?;
-                let mut bytes = vec![0u8; length as usize];
-                reader.read_exact(&mut bytes)?;
-                String::from_utf8(bytes)
-                    .map(Tag::String)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-            }
+            8 => reader.context("String", read_string).map(Tag::String),
             9 => {
-                let list_type = reader.read_u8()?;
-                let length = reader.read_i32::<BigEndian>()?;
-                let mut list = Vec::with_capacity(length as usize);
+                let list_type = reader.context("List element type", |r| r.read_u8())?;
+                let length = reader.context("List length", read_length)?;
+                // End tags have no payload, so only an empty list may declare them
+                if list_type > 12 || (list_type == 0 && length > 0) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Invalid element type {} for a list of {} elements",
+                            list_type, length
+                        ),
+                    ));
+                }
+                let mut list = Vec::with_capacity(length.min(MAX_PREALLOCATED_ELEMENTS));
                 for _ in 0..length {
-                    list.push(Tag::read_payload(reader, list_type)?);
+                    list.push(Tag::read_payload(reader, list_type, depth + 1)?);
                 }
                 Ok(Tag::List(list))
             }
             10 => {
-                let mut compound = HashMap::new();
+                let mut compound = Compound::new();
                 loop {
-                    let (name, tag) = Tag::read(reader)?;
+                    let (name, tag) = Tag::read_named(reader, depth + 1)?;
                     if let Tag::End = tag {
                         break;
                     }
@@ -103,18 +175,18 @@ impl Tag {
                 Ok(Tag::Compound(compound))
             }
             11 => {
-                let length = reader.read_i32::<BigEndian>()?;
-                let mut ints = Vec::with_capacity(length as usize);
+                let length = reader.context("IntArray length", read_length)?;
+                let mut ints = Vec::with_capacity(length.min(MAX_PREALLOCATED_ELEMENTS));
                 for _ in 0..length {
-                    ints.push(reader.read_i32::<BigEndian>()?);
+                    ints.push(reader.context("IntArray element", |r| r.read_i32::<BigEndian>())?);
                 }
                 Ok(Tag::IntArray(ints))
             }
             12 => {
-                let length = reader.read_i32::<BigEndian>()?;
-                let mut longs = Vec::with_capacity(length as usize);
+                let length = reader.context("LongArray length", read_length)?;
+                let mut longs = Vec::with_capacity(length.min(MAX_PREALLOCATED_ELEMENTS));
                 for _ in 0..length {
-                    longs.push(reader.read_i64::<BigEndian>()?);
+                    longs.push(reader.context("LongArray element", |r| r.read_i64::<BigEndian>())?);
                 }
                 Ok(Tag::LongArray(longs))
             }
@@ -129,13 +201,18 @@ impl Tag {
         writer.write_u8(self.get_type_id())?;
 
         if !matches!(self, Tag::End) {
-            writer.write_u16::<BigEndian>(name.len() as u16)?;
-            writer.write_all(name.as_bytes())?;
+            write_string(writer, name)?;
         }
 
         self.write_payload(writer)
     }
 
+    /// Writes the tag in the network form sent since protocol 764 (1.20.2), with no root name
+    pub fn write_network<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u8(self.get_type_id())?;
+        self.write_payload(writer)
+    }
+
     fn write_payload<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match self {
             Tag::End => Ok(()),
@@ -152,10 +229,7 @@ impl Tag {
                 }
                 Ok(())
             }
-            Tag::String(v) => {
-                writer.write_u16::<BigEndian>(v.len() as u16)?;
-                writer.write_all(v.as_bytes())
-            }
+            Tag::String(v) => write_string(writer, v),
             Tag::List(v) => {
                 if v.is_empty() {
                     writer.write_u8(0)?; // TAG_End for empty lists
@@ -192,7 +266,14 @@ impl Tag {
         }
     }
 
-    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound_mut(&mut self) -> Option<&mut Compound> {
         match self {
             Tag::Compound(map) => Some(map),
             _ => None,
@@ -206,6 +287,35 @@ impl Tag {
         }
     }
 
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Tag>> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Finds the tag at a path of dot-separated compound keys, each optionally followed by list
+    /// indices, such as `minecraft:dimension_type.value[0].name`. Keys containing `.` or `[`
+    /// can't be reached this way.
+    pub fn get_path(&self, path: &str) -> Option<&Tag> {
+        parse_path(path)?
+            .into_iter()
+            .try_fold(self, |tag, step| match step {
+                PathStep::Key(key) => tag.as_compound()?.get(key),
+                PathStep::Index(index) => tag.as_list()?.get(index),
+            })
+    }
+
+    /// Like [`Tag::get_path`], but allows the tag found to be changed in place
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Tag> {
+        parse_path(path)?
+            .into_iter()
+            .try_fold(self, |tag, step| match step {
+                PathStep::Key(key) => tag.as_compound_mut()?.get_mut(key),
+                PathStep::Index(index) => tag.as_list_mut()?.get_mut(index),
+            })
+    }
+
     pub fn as_string(&self) -> Option<&String> {
         match self {
             Tag::String(s) => Some(s),
@@ -282,11 +392,198 @@ impl NBTFile {
     }
 
     pub fn write_gzip<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        let mut encoder = GzEncoder::new(writer, Compression::default());
+        self.write_gzip_with(writer, Compression::default())
+    }
+
+    /// Writes the file gzip compressed at the given level. Higher levels take more CPU time for
+    /// smaller output.
+    pub fn write_gzip_with<W: Write>(&self, writer: &mut W, level: Compression) -> io::Result<()> {
+        let mut encoder = GzEncoder::new(writer, level);
+        self.write(&mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    pub fn read_zlib<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut decoder = ZlibDecoder::new(reader);
+        Self::read(&mut decoder)
+    }
+
+    pub fn write_zlib<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_zlib_with(writer, Compression::default())
+    }
+
+    /// Writes the file zlib compressed at the given level, the way chunks are stored in region
+    /// files
+    pub fn write_zlib_with<W: Write>(&self, writer: &mut W, level: Compression) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(writer, level);
         self.write(&mut encoder)?;
         encoder.finish()?;
         Ok(())
     }
+
+    /// Reads NBT data that is gzip compressed, zlib compressed or not compressed at all, telling
+    /// them apart by the first byte. Empty data is an `UnexpectedEof` error.
+    pub fn read_compressed<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut first = [0u8; 1];
+        reader.read_exact(&mut first).map_err(|read_error| {
+            io::Error::new(read_error.kind(), "NBT data is empty".to_owned())
+        })?;
+
+        let mut reader = io::Cursor::new(first).chain(reader);
+        match first[0] {
+            GZIP_MAGIC => Self::read_gzip(&mut reader),
+            ZLIB_MAGIC => Self::read_zlib(&mut reader),
+            _ => Self::read(&mut reader),
+        }
+    }
+
+    /// Reads an NBT file from disk, gzip compressed, zlib compressed or not compressed
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_compressed(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the file to disk gzip compressed, the way Minecraft stores level and player data.
+    /// The data is written to a temporary file first and moved into place, so a crash while
+    /// saving never leaves a truncated file behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_with(path, Compression::default())
+    }
+
+    /// Writes the file to disk like [`NBTFile::save`], compressed at the given level
+    pub fn save_with<P: AsRef<Path>>(&self, path: P, level: Compression) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        self.write_gzip_with(&mut writer, level)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&temp_path, path)
+    }
+}
+
+/// First byte of gzip data
+const GZIP_MAGIC: u8 = 0x1F;
+/// First byte of zlib data compressed with the default 32 KiB window, as written by flate2 and
+/// Minecraft
+const ZLIB_MAGIC: u8 = 0x78;
+
+/// Most elements allocated up front for an array or list. The length comes from the data, so
+/// anything longer grows as its elements are actually read instead of trusting it.
+const MAX_PREALLOCATED_ELEMENTS: usize = 1024;
+
+/// Lists and compounds a tag read with [`Tag::read`] can be nested in, the limit vanilla applies
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+/// Reader that counts the bytes read through it, so a failed read can say where in the data it
+/// happened, and enforces the limits of [`Tag::read_limited`]
+struct OffsetReader<R> {
+    inner: R,
+    offset: u64,
+    max_depth: usize,
+    max_bytes: u64,
+}
+
+impl<R: Read> OffsetReader<R> {
+    fn new(inner: R, max_depth: usize, max_bytes: u64) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            max_depth,
+            max_bytes,
+        }
+    }
+
+    /// Runs a read, adding what was being read and the byte it started at to any error. The
+    /// error keeps its kind, so a truncated file still reports `UnexpectedEof`.
+    fn context<T>(
+        &mut self,
+        what: &str,
+        read: impl FnOnce(&mut Self) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let offset = self.offset;
+        read(self).map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("failed reading {} at byte {}: {}", what, offset, error),
+            )
+        })
+    }
+}
+
+impl<R: Read> Read for OffsetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.offset += read as u64;
+        if self.offset > self.max_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Tag is larger than {} bytes", self.max_bytes),
+            ));
+        }
+        Ok(read)
+    }
+}
+
+/// Reads a modified UTF-8 string prefixed with its length in bytes
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let length = reader.read_u16::<BigEndian>()?;
+    let mut bytes = vec![0u8; length as usize];
+    reader.read_exact(&mut bytes)?;
+    modified_utf8::decode(&bytes)
+}
+
+/// Writes a string as modified UTF-8 prefixed with its length in bytes, which has to fit in a u16
+fn write_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    let bytes = modified_utf8::encode(value);
+    let length = u16::try_from(bytes.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("String of {} bytes is too long for NBT", bytes.len()),
+        )
+    })?;
+    writer.write_u16::<BigEndian>(length)?;
+    writer.write_all(&bytes)
+}
+
+/// One step of a path given to [`Tag::get_path`]
+enum PathStep<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Splits a path into its steps. Returns `None` if it is malformed, such as with an empty key or
+/// an unclosed index.
+fn parse_path(path: &str) -> Option<Vec<PathStep<'_>>> {
+    let mut steps = Vec::new();
+    for segment in path.split('.') {
+        let (key, mut indices) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+        if !key.is_empty() {
+            steps.push(PathStep::Key(key));
+        } else if indices.is_empty() || !steps.is_empty() {
+            return None;
+        }
+        while !indices.is_empty() {
+            let rest = indices.strip_prefix('[')?;
+            let end = rest.find(']')?;
+            steps.push(PathStep::Index(rest[..end].parse().ok()?));
+            indices = &rest[end + 1..];
+        }
+    }
+    Some(steps)
+}
+
+/// Reads the length of an array or list, rejecting negative ones
+fn read_length<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let length = reader.read_i32::<BigEndian>()?;
+    usize::try_from(length).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Negative length: {}", length),
+        )
+    })
 }
 
 #[cfg(test)]
@@ -306,7 +603,7 @@ mod tests {
         assert_eq!(Tag::ByteArray(vec![]).get_type_id(), 7);
         assert_eq!(Tag::String("".to_string()).get_type_id(), 8);
         assert_eq!(Tag::List(vec![]).get_type_id(), 9);
-        assert_eq!(Tag::Compound(HashMap::new()).get_type_id(), 10);
+        assert_eq!(Tag::Compound(Compound::new()).get_type_id(), 10);
         assert_eq!(Tag::IntArray(vec![]).get_type_id(), 11);
         assert_eq!(Tag::LongArray(vec![]).get_type_id(), 12);
     }
@@ -314,7 +611,7 @@ mod tests {
     #[test]
     fn test_tag_as_methods() {
         // Test as_compound
-        let mut map = HashMap::new();
+        let mut map = Compound::new();
         map.insert("test".to_string(), Tag::Int(42));
         let compound = Tag::Compound(map);
         assert!(compound.as_compound().is_some());
@@ -346,6 +643,35 @@ mod tests {
     }
 
     #[test]
+    fn test_get_path() {
+        let mut codec = Tag::from_snbt(
+            r#"{"minecraft:dimension_type": {value: [{name: "minecraft:overworld", element: {ambient_light: 0.0f}}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            codec.get_path("minecraft:dimension_type.value[0].name"),
+            Some(&Tag::String("minecraft:overworld".to_owned()))
+        );
+        assert!(codec.get_path("minecraft:dimension_type.value").is_some());
+        assert_eq!(codec.get_path("minecraft:dimension_type.value[1]"), None);
+        assert_eq!(codec.get_path("minecraft:dimension_type.missing"), None);
+        assert_eq!(codec.get_path("minecraft:dimension_type..value"), None);
+        assert_eq!(codec.get_path("minecraft:dimension_type.value[0"), None);
+
+        *codec
+            .get_path_mut("minecraft:dimension_type.value[0].element.ambient_light")
+            .unwrap() = Tag::Float(1.0);
+        assert_eq!(
+            codec.get_path("minecraft:dimension_type.value[0].element.ambient_light"),
+            Some(&Tag::Float(1.0))
+        );
+
+        let list = Tag::List(vec![Tag::List(vec![Tag::Int(7)])]);
+        assert_eq!(list.get_path("[0][0]"), Some(&Tag::Int(7)));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn test_tag_read_write() {
         let test_cases = vec![
             (Tag::Byte(42), "byte"),
@@ -378,7 +704,7 @@ mod tests {
 
     #[test]
     fn test_compound_tag_read_write() {
-        let mut compound = HashMap::new();
+        let mut compound = Compound::new();
         compound.insert("byte".to_string(), Tag::Byte(42));
         compound.insert("string".to_string(), Tag::String("test".to_string()));
         compound.insert(
@@ -398,9 +724,52 @@ mod tests {
         assert_eq!(read_tag, tag);
     }
 
+    #[test]
+    fn test_compound_keeps_insertion_order() {
+        let mut compound = Compound::new();
+        compound.insert("z".to_string(), Tag::Byte(1));
+        compound.insert("a".to_string(), Tag::Byte(2));
+        let mut tag = Tag::Compound(compound);
+        tag.as_compound_mut()
+            .unwrap()
+            .insert("m".to_string(), Tag::Byte(3));
+
+        let mut buffer = Vec::new();
+        tag.write(&mut buffer, "").unwrap();
+        assert_eq!(
+            buffer,
+            [
+                10, 0, 0, // root compound
+                1, 0, 1, b'z', 1, // z
+                1, 0, 1, b'a', 2, // a
+                1, 0, 1, b'm', 3, // m
+                0,
+            ]
+        );
+
+        let (_, read_tag) = Tag::read(&mut Cursor::new(buffer)).unwrap();
+        let keys: Vec<_> = read_tag.as_compound().unwrap().keys().collect();
+        assert_eq!(keys, ["z", "a", "m"]);
+    }
+
+    #[test]
+    fn test_network_form_has_no_root_name() {
+        let mut compound = Compound::new();
+        compound.insert("id".to_string(), Tag::Short(5));
+        let tag = Tag::Compound(compound);
+
+        let mut buffer = Vec::new();
+        tag.write_network(&mut buffer).unwrap();
+        assert_eq!(buffer, [10, 2, 0, 2, b'i', b'd', 0, 5, 0]);
+
+        let mut cursor = Cursor::new(buffer);
+        assert_eq!(Tag::read_network(&mut cursor).unwrap(), tag);
+        assert_eq!(cursor.position(), 9);
+    }
+
     #[test]
     fn test_nbt_file() {
-        let mut compound = HashMap::new();
+        let mut compound = Compound::new();
         compound.insert("name".to_string(), Tag::String("Test".to_string()));
         compound.insert("value".to_string(), Tag::Int(42));
 
@@ -427,10 +796,139 @@ mod tests {
         assert_eq!(gzip_read.root, original.root);
     }
 
+    #[test]
+    fn test_nbt_file_open_save() {
+        let dir = std::env::temp_dir().join(format!("elytra-nbt-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut compound = Compound::new();
+        compound.insert("value".to_string(), Tag::Long(7));
+        let original = NBTFile::new("".to_string(), Tag::Compound(compound));
+
+        let gzip_path = dir.join("gzip.dat");
+        original.save(&gzip_path).unwrap();
+        let opened = NBTFile::open(&gzip_path).unwrap();
+        assert_eq!(opened.root, original.root);
+
+        // Uncompressed files open as well
+        let raw_path = dir.join("raw.dat");
+        let mut raw = Vec::new();
+        original.write(&mut raw).unwrap();
+        std::fs::write(&raw_path, raw).unwrap();
+        assert_eq!(NBTFile::open(&raw_path).unwrap().root, original.root);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compression_levels() {
+        let states = (0..4096).map(|i| ((i * 7) % 13) as i64).collect();
+        let mut compound = Compound::new();
+        compound.insert("BlockStates".to_string(), Tag::LongArray(states));
+        let original = NBTFile::new("".to_string(), Tag::Compound(compound));
+
+        let mut fast = Vec::new();
+        let mut small = Vec::new();
+        original
+            .write_gzip_with(&mut fast, Compression::fast())
+            .unwrap();
+        original
+            .write_gzip_with(&mut small, Compression::best())
+            .unwrap();
+        assert!(small.len() <= fast.len());
+        for compressed in [fast, small] {
+            let read = NBTFile::read_gzip(&mut Cursor::new(compressed)).unwrap();
+            assert_eq!(read.root, original.root);
+        }
+
+        let mut fast = Vec::new();
+        let mut small = Vec::new();
+        original
+            .write_zlib_with(&mut fast, Compression::new(1))
+            .unwrap();
+        original
+            .write_zlib_with(&mut small, Compression::new(9))
+            .unwrap();
+        assert!(small.len() <= fast.len());
+        for compressed in [fast, small] {
+            let read = NBTFile::read_zlib(&mut Cursor::new(compressed)).unwrap();
+            assert_eq!(read.root, original.root);
+        }
+    }
+
+    #[test]
+    fn test_read_compressed_detects_compression() {
+        let mut compound = Compound::new();
+        compound.insert("name".to_string(), Tag::String("Steve".to_string()));
+        let original = NBTFile::new("root".to_string(), Tag::Compound(compound));
+
+        let mut gzip = Vec::new();
+        original.write_gzip(&mut gzip).unwrap();
+        let mut zlib = Vec::new();
+        original.write_zlib(&mut zlib).unwrap();
+        let mut raw = Vec::new();
+        original.write(&mut raw).unwrap();
+
+        for data in [&gzip, &zlib, &raw] {
+            let read = NBTFile::read_compressed(&mut data.as_slice()).unwrap();
+            assert_eq!(read.name, original.name);
+            assert_eq!(read.root, original.root);
+        }
+
+        let empty = NBTFile::read_compressed(&mut io::empty()).err().unwrap();
+        assert_eq!(empty.kind(), io::ErrorKind::UnexpectedEof);
+        for data in [&gzip, &zlib, &raw] {
+            let truncated = &data[..data.len() / 2];
+            assert!(NBTFile::read_compressed(&mut &truncated[..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_strings_are_written_as_modified_utf8() {
+        let mut buffer = Vec::new();
+        Tag::String("a\0\u{1F980}".to_string())
+            .write(&mut buffer, "\0")
+            .unwrap();
+        let expected = [
+            &[8, 0, 2, 0xC0, 0x80][..],
+            &[0, 9, b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBE, 0xED, 0xB6, 0x80],
+        ]
+        .concat();
+        assert_eq!(buffer, expected);
+
+        let (name, tag) = Tag::read(&mut Cursor::new(buffer)).unwrap();
+        assert_eq!(name, "\0");
+        assert_eq!(tag, Tag::String("a\0\u{1F980}".to_string()));
+    }
+
+    #[test]
+    fn test_deeply_nested_lists_are_rejected() {
+        // A list holding a list holding a list, 10000 levels deep
+        let mut data = vec![9, 0, 0];
+        for _ in 0..10000 {
+            data.extend_from_slice(&[9, 0, 0, 0, 1]);
+        }
+        let error = Tag::read(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = Tag::read_limited(&mut Cursor::new(&data), 4, u64::MAX).unwrap_err();
+        assert!(error.to_string().contains("deeper than 4 levels"));
+        let error = Tag::read_limited(&mut Cursor::new(&data), 512, 100).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Right at the limit is fine
+        let mut shallow = vec![9, 0, 0];
+        for _ in 0..3 {
+            shallow.extend_from_slice(&[9, 0, 0, 0, 1]);
+        }
+        shallow.extend_from_slice(&[0, 0, 0, 0, 0]);
+        assert!(Tag::read_limited(&mut Cursor::new(&shallow), 4, u64::MAX).is_ok());
+    }
+
     #[test]
     fn test_invalid_tag_type() {
-        let mut buffer = vec![255]; // Invalid tag type
-        let result = Tag::read_payload(&mut Cursor::new(buffer), 255);
+        let buffer = vec![255]; // Invalid tag type
+        let mut reader = OffsetReader::new(Cursor::new(buffer), DEFAULT_MAX_DEPTH, u64::MAX);
+        let result = Tag::read_payload(&mut reader, 255, 0);
         assert!(result.is_err());
     }
 
@@ -446,4 +944,63 @@ mod tests {
         assert_eq!(name, "empty");
         assert_eq!(read_tag, tag);
     }
+
+    #[test]
+    fn test_list_element_type_is_validated() {
+        // Unnamed list declaring element type 99, empty or not
+        for length in [0i32, 1] {
+            let mut data = vec![9, 0, 0, 99];
+            data.extend_from_slice(&length.to_be_bytes());
+            data.push(0);
+            let error = Tag::read(&mut Cursor::new(&data)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // End is only allowed as the type of an empty list
+        let mut data = vec![9, 0, 0, 0];
+        data.extend_from_slice(&0i32.to_be_bytes());
+        let (_, tag) = Tag::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(tag, Tag::List(vec![]));
+
+        let mut data = vec![9, 0, 0, 0];
+        data.extend_from_slice(&2i32.to_be_bytes());
+        let error = Tag::read(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_truncated_data_reports_offset() {
+        let mut compound = Compound::new();
+        compound.insert("states".to_string(), Tag::LongArray(vec![7; 64]));
+        let mut data = Vec::new();
+        Tag::Compound(compound).write(&mut data, "").unwrap();
+
+        // The array's elements start after the root's header (3 bytes), the entry's header (9)
+        // and the array length (4). Cut the data partway through the 21st element.
+        data.truncate(16 + 20 * 8 + 3);
+        let error = Tag::read(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(
+            error
+                .to_string()
+                .starts_with("failed reading LongArray element at byte 176"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_bogus_lengths_are_rejected_without_allocating() {
+        // Int array claiming i32::MAX elements followed by a single one
+        let mut data = vec![11, 0, 0];
+        data.extend_from_slice(&i32::MAX.to_be_bytes());
+        data.extend_from_slice(&1i32.to_be_bytes());
+        let error = Tag::read(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut data = vec![7, 0, 0];
+        data.extend_from_slice(&(-1i32).to_be_bytes());
+        let error = Tag::read(&mut Cursor::new(&data)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,101 @@
+//! Java's modified UTF-8, the string encoding of NBT. It differs from UTF-8 in two ways: U+0000
+//! takes two bytes, so encoded strings never contain a zero byte, and characters outside the
+//! Basic Multilingual Plane are written as their two UTF-16 surrogates, three bytes each.
+
+use std::borrow::Cow;
+use std::io;
+
+/// Encodes a string as modified UTF-8. Strings of ASCII without NUL are returned as they are.
+pub fn encode(value: &str) -> Cow<'_, [u8]> {
+    if value.bytes().all(|b| (1..0x80).contains(&b)) {
+        return Cow::Borrowed(value.as_bytes());
+    }
+    let mut bytes = Vec::with_capacity(value.len() + 8);
+    for unit in value.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    Cow::Owned(bytes)
+}
+
+/// Decodes modified UTF-8. Malformed bytes and surrogates that don't form a pair, which Rust
+/// strings can't hold, are `InvalidData` errors.
+pub fn decode(bytes: &[u8]) -> io::Result<String> {
+    if bytes.iter().all(|&b| (1..0x80).contains(&b)) {
+        return Ok(String::from_utf8(bytes.to_vec()).expect("ASCII is valid UTF-8"));
+    }
+    let malformed = |index: usize| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Malformed modified UTF-8 at byte {}", index),
+        )
+    };
+    let continuation = |index: usize| match bytes.get(index) {
+        Some(&b) if b & 0xC0 == 0x80 => Ok((b & 0x3F) as u16),
+        _ => Err(malformed(index)),
+    };
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let first = bytes[index];
+        match first {
+            0x01..=0x7F => {
+                units.push(first as u16);
+                index += 1;
+            }
+            0xC0..=0xDF => {
+                units.push(((first & 0x1F) as u16) << 6 | continuation(index + 1)?);
+                index += 2;
+            }
+            0xE0..=0xEF => {
+                units.push(
+                    ((first & 0x0F) as u16) << 12
+                        | continuation(index + 1)? << 6
+                        | continuation(index + 2)?,
+                );
+                index += 3;
+            }
+            _ => return Err(malformed(index)),
+        }
+    }
+    String::from_utf16(&units)
+        .map_err(|utf16_error| io::Error::new(io::ErrorKind::InvalidData, utf16_error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_and_decode() {
+        for (value, encoded) in [
+            ("Steve", b"Steve".to_vec()),
+            ("\0", vec![0xC0, 0x80]),
+            ("\u{1F980}", vec![0xED, 0xA0, 0xBE, 0xED, 0xB6, 0x80]),
+            ("é", vec![0xC3, 0xA9]),
+        ] {
+            assert_eq!(encode(value), encoded.as_slice(), "{:?}", value);
+            assert_eq!(decode(&encoded).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_data() {
+        // A raw zero byte, a truncated sequence and a lone surrogate
+        for bytes in [&[0x00][..], &[0xC3], &[0xED, 0xA0, 0xBE]] {
+            let error = decode(bytes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
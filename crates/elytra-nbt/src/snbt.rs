@@ -0,0 +1,493 @@
+use crate::{Compound, Tag};
+use std::error::Error;
+use std::fmt;
+
+/// Deepest nesting of compounds and lists accepted when parsing, as in vanilla
+const MAX_DEPTH: usize = 512;
+
+/// Error from parsing SNBT, with the character offset it was found at
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnbtError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SnbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Error for SnbtError {}
+
+impl Tag {
+    /// Parses stringified NBT, the text form used in commands, such as `{Count:1b,id:"stone"}`
+    pub fn from_snbt(snbt: &str) -> Result<Tag, SnbtError> {
+        let mut parser = Parser {
+            chars: snbt.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let tag = parser.read_value()?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("Trailing data after the value"));
+        }
+        Ok(tag)
+    }
+
+    /// Writes the tag as stringified NBT the way `/data get` prints it, with the type suffix of
+    /// each number
+    pub fn to_snbt(&self) -> String {
+        let mut snbt = String::new();
+        self.write_snbt(&mut snbt);
+        snbt
+    }
+
+    fn write_snbt(&self, out: &mut String) {
+        match self {
+            Tag::End => out.push_str("END"),
+            Tag::Byte(v) => out.push_str(&format!("{}b", v)),
+            Tag::Short(v) => out.push_str(&format!("{}s", v)),
+            Tag::Int(v) => out.push_str(&v.to_string()),
+            Tag::Long(v) => out.push_str(&format!("{}L", v)),
+            Tag::Float(v) => out.push_str(&format!("{:?}f", v)),
+            Tag::Double(v) => out.push_str(&format!("{:?}d", v)),
+            Tag::ByteArray(v) => write_array(out, 'B', v.iter().map(|b| format!("{}b", b))),
+            Tag::String(v) => {
+                if is_bare_string(v) {
+                    out.push_str(v);
+                } else {
+                    push_quoted(out, v);
+                }
+            }
+            Tag::List(v) => {
+                out.push('[');
+                for (index, tag) in v.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    tag.write_snbt(out);
+                }
+                out.push(']');
+            }
+            Tag::Compound(v) => {
+                out.push('{');
+                for (index, (key, tag)) in v.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    if !key.is_empty() && key.chars().all(is_bare_char) {
+                        out.push_str(key);
+                    } else {
+                        push_quoted(out, key);
+                    }
+                    out.push(':');
+                    tag.write_snbt(out);
+                }
+                out.push('}');
+            }
+            Tag::IntArray(v) => write_array(out, 'I', v.iter().map(|i| i.to_string())),
+            Tag::LongArray(v) => write_array(out, 'L', v.iter().map(|l| format!("{}L", l))),
+        }
+    }
+}
+
+fn write_array(out: &mut String, prefix: char, elements: impl Iterator<Item = String>) {
+    out.push('[');
+    out.push(prefix);
+    out.push(';');
+    out.push_str(&elements.collect::<Vec<_>>().join(","));
+    out.push(']');
+}
+
+/// Quotes a string with double quotes, or single quotes if its first quote is a double quote,
+/// escaping backslashes and the chosen quote
+fn push_quoted(out: &mut String, value: &str) {
+    let quote = match value.chars().find(|&c| c == '"' || c == '\'') {
+        Some('"') => '\'',
+        _ => '"',
+    };
+    out.push(quote);
+    for c in value.chars() {
+        if c == '\\' || c == quote {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push(quote);
+}
+
+/// Characters allowed in unquoted strings and keys
+fn is_bare_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// Whether a string can be written without quotes and still be read back as a string rather
+/// than as a number or boolean
+fn is_bare_string(value: &str) -> bool {
+    !value.is_empty()
+        && value.chars().all(is_bare_char)
+        && matches!(parse_bare(value), Tag::String(_))
+}
+
+/// Reads an unquoted token as the number it spells out with its suffix, or as a string if it
+/// isn't one. Numbers out of range for their type are strings too, as in vanilla.
+fn parse_bare(token: &str) -> Tag {
+    match token {
+        "true" => return Tag::Byte(1),
+        "false" => return Tag::Byte(0),
+        _ => {}
+    }
+    // Bare tokens are ASCII, so the suffix is the last byte
+    let (body, suffix) = token.split_at(token.len().saturating_sub(1));
+    let parsed = match suffix.to_ascii_lowercase().as_str() {
+        "b" if is_integer(body) => body.parse().ok().map(Tag::Byte),
+        "s" if is_integer(body) => body.parse().ok().map(Tag::Short),
+        "l" if is_integer(body) => body.parse().ok().map(Tag::Long),
+        "f" if is_decimal(body) => body.parse().ok().map(Tag::Float),
+        "d" if is_decimal(body) => body.parse().ok().map(Tag::Double),
+        _ if is_integer(token) => token.parse().ok().map(Tag::Int),
+        _ if is_decimal(token) => token.parse().ok().map(Tag::Double),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Tag::String(token.to_owned()))
+}
+
+/// Optional sign followed by digits
+fn is_integer(token: &str) -> bool {
+    let digits = token.strip_prefix(['+', '-']).unwrap_or(token);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Optional sign, digits with an optional fraction, and an optional exponent, like `-1.5e3`
+fn is_decimal(token: &str) -> bool {
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    let unsigned = token.strip_prefix(['+', '-']).unwrap_or(token);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (mantissa, ""),
+    };
+    let valid_mantissa =
+        all_digits(whole) && all_digits(fraction) && !(whole.is_empty() && fraction.is_empty());
+    let valid_exponent = exponent.is_none_or(|exponent| {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        !digits.is_empty() && all_digits(digits)
+    });
+    valid_mantissa && valid_exponent
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    /// Compounds and lists currently open
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> SnbtError {
+        SnbtError {
+            position: self.position,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(format!("Expected '{}'", expected)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Consumes `c` if it is the next character other than whitespace
+    fn accept(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_value(&mut self) -> Result<Tag, SnbtError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::read_compound),
+            Some('[') => self.nested(Self::read_list_or_array),
+            Some('"' | '\'') => self.read_quoted().map(Tag::String),
+            _ => self.read_bare().map(|token| parse_bare(&token)),
+        }
+    }
+
+    /// Runs the reader of a compound or list one level deeper, refusing to go past `MAX_DEPTH`
+    fn nested(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<Tag, SnbtError>,
+    ) -> Result<Tag, SnbtError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!("Nested deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let tag = read(self);
+        self.depth -= 1;
+        tag
+    }
+
+    fn read_bare(&mut self) -> Result<String, SnbtError> {
+        let start = self.position;
+        while self.peek().is_some_and(is_bare_char) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error("Expected a value"));
+        }
+        Ok(self.chars[start..self.position].iter().collect())
+    }
+
+    fn read_quoted(&mut self) -> Result<String, SnbtError> {
+        let quote = self.chars[self.position];
+        self.position += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated string")),
+                Some('\\') => {
+                    self.position += 1;
+                    match self.peek() {
+                        Some(escaped @ ('\\' | '"' | '\'')) => value.push(escaped),
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    }
+                }
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(value);
+                }
+                Some(c) => value.push(c),
+            }
+            self.position += 1;
+        }
+    }
+
+    fn read_key(&mut self) -> Result<String, SnbtError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"' | '\'') => self.read_quoted(),
+            _ => self.read_bare(),
+        }
+    }
+
+    fn read_compound(&mut self) -> Result<Tag, SnbtError> {
+        self.expect('{')?;
+        let mut compound = Compound::new();
+        if self.accept('}') {
+            return Ok(Tag::Compound(compound));
+        }
+        loop {
+            let key = self.read_key()?;
+            self.expect(':')?;
+            let value = self.read_value()?;
+            compound.insert(key, value);
+            if self.accept('}') {
+                return Ok(Tag::Compound(compound));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn read_list_or_array(&mut self) -> Result<Tag, SnbtError> {
+        self.expect('[')?;
+        let array_type = match (
+            self.chars.get(self.position),
+            self.chars.get(self.position + 1),
+        ) {
+            (Some(&prefix @ ('B' | 'I' | 'L')), Some(';')) => Some(prefix),
+            _ => None,
+        };
+        let Some(array_type) = array_type else {
+            return self.read_list();
+        };
+        self.position += 2;
+
+        let elements = self.read_elements()?;
+        let mismatch = |position| SnbtError {
+            position,
+            message: format!("Element of the wrong type in a {} array", array_type),
+        };
+        match array_type {
+            'B' => elements
+                .into_iter()
+                .map(|(position, tag)| match tag {
+                    Tag::Byte(b) => Ok(b),
+                    _ => Err(mismatch(position)),
+                })
+                .collect::<Result<_, _>>()
+                .map(Tag::ByteArray),
+            'I' => elements
+                .into_iter()
+                .map(|(position, tag)| match tag {
+                    Tag::Int(i) => Ok(i),
+                    _ => Err(mismatch(position)),
+                })
+                .collect::<Result<_, _>>()
+                .map(Tag::IntArray),
+            _ => elements
+                .into_iter()
+                .map(|(position, tag)| match tag {
+                    Tag::Long(l) => Ok(l),
+                    _ => Err(mismatch(position)),
+                })
+                .collect::<Result<_, _>>()
+                .map(Tag::LongArray),
+        }
+    }
+
+    fn read_list(&mut self) -> Result<Tag, SnbtError> {
+        let elements = self.read_elements()?;
+        if let Some((position, _)) = elements
+            .iter()
+            .find(|(_, tag)| tag.get_type_id() != elements[0].1.get_type_id())
+        {
+            return Err(SnbtError {
+                position: *position,
+                message: "List elements must all have the same type".to_owned(),
+            });
+        }
+        Ok(Tag::List(
+            elements.into_iter().map(|(_, tag)| tag).collect(),
+        ))
+    }
+
+    /// Reads comma separated values up to the closing bracket, with the position of each
+    fn read_elements(&mut self) -> Result<Vec<(usize, Tag)>, SnbtError> {
+        let mut elements = Vec::new();
+        if self.accept(']') {
+            return Ok(elements);
+        }
+        loop {
+            self.skip_whitespace();
+            let position = self.position;
+            elements.push((position, self.read_value()?));
+            if self.accept(']') {
+                return Ok(elements);
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(
+            entries
+                .into_iter()
+                .map(|(key, tag)| (key.to_owned(), tag))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_to_snbt() {
+        let tag = compound(vec![
+            ("Count", Tag::Byte(1)),
+            ("Damage", Tag::Short(2)),
+            ("Time", Tag::Long(64)),
+            ("Scale", Tag::Float(3.0)),
+            ("Speed", Tag::Double(0.25)),
+            ("Ints", Tag::IntArray(vec![1, 2, 3])),
+            ("Bytes", Tag::ByteArray(vec![-1, 0])),
+            ("Longs", Tag::LongArray(vec![])),
+            ("id", Tag::String("minecraft:stone".to_owned())),
+            ("Tags", Tag::List(vec![Tag::String("fast".to_owned())])),
+            ("Number", Tag::String("12".to_owned())),
+            ("with space", Tag::Int(-7)),
+        ]);
+        assert_eq!(
+            tag.to_snbt(),
+            r#"{Count:1b,Damage:2s,Time:64L,Scale:3.0f,Speed:0.25d,Ints:[I;1,2,3],Bytes:[B;-1b,0b],Longs:[L;],id:"minecraft:stone",Tags:[fast],Number:"12","with space":-7}"#
+        );
+        assert_eq!(Tag::from_snbt(&tag.to_snbt()).unwrap(), tag);
+    }
+
+    #[test]
+    fn test_quoted_json_round_trips() {
+        let snbt = r#"{display:{Name:'{"text":"Hi"}'}}"#;
+        let tag = Tag::from_snbt(snbt).unwrap();
+        assert_eq!(
+            tag,
+            compound(vec![(
+                "display",
+                compound(vec![("Name", Tag::String(r#"{"text":"Hi"}"#.to_owned()))])
+            )])
+        );
+        assert_eq!(tag.to_snbt(), snbt);
+
+        let escaped = Tag::from_snbt(r#""say \"hi\" \\ 'there'""#).unwrap();
+        assert_eq!(escaped, Tag::String(r#"say "hi" \ 'there'"#.to_owned()));
+        assert_eq!(Tag::from_snbt(&escaped.to_snbt()).unwrap(), escaped);
+    }
+
+    #[test]
+    fn test_from_snbt_types() {
+        let tag = Tag::from_snbt(
+            " { a : 1b , b:2S, c:3, d:4l, e:1.5F, f:2.5, g:1e3d, h:true, i:[ ], j:[I; 1, -2], k:2147483648 } ",
+        )
+        .unwrap();
+        let expected = compound(vec![
+            ("a", Tag::Byte(1)),
+            ("b", Tag::Short(2)),
+            ("c", Tag::Int(3)),
+            ("d", Tag::Long(4)),
+            ("e", Tag::Float(1.5)),
+            ("f", Tag::Double(2.5)),
+            ("g", Tag::Double(1000.0)),
+            ("h", Tag::Byte(1)),
+            ("i", Tag::List(vec![])),
+            ("j", Tag::IntArray(vec![1, -2])),
+            // Too big for an int, so it stays a string
+            ("k", Tag::String("2147483648".to_owned())),
+        ]);
+        assert_eq!(tag, expected);
+    }
+
+    #[test]
+    fn test_from_snbt_errors() {
+        for (snbt, position) in [
+            ("{a:1", 4),
+            ("[1,2b]", 3),
+            ("[B;1b,2]", 6),
+            ("{a:1} x", 6),
+            ("'open", 5),
+            ("{a:\"\\n\"}", 5),
+            ("", 0),
+        ] {
+            assert_eq!(
+                Tag::from_snbt(snbt).unwrap_err().position,
+                position,
+                "{}",
+                snbt
+            );
+        }
+        let deep = "[".repeat(MAX_DEPTH + 1);
+        assert!(Tag::from_snbt(&deep).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use elytra_protocol::chunk_data::{ChunkSection, SECTION_VOLUME};
+
+/// Fills a section block by block, cycling through `unique_states` different block states
+fn fill_section(unique_states: u32) -> ChunkSection {
+    let mut section = ChunkSection::new();
+    for index in 0..SECTION_VOLUME {
+        let (x, y, z) = (index & 15, index >> 8, (index >> 4) & 15);
+        section.set_block_state(x, y, z, 1 + index as u32 % unique_states);
+    }
+    section
+}
+
+fn bench_fill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fill_section");
+    for unique_states in [1, 16, 256, 4096] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(unique_states),
+            &unique_states,
+            |b, &unique_states| b.iter(|| fill_section(black_box(unique_states))),
+        );
+    }
+    group.finish();
+}
+
+fn bench_overwrite(c: &mut Criterion) {
+    // The palette is already full, so this measures the lookup without any resizing
+    let section = fill_section(256);
+    c.bench_function("overwrite_full_palette", |b| {
+        b.iter_batched_ref(
+            || section.clone(),
+            |section| {
+                for index in 0..SECTION_VOLUME {
+                    let (x, y, z) = (index & 15, index >> 8, (index >> 4) & 15);
+                    section.set_block_state(x, y, z, 1 + (index as u32 * 7 + 3) % 256);
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_get(c: &mut Criterion) {
+    let section = fill_section(256);
+    c.bench_function("get_block_state", |b| {
+        b.iter(|| {
+            let mut sum = 0u64;
+            for y in 0..16 {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        sum += section.get_block_state(x, y, z) as u64;
+                    }
+                }
+            }
+            black_box(sum)
+        })
+    });
+}
+
+criterion_group!(benches, bench_fill, bench_overwrite, bench_get);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+use crate::packet::MinecraftPacketBuffer;
+use std::io;
+
+/// Growable set of bits, sent as a VarInt count of longs followed by the longs. Bit 0 is the
+/// lowest bit of the first long. Protocols after 1.16 send light and section masks this way,
+/// where 1.16 fits them in a single VarInt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitSet {
+    /// Never ends in a zero long, so equal sets compare equal and are written the same way
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a bit, growing the set as needed
+    pub fn set(&mut self, bit: usize) {
+        let word = bit / 64;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (bit % 64);
+    }
+
+    /// Clears a bit. Bits past the end of the set are already clear.
+    pub fn clear(&mut self, bit: usize) {
+        if let Some(word) = self.words.get_mut(bit / 64) {
+            *word &= !(1 << (bit % 64));
+            self.trim();
+        }
+    }
+
+    pub fn get(&self, bit: usize) -> bool {
+        self.words
+            .get(bit / 64)
+            .is_some_and(|word| word & (1 << (bit % 64)) != 0)
+    }
+
+    /// Number of bits set
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Positions of the set bits, lowest first
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| index * 64 + bit)
+        })
+    }
+
+    /// The lowest 32 bits as a 1.16 VarInt mask, or `None` if a higher bit is set
+    pub fn to_mask(&self) -> Option<i32> {
+        match self.words.as_slice() {
+            [] => Some(0),
+            [word] => u32::try_from(*word).ok().map(|mask| mask as i32),
+            _ => None,
+        }
+    }
+
+    /// Writes the number of longs as a VarInt, followed by the longs
+    pub fn write_to(&self, buffer: &mut MinecraftPacketBuffer) {
+        buffer.write_varint(self.words.len() as i32);
+        for &word in &self.words {
+            buffer.write_i64(word as i64);
+        }
+    }
+
+    /// Reads a set written by `write_to`
+    pub fn read_from(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let length = buffer.read_length(8)?;
+        let mut words = Vec::with_capacity(length);
+        for _ in 0..length {
+            words.push(buffer.read_i64()? as u64);
+        }
+        let mut bit_set = Self { words };
+        bit_set.trim();
+        Ok(bit_set)
+    }
+
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+}
+
+impl From<i32> for BitSet {
+    /// Converts a 1.16 VarInt mask
+    fn from(mask: i32) -> Self {
+        let mut bit_set = Self {
+            words: vec![mask as u32 as u64],
+        };
+        bit_set.trim();
+        bit_set
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(bits: I) -> Self {
+        let mut bit_set = Self::new();
+        for bit in bits {
+            bit_set.set(bit);
+        }
+        bit_set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get_and_clear() {
+        let mut bits = BitSet::new();
+        assert!(bits.is_empty());
+        bits.set(3);
+        bits.set(64);
+        bits.set(130);
+        assert!(bits.get(3) && bits.get(64) && bits.get(130));
+        assert!(!bits.get(4) && !bits.get(1000));
+        assert_eq!(bits.count_ones(), 3);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), vec![3, 64, 130]);
+        assert_eq!(bits.to_mask(), None);
+
+        bits.clear(130);
+        bits.clear(64);
+        bits.clear(500);
+        assert_eq!(bits, BitSet::from(0b1000));
+        assert_eq!(bits.to_mask(), Some(0b1000));
+        assert_eq!(BitSet::from(-1).count_ones(), 32);
+        assert_eq!(BitSet::from(-1).to_mask(), Some(-1));
+    }
+
+    #[test]
+    fn test_bit_set_round_trip() {
+        let bits: BitSet = [0, 63, 65].into_iter().collect();
+        let mut buffer = MinecraftPacketBuffer::new();
+        bits.write_to(&mut buffer);
+
+        let mut expected = vec![2];
+        expected.extend_from_slice(&(1u64 | 1 << 63).to_be_bytes());
+        expected.extend_from_slice(&0b10u64.to_be_bytes());
+        assert_eq!(buffer.get_buffer(), &expected[..]);
+        assert_eq!(BitSet::read_from(&mut buffer).unwrap(), bits);
+
+        // Trailing zero longs from another writer don't change the set
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_varint(2);
+        buffer.write_i64(5);
+        buffer.write_i64(0);
+        assert_eq!(BitSet::read_from(&mut buffer).unwrap(), BitSet::from(5));
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_varint(3);
+        buffer.write_i64(5);
+        let error = BitSet::read_from(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
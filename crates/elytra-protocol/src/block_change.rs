@@ -0,0 +1,196 @@
+use crate::block_state::BlockState;
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Block Change (clientbound)
+/// Replaces a single block the client has loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockChangePacket {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// Global palette id of the new block state
+    pub block_state: u32,
+}
+
+impl BlockChangePacket {
+    pub fn new(x: i32, y: i32, z: i32, block_state: u32) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            block_state,
+        }
+    }
+}
+
+impl Packet for BlockChangePacket {
+    fn packet_id() -> i32 {
+        0x0B
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let (x, y, z) = buffer.read_position()?;
+        Ok(Self {
+            x,
+            y,
+            z,
+            block_state: BlockState::from_network_id(buffer.read_varint()?).global_id(),
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_position(self.x, self.y, self.z);
+        buffer.write_varint(self.block_state as i32);
+        Ok(())
+    }
+}
+
+/// Multi Block Change (clientbound)
+/// Replaces any number of blocks within one 16x16x16 chunk section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiBlockChangePacket {
+    /// Section coordinates (block coordinates divided by 16)
+    pub section_x: i32,
+    pub section_y: i32,
+    pub section_z: i32,
+    /// Whether the client should skip recalculating light for the changed blocks
+    pub trust_edges: bool,
+    /// Changed blocks as (x, y, z) within the section and the new global palette id
+    pub blocks: Vec<((u8, u8, u8), u32)>,
+}
+
+impl MultiBlockChangePacket {
+    pub fn new(section_x: i32, section_y: i32, section_z: i32) -> Self {
+        Self {
+            section_x,
+            section_y,
+            section_z,
+            trust_edges: false,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Adds a block, given by its coordinates within the section (0-15)
+    pub fn push(&mut self, x: u8, y: u8, z: u8, block_state: u32) {
+        self.blocks.push(((x & 0xF, y & 0xF, z & 0xF), block_state));
+    }
+}
+
+impl Packet for MultiBlockChangePacket {
+    fn packet_id() -> i32 {
+        0x3B
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let section = buffer.read_i64()?;
+        let trust_edges = buffer.read_bool()?;
+        let count = buffer.read_varint()?;
+        if count < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Negative block count",
+            ));
+        }
+
+        let mut blocks = Vec::with_capacity((count as usize).min(4096));
+        for _ in 0..count {
+            let entry = buffer.read_varlong()?;
+            let position = (
+                (entry >> 8 & 0xF) as u8,
+                (entry & 0xF) as u8,
+                (entry >> 4 & 0xF) as u8,
+            );
+            let block_state = i32::try_from(entry >> 12).unwrap_or(-1);
+            blocks.push((
+                position,
+                BlockState::from_network_id(block_state).global_id(),
+            ));
+        }
+
+        Ok(Self {
+            section_x: (section >> 42) as i32,
+            section_y: (section << 44 >> 44) as i32,
+            section_z: (section << 22 >> 42) as i32,
+            trust_edges,
+            blocks,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i64(
+            ((self.section_x as i64 & 0x3FFFFF) << 42)
+                | ((self.section_z as i64 & 0x3FFFFF) << 20)
+                | (self.section_y as i64 & 0xFFFFF),
+        );
+        buffer.write_bool(self.trust_edges);
+        buffer.write_varint(self.blocks.len() as i32);
+        for ((x, y, z), block_state) in &self.blocks {
+            buffer.write_varlong(
+                ((*block_state as i64) << 12) | ((*x as i64) << 8) | ((*z as i64) << 4) | *y as i64,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_data::AIR;
+
+    #[test]
+    fn test_block_change_round_trip() {
+        let packet = BlockChangePacket::new(-5, 64, 300, 1);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.buffer.len(), 1 + 8 + 1);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            BlockChangePacket::packet_id()
+        );
+        assert_eq!(
+            BlockChangePacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+
+    #[test]
+    fn test_unknown_block_state_reads_as_air() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_position(1, 2, 3);
+        buffer.write_varint(1_000_000);
+        assert_eq!(
+            BlockChangePacket::read_from_buffer(&mut buffer).unwrap(),
+            BlockChangePacket::new(1, 2, 3, AIR)
+        );
+
+        let mut packet = MultiBlockChangePacket::new(0, 0, 0);
+        packet.push(1, 1, 1, u32::MAX);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        buffer.read_varint().unwrap();
+        let decoded = MultiBlockChangePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(decoded.blocks, vec![((1, 1, 1), AIR)]);
+    }
+
+    #[test]
+    fn test_multi_block_change_round_trip() {
+        let mut packet = MultiBlockChangePacket::new(-2, 4, 7);
+        packet.push(0, 0, 0, 1);
+        packet.push(15, 3, 9, 17111);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            MultiBlockChangePacket::packet_id()
+        );
+        let decoded = MultiBlockChangePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(decoded, packet);
+        assert!(buffer.is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::chunk_data::AIR;
+use elytra_logger::log::log;
+use elytra_logger::severity::LogSeverity::Warning;
+use std::ops::RangeInclusive;
+
+/// Highest global palette id known to a 1.16.5 client
+pub const MAX_GLOBAL_ID: u32 = 17111;
+
+/// Global palette id of void air, found below the bottom of the world
+pub const VOID_AIR: u32 = 9669;
+/// Global palette id of cave air, placed by world generation in carved caves
+pub const CAVE_AIR: u32 = 9670;
+
+/// Standing torches and all four wall torch directions
+const TORCHES: RangeInclusive<u32> = 1435..=1439;
+/// Fire at every age and side, followed by soul fire
+const FIRE: RangeInclusive<u32> = 1440..=1952;
+/// Standing signs of every wood type, rotation and waterlogging
+const STANDING_SIGNS: RangeInclusive<u32> = 3381..=3572;
+
+/// Ranges of block states other than air that entities can move through. Fluids count as
+/// blocking motion for heightmaps.
+const NON_BLOCKING_STATES: &[RangeInclusive<u32>] = &[TORCHES, FIRE, STANDING_SIGNS];
+
+/// A block state, identified by its id in the global palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockState(u32);
+
+impl BlockState {
+    pub const AIR: BlockState = BlockState(AIR);
+
+    /// Returns the block state with this global palette id, or `None` if no block uses it
+    pub fn from_global_id_checked(id: u32) -> Option<Self> {
+        (id <= MAX_GLOBAL_ID).then_some(Self(id))
+    }
+
+    /// Converts an id from saved data, which may come from a newer version with more block
+    /// states. Unknown ids are logged and read as air.
+    pub fn from_global_id(id: u32) -> Self {
+        Self::from_global_id_checked(id).unwrap_or_else(|| {
+            log(format!("Unknown block state id {}, using air", id), Warning);
+            Self::AIR
+        })
+    }
+
+    /// Converts an id read from a packet. A peer can send anything, so unknown ids are logged
+    /// and read as air instead of being trusted.
+    pub fn from_network_id(id: i32) -> Self {
+        u32::try_from(id)
+            .ok()
+            .and_then(Self::from_global_id_checked)
+            .unwrap_or_else(|| {
+                log(
+                    format!("Unknown block state id {} received, using air", id),
+                    Warning,
+                );
+                Self::AIR
+            })
+    }
+
+    pub fn global_id(self) -> u32 {
+        self.0
+    }
+
+    /// Whether this is any kind of air: plain air, cave air or void air
+    pub fn is_air(self) -> bool {
+        matches!(self.0, AIR | VOID_AIR | CAVE_AIR)
+    }
+
+    /// Whether the block stops entities moving through it or holds a fluid, which is what the
+    /// MOTION_BLOCKING heightmap tracks
+    pub fn blocks_motion(self) -> bool {
+        !self.is_air()
+            && !NON_BLOCKING_STATES
+                .iter()
+                .any(|states| states.contains(&self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_ids_become_air() {
+        assert_eq!(
+            BlockState::from_global_id_checked(1).map(BlockState::global_id),
+            Some(1)
+        );
+        assert!(BlockState::from_global_id_checked(MAX_GLOBAL_ID).is_some());
+        assert_eq!(BlockState::from_global_id_checked(MAX_GLOBAL_ID + 1), None);
+        assert_eq!(BlockState::from_global_id_checked(u32::MAX), None);
+
+        assert_eq!(BlockState::from_network_id(9).global_id(), 9);
+        assert_eq!(BlockState::from_network_id(40000), BlockState::AIR);
+        assert_eq!(BlockState::from_network_id(-1), BlockState::AIR);
+        assert_eq!(
+            BlockState::from_global_id(MAX_GLOBAL_ID + 1),
+            BlockState::AIR
+        );
+    }
+
+    #[test]
+    fn test_is_air() {
+        assert!(BlockState::AIR.is_air());
+        assert!(BlockState(CAVE_AIR).is_air());
+        assert!(BlockState(VOID_AIR).is_air());
+        assert!(!BlockState(1).is_air());
+        assert!(!BlockState(1435).is_air());
+    }
+
+    #[test]
+    fn test_blocks_motion() {
+        assert!(!BlockState::AIR.blocks_motion());
+        assert!(BlockState(1).blocks_motion());
+        // Water, a torch and an oak sign
+        assert!(BlockState(34).blocks_motion());
+        assert!(!BlockState(1435).blocks_motion());
+        assert!(!BlockState(3382).blocks_motion());
+        assert!(!BlockState(CAVE_AIR).blocks_motion());
+        assert!(!BlockState(VOID_AIR).blocks_motion());
+        // Bubble column, right after cave air
+        assert!(BlockState(CAVE_AIR + 1).blocks_motion());
+    }
+}
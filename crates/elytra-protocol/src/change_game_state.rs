@@ -0,0 +1,127 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use elytra_common::types::GameMode;
+use std::io;
+
+/// Change Game State (clientbound)
+/// Notifies the client of a change to the world or its own state. What `value` means depends on
+/// the reason.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeGameStatePacket {
+    pub reason: u8,
+    pub value: f32,
+}
+
+impl ChangeGameStatePacket {
+    /// Shows "No respawn block available" to the player
+    pub const NO_RESPAWN_BLOCK_AVAILABLE: u8 = 0;
+    /// Stops the rain
+    pub const END_RAINING: u8 = 1;
+    /// Starts the rain
+    pub const BEGIN_RAINING: u8 = 2;
+    /// Changes the game mode, `value` being the game mode ID
+    pub const CHANGE_GAMEMODE: u8 = 3;
+    /// Exits the end; 0 respawns the player, 1 rolls the credits first
+    pub const WIN_GAME: u8 = 4;
+    /// Shows a demo screen or message, depending on `value`
+    pub const DEMO_EVENT: u8 = 5;
+    /// Plays the sound of an arrow hitting another player
+    pub const ARROW_HIT_PLAYER: u8 = 6;
+    /// Sets the rain level, from 0 to 1
+    pub const RAIN_LEVEL_CHANGE: u8 = 7;
+    /// Sets the thunder level, from 0 to 1
+    pub const THUNDER_LEVEL_CHANGE: u8 = 8;
+    /// Plays the pufferfish sting sound
+    pub const PUFFERFISH_STING: u8 = 9;
+    /// Plays the elder guardian effect and sound
+    pub const ELDER_GUARDIAN_APPEARANCE: u8 = 10;
+    /// 0 shows the respawn screen on death, 1 respawns immediately
+    pub const ENABLE_RESPAWN_SCREEN: u8 = 11;
+
+    pub fn new(reason: u8, value: f32) -> Self {
+        Self { reason, value }
+    }
+
+    /// Switches the client to another game mode
+    pub fn change_gamemode(gamemode: GameMode) -> Self {
+        Self::new(Self::CHANGE_GAMEMODE, gamemode.id() as f32)
+    }
+
+    pub fn begin_raining() -> Self {
+        Self::new(Self::BEGIN_RAINING, 0.0)
+    }
+
+    pub fn end_raining() -> Self {
+        Self::new(Self::END_RAINING, 0.0)
+    }
+
+    /// Sets how heavily it rains, from 0 to 1
+    pub fn rain_level(level: f32) -> Self {
+        Self::new(Self::RAIN_LEVEL_CHANGE, level.clamp(0.0, 1.0))
+    }
+
+    /// Sets how dark the thunderstorm makes the sky, from 0 to 1
+    pub fn thunder_level(level: f32) -> Self {
+        Self::new(Self::THUNDER_LEVEL_CHANGE, level.clamp(0.0, 1.0))
+    }
+
+    /// Toggles whether the client shows the respawn screen when the player dies
+    pub fn enable_respawn_screen(enabled: bool) -> Self {
+        Self::new(Self::ENABLE_RESPAWN_SCREEN, if enabled { 0.0 } else { 1.0 })
+    }
+}
+
+impl Packet for ChangeGameStatePacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x1D
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            reason: buffer.read_u8()?,
+            value: buffer.read_f32()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_u8(self.reason);
+        buffer.write_f32(self.value)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_gamemode_bytes() {
+        let packet = ChangeGameStatePacket::change_gamemode(GameMode::Creative);
+        assert_eq!(packet.value, 1.0);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.buffer, vec![0x1D, 0x03, 0x3F, 0x80, 0x00, 0x00]);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            ChangeGameStatePacket::packet_id()
+        );
+        let read = ChangeGameStatePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, packet);
+    }
+
+    #[test]
+    fn test_enable_respawn_screen_value() {
+        let enabled = ChangeGameStatePacket::enable_respawn_screen(true);
+        assert_eq!(enabled.reason, ChangeGameStatePacket::ENABLE_RESPAWN_SCREEN);
+        assert_eq!(enabled.value, 0.0);
+        assert_eq!(
+            ChangeGameStatePacket::enable_respawn_screen(false).value,
+            1.0
+        );
+    }
+}
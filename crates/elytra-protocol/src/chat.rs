@@ -0,0 +1,151 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use crate::text_component::TextComponent;
+use std::io;
+use uuid::Uuid;
+
+/// Longest message the client is allowed to send
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+
+/// Chat Message (serverbound)
+/// A line typed into the chat box. Messages starting with `/` are commands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessageServerboundPacket {
+    pub message: String,
+}
+
+impl ChatMessageServerboundPacket {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    /// The command line without its leading `/`, if the message is a command
+    pub fn command(&self) -> Option<&str> {
+        self.message.strip_prefix('/')
+    }
+}
+
+impl Packet for ChatMessageServerboundPacket {
+    fn packet_id() -> i32 {
+        0x03
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let message = buffer.read_string()?;
+        if message.chars().count() > MAX_CHAT_MESSAGE_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Chat message is longer than {} characters",
+                    MAX_CHAT_MESSAGE_LENGTH
+                ),
+            ));
+        }
+        Ok(Self { message })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.message);
+        Ok(())
+    }
+}
+
+/// Chat Message (clientbound)
+/// Shows a message in the chat box or above the hotbar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessagePacket {
+    /// The message as a chat component
+    pub json: String,
+    /// Where the message is shown, one of the position constants
+    pub position: u8,
+    /// Player who sent the message, or the nil UUID for messages from the server
+    pub sender: Uuid,
+}
+
+impl ChatMessagePacket {
+    /// A message sent by a player
+    pub const CHAT: u8 = 0;
+    /// Feedback from the server, such as command results
+    pub const SYSTEM: u8 = 1;
+    /// Shown above the hotbar
+    pub const GAME_INFO: u8 = 2;
+
+    pub fn new(component: &TextComponent, position: u8, sender: Uuid) -> Self {
+        Self {
+            json: component.to_json(),
+            position,
+            sender,
+        }
+    }
+
+    /// A system message from the server
+    pub fn system(component: &TextComponent) -> Self {
+        Self::new(component, Self::SYSTEM, Uuid::nil())
+    }
+}
+
+impl Packet for ChatMessagePacket {
+    fn packet_id() -> i32 {
+        0x0E
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            json: buffer.read_string()?,
+            position: buffer.read_u8()?,
+            sender: buffer.read_uuid()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.json);
+        buffer.write_u8(self.position);
+        buffer.write_uuid(self.sender);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_message_round_trip() {
+        let packet = ChatMessageServerboundPacket::new("/time set night".to_owned());
+        assert_eq!(packet.command(), Some("time set night"));
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            ChatMessageServerboundPacket::packet_id()
+        );
+        assert_eq!(
+            ChatMessageServerboundPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+
+    #[test]
+    fn test_system_message_round_trip() {
+        let packet = ChatMessagePacket::system(&TextComponent::new("Hello").color("red"));
+        assert_eq!(packet.json, r#"{"text":"Hello","color":"red"}"#);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.read_varint().unwrap(), 0x0E);
+        let read = ChatMessagePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, packet);
+        assert_eq!(read.position, ChatMessagePacket::SYSTEM);
+        assert!(read.sender.is_nil());
+    }
+
+    #[test]
+    fn test_chat_message_too_long() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_string(&"a".repeat(MAX_CHAT_MESSAGE_LENGTH + 1));
+        let result = ChatMessageServerboundPacket::read_from_buffer(&mut buffer);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
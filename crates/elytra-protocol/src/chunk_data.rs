@@ -0,0 +1,1022 @@
+use crate::block_state::BlockState;
+use crate::packet::{varint_size, MinecraftPacketBuffer, Packet, MAX_BYTE_ARRAY_LENGTH};
+use elytra_nbt::{Compound, Tag};
+use rustc_hash::FxHashMap;
+use std::io;
+
+/// Number of blocks in a 16x16x16 chunk section
+pub const SECTION_VOLUME: usize = 4096;
+/// Number of sections in a chunk column (y 0 to 255)
+pub const SECTIONS_PER_CHUNK: usize = 16;
+/// Number of biome entries sent with a full chunk (one per 4x4x4 cell)
+pub const BIOMES_LENGTH: usize = 1024;
+/// Smallest bits per block the client accepts for an indirect palette
+pub const MIN_BITS_PER_BLOCK: u8 = 4;
+/// Largest bits per block that still uses an indirect palette
+pub const MAX_INDIRECT_BITS_PER_BLOCK: u8 = 8;
+/// Bits per block of the global palette in 1.16.5
+pub const GLOBAL_BITS_PER_BLOCK: u8 = 15;
+/// Global palette id of air
+pub const AIR: u32 = 0;
+/// Default limit for a serialized chunk data packet, the largest packet the protocol allows
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = MAX_BYTE_ARRAY_LENGTH;
+
+/// Bits used per entry in a heightmap
+const HEIGHTMAP_BITS: usize = 9;
+
+/// Whether a heightmap counts a block state
+pub type CountedBlocks = fn(u32) -> bool;
+
+/// Heightmaps sent with and saved with chunks, each with the blocks it counts. A column's height
+/// is one above its highest counted block.
+pub const HEIGHTMAPS: [(&str, CountedBlocks); 2] = [
+    ("MOTION_BLOCKING", |state| {
+        BlockState::from_global_id(state).blocks_motion()
+    }),
+    ("WORLD_SURFACE", |state| {
+        !BlockState::from_global_id(state).is_air()
+    }),
+];
+
+/// Block state palette of a chunk section
+#[derive(Debug, Clone, PartialEq)]
+pub enum Palette {
+    /// Every block has this global palette id. Sent with 0 bits per block and no data array.
+    Single(u32),
+    /// The data array holds indices into this list of global palette ids
+    Indirect(Vec<u32>),
+    /// The data array holds global palette ids
+    Direct,
+}
+
+/// A 16x16x16 section of blocks, stored the way it is sent over the network
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSection {
+    /// Number of non-air blocks, used by the client for rendering
+    block_count: i16,
+    bits_per_block: u8,
+    palette: Palette,
+    /// Index of each global palette id in an indirect palette, so lookups don't have to scan the
+    /// palette. Always empty for the direct palette. Fx hashing keeps this cheaper than a scan
+    /// even for the smallest palettes.
+    state_to_index: FxHashMap<u32, u32>,
+    /// Packed palette indices. Since 1.16 an entry never spans two longs.
+    data: Vec<i64>,
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkSection {
+    /// Creates a section filled with air
+    pub fn new() -> Self {
+        Self {
+            block_count: 0,
+            bits_per_block: 0,
+            palette: Palette::Single(AIR),
+            state_to_index: FxHashMap::default(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Creates a section where every block has the given state. An unknown state fills it with
+    /// air.
+    pub fn filled(state: u32) -> Self {
+        let state = BlockState::from_global_id(state).global_id();
+        let mut section = Self::new();
+        if state != AIR {
+            section.palette = Palette::Single(state);
+            section.block_count = SECTION_VOLUME as i16;
+        }
+        section
+    }
+
+    pub fn block_count(&self) -> i16 {
+        self.block_count
+    }
+
+    pub fn bits_per_block(&self) -> u8 {
+        self.bits_per_block
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Returns the global palette id of the block at the given section-relative coordinates
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.state_at(block_index(x, y, z))
+    }
+
+    /// Sets the block at the given section-relative coordinates to a global palette id, growing
+    /// the palette if the state isn't in it yet. An id no 1.16.5 block uses sets air, since it
+    /// would otherwise be cut down to the palette's bits and show up as some other block.
+    pub fn set_block_state(&mut self, x: usize, y: usize, z: usize, state: u32) {
+        let state = BlockState::from_global_id(state).global_id();
+        let index = block_index(x, y, z);
+        let previous = self.state_at(index);
+        if previous == state {
+            return;
+        }
+
+        let value = self.get_or_add_palette_entry(state);
+        self.set_raw(index, value);
+
+        if previous == AIR {
+            self.block_count += 1;
+        } else if state == AIR {
+            self.block_count -= 1;
+        }
+    }
+
+    /// Returns the value stored in the data array for a global palette id, adding it to the
+    /// palette (and resizing the data array if needed) when it isn't present. A single-valued
+    /// section first becomes an indirect one holding its state at index 0.
+    fn get_or_add_palette_entry(&mut self, state: u32) -> u32 {
+        let palette = match &mut self.palette {
+            Palette::Single(single) => {
+                let single = *single;
+                self.palette = Palette::Indirect(vec![single]);
+                self.state_to_index = index_palette(&[single]);
+                self.bits_per_block = MIN_BITS_PER_BLOCK;
+                self.data = vec![0; data_array_length(MIN_BITS_PER_BLOCK)];
+                return self.get_or_add_palette_entry(state);
+            }
+            Palette::Direct => return state,
+            Palette::Indirect(palette) => palette,
+        };
+
+        if let Some(&index) = self.state_to_index.get(&state) {
+            return index;
+        }
+
+        if palette.len() < 1 << self.bits_per_block {
+            let index = palette.len() as u32;
+            palette.push(state);
+            self.state_to_index.insert(state, index);
+            return index;
+        }
+
+        self.resize(self.bits_per_block + 1);
+        self.get_or_add_palette_entry(state)
+    }
+
+    /// Repacks the data array with a new number of bits per block, switching to the global
+    /// palette once an indirect palette would need more than 8 bits
+    fn resize(&mut self, bits_per_block: u8) {
+        let mut values: Vec<u32> = (0..SECTION_VOLUME).map(|i| self.get_raw(i)).collect();
+
+        if bits_per_block > MAX_INDIRECT_BITS_PER_BLOCK {
+            if let Palette::Indirect(palette) = &self.palette {
+                for value in values.iter_mut() {
+                    *value = palette[*value as usize];
+                }
+            }
+            self.palette = Palette::Direct;
+            self.state_to_index.clear();
+            self.bits_per_block = GLOBAL_BITS_PER_BLOCK;
+        } else {
+            self.bits_per_block = bits_per_block;
+        }
+
+        self.data = vec![0; data_array_length(self.bits_per_block)];
+        for (index, value) in values.into_iter().enumerate() {
+            self.set_raw(index, value);
+        }
+    }
+
+    fn state_at(&self, index: usize) -> u32 {
+        match &self.palette {
+            Palette::Single(state) => *state,
+            Palette::Indirect(palette) => palette[self.get_raw(index) as usize],
+            Palette::Direct => self.get_raw(index),
+        }
+    }
+
+    fn get_raw(&self, index: usize) -> u32 {
+        let bits = self.bits_per_block as usize;
+        let values_per_long = 64 / bits;
+        let long_index = index / values_per_long;
+        let bit_offset = (index % values_per_long) * bits;
+        let mask = (1u64 << bits) - 1;
+
+        ((self.data[long_index] as u64 >> bit_offset) & mask) as u32
+    }
+
+    fn set_raw(&mut self, index: usize, value: u32) {
+        let bits = self.bits_per_block as usize;
+        let values_per_long = 64 / bits;
+        let long_index = index / values_per_long;
+        let bit_offset = (index % values_per_long) * bits;
+        let mask = (1u64 << bits) - 1;
+
+        let mut long = self.data[long_index] as u64;
+        long &= !(mask << bit_offset);
+        long |= (value as u64 & mask) << bit_offset;
+        self.data[long_index] = long as i64;
+    }
+
+    /// Returns the exact number of bytes `write` produces for this section
+    pub fn serialized_size(&self) -> usize {
+        let palette_size = match &self.palette {
+            Palette::Single(state) => varint_size(1) + varint_size(*state as i32),
+            Palette::Indirect(palette) => {
+                varint_size(palette.len() as i32)
+                    + palette
+                        .iter()
+                        .map(|&entry| varint_size(entry as i32))
+                        .sum::<usize>()
+            }
+            Palette::Direct => 0,
+        };
+
+        2 + 1 + palette_size + varint_size(self.data.len() as i32) + self.data.len() * 8
+    }
+
+    /// Writes the section in the chunk data format: block count, bits per block, palette and the
+    /// length-prefixed data array
+    pub fn write(&self, buffer: &mut MinecraftPacketBuffer) {
+        buffer.write_i16(self.block_count);
+        buffer.write_u8(self.bits_per_block);
+
+        match &self.palette {
+            Palette::Single(state) => {
+                buffer.write_varint(1);
+                buffer.write_varint(*state as i32);
+            }
+            Palette::Indirect(palette) => {
+                buffer.write_varint(palette.len() as i32);
+                for &entry in palette {
+                    buffer.write_varint(entry as i32);
+                }
+            }
+            Palette::Direct => {}
+        }
+
+        buffer.write_varint(self.data.len() as i32);
+        for &long in &self.data {
+            buffer.write_i64(long);
+        }
+    }
+
+    /// Reads a section written by `write`. A section with 0 bits per block must have exactly one
+    /// palette entry and an empty data array.
+    pub fn read(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let block_count = buffer.read_i16()?;
+        let bits_per_block = buffer.read_u8()?;
+
+        let (bits_per_block, palette) = if bits_per_block == 0 {
+            let length = buffer.read_length(1)?;
+            if length != 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid palette length {} for 0 bits per block", length),
+                ));
+            }
+            let state = BlockState::from_network_id(buffer.read_varint()?).global_id();
+            (0, Palette::Single(state))
+        } else if bits_per_block <= MAX_INDIRECT_BITS_PER_BLOCK {
+            // Every palette entry is a VarInt of at least one byte
+            let length = buffer.read_length(1)?;
+            let mut palette = Vec::with_capacity(length);
+            for _ in 0..length {
+                palette.push(BlockState::from_network_id(buffer.read_varint()?).global_id());
+            }
+            (
+                bits_per_block.max(MIN_BITS_PER_BLOCK),
+                Palette::Indirect(palette),
+            )
+        } else {
+            (GLOBAL_BITS_PER_BLOCK, Palette::Direct)
+        };
+        let state_to_index = match &palette {
+            Palette::Indirect(palette) => index_palette(palette),
+            Palette::Single(_) | Palette::Direct => FxHashMap::default(),
+        };
+
+        let length = buffer.read_length(8)?;
+        let expected_length = match palette {
+            Palette::Single(_) => 0,
+            _ => data_array_length(bits_per_block),
+        };
+        if length != expected_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid data array length {} for {} bits per block",
+                    length, bits_per_block
+                ),
+            ));
+        }
+        let mut data = Vec::with_capacity(length);
+        for _ in 0..length {
+            data.push(buffer.read_i64()?);
+        }
+
+        Ok(Self {
+            block_count,
+            bits_per_block,
+            palette,
+            state_to_index,
+            data,
+        })
+    }
+}
+
+/// Chunk Data (clientbound)
+/// Sends the block sections, heightmaps, biomes and block entities of a chunk column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkDataPacket {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// Whether this is a new chunk (with biomes) rather than an update of some sections
+    pub full_chunk: bool,
+    /// Bit mask of the sections present in `sections`, bit 0 being the lowest section
+    pub primary_bit_mask: i32,
+    /// NBT compound holding the MOTION_BLOCKING and WORLD_SURFACE heightmaps
+    pub heightmaps: Tag,
+    /// Biome ids, only sent with full chunks
+    pub biomes: Option<Vec<i32>>,
+    /// One section per bit set in `primary_bit_mask`, always stored in ascending y order. This is
+    /// the order the protocol expects and what `get_section`'s bit counting relies on.
+    pub sections: Vec<ChunkSection>,
+    /// NBT compounds of the block entities in the chunk
+    pub block_entities: Vec<Tag>,
+    /// Largest number of bytes the packet is allowed to serialize to
+    max_size: usize,
+}
+
+impl ChunkDataPacket {
+    /// Creates a full chunk from its 16 sections, lowest first. Missing sections and sections
+    /// that only contain air are left out of the packet.
+    pub fn new_full_chunk(
+        chunk_x: i32,
+        chunk_z: i32,
+        sections: Vec<Option<ChunkSection>>,
+        biomes: Vec<i32>,
+    ) -> Self {
+        let mut primary_bit_mask = 0;
+        let mut present_sections = Vec::new();
+        for (y, section) in sections.into_iter().enumerate().take(SECTIONS_PER_CHUNK) {
+            if let Some(section) = section.filter(|section| section.block_count() > 0) {
+                primary_bit_mask |= 1 << y;
+                present_sections.push(section);
+            }
+        }
+
+        let heightmaps = heightmaps_for_sections(primary_bit_mask, &present_sections);
+
+        Self {
+            chunk_x,
+            chunk_z,
+            full_chunk: true,
+            primary_bit_mask,
+            heightmaps,
+            biomes: Some(biomes),
+            sections: present_sections,
+            block_entities: Vec::new(),
+            max_size: DEFAULT_MAX_CHUNK_SIZE,
+        }
+    }
+
+    /// Creates a non-full chunk that only updates the given sections, keyed by section index
+    /// (0-15). The sections may be given in any order; they are sorted by y so that they line up
+    /// with the bits of the primary bit mask. If an index is given twice, the last section wins.
+    pub fn new_section_update(
+        chunk_x: i32,
+        chunk_z: i32,
+        sections_to_update: &[(usize, ChunkSection)],
+    ) -> Self {
+        let mut sorted_sections: Vec<&(usize, ChunkSection)> = sections_to_update
+            .iter()
+            .filter(|(y, _)| *y < SECTIONS_PER_CHUNK)
+            .collect();
+        // Stable sort keeps duplicates in the order they were given
+        sorted_sections.sort_by_key(|(y, _)| *y);
+
+        let mut primary_bit_mask = 0;
+        let mut sections: Vec<ChunkSection> = Vec::with_capacity(sorted_sections.len());
+        for (y, section) in sorted_sections {
+            if primary_bit_mask & (1 << y) != 0 {
+                // Replace the earlier section with the same index
+                sections.pop();
+            }
+            primary_bit_mask |= 1 << y;
+            sections.push(section.clone());
+        }
+
+        let heightmaps = heightmaps_for_sections(primary_bit_mask, &sections);
+
+        Self {
+            chunk_x,
+            chunk_z,
+            full_chunk: false,
+            primary_bit_mask,
+            heightmaps,
+            biomes: None,
+            sections,
+            block_entities: Vec::new(),
+            max_size: DEFAULT_MAX_CHUNK_SIZE,
+        }
+    }
+
+    /// Sets the largest size in bytes the packet may serialize to. Writing a packet above this
+    /// limit fails instead of producing it, which guards against pathological palettes.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Whether the section at the given index (0-15) is present
+    pub fn has_section(&self, y: usize) -> bool {
+        y < SECTIONS_PER_CHUNK && self.primary_bit_mask & (1 << y) != 0
+    }
+
+    /// Returns the section at the given index (0-15), if present. The position in `sections` is
+    /// the number of present sections below `y`, which holds because sections are kept sorted.
+    pub fn get_section(&self, y: usize) -> Option<&ChunkSection> {
+        if !self.has_section(y) {
+            return None;
+        }
+
+        let section_index = (0..y).filter(|&i| self.has_section(i)).count();
+        self.sections.get(section_index)
+    }
+}
+
+impl Packet for ChunkDataPacket {
+    fn packet_id() -> i32 {
+        0x20
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let chunk_x = buffer.read_i32()?;
+        let chunk_z = buffer.read_i32()?;
+        let full_chunk = buffer.read_bool()?;
+        let primary_bit_mask = buffer.read_varint()?;
+        let heightmaps = buffer.read_nbt()?;
+
+        let biomes = if full_chunk {
+            let length = buffer.read_length(1)?;
+            let mut biomes = Vec::with_capacity(length);
+            for _ in 0..length {
+                biomes.push(buffer.read_varint()?);
+            }
+            Some(biomes)
+        } else {
+            None
+        };
+
+        let size = buffer.read_varint()? as usize;
+        let sections_start = buffer.get_cursor();
+        let mut sections = Vec::new();
+        for _ in 0..primary_bit_mask.count_ones() {
+            sections.push(ChunkSection::read(buffer)?);
+        }
+        if buffer.get_cursor() - sections_start != size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Chunk section data doesn't match its declared size",
+            ));
+        }
+
+        // Each block entity is at least an empty compound's type byte
+        let block_entity_count = buffer.read_length(1)?;
+        let mut block_entities = Vec::with_capacity(block_entity_count);
+        for _ in 0..block_entity_count {
+            let block_entity = buffer.read_nbt()?;
+            block_entities.push(block_entity);
+        }
+
+        Ok(Self {
+            chunk_x,
+            chunk_z,
+            full_chunk,
+            primary_bit_mask,
+            heightmaps,
+            biomes,
+            sections,
+            block_entities,
+            max_size: DEFAULT_MAX_CHUNK_SIZE,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        if self.sections.len() != self.primary_bit_mask.count_ones() as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Primary bit mask has {} sections set but {} sections were given",
+                    self.primary_bit_mask.count_ones(),
+                    self.sections.len()
+                ),
+            ));
+        }
+
+        // NBT is small, serialize it up front so the exact packet size is known
+        let mut heightmaps = Vec::new();
+        self.heightmaps.write(&mut heightmaps, "")?;
+        let mut block_entities = Vec::new();
+        for block_entity in &self.block_entities {
+            block_entity.write(&mut block_entities, "")?;
+        }
+
+        let biomes_size = self.biomes.as_ref().map_or(0, |biomes| {
+            varint_size(biomes.len() as i32)
+                + biomes
+                    .iter()
+                    .map(|&biome| varint_size(biome))
+                    .sum::<usize>()
+        });
+        let sections_size: usize = self
+            .sections
+            .iter()
+            .map(ChunkSection::serialized_size)
+            .sum();
+        let size = varint_size(Self::packet_id())
+            + 4
+            + 4
+            + 1
+            + varint_size(self.primary_bit_mask)
+            + heightmaps.len()
+            + biomes_size
+            + varint_size(sections_size as i32)
+            + sections_size
+            + varint_size(self.block_entities.len() as i32)
+            + block_entities.len();
+
+        if size > self.max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Chunk ({}, {}) serializes to {} bytes, above the {} byte limit",
+                    self.chunk_x, self.chunk_z, size, self.max_size
+                ),
+            ));
+        }
+        buffer.buffer.reserve_exact(size);
+
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i32(self.chunk_x);
+        buffer.write_i32(self.chunk_z);
+        buffer.write_bool(self.full_chunk);
+        buffer.write_varint(self.primary_bit_mask);
+        buffer.write_bytes_raw(&heightmaps);
+
+        if let Some(biomes) = &self.biomes {
+            buffer.write_varint(biomes.len() as i32);
+            for &biome in biomes {
+                buffer.write_varint(biome);
+            }
+        }
+
+        // Sections are streamed straight into the buffer behind their precomputed size
+        buffer.write_varint(sections_size as i32);
+        for section in &self.sections {
+            section.write(buffer);
+        }
+
+        buffer.write_varint(self.block_entities.len() as i32);
+        buffer.write_bytes_raw(&block_entities);
+
+        Ok(())
+    }
+}
+
+/// Index of a block inside a section's data array
+fn block_index(x: usize, y: usize, z: usize) -> usize {
+    (y << 8) | (z << 4) | x
+}
+
+/// Maps each global palette id to its index in the palette. A palette read from the network may
+/// list a state twice, in which case the first index wins like it would for a linear search.
+fn index_palette(palette: &[u32]) -> FxHashMap<u32, u32> {
+    let mut state_to_index = FxHashMap::default();
+    state_to_index.reserve(palette.len());
+    for (index, &state) in palette.iter().enumerate() {
+        state_to_index.entry(state).or_insert(index as u32);
+    }
+    state_to_index
+}
+
+/// Number of longs needed to store a section at the given bits per block
+fn data_array_length(bits_per_block: u8) -> usize {
+    let values_per_long = 64 / bits_per_block as usize;
+    SECTION_VOLUME.div_ceil(values_per_long)
+}
+
+/// Builds the compound of every heightmap in `HEIGHTMAPS` from the present sections
+fn heightmaps_for_sections(primary_bit_mask: i32, sections: &[ChunkSection]) -> Tag {
+    let mut compound = Compound::new();
+    for (name, counts) in HEIGHTMAPS {
+        compound.insert(
+            name.to_string(),
+            Tag::LongArray(create_heightmap(primary_bit_mask, sections, counts)),
+        );
+    }
+    Tag::Compound(compound)
+}
+
+/// Builds a packed heightmap, each column's height being one above its highest block the
+/// predicate counts
+fn create_heightmap(
+    primary_bit_mask: i32,
+    sections: &[ChunkSection],
+    counts: impl Fn(u32) -> bool,
+) -> Vec<i64> {
+    let mut heights = [0u16; 256];
+    let section_ys = (0..SECTIONS_PER_CHUNK).filter(|&y| primary_bit_mask & (1 << y) != 0);
+
+    for (section_y, section) in section_ys.zip(sections) {
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    if counts(section.get_block_state(x, y, z)) {
+                        heights[z * 16 + x] = (section_y * 16 + y + 1) as u16;
+                    }
+                }
+            }
+        }
+    }
+    pack_heightmap(&heights)
+}
+
+/// Packs 256 heights into longs at 9 bits each. As with sections, 1.16 never lets an entry span
+/// two longs, so each long holds 7 entries and its top bit is unused.
+pub fn pack_heightmap(heights: &[u16; 256]) -> Vec<i64> {
+    let values_per_long = 64 / HEIGHTMAP_BITS;
+    let mut longs = vec![0i64; heights.len().div_ceil(values_per_long)];
+
+    for (index, &height) in heights.iter().enumerate() {
+        let long_index = index / values_per_long;
+        let bit_offset = (index % values_per_long) * HEIGHTMAP_BITS;
+        longs[long_index] |= ((height as u64 & 0x1FF) << bit_offset) as i64;
+    }
+
+    longs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_state::{CAVE_AIR, MAX_GLOBAL_ID};
+    use std::collections::HashSet;
+
+    fn full_chunk(sections: Vec<Option<ChunkSection>>) -> ChunkDataPacket {
+        ChunkDataPacket::new_full_chunk(0, 0, sections, vec![1; BIOMES_LENGTH])
+    }
+
+    #[test]
+    fn test_section_get_set_block_state() {
+        let mut section = ChunkSection::new();
+        assert_eq!(section.get_block_state(1, 2, 3), AIR);
+
+        section.set_block_state(1, 2, 3, 9);
+        section.set_block_state(15, 15, 15, 1);
+        assert_eq!(section.get_block_state(1, 2, 3), 9);
+        assert_eq!(section.get_block_state(15, 15, 15), 1);
+        assert_eq!(section.get_block_state(0, 0, 0), AIR);
+        assert_eq!(section.block_count(), 2);
+
+        section.set_block_state(1, 2, 3, AIR);
+        assert_eq!(section.block_count(), 1);
+    }
+
+    #[test]
+    fn test_unknown_block_states_become_air() {
+        let mut section = ChunkSection::new();
+        section.set_block_state(0, 0, 0, 1);
+        section.set_block_state(1, 0, 0, MAX_GLOBAL_ID + 1);
+        section.set_block_state(0, 0, 0, u32::MAX);
+        assert_eq!(section.get_block_state(0, 0, 0), AIR);
+        assert_eq!(section.get_block_state(1, 0, 0), AIR);
+        assert_eq!(section.block_count(), 0);
+
+        assert_eq!(ChunkSection::filled(MAX_GLOBAL_ID + 1).block_count(), 0);
+    }
+
+    #[test]
+    fn test_single_valued_section() {
+        let mut section = ChunkSection::filled(1);
+        assert_eq!(section.bits_per_block(), 0);
+        assert_eq!(section.palette(), &Palette::Single(1));
+        assert_eq!(section.get_block_state(7, 8, 9), 1);
+
+        // Block count, bits per block, a one-entry palette and an empty data array
+        let mut buffer = MinecraftPacketBuffer::new();
+        section.write(&mut buffer);
+        assert_eq!(buffer.get_buffer(), &[0x10, 0x00, 0, 1, 1, 0]);
+        assert_eq!(buffer.buffer.len(), section.serialized_size());
+        assert_eq!(ChunkSection::read(&mut buffer).unwrap(), section);
+
+        // Writing the same state keeps it single-valued, a second state promotes it
+        section.set_block_state(0, 0, 0, 1);
+        assert_eq!(section.palette(), &Palette::Single(1));
+        section.set_block_state(0, 0, 0, AIR);
+        assert_eq!(section.bits_per_block(), MIN_BITS_PER_BLOCK);
+        assert_eq!(section.palette(), &Palette::Indirect(vec![1, AIR]));
+        assert_eq!(section.get_block_state(0, 0, 0), AIR);
+        assert_eq!(section.get_block_state(1, 0, 0), 1);
+        assert_eq!(section.block_count(), SECTION_VOLUME as i16 - 1);
+        assert_index_matches_palette(&section);
+    }
+
+    #[test]
+    fn test_single_valued_section_must_have_one_entry_and_no_data() {
+        let section_with = |palette: &[i32], data_length: i32| {
+            let mut buffer = MinecraftPacketBuffer::new();
+            buffer.write_i16(0);
+            buffer.write_u8(0);
+            buffer.write_varint(palette.len() as i32);
+            for &entry in palette {
+                buffer.write_varint(entry);
+            }
+            buffer.write_varint(data_length);
+            for _ in 0..data_length {
+                buffer.write_i64(0);
+            }
+            ChunkSection::read(&mut buffer)
+        };
+
+        assert_eq!(section_with(&[0], 0).unwrap(), ChunkSection::new());
+        for (palette, data_length) in [(&[][..], 0), (&[0, 1][..], 0), (&[0][..], 256)] {
+            let error = section_with(palette, data_length).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_section_palette_grows_to_direct() {
+        let mut section = ChunkSection::new();
+        for i in 0..300 {
+            section.set_block_state(i % 16, i / 256, (i / 16) % 16, i as u32 + 1);
+        }
+        assert_eq!(section.bits_per_block(), GLOBAL_BITS_PER_BLOCK);
+        assert_eq!(section.palette(), &Palette::Direct);
+
+        for i in 0..300 {
+            assert_eq!(
+                section.get_block_state(i % 16, i / 256, (i / 16) % 16),
+                i as u32 + 1
+            );
+        }
+    }
+
+    /// Checks the cached index against a linear search of the palette
+    fn assert_index_matches_palette(section: &ChunkSection) {
+        match section.palette() {
+            Palette::Single(_) => assert!(section.state_to_index.is_empty()),
+            Palette::Indirect(palette) => {
+                let unique_states: HashSet<&u32> = palette.iter().collect();
+                assert_eq!(section.state_to_index.len(), unique_states.len());
+                for &state in palette {
+                    let position = palette.iter().position(|&entry| entry == state).unwrap();
+                    assert_eq!(section.state_to_index[&state], position as u32);
+                }
+            }
+            Palette::Direct => assert!(section.state_to_index.is_empty()),
+        }
+    }
+
+    #[test]
+    fn test_cached_palette_index_matches_linear_scan() {
+        let mut section = ChunkSection::new();
+        let mut expected = vec![AIR; SECTION_VOLUME];
+
+        // A simple LCG gives a repeatable mix of new and already seen states
+        let mut seed: u32 = 12345;
+        for round in 0..3000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let index = (seed >> 8) as usize % SECTION_VOLUME;
+            let state = (seed >> 20) % 200;
+            let (x, y, z) = (index & 15, index >> 8, (index >> 4) & 15);
+
+            section.set_block_state(x, y, z, state);
+            expected[index] = state;
+            if round % 100 == 0 {
+                assert_index_matches_palette(&section);
+            }
+        }
+        assert_eq!(section.bits_per_block(), MAX_INDIRECT_BITS_PER_BLOCK);
+        assert_index_matches_palette(&section);
+        for (index, &state) in expected.iter().enumerate() {
+            assert_eq!(
+                section.get_block_state(index & 15, index >> 8, (index >> 4) & 15),
+                state
+            );
+        }
+        let block_count = expected.iter().filter(|&&state| state != AIR).count();
+        assert_eq!(section.block_count() as usize, block_count);
+
+        // A palette read back with a duplicate entry resolves to the first one
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_i16(0);
+        buffer.write_u8(MIN_BITS_PER_BLOCK);
+        buffer.write_varint(3);
+        for state in [AIR, 7, 7] {
+            buffer.write_varint(state as i32);
+        }
+        buffer.write_varint(data_array_length(MIN_BITS_PER_BLOCK) as i32);
+        for _ in 0..data_array_length(MIN_BITS_PER_BLOCK) {
+            buffer.write_i64(0);
+        }
+        let mut read_section = ChunkSection::read(&mut buffer).unwrap();
+        assert_index_matches_palette(&read_section);
+        read_section.set_block_state(0, 0, 0, 7);
+        assert_eq!(read_section.get_raw(0), 1);
+    }
+
+    #[test]
+    fn test_huge_palette_length_is_rejected() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_i16(1);
+        buffer.write_u8(4);
+        buffer.write_varint(i32::MAX);
+        buffer.write_varint(1);
+        let error = ChunkSection::read(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_section_serialized_size_matches_write() {
+        let mut section = ChunkSection::filled(1);
+        section.set_block_state(3, 4, 5, 200);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        section.write(&mut buffer);
+        assert_eq!(buffer.buffer.len(), section.serialized_size());
+
+        let read_section = ChunkSection::read(&mut buffer).unwrap();
+        assert_eq!(read_section, section);
+    }
+
+    #[test]
+    fn test_chunk_data_round_trip() {
+        let mut sections = vec![None; SECTIONS_PER_CHUNK];
+        sections[0] = Some(ChunkSection::filled(33));
+        sections[3] = Some(ChunkSection::filled(10));
+        sections[5] = Some(ChunkSection::new()); // Only air, left out
+        let packet = full_chunk(sections);
+        assert_eq!(packet.primary_bit_mask, 0b1001);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+        assert_eq!(
+            read_buffer.read_varint().unwrap(),
+            ChunkDataPacket::packet_id()
+        );
+        let read_packet = ChunkDataPacket::read_from_buffer(&mut read_buffer).unwrap();
+        assert_eq!(read_packet, packet);
+        assert!(read_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_dense_direct_chunk_fits_and_is_presized() {
+        // Every block in every section gets a distinct state, forcing the global palette
+        let mut section = ChunkSection::new();
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    section.set_block_state(x, y, z, block_index(x, y, z) as u32 + 1);
+                }
+            }
+        }
+        assert_eq!(section.palette(), &Palette::Direct);
+        let packet = full_chunk(vec![Some(section); SECTIONS_PER_CHUNK]);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert!(buffer.buffer.len() <= DEFAULT_MAX_CHUNK_SIZE);
+
+        // The buffer was reserved once at the exact size instead of growing as it was written
+        assert_eq!(buffer.buffer.capacity(), buffer.buffer.len());
+    }
+
+    #[test]
+    fn test_chunk_above_max_size_is_rejected() {
+        let packet =
+            full_chunk(vec![Some(ChunkSection::filled(1)); SECTIONS_PER_CHUNK]).with_max_size(1024);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        let result = packet.write_to_buffer(&mut buffer);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(buffer.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_get_section() {
+        let mut sections = vec![None; SECTIONS_PER_CHUNK];
+        sections[2] = Some(ChunkSection::filled(1));
+        sections[7] = Some(ChunkSection::filled(7));
+        let packet = full_chunk(sections);
+
+        assert!(packet.get_section(0).is_none());
+        assert_eq!(packet.get_section(2).unwrap().get_block_state(0, 0, 0), 1);
+        assert_eq!(packet.get_section(7).unwrap().get_block_state(0, 0, 0), 7);
+    }
+
+    #[test]
+    fn test_get_section_with_out_of_order_update() {
+        let sections_to_update = [
+            (9, ChunkSection::filled(9)),
+            (2, ChunkSection::filled(2)),
+            (14, ChunkSection::filled(14)),
+            (5, ChunkSection::filled(5)),
+        ];
+        let packet = ChunkDataPacket::new_section_update(1, -1, &sections_to_update);
+        assert!(!packet.full_chunk);
+        assert_eq!(
+            packet.primary_bit_mask,
+            (1 << 2) | (1 << 5) | (1 << 9) | (1 << 14)
+        );
+
+        for (y, _) in &sections_to_update {
+            let section = packet.get_section(*y).unwrap();
+            assert_eq!(section.get_block_state(0, 0, 0), *y as u32);
+        }
+        assert!(packet.get_section(3).is_none());
+
+        // The written packet keeps the sections in ascending order as well
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+        read_buffer.read_varint().unwrap();
+        let read_packet = ChunkDataPacket::read_from_buffer(&mut read_buffer).unwrap();
+        assert_eq!(
+            read_packet.get_section(9).unwrap().get_block_state(0, 0, 0),
+            9
+        );
+    }
+
+    #[test]
+    fn test_section_update_duplicate_index_keeps_last() {
+        let sections_to_update = [(4, ChunkSection::filled(1)), (4, ChunkSection::filled(2))];
+        let packet = ChunkDataPacket::new_section_update(0, 0, &sections_to_update);
+        assert_eq!(packet.sections.len(), 1);
+        assert_eq!(packet.get_section(4).unwrap().get_block_state(0, 0, 0), 2);
+    }
+
+    #[test]
+    fn test_both_heightmaps_are_sent() {
+        let mut section = ChunkSection::filled(1);
+        // A torch on top of the stone only counts toward the world surface
+        section.set_block_state(0, 15, 0, AIR);
+        section.set_block_state(0, 14, 0, 1435);
+        // Cave air next to it counts toward neither
+        section.set_block_state(1, 15, 0, CAVE_AIR);
+        let packet = full_chunk(vec![Some(section)]);
+
+        let heightmaps = packet.heightmaps.as_compound().unwrap();
+        let heights = |name: &str| match &heightmaps[name] {
+            Tag::LongArray(longs) => {
+                assert_eq!(longs.len(), 37);
+                (longs[0] & 0x1FF, (longs[0] >> 9) & 0x1FF)
+            }
+            tag => panic!("{} is not a long array: {:?}", name, tag),
+        };
+        assert_eq!(heights("MOTION_BLOCKING"), (14, 15));
+        assert_eq!(heights("WORLD_SURFACE"), (15, 15));
+    }
+
+    #[test]
+    fn test_heightmap_packing() {
+        let mut heights = [0u16; 256];
+        heights[0] = 1;
+        heights[6] = 256;
+        heights[7] = 511;
+
+        let longs = pack_heightmap(&heights);
+        assert_eq!(longs.len(), 37);
+        assert_eq!(longs[0] & 0x1FF, 1);
+        assert_eq!((longs[0] >> 54) & 0x1FF, 256);
+        assert_eq!(longs[0] >> 63, 0); // Padding bit stays clear
+        assert_eq!(longs[1] & 0x1FF, 511);
+    }
+
+    #[test]
+    fn test_heightmap_entries_never_span_longs() {
+        // With every height at its maximum, each long has exactly 7 entries at offsets 0, 9, ...,
+        // 54 and only its padding bit clear
+        let longs = pack_heightmap(&[511; 256]);
+        assert_eq!(longs.len(), 37);
+        for &long in &longs[..36] {
+            assert_eq!(long, i64::MAX);
+        }
+        // The last long holds the remaining 4 entries
+        assert_eq!(longs[36], (1 << 36) - 1);
+
+        let mut heights = [0u16; 256];
+        heights[13] = 300;
+        let longs = pack_heightmap(&heights);
+        assert_eq!(longs[1], 300 << 54);
+        assert!(longs
+            .iter()
+            .enumerate()
+            .all(|(i, &long)| i == 1 || long == 0));
+    }
+}
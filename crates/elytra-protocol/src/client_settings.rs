@@ -1,6 +1,6 @@
 ﻿use crate::packet::{MinecraftPacketBuffer, Packet};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ClientSettingsPacket {
     locale: String,
     view_distance: u8,
@@ -28,6 +28,11 @@ impl ClientSettingsPacket {
             main_hand,
         }
     }
+
+    /// Render distance the client asked for, in chunks
+    pub fn view_distance(&self) -> u8 {
+        self.view_distance
+    }
 }
 
 impl Packet for ClientSettingsPacket {
@@ -47,6 +52,7 @@ impl Packet for ClientSettingsPacket {
     }
 
     fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> std::io::Result<()> {
+        buffer.write_varint(Self::packet_id());
         buffer.write_string(&self.locale);
         buffer.write_u8(self.view_distance);
         buffer.write_varint(self.chat_mode as i32);
@@ -56,3 +62,24 @@ impl Packet for ClientSettingsPacket {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_settings_round_trip() {
+        let settings = ClientSettingsPacket::new("en_us".to_owned(), 6, 0, true, 0x7F, 1);
+        let mut buffer = MinecraftPacketBuffer::new();
+        settings.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            ClientSettingsPacket::packet_id()
+        );
+        let read = ClientSettingsPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, settings);
+        assert_eq!(read.view_distance(), 6);
+        assert!(buffer.is_empty());
+    }
+}
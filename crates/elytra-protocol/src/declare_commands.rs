@@ -162,6 +162,12 @@ impl DeclareCommandsPacket {
     }
 }
 
+impl Default for DeclareCommandsPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Parser {
     fn write(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
         match self {
@@ -222,6 +228,8 @@ impl Parser {
                 }
                 buffer.write_u8(flags);
             }
+            Parser::BlockPos => buffer.write_string("minecraft:block_pos"),
+            Parser::BlockState => buffer.write_string("minecraft:block_state"),
             // Add other parser implementations as needed
             _ => buffer.write_string("minecraft:entity"), // Default case
         }
@@ -295,3 +303,48 @@ impl Packet for DeclareCommandsPacket {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_parsers() {
+        let mut packet = DeclareCommandsPacket::new();
+        let mut pos_node = CommandNode::new_argument("pos", Parser::BlockPos, false);
+        let block_index =
+            packet.add_node(CommandNode::new_argument("block", Parser::BlockState, true));
+        pos_node.add_child(block_index);
+        let pos_index = packet.add_node(pos_node);
+        packet.get_root_mut().add_child(pos_index);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.read_varint().unwrap(), 0x10);
+        assert_eq!(buffer.read_varint().unwrap(), 3);
+
+        // Root
+        assert_eq!(buffer.read_u8().unwrap(), NODE_TYPE_ROOT);
+        assert_eq!(buffer.read_varint().unwrap(), 1);
+        assert_eq!(buffer.read_varint().unwrap(), pos_index);
+
+        // Block state argument, the end of the command
+        assert_eq!(
+            buffer.read_u8().unwrap(),
+            NODE_TYPE_ARGUMENT | FLAG_EXECUTABLE
+        );
+        assert_eq!(buffer.read_varint().unwrap(), 0);
+        assert_eq!(buffer.read_string().unwrap(), "block");
+        assert_eq!(buffer.read_string().unwrap(), "minecraft:block_state");
+
+        // Position argument
+        assert_eq!(buffer.read_u8().unwrap(), NODE_TYPE_ARGUMENT);
+        assert_eq!(buffer.read_varint().unwrap(), 1);
+        assert_eq!(buffer.read_varint().unwrap(), block_index);
+        assert_eq!(buffer.read_string().unwrap(), "pos");
+        assert_eq!(buffer.read_string().unwrap(), "minecraft:block_pos");
+
+        assert_eq!(buffer.read_varint().unwrap(), 0);
+        assert!(buffer.is_empty());
+    }
+}
@@ -4,6 +4,12 @@ pub struct DeclareRecipesPacket {
     recipes_count: u8, // Using u8 here because Elytra is going to send 0 anyway
 }
 
+impl Default for DeclareRecipesPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Packet for DeclareRecipesPacket {
     fn packet_id() -> i32
     where
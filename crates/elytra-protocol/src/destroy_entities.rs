@@ -0,0 +1,39 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Destroy Entities (clientbound)
+/// Removes entities from the client's world, such as a player who has left
+#[derive(Debug, Clone, PartialEq)]
+pub struct DestroyEntitiesPacket {
+    pub entity_ids: Vec<i32>,
+}
+
+impl DestroyEntitiesPacket {
+    pub fn new(entity_ids: Vec<i32>) -> Self {
+        Self { entity_ids }
+    }
+}
+
+impl Packet for DestroyEntitiesPacket {
+    fn packet_id() -> i32 {
+        0x36
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let count = buffer.read_varint()?;
+        let mut entity_ids = Vec::new();
+        for _ in 0..count {
+            entity_ids.push(buffer.read_varint()?);
+        }
+        Ok(Self { entity_ids })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_ids.len() as i32);
+        for entity_id in &self.entity_ids {
+            buffer.write_varint(*entity_id);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use crate::text_component::TextComponent;
+use std::io;
+
+/// Disconnect (clientbound, play)
+/// Kicks the player, showing the reason on the disconnect screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisconnectPacket {
+    /// Chat component JSON
+    pub reason: String,
+}
+
+impl DisconnectPacket {
+    /// Creates a disconnect packet with a plain text reason
+    pub fn new(txt: &str) -> Self {
+        Self::from_component(TextComponent::new(txt))
+    }
+
+    /// Creates a disconnect packet with a formatted reason
+    pub fn from_component(component: TextComponent) -> Self {
+        Self {
+            reason: component.to_json(),
+        }
+    }
+}
+
+impl Packet for DisconnectPacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x19
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            reason: buffer.read_string()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.reason);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_colored_disconnect_json() {
+        let packet =
+            DisconnectPacket::from_component(TextComponent::new("Kicked").color("gold").bold());
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.read_varint().unwrap(), DisconnectPacket::packet_id());
+
+        let read = DisconnectPacket::read_from_buffer(&mut buffer).unwrap();
+        let reason: serde_json::Value = serde_json::from_str(&read.reason).unwrap();
+        assert_eq!(
+            reason,
+            json!({"text": "Kicked", "color": "gold", "bold": true})
+        );
+    }
+}
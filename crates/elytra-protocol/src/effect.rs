@@ -0,0 +1,83 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Particles and sound of a block being broken. The data is the block state id.
+pub const BLOCK_BREAK: i32 = 2001;
+
+/// Effect (clientbound)
+/// Plays a built-in sound or particle effect, such as a door opening or a block breaking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectPacket {
+    pub effect_id: i32,
+    /// Block the effect plays at
+    pub position: (i32, i32, i32),
+    /// Extra data whose meaning depends on the effect, such as a block state id
+    pub data: i32,
+    /// Plays the sound at full volume wherever the player is. Only used by a few global sounds
+    /// such as the wither spawning.
+    pub disable_relative_volume: bool,
+}
+
+impl EffectPacket {
+    pub fn new(effect_id: i32, position: (i32, i32, i32), data: i32) -> Self {
+        Self {
+            effect_id,
+            position,
+            data,
+            disable_relative_volume: false,
+        }
+    }
+
+    /// Particles and sound of the block with the given state breaking
+    pub fn block_break(position: (i32, i32, i32), block_state: u32) -> Self {
+        Self::new(BLOCK_BREAK, position, block_state as i32)
+    }
+}
+
+impl Packet for EffectPacket {
+    fn packet_id() -> i32 {
+        0x21
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            effect_id: buffer.read_i32()?,
+            position: buffer.read_position()?,
+            data: buffer.read_i32()?,
+            disable_relative_volume: buffer.read_bool()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        let (x, y, z) = self.position;
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i32(self.effect_id);
+        buffer.write_position(x, y, z);
+        buffer.write_i32(self.data);
+        buffer.write_bool(self.disable_relative_volume);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_break_effect() {
+        let packet = EffectPacket::block_break((18, 64, -7), 1);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let position = (18u64 << 38) | ((-7i64 as u64 & 0x3FF_FFFF) << 12) | 64;
+        let mut expected = vec![0x21];
+        expected.extend_from_slice(&2001i32.to_be_bytes());
+        expected.extend_from_slice(&position.to_be_bytes());
+        expected.extend_from_slice(&1i32.to_be_bytes());
+        expected.push(0);
+        assert_eq!(buffer.get_buffer(), &expected[..]);
+
+        assert_eq!(buffer.read_varint().unwrap(), EffectPacket::packet_id());
+        assert_eq!(EffectPacket::read_from_buffer(&mut buffer).unwrap(), packet);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::Aes128;
+use rand::RngCore;
+use rsa::pkcs8::EncodePublicKey;
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Encryption Request (clientbound, login)
+/// Starts encryption for an online-mode login. The client answers with an
+/// [`EncryptionResponsePacket`] holding a shared secret encrypted with the public key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptionRequestPacket {
+    /// Empty since 1.7, still hashed into the session server request
+    pub server_id: String,
+    /// The server's RSA public key, DER encoded
+    pub public_key: Vec<u8>,
+    /// Random bytes the client sends back encrypted, proving it holds the same key
+    pub verify_token: Vec<u8>,
+}
+
+impl Packet for EncryptionRequestPacket {
+    fn packet_id() -> i32 {
+        0x01
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            server_id: buffer.read_string()?,
+            public_key: buffer.read_byte_array()?,
+            verify_token: buffer.read_byte_array()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.server_id);
+        buffer.write_byte_array(&self.public_key);
+        buffer.write_byte_array(&self.verify_token);
+        Ok(())
+    }
+}
+
+/// Encryption Response (serverbound, login)
+/// Both fields are encrypted with the server's public key. Once this is sent, everything in
+/// both directions is encrypted with the shared secret, see [`EncryptedStream`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptionResponsePacket {
+    pub shared_secret: Vec<u8>,
+    pub verify_token: Vec<u8>,
+}
+
+impl Packet for EncryptionResponsePacket {
+    fn packet_id() -> i32 {
+        0x01
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            shared_secret: buffer.read_byte_array()?,
+            verify_token: buffer.read_byte_array()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_byte_array(&self.shared_secret);
+        buffer.write_byte_array(&self.verify_token);
+        Ok(())
+    }
+}
+
+/// Size of the RSA key generated for online-mode logins, the same as the vanilla server's
+const SERVER_KEY_BITS: usize = 1024;
+/// Length of the random verify token sent in an Encryption Request
+const VERIFY_TOKEN_LENGTH: usize = 4;
+
+/// RSA key pair the server receives shared secrets with. Clients are sent the public half in
+/// the Encryption Request and encrypt their shared secret and the verify token with it.
+pub struct ServerKey {
+    private_key: RsaPrivateKey,
+    /// Public key as a DER encoded SubjectPublicKeyInfo, the form clients expect
+    public_key_der: Vec<u8>,
+}
+
+impl ServerKey {
+    /// Generates a new key pair. This takes a noticeable moment, so it is done once at startup.
+    pub fn generate() -> io::Result<Self> {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), SERVER_KEY_BITS)
+            .map_err(io::Error::other)?;
+        let public_key_der = private_key
+            .to_public_key()
+            .to_public_key_der()
+            .map_err(io::Error::other)?
+            .into_vec();
+        Ok(Self {
+            private_key,
+            public_key_der,
+        })
+    }
+
+    /// Encryption Request with the public key and a fresh random verify token
+    pub fn encryption_request(&self) -> EncryptionRequestPacket {
+        let mut verify_token = vec![0u8; VERIFY_TOKEN_LENGTH];
+        rand::thread_rng().fill_bytes(&mut verify_token);
+        EncryptionRequestPacket {
+            server_id: String::new(),
+            public_key: self.public_key_der.clone(),
+            verify_token,
+        }
+    }
+
+    /// Decrypts a field of an Encryption Response
+    pub fn decrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.private_key
+            .decrypt(Pkcs1v15Encrypt, data)
+            .map_err(|rsa_error| io::Error::new(io::ErrorKind::InvalidData, rsa_error))
+    }
+}
+
+/// AES-128 in CFB8 mode, the stream cipher a connection switches to after the Encryption
+/// Response. Both directions start with the shared secret as key and IV.
+type Encryptor = cfb8::Encryptor<Aes128>;
+type Decryptor = cfb8::Decryptor<Aes128>;
+
+/// Stream that encrypts everything written to it and decrypts everything read from it once a
+/// shared secret is set. Without one, bytes pass through unchanged, so a connection can be
+/// wrapped before it is known whether the login will be encrypted.
+pub struct EncryptedStream<S> {
+    inner: S,
+    ciphers: Option<(Encryptor, Decryptor)>,
+    /// Bytes already encrypted but not yet taken by the inner stream. Encrypting advances the
+    /// cipher, so they can't be encrypted again on the next write.
+    pending: Vec<u8>,
+}
+
+impl<S> EncryptedStream<S> {
+    pub fn new(inner: S, shared_secret: Option<[u8; 16]>) -> Self {
+        Self {
+            inner,
+            ciphers: shared_secret.map(|secret| {
+                (
+                    Encryptor::new(&secret.into(), &secret.into()),
+                    Decryptor::new(&secret.into(), &secret.into()),
+                )
+            }),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.ciphers.is_some()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: AsyncWrite + Unpin> EncryptedStream<S> {
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EncryptedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let start = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        if let Some((_, decrypt)) = &mut this.ciphers {
+            // CFB8 works on one-byte blocks, so any amount read can be decrypted right away
+            for byte in buf.filled_mut()[start..].chunks_exact_mut(1) {
+                decrypt.decrypt_block_mut(byte.into());
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.ciphers.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        ready!(this.poll_write_pending(cx))?;
+
+        this.pending.extend_from_slice(buf);
+        if let Some((encrypt, _)) = &mut this.ciphers {
+            for byte in this.pending.chunks_exact_mut(1) {
+                encrypt.encrypt_block_mut(byte.into());
+            }
+        }
+        // The bytes are taken either way; whatever the inner stream doesn't accept yet goes out
+        // on the next write or flush
+        if let Poll::Ready(Err(write_error)) = this.poll_write_pending(cx) {
+            return Poll::Ready(Err(write_error));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_encrypted_stream() {
+        let secret = [7u8; 16];
+        let (client, server) = tokio::io::duplex(8);
+        let mut server = EncryptedStream::new(server, Some(secret));
+        let mut client = EncryptedStream::new(client, Some(secret));
+        assert!(server.is_encrypted());
+
+        // More than the pipe holds at once, so writes are only partly taken
+        let message: Vec<u8> = (0..100).collect();
+        let expected = message.clone();
+        let writer = tokio::spawn(async move {
+            server.write_all(&message).await.unwrap();
+            server.flush().await.unwrap();
+        });
+        let mut received = vec![0u8; 100];
+        client.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+        writer.await.unwrap();
+
+        // Only the encrypted bytes cross the pipe
+        let (mut raw_client, raw_server) = tokio::io::duplex(64);
+        let mut encrypted = EncryptedStream::new(raw_server, Some(secret));
+        encrypted.write_all(b"hello").await.unwrap();
+        encrypted.flush().await.unwrap();
+        let mut wire = [0u8; 5];
+        raw_client.read_exact(&mut wire).await.unwrap();
+        // AES-128-CFB8 with the secret as key and IV, as `openssl enc -aes-128-cfb8` gives it
+        assert_eq!(wire, [0xB3, 0x46, 0x00, 0x88, 0xEE]);
+    }
+
+    #[test]
+    fn test_encryption_packets_round_trip() {
+        let request = EncryptionRequestPacket {
+            server_id: String::new(),
+            public_key: vec![0x30, 0x81, 0x9F],
+            verify_token: vec![1, 2, 3, 4],
+        };
+        let mut buffer = MinecraftPacketBuffer::new();
+        request.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.get_buffer(),
+            [0x01, 0, 3, 0x30, 0x81, 0x9F, 4, 1, 2, 3, 4]
+        );
+        assert_eq!(buffer.read_varint().unwrap(), 0x01);
+        assert_eq!(
+            EncryptionRequestPacket::read_from_buffer(&mut buffer).unwrap(),
+            request
+        );
+
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![2, 9, 8, 1, 5]);
+        assert_eq!(
+            EncryptionResponsePacket::read_from_buffer(&mut buffer).unwrap(),
+            EncryptionResponsePacket {
+                shared_secret: vec![9, 8],
+                verify_token: vec![5],
+            }
+        );
+    }
+
+    #[test]
+    fn test_server_key_decrypts_what_the_public_key_encrypts() {
+        use rsa::pkcs8::DecodePublicKey;
+        use rsa::RsaPublicKey;
+
+        let key = ServerKey::generate().unwrap();
+        let request = key.encryption_request();
+        assert_eq!(request.server_id, "");
+        assert_eq!(request.verify_token.len(), VERIFY_TOKEN_LENGTH);
+
+        // What a client does with the request
+        let public_key = RsaPublicKey::from_public_key_der(&request.public_key).unwrap();
+        let encrypted_token = public_key
+            .encrypt(
+                &mut rand::thread_rng(),
+                Pkcs1v15Encrypt,
+                &request.verify_token,
+            )
+            .unwrap();
+        assert_eq!(key.decrypt(&encrypted_token).unwrap(), request.verify_token);
+
+        assert!(key.decrypt(&request.verify_token).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Entity Action (serverbound)
+/// Sent when the player starts or stops sneaking or sprinting, leaves a bed, or controls a
+/// horse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityActionPacket {
+    /// Entity id of the player
+    pub entity_id: i32,
+    pub action_id: i32,
+    /// Strength of a horse jump, from 0 to 100; 0 for all other actions
+    pub jump_boost: i32,
+}
+
+impl EntityActionPacket {
+    pub const START_SNEAKING: i32 = 0;
+    pub const STOP_SNEAKING: i32 = 1;
+    pub const LEAVE_BED: i32 = 2;
+    pub const START_SPRINTING: i32 = 3;
+    pub const STOP_SPRINTING: i32 = 4;
+    pub const START_HORSE_JUMP: i32 = 5;
+    pub const STOP_HORSE_JUMP: i32 = 6;
+    pub const OPEN_HORSE_INVENTORY: i32 = 7;
+    pub const START_ELYTRA_FLYING: i32 = 8;
+
+    pub fn new(entity_id: i32, action_id: i32) -> Self {
+        Self {
+            entity_id,
+            action_id,
+            jump_boost: 0,
+        }
+    }
+}
+
+impl Packet for EntityActionPacket {
+    fn packet_id() -> i32 {
+        0x1C
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_varint()?,
+            action_id: buffer.read_varint()?,
+            jump_boost: buffer.read_varint()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_varint(self.action_id);
+        buffer.write_varint(self.jump_boost);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Entity Effect (clientbound)
+/// Gives an entity a status effect, such as speed or poison.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityEffectPacket {
+    pub entity_id: i32,
+    pub effect_id: i8,
+    /// Level of the effect minus one
+    pub amplifier: i8,
+    /// Length of the effect in ticks
+    pub duration: i32,
+    pub flags: u8,
+}
+
+impl EntityEffectPacket {
+    /// The effect comes from a beacon, which makes its particles fainter
+    pub const AMBIENT: u8 = 0x01;
+    pub const SHOW_PARTICLES: u8 = 0x02;
+    pub const SHOW_ICON: u8 = 0x04;
+
+    /// Effect with particles and an icon, like one from a potion
+    pub fn new(entity_id: i32, effect_id: i8, amplifier: i8, duration: i32) -> Self {
+        Self {
+            entity_id,
+            effect_id,
+            amplifier,
+            duration,
+            flags: Self::SHOW_PARTICLES | Self::SHOW_ICON,
+        }
+    }
+}
+
+impl Packet for EntityEffectPacket {
+    fn packet_id() -> i32 {
+        0x59
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_varint()?,
+            effect_id: buffer.read_i8()?,
+            amplifier: buffer.read_i8()?,
+            duration: buffer.read_varint()?,
+            flags: buffer.read_u8()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_i8(self.effect_id);
+        buffer.write_i8(self.amplifier);
+        buffer.write_varint(self.duration);
+        buffer.write_u8(self.flags);
+        Ok(())
+    }
+}
+
+/// Remove Entity Effect (clientbound)
+/// Ends a status effect before, or when, it runs out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoveEntityEffectPacket {
+    pub entity_id: i32,
+    pub effect_id: i8,
+}
+
+impl Packet for RemoveEntityEffectPacket {
+    fn packet_id() -> i32 {
+        0x3B
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_varint()?,
+            effect_id: buffer.read_i8()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_i8(self.effect_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_effect_round_trip() {
+        // Speed II for 30 seconds
+        let packet = EntityEffectPacket::new(5, 1, 1, 600);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.get_buffer(),
+            &[0x59, 0x05, 0x01, 0x01, 0xD8, 0x04, 0x06][..]
+        );
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            EntityEffectPacket::packet_id()
+        );
+        assert_eq!(
+            EntityEffectPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+}
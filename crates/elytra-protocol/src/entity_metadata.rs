@@ -0,0 +1,185 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Index of the base entity flags byte
+pub const ENTITY_FLAGS_INDEX: u8 = 0;
+/// Index of the entity's pose
+pub const POSE_INDEX: u8 = 6;
+
+/// Entity flags bit set while crouching
+pub const FLAG_CROUCHING: i8 = 0x02;
+/// Entity flags bit set while sprinting
+pub const FLAG_SPRINTING: i8 = 0x08;
+
+/// Marks the end of the metadata entries
+const END_OF_METADATA: u8 = 0xFF;
+
+/// Pose an entity is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pose {
+    #[default]
+    Standing,
+    FallFlying,
+    Sleeping,
+    Swimming,
+    SpinAttack,
+    Sneaking,
+    Dying,
+}
+
+impl Pose {
+    fn id(self) -> i32 {
+        self as i32
+    }
+
+    fn from_id(id: i32) -> io::Result<Self> {
+        Ok(match id {
+            0 => Pose::Standing,
+            1 => Pose::FallFlying,
+            2 => Pose::Sleeping,
+            3 => Pose::Swimming,
+            4 => Pose::SpinAttack,
+            5 => Pose::Sneaking,
+            6 => Pose::Dying,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown pose {}", id),
+                ))
+            }
+        })
+    }
+}
+
+/// Value of a metadata entry. Only the types the server sends are supported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    Byte(i8),
+    VarInt(i32),
+    Float(f32),
+    Boolean(bool),
+    Pose(Pose),
+}
+
+impl MetadataValue {
+    fn type_id(&self) -> i32 {
+        match self {
+            MetadataValue::Byte(_) => 0,
+            MetadataValue::VarInt(_) => 1,
+            MetadataValue::Float(_) => 2,
+            MetadataValue::Boolean(_) => 7,
+            MetadataValue::Pose(_) => 18,
+        }
+    }
+}
+
+/// Entity Metadata (clientbound)
+/// Updates some of the properties, such as flags and pose, of an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityMetadataPacket {
+    pub entity_id: i32,
+    /// Changed entries as (index, value)
+    pub entries: Vec<(u8, MetadataValue)>,
+}
+
+impl EntityMetadataPacket {
+    pub fn new(entity_id: i32) -> Self {
+        Self {
+            entity_id,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, index: u8, value: MetadataValue) -> Self {
+        self.entries.push((index, value));
+        self
+    }
+
+    pub fn get(&self, index: u8) -> Option<&MetadataValue> {
+        self.entries
+            .iter()
+            .find(|(entry_index, _)| *entry_index == index)
+            .map(|(_, value)| value)
+    }
+}
+
+impl Packet for EntityMetadataPacket {
+    fn packet_id() -> i32 {
+        0x44
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let entity_id = buffer.read_varint()?;
+        let mut entries = Vec::new();
+        loop {
+            let index = buffer.read_u8()?;
+            if index == END_OF_METADATA {
+                break;
+            }
+            let value = match buffer.read_varint()? {
+                0 => MetadataValue::Byte(buffer.read_i8()?),
+                1 => MetadataValue::VarInt(buffer.read_varint()?),
+                2 => MetadataValue::Float(buffer.read_f32()?),
+                7 => MetadataValue::Boolean(buffer.read_bool()?),
+                18 => MetadataValue::Pose(Pose::from_id(buffer.read_varint()?)?),
+                type_id => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unsupported metadata type {}", type_id),
+                    ))
+                }
+            };
+            entries.push((index, value));
+        }
+        Ok(Self { entity_id, entries })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        for (index, value) in &self.entries {
+            buffer.write_u8(*index);
+            buffer.write_varint(value.type_id());
+            match value {
+                MetadataValue::Byte(value) => buffer.write_i8(*value),
+                MetadataValue::VarInt(value) => buffer.write_varint(*value),
+                MetadataValue::Float(value) => buffer.write_f32(*value)?,
+                MetadataValue::Boolean(value) => buffer.write_bool(*value),
+                MetadataValue::Pose(pose) => buffer.write_varint(pose.id()),
+            }
+        }
+        buffer.write_u8(END_OF_METADATA);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_metadata_round_trip() {
+        let packet = EntityMetadataPacket::new(7)
+            .with(ENTITY_FLAGS_INDEX, MetadataValue::Byte(FLAG_CROUCHING))
+            .with(POSE_INDEX, MetadataValue::Pose(Pose::Sneaking))
+            .with(9, MetadataValue::Float(20.0));
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            &buffer.get_buffer()[..6],
+            &[0x44, 7, 0, 0, FLAG_CROUCHING as u8, 6]
+        );
+        assert_eq!(buffer.get_buffer().last(), Some(&END_OF_METADATA));
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            EntityMetadataPacket::packet_id()
+        );
+        let decoded = EntityMetadataPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(decoded, packet);
+        assert_eq!(
+            decoded.get(POSE_INDEX),
+            Some(&MetadataValue::Pose(Pose::Sneaking))
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::packet::{Angle, MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Entity Rotation (clientbound)
+/// Turns an entity's body and sets where it is looking up or down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityRotationPacket {
+    pub entity_id: i32,
+    pub yaw: Angle,
+    pub pitch: Angle,
+    pub on_ground: bool,
+}
+
+impl Packet for EntityRotationPacket {
+    fn packet_id() -> i32 {
+        0x29
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_varint()?,
+            yaw: buffer.read_angle()?,
+            pitch: buffer.read_angle()?,
+            on_ground: buffer.read_bool()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_angle(self.yaw);
+        buffer.write_angle(self.pitch);
+        buffer.write_bool(self.on_ground);
+        Ok(())
+    }
+}
+
+/// Entity Head Look (clientbound)
+/// Turns an entity's head, which Entity Rotation leaves alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityHeadLookPacket {
+    pub entity_id: i32,
+    pub head_yaw: Angle,
+}
+
+impl Packet for EntityHeadLookPacket {
+    fn packet_id() -> i32 {
+        0x3A
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_varint()?,
+            head_yaw: buffer.read_angle()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_angle(self.head_yaw);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_angle_conversion() {
+        assert_eq!(Angle::from_degrees(0.0), Angle(0));
+        assert_eq!(Angle::from_degrees(90.0), Angle(64));
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle::from_degrees(360.0), Angle(0));
+        assert_eq!(Angle(128).to_degrees(), 180.0);
+        // Converting back and forth settles on the same step
+        let angle = Angle::from_degrees(123.4);
+        assert_eq!(Angle::from_degrees(angle.to_degrees()), angle);
+    }
+
+    #[test]
+    fn test_write_entity_rotation() {
+        let packet = EntityRotationPacket {
+            entity_id: 3,
+            yaw: Angle::from_degrees(90.0),
+            pitch: Angle::from_degrees(-45.0),
+            on_ground: true,
+        };
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.get_buffer(), &[0x29, 3, 64, 224, 1][..]);
+    }
+}
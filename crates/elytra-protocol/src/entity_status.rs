@@ -0,0 +1,62 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Entity Status (clientbound)
+/// Triggers an entity event on the client, such as a hurt animation. What the status means
+/// depends on the type of entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityStatusPacket {
+    pub entity_id: i32,
+    pub status: i8,
+}
+
+impl EntityStatusPacket {
+    /// Status telling a player its permission level is 0; levels 1 to 4 follow it
+    pub const OP_PERMISSION_LEVEL_0: i8 = 24;
+
+    pub fn new(entity_id: i32, status: i8) -> Self {
+        Self { entity_id, status }
+    }
+
+    /// Tells a player which operator permission level (0-4) it has, which the client uses to
+    /// decide what to show, such as the gamemode switcher
+    pub fn op_permission_level(entity_id: i32, level: u8) -> Self {
+        Self::new(entity_id, Self::OP_PERMISSION_LEVEL_0 + level.min(4) as i8)
+    }
+}
+
+impl Packet for EntityStatusPacket {
+    fn packet_id() -> i32 {
+        0x1A
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_i32()?,
+            status: buffer.read_i8()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i32(self.entity_id);
+        buffer.write_i8(self.status);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_op_permission_level_bytes() {
+        let packet = EntityStatusPacket::op_permission_level(5, 4);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.buffer, vec![0x1A, 0, 0, 0, 5, 28]);
+
+        // Levels above 4 don't exist and are capped
+        assert_eq!(EntityStatusPacket::op_permission_level(5, 9).status, 28);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Velocity units per block per tick
+const UNITS_PER_BLOCK: f64 = 8000.0;
+
+/// Entity Velocity (clientbound)
+/// Sets how fast an entity is moving, such as after being knocked back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityVelocityPacket {
+    pub entity_id: i32,
+    /// Velocity in 1/8000 of a block per tick
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+impl EntityVelocityPacket {
+    /// Creates the packet from a velocity in blocks per tick. Each axis is capped at about 4
+    /// blocks per tick, the most the packet can carry.
+    pub fn new(entity_id: i32, (x, y, z): (f64, f64, f64)) -> Self {
+        let to_units = |velocity: f64| {
+            (velocity * UNITS_PER_BLOCK).clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        };
+        Self {
+            entity_id,
+            velocity_x: to_units(x),
+            velocity_y: to_units(y),
+            velocity_z: to_units(z),
+        }
+    }
+}
+
+impl Packet for EntityVelocityPacket {
+    fn packet_id() -> i32 {
+        0x46
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            entity_id: buffer.read_varint()?,
+            velocity_x: buffer.read_i16()?,
+            velocity_y: buffer.read_i16()?,
+            velocity_z: buffer.read_i16()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_i16(self.velocity_x);
+        buffer.write_i16(self.velocity_y);
+        buffer.write_i16(self.velocity_z);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_velocity_units() {
+        let packet = EntityVelocityPacket::new(3, (0.5, -0.25, 10.0));
+        assert_eq!(
+            (packet.velocity_x, packet.velocity_y, packet.velocity_z),
+            (4000, -2000, i16::MAX)
+        );
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            EntityVelocityPacket::packet_id()
+        );
+        assert_eq!(
+            EntityVelocityPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Experience points needed to go from `level` to the next one
+pub fn experience_to_next_level(level: i32) -> i32 {
+    match level {
+        ..=15 => 2 * level + 7,
+        16..=30 => 5 * level - 38,
+        _ => level.saturating_mul(9) - 158,
+    }
+}
+
+/// Set Experience (clientbound)
+/// Updates the experience bar and the level shown above it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetExperiencePacket {
+    /// How full the bar is, from 0 to 1
+    pub experience_bar: f32,
+    pub level: i32,
+    pub total_experience: i32,
+}
+
+impl SetExperiencePacket {
+    pub fn new(experience_bar: f32, level: i32, total_experience: i32) -> Self {
+        Self {
+            experience_bar: experience_bar.clamp(0.0, 1.0),
+            level,
+            total_experience,
+        }
+    }
+}
+
+impl Packet for SetExperiencePacket {
+    fn packet_id() -> i32 {
+        0x48
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            experience_bar: buffer.read_f32()?,
+            level: buffer.read_varint()?,
+            total_experience: buffer.read_varint()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_f32(self.experience_bar)?;
+        buffer.write_varint(self.level);
+        buffer.write_varint(self.total_experience);
+        Ok(())
+    }
+}
+
+/// Collect Item (clientbound)
+/// Plays the animation of an item or experience orb flying into whoever picked it up. The
+/// collected entity still has to be destroyed separately.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectItemPacket {
+    pub collected_entity_id: i32,
+    pub collector_entity_id: i32,
+    /// Items picked up from the stack; 1 for experience orbs
+    pub pickup_item_count: i32,
+}
+
+impl Packet for CollectItemPacket {
+    fn packet_id() -> i32 {
+        0x55
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            collected_entity_id: buffer.read_varint()?,
+            collector_entity_id: buffer.read_varint()?,
+            pickup_item_count: buffer.read_varint()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.collected_entity_id);
+        buffer.write_varint(self.collector_entity_id);
+        buffer.write_varint(self.pickup_item_count);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_experience_to_next_level() {
+        assert_eq!(experience_to_next_level(0), 7);
+        assert_eq!(experience_to_next_level(15), 37);
+        assert_eq!(experience_to_next_level(16), 42);
+        assert_eq!(experience_to_next_level(30), 112);
+        assert_eq!(experience_to_next_level(31), 121);
+    }
+
+    #[test]
+    fn test_experience_bar_is_clamped() {
+        assert_eq!(SetExperiencePacket::new(1.5, 3, 40).experience_bar, 1.0);
+        assert_eq!(SetExperiencePacket::new(-0.5, 3, 40).experience_bar, 0.0);
+    }
+}
@@ -17,18 +17,8 @@ impl Packet for HandshakePacket {
         0x00
     }
 
-    /// Reads the packet from the buffer
+    /// Reads the packet from the buffer. The packet ID must already have been consumed.
     fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
-        let _packet_length = buffer.read_varint()?;
-        let packet_id = buffer.read_varint()?;
-
-        if packet_id != 0x00 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid packet ID",
-            ));
-        }
-
         let packet = HandshakePacket {
             protocol_version: buffer.read_varint()?,
             server_address: buffer.read_string()?,
@@ -41,6 +31,7 @@ impl Packet for HandshakePacket {
 
     /// Writes the packet to the buffer
     fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
         buffer.write_varint(self.protocol_version);
         buffer.write_string(&self.server_address);
         buffer.write_u16(self.server_port);
@@ -48,3 +39,29 @@ impl Packet for HandshakePacket {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake_round_trip() {
+        let handshake = HandshakePacket {
+            protocol_version: 754,
+            server_address: "localhost".to_owned(),
+            server_port: 25565,
+            next_state: 2,
+        };
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        handshake.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.read_varint().unwrap(), HandshakePacket::packet_id());
+        let read = HandshakePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read.protocol_version, 754);
+        assert_eq!(read.server_address, "localhost");
+        assert_eq!(read.server_port, 25565);
+        assert_eq!(read.next_state, 2);
+        assert!(buffer.is_empty());
+    }
+}
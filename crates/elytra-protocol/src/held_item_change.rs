@@ -12,6 +12,12 @@ impl Packet for HeldItemChangePacket {
         0x3F
     }
 
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> std::io::Result<Self> {
+        Ok(HeldItemChangePacket {
+            slot: buffer.read_u8()?,
+        })
+    }
+
     fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> std::io::Result<()> {
         buffer.write_varint(Self::packet_id());
         buffer.write_u8(self.slot);
@@ -24,4 +30,37 @@ impl HeldItemChangePacket {
     pub fn new(slot: u8) -> HeldItemChangePacket {
         HeldItemChangePacket { slot }
     }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+}
+
+/// Held Item Change (serverbound). Sent by the client when the player selects another hotbar slot.
+#[derive(Debug)]
+pub struct HeldItemChangeServerboundPacket {
+    /// The slot which the player has selected (0-8)
+    pub slot: i16,
+}
+
+impl Packet for HeldItemChangeServerboundPacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x25
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> std::io::Result<Self> {
+        Ok(HeldItemChangeServerboundPacket {
+            slot: buffer.read_i16()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> std::io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i16(self.slot);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,136 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Interact Entity (serverbound)
+/// Sent when the player right-clicks or attacks an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InteractEntityPacket {
+    /// Entity id of the target
+    pub entity_id: i32,
+    pub interaction_type: i32,
+    /// Where on the target's hitbox the player clicked, relative to the target. Only sent with
+    /// `INTERACT_AT`.
+    pub target: Option<(f32, f32, f32)>,
+    /// 0 for the main hand, 1 for the off hand. Not sent with `ATTACK`.
+    pub hand: Option<i32>,
+    /// Whether the player was sneaking
+    pub sneaking: bool,
+}
+
+impl InteractEntityPacket {
+    pub const INTERACT: i32 = 0;
+    pub const ATTACK: i32 = 1;
+    pub const INTERACT_AT: i32 = 2;
+
+    pub fn attack(entity_id: i32, sneaking: bool) -> Self {
+        Self {
+            entity_id,
+            interaction_type: Self::ATTACK,
+            target: None,
+            hand: None,
+            sneaking,
+        }
+    }
+
+    pub fn is_attack(&self) -> bool {
+        self.interaction_type == Self::ATTACK
+    }
+}
+
+impl Packet for InteractEntityPacket {
+    fn packet_id() -> i32 {
+        0x0E
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let entity_id = buffer.read_varint()?;
+        let interaction_type = buffer.read_varint()?;
+        let (target, hand) = match interaction_type {
+            Self::INTERACT => (None, Some(buffer.read_varint()?)),
+            Self::ATTACK => (None, None),
+            Self::INTERACT_AT => {
+                let target = (buffer.read_f32()?, buffer.read_f32()?, buffer.read_f32()?);
+                (Some(target), Some(buffer.read_varint()?))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown interaction type {}", interaction_type),
+                ))
+            }
+        };
+        Ok(Self {
+            entity_id,
+            interaction_type,
+            target,
+            hand,
+            sneaking: buffer.read_bool()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.entity_id);
+        buffer.write_varint(self.interaction_type);
+        if let Some((x, y, z)) = self.target {
+            buffer.write_f32(x)?;
+            buffer.write_f32(y)?;
+            buffer.write_f32(z)?;
+        }
+        if let Some(hand) = self.hand {
+            buffer.write_varint(hand);
+        }
+        buffer.write_bool(self.sneaking);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_interact() {
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![42, 0, 1, 0]);
+        let packet = InteractEntityPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(packet.entity_id, 42);
+        assert_eq!(packet.interaction_type, InteractEntityPacket::INTERACT);
+        assert_eq!(packet.target, None);
+        assert_eq!(packet.hand, Some(1));
+        assert!(!packet.sneaking);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_attack() {
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![0x80, 0x01, 1, 1]);
+        let packet = InteractEntityPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(packet, InteractEntityPacket::attack(128, true));
+        assert!(packet.is_attack());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_interact_at() {
+        let mut bytes = vec![7, 2];
+        bytes.extend_from_slice(&0.5f32.to_be_bytes());
+        bytes.extend_from_slice(&1.25f32.to_be_bytes());
+        bytes.extend_from_slice(&(-0.5f32).to_be_bytes());
+        bytes.extend_from_slice(&[0, 1]);
+        let mut buffer = MinecraftPacketBuffer::from_bytes(bytes);
+
+        let packet = InteractEntityPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(packet.entity_id, 7);
+        assert_eq!(packet.interaction_type, InteractEntityPacket::INTERACT_AT);
+        assert_eq!(packet.target, Some((0.5, 1.25, -0.5)));
+        assert_eq!(packet.hand, Some(0));
+        assert!(packet.sneaking);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_unknown_interaction_type() {
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![7, 3, 0]);
+        assert!(InteractEntityPacket::read_from_buffer(&mut buffer).is_err());
+    }
+}
@@ -1,12 +1,13 @@
-﻿use elytra_nbt::Tag;
+﻿use elytra_nbt::{Compound, Tag};
+use elytra_common::types::GameMode;
 use super::packet::*;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
 use tokio::io::Result;
 
 pub struct JoinGamePacket {
     pub entity_id: i32,
     pub is_hardcore: bool,
-    pub gamemode: u8,
+    pub gamemode: GameMode,
     pub previous_gamemode: i8,    // Byte, -1 if no previous gamemode
     pub world_names: Vec<String>, // Array of Identifier
     pub dimension_codec: Tag,     // NBT Tag Compound
@@ -23,11 +24,16 @@ pub struct JoinGamePacket {
 
 impl JoinGamePacket {
     // A helper constructor that builds the NBT data with default values.
-    pub fn new(entity_id: i32, world_names: Vec<String>, world_name: String) -> Self {
+    pub fn new(
+        entity_id: i32,
+        gamemode: GameMode,
+        world_names: Vec<String>,
+        world_name: String,
+    ) -> Self {
         Self {
             entity_id,
             is_hardcore: false,
-            gamemode: 0,
+            gamemode,
             previous_gamemode: -1,
             world_names,
             // Build an NBT compound with keys like "minecraft:dimension_type" and "minecraft:worldgen/biome".
@@ -44,6 +50,16 @@ impl JoinGamePacket {
             is_flat: false,
         }
     }
+
+    /// Whether the dimension being joined has sky light, as its `has_skylight` flag says.
+    /// Dimensions that leave the flag out are treated as having it, like the overworld.
+    pub fn has_skylight(&self) -> bool {
+        self.dimension
+            .as_compound()
+            .and_then(|dimension| dimension.get("has_skylight"))
+            .and_then(Tag::as_i8)
+            .is_none_or(|has_skylight| has_skylight != 0)
+    }
 }
 
 impl Packet for JoinGamePacket {
@@ -51,11 +67,51 @@ impl Packet for JoinGamePacket {
         0x24
     }
 
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> Result<Self> {
+        let entity_id = buffer.read_i32()?;
+        let is_hardcore = buffer.read_bool()?;
+        let gamemode_id = buffer.read_u8()?;
+        let gamemode = GameMode::from_id(gamemode_id).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown game mode: {}", gamemode_id),
+            )
+        })?;
+        let previous_gamemode = buffer.read_i8()?;
+
+        let world_count = buffer.read_varint()?;
+        let mut world_names = Vec::new();
+        for _ in 0..world_count {
+            world_names.push(buffer.read_string()?);
+        }
+
+        let dimension_codec = buffer.read_nbt()?;
+        let dimension = buffer.read_nbt()?;
+
+        Ok(Self {
+            entity_id,
+            is_hardcore,
+            gamemode,
+            previous_gamemode,
+            world_names,
+            dimension_codec,
+            dimension,
+            world_name: buffer.read_string()?,
+            hashed_seed: buffer.read_i64()?,
+            max_players: buffer.read_varint()?,
+            view_distance: buffer.read_varint()?,
+            reduced_debug_info: buffer.read_bool()?,
+            enable_respawn_screen: buffer.read_bool()?,
+            is_debug: buffer.read_bool()?,
+            is_flat: buffer.read_bool()?,
+        })
+    }
+
     fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> Result<()> {
         buffer.write_varint(Self::packet_id());
         buffer.write_i32(self.entity_id);
         buffer.write_bool(self.is_hardcore);
-        buffer.write_u8(self.gamemode);
+        buffer.write_u8(self.gamemode.id());
         buffer.write_i8(self.previous_gamemode);
 
         // Write world names array
@@ -82,21 +138,29 @@ impl Packet for JoinGamePacket {
     }
 }
 
+/// Hashes a world seed the way vanilla does for Join Game and Respawn: the first 8 bytes of the
+/// SHA-256 of the seed, both read and written little-endian. The client uses it for biome
+/// blending without learning the seed itself.
+pub fn hash_seed(seed: i64) -> i64 {
+    let digest = Sha256::digest(seed.to_le_bytes());
+    let mut first_bytes = [0u8; 8];
+    first_bytes.copy_from_slice(&digest[..8]);
+    i64::from_le_bytes(first_bytes)
+}
+
 /// Constructs a default dimension codec NBT compound tag that includes the keys
 /// required by the protocol, such as "minecraft:dimension_type" and "minecraft:worldgen/biome".
-///
-/// TODO: Has to be read from config
-fn default_dimension_codec() -> Tag {
-    let mut compound = HashMap::new();
+pub fn default_dimension_codec() -> Tag {
+    let mut compound = Compound::new();
 
     // Create the dimension registry
-    let mut dimension_registry = HashMap::new();
+    let mut dimension_registry = Compound::new();
     dimension_registry.insert(
         "type".to_string(),
         Tag::String("minecraft:dimension_type".to_string()),
     );
 
-    let mut overworld_details = HashMap::new();
+    let mut overworld_details = Compound::new();
     overworld_details.insert("piglin_safe".to_string(), Tag::Byte(0));
     overworld_details.insert("natural".to_string(), Tag::Byte(1));
     overworld_details.insert("ambient_light".to_string(), Tag::Float(0.0));
@@ -117,7 +181,7 @@ fn default_dimension_codec() -> Tag {
     overworld_details.insert("ultrawarm".to_string(), Tag::Byte(0));
     overworld_details.insert("has_ceiling".to_string(), Tag::Byte(0));
 
-    let mut overworld_entry = HashMap::new();
+    let mut overworld_entry = Compound::new();
     overworld_entry.insert(
         "name".to_string(),
         Tag::String("minecraft:overworld".to_string()),
@@ -136,13 +200,13 @@ fn default_dimension_codec() -> Tag {
     );
 
     // Create the biome registry
-    let mut biome_registry = HashMap::new();
+    let mut biome_registry = Compound::new();
     biome_registry.insert(
         "type".to_string(),
         Tag::String("minecraft:worldgen/biome".to_string()),
     );
 
-    let mut plains_details = HashMap::new();
+    let mut plains_details = Compound::new();
     plains_details.insert("precipitation".to_string(), Tag::String("rain".to_string()));
     plains_details.insert("temperature".to_string(), Tag::Float(0.8));
     plains_details.insert(
@@ -154,7 +218,7 @@ fn default_dimension_codec() -> Tag {
     plains_details.insert("depth".to_string(), Tag::Float(0.125));
     plains_details.insert("category".to_string(), Tag::String("plains".to_string()));
 
-    let mut effects = HashMap::new();
+    let mut effects = Compound::new();
     effects.insert("sky_color".to_string(), Tag::Int(7907327));
     effects.insert("water_fog_color".to_string(), Tag::Int(329011));
     effects.insert("fog_color".to_string(), Tag::Int(12638463));
@@ -162,7 +226,7 @@ fn default_dimension_codec() -> Tag {
     effects.insert(
         "mood_sound".to_string(),
         Tag::Compound({
-            let mut mood = HashMap::new();
+            let mut mood = Compound::new();
             mood.insert("tick_delay".to_string(), Tag::Int(6000));
             mood.insert("offset".to_string(), Tag::Double(2.0));
             mood.insert(
@@ -175,7 +239,7 @@ fn default_dimension_codec() -> Tag {
     );
     plains_details.insert("effects".to_string(), Tag::Compound(effects));
 
-    let mut plains_entry = HashMap::new();
+    let mut plains_entry = Compound::new();
     plains_entry.insert(
         "name".to_string(),
         Tag::String("minecraft:plains".to_string()),
@@ -199,7 +263,7 @@ fn default_dimension_codec() -> Tag {
 /// Constructs a default dimension NBT compound tag for the world you are joining.
 /// This example includes keys such as "min_y", "height", and "logical_height".
 fn default_dimension() -> Tag {
-    let mut compound = HashMap::new();
+    let mut compound = Compound::new();
 
     // Add the required dimension properties
     compound.insert("piglin_safe".to_string(), Tag::Byte(0));
@@ -226,3 +290,52 @@ fn default_dimension() -> Tag {
 
     Tag::Compound(compound)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamemode_byte() {
+        let packet = JoinGamePacket::new(
+            1,
+            GameMode::Creative,
+            vec!["minecraft:overworld".to_owned()],
+            "minecraft:overworld".to_owned(),
+        );
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        // Packet ID, then the entity ID and hardcore flag precede the game mode
+        assert_eq!(buffer.buffer[1 + 4 + 1], 1);
+
+        assert_eq!(buffer.read_varint().unwrap(), JoinGamePacket::packet_id());
+        let read = JoinGamePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read.gamemode, GameMode::Creative);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_has_skylight_follows_dimension() {
+        let mut packet = JoinGamePacket::new(
+            1,
+            GameMode::Survival,
+            vec!["minecraft:the_nether".to_owned()],
+            "minecraft:the_nether".to_owned(),
+        );
+        assert!(packet.has_skylight());
+
+        if let Tag::Compound(dimension) = &mut packet.dimension {
+            dimension.insert("has_skylight".to_string(), Tag::Byte(0));
+        }
+        assert!(!packet.has_skylight());
+    }
+
+    #[test]
+    fn test_hash_seed() {
+        assert_eq!(hash_seed(0), 8794265229978523055);
+        assert_eq!(hash_seed(12345), 293737985876514017);
+        assert_eq!(hash_seed(-4172144997902289642), 2159143436479834350);
+    }
+}
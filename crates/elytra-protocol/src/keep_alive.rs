@@ -12,7 +12,6 @@ impl Packet for KeepAlivePacket {
     }
 
     fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
-        let _packet_id = buffer.read_varint()?;
         Ok(KeepAlivePacket {
             keep_alive_id: buffer.read_i64()?,
         })
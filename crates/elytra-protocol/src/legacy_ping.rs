@@ -0,0 +1,83 @@
+/// First byte of the server list ping sent by clients before 1.7, where a modern client would
+/// send the length of its handshake
+pub const LEGACY_PING_ID: u8 = 0xFE;
+/// Byte clients from 1.4 on send after [`LEGACY_PING_ID`], asking for the response format that
+/// includes the server's version
+pub const LEGACY_PING_PAYLOAD: u8 = 0x01;
+/// Kick packet the legacy status is sent in
+const KICK_PACKET_ID: u8 = 0xFF;
+
+/// Server list entry for clients that only speak the pre-Netty protocol. The connection is
+/// closed once it has been sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyStatusResponse {
+    pub protocol: i32,
+    pub version: String,
+    pub motd: String,
+    pub online: usize,
+    pub max_players: usize,
+}
+
+impl LegacyStatusResponse {
+    /// Encodes the response as a kick packet holding a UTF-16 string. With `with_version`, the
+    /// fields are `\0`-separated after a `§1` marker, as 1.4 to 1.6 clients expect; otherwise
+    /// only the MOTD and player counts are sent, separated by `§`.
+    pub fn to_bytes(&self, with_version: bool) -> Vec<u8> {
+        let status = if with_version {
+            format!(
+                "§1\0{}\0{}\0{}\0{}\0{}",
+                self.protocol, self.version, self.motd, self.online, self.max_players
+            )
+        } else {
+            // Older clients split on every `§`, so the MOTD can't keep its formatting codes
+            format!(
+                "{}§{}§{}",
+                self.motd.replace('§', ""),
+                self.online,
+                self.max_players
+            )
+        };
+        let units: Vec<u16> = status.encode_utf16().collect();
+
+        let mut bytes = Vec::with_capacity(3 + units.len() * 2);
+        bytes.push(KICK_PACKET_ID);
+        bytes.extend_from_slice(&(units.len() as u16).to_be_bytes());
+        for unit in units {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> String {
+        assert_eq!(bytes[0], KICK_PACKET_ID);
+        let length = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+        let units: Vec<u16> = bytes[3..]
+            .chunks(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        assert_eq!(units.len(), length);
+        String::from_utf16(&units).unwrap()
+    }
+
+    #[test]
+    fn test_legacy_status_formats() {
+        let response = LegacyStatusResponse {
+            protocol: 754,
+            version: "1.16.5".to_owned(),
+            motd: "§aAn Elytra Server".to_owned(),
+            online: 3,
+            max_players: 20,
+        };
+        let status = decode(&response.to_bytes(true));
+        assert_eq!(
+            status.split('\0').collect::<Vec<_>>(),
+            ["§1", "754", "1.16.5", "§aAn Elytra Server", "3", "20"]
+        );
+        assert_eq!(decode(&response.to_bytes(false)), "aAn Elytra Server§3§20");
+    }
+}
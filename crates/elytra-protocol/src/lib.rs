@@ -11,4 +11,37 @@ pub mod client_settings;
 pub mod handshake;
 pub mod status;
 pub mod join_game;
-pub mod held_item_change; 
\ No newline at end of file
+pub mod held_item_change;
+pub mod update_light;
+pub mod chunk_data;
+pub mod player_info;
+pub mod vehicle;
+pub mod change_game_state;
+pub mod text_component;
+pub mod disconnect;
+pub mod time_update;
+pub mod chat;
+pub mod entity_status;
+pub mod block_change;
+pub mod block_state;
+pub mod entity_metadata;
+pub mod entity_action;
+pub mod interact_entity;
+pub mod entity_velocity;
+pub mod player_list_header;
+pub mod effect;
+pub mod protocol_version;
+pub mod scoreboard;
+pub mod resource_pack;
+pub mod named_sound_effect;
+pub mod experience;
+pub mod entity_effect;
+pub mod entity_rotation;
+pub mod world_border;
+pub mod encryption;
+pub mod bit_set;
+pub mod plugin_message;
+pub mod player_abilities;
+pub mod spawn_position;
+pub mod legacy_ping;
+pub mod destroy_entities;
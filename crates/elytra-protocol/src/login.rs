@@ -1,5 +1,5 @@
 ﻿use super::packet::*;
-use serde_json::json;
+use super::text_component::TextComponent;
 use tokio::io::Result;
 use uuid::Uuid;
 
@@ -13,6 +13,12 @@ impl Packet for LoginStartPacket {
 
         Ok(LoginStartPacket { username })
     }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.username);
+        Ok(())
+    }
 }
 
 pub struct LoginSuccessPacket {
@@ -57,13 +63,15 @@ pub struct LoginDisconnectPacket {
 }
 
 impl LoginDisconnectPacket {
-    #[allow(dead_code)]
-    pub(crate) fn new(txt: String) -> Self {
+    /// Creates a login disconnect packet with a plain text reason
+    pub fn new(txt: String) -> Self {
+        Self::from_component(TextComponent::new(txt))
+    }
+
+    /// Creates a login disconnect packet with a formatted reason
+    pub fn from_component(component: TextComponent) -> Self {
         LoginDisconnectPacket {
-            reason: json!({
-                "text": txt
-            })
-            .to_string(),
+            reason: component.to_json(),
         }
     }
 }
@@ -80,3 +88,37 @@ impl Packet for LoginDisconnectPacket {
         Ok(())
     }
 }
+
+/// Set Compression (clientbound)
+/// Every packet after this one, in both directions, is framed as described by
+/// [`CompressionState`]. A negative threshold turns compression off again.
+pub struct SetCompressionPacket {
+    pub threshold: i32,
+}
+
+impl SetCompressionPacket {
+    /// Compression state both sides switch to once this packet has been sent
+    pub fn state(&self) -> CompressionState {
+        CompressionState {
+            threshold: usize::try_from(self.threshold).ok(),
+        }
+    }
+}
+
+impl Packet for SetCompressionPacket {
+    fn packet_id() -> i32 {
+        0x03
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> Result<Self> {
+        Ok(SetCompressionPacket {
+            threshold: buffer.read_varint()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.threshold);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Named Sound Effect (clientbound)
+/// Plays a sound referenced by its name, such as one added by a resource pack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedSoundEffectPacket {
+    /// Sound id, like `minecraft:entity.player.levelup`
+    pub sound_name: String,
+    /// Volume slider the sound is played under
+    pub category: i32,
+    /// Position as fixed-point numbers, the coordinate multiplied by 8
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// 1.0 is 100%, higher values can be heard from further away
+    pub volume: f32,
+    /// Between 0.5 and 2.0, 1.0 playing the sound at its normal speed
+    pub pitch: f32,
+}
+
+impl NamedSoundEffectPacket {
+    pub const MASTER: i32 = 0;
+    pub const MUSIC: i32 = 1;
+    pub const RECORDS: i32 = 2;
+    pub const WEATHER: i32 = 3;
+    pub const BLOCKS: i32 = 4;
+    pub const HOSTILE: i32 = 5;
+    pub const NEUTRAL: i32 = 6;
+    pub const PLAYERS: i32 = 7;
+    pub const AMBIENT: i32 = 8;
+    pub const VOICE: i32 = 9;
+
+    /// Plays a sound at normal volume and pitch at the given position
+    pub fn new(sound_name: &str, category: i32, (x, y, z): (f64, f64, f64)) -> Self {
+        Self {
+            sound_name: sound_name.to_owned(),
+            category,
+            x: (x * 8.0) as i32,
+            y: (y * 8.0) as i32,
+            z: (z * 8.0) as i32,
+            volume: 1.0,
+            pitch: 1.0,
+        }
+    }
+}
+
+impl Packet for NamedSoundEffectPacket {
+    fn packet_id() -> i32 {
+        0x18
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            sound_name: buffer.read_string()?,
+            category: buffer.read_varint()?,
+            x: buffer.read_i32()?,
+            y: buffer.read_i32()?,
+            z: buffer.read_i32()?,
+            volume: buffer.read_f32()?,
+            pitch: buffer.read_f32()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.sound_name);
+        buffer.write_varint(self.category);
+        buffer.write_i32(self.x);
+        buffer.write_i32(self.y);
+        buffer.write_i32(self.z);
+        buffer.write_f32(self.volume)?;
+        buffer.write_f32(self.pitch)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_up_sound() {
+        let packet = NamedSoundEffectPacket::new(
+            "minecraft:entity.player.levelup",
+            NamedSoundEffectPacket::PLAYERS,
+            (1.5, 64.0, -2.25),
+        );
+        assert_eq!((packet.x, packet.y, packet.z), (12, 512, -18));
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let mut expected = vec![0x18, 31];
+        expected.extend_from_slice(b"minecraft:entity.player.levelup");
+        expected.push(7);
+        expected.extend_from_slice(&12i32.to_be_bytes());
+        expected.extend_from_slice(&512i32.to_be_bytes());
+        expected.extend_from_slice(&(-18i32).to_be_bytes());
+        expected.extend_from_slice(&1.0f32.to_be_bytes());
+        expected.extend_from_slice(&1.0f32.to_be_bytes());
+        assert_eq!(buffer.get_buffer(), &expected[..]);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            NamedSoundEffectPacket::packet_id()
+        );
+        assert_eq!(
+            NamedSoundEffectPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+}
@@ -1,5 +1,10 @@
-use std::io;
-use tokio::io::AsyncWriteExt;
+use elytra_nbt::{Tag, DEFAULT_MAX_DEPTH};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 
 /// Packet trait. Contains the packet ID and the functions to write and read the packet.
 pub trait Packet {
@@ -27,24 +32,253 @@ pub trait Packet {
     }
 }
 
+/// Bytes shown on each side of the cursor in a hex dump
+const HEX_DUMP_RADIUS: usize = 16;
+
+/// Reads a packet body like [`Packet::read_from_buffer`]. With `hex_dump` set, a failure also
+/// carries a hex dump of the bytes around where reading stopped. The dump holds whatever the
+/// client sent, so it is meant for debugging rather than production logs.
+pub fn read_packet_body<T: Packet>(
+    buffer: &mut MinecraftPacketBuffer,
+    hex_dump: bool,
+) -> io::Result<T> {
+    match T::read_from_buffer(buffer) {
+        Err(read_error) if hex_dump => Err(io::Error::new(
+            read_error.kind(),
+            format!("{} ({})", read_error, buffer.hex_dump()),
+        )),
+        result => result,
+    }
+}
+
 /// Sends a packet to the client
 pub async fn send_packet<T: Packet, W: AsyncWriteExt + Unpin>(
     packet: T,
     writer: &mut W,
 ) -> io::Result<()> {
+    send_raw(&frame_packet(&packet)?, writer).await
+}
+
+/// Serializes a packet with its length prefix, ready to be written to any number of clients
+pub fn frame_packet<T: Packet>(packet: &T) -> io::Result<Vec<u8>> {
     let mut response_buffer = MinecraftPacketBuffer::new();
     packet.write_to_buffer(&mut response_buffer)?;
+    Ok(prefix_length(&response_buffer.buffer))
+}
 
-    let mut packet_with_length = MinecraftPacketBuffer::new();
-    packet_with_length.write_varint(response_buffer.buffer.len() as i32);
-    packet_with_length
-        .buffer
-        .extend_from_slice(&response_buffer.buffer);
+fn prefix_length(data: &[u8]) -> Vec<u8> {
+    let mut packet_with_length =
+        MinecraftPacketBuffer::with_capacity(varint_size(data.len() as i32) + data.len());
+    packet_with_length.write_varint(data.len() as i32);
+    packet_with_length.buffer.extend_from_slice(data);
+    packet_with_length.buffer
+}
 
-    writer.write_all(&packet_with_length.buffer).await?;
-    writer.flush().await?;
+/// Compression negotiated with the Set Compression packet. Once a threshold is set, every frame
+/// holds the packet length, the uncompressed data length, then the packet ID and body. Packets of
+/// at least `threshold` bytes are zlib compressed; smaller ones are sent as is with a data length
+/// of 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionState {
+    /// Smallest packet that is compressed, or `None` before compression has been set
+    pub threshold: Option<usize>,
+}
 
-    Ok(())
+impl CompressionState {
+    /// Frames a serialized packet, the packet ID followed by its body
+    pub fn frame(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return Ok(prefix_length(data)),
+        };
+
+        let mut body = MinecraftPacketBuffer::new();
+        if data.len() >= threshold {
+            body.write_varint(data.len() as i32);
+            let mut encoder = ZlibEncoder::new(body, Compression::default());
+            encoder.write_all(data)?;
+            body = encoder.finish()?;
+        } else {
+            body.write_varint(0);
+            body.write_bytes_raw(data);
+        }
+        Ok(prefix_length(&body.buffer))
+    }
+
+    /// Unwraps a frame read by [`read_packet`], giving a buffer that holds the packet ID followed
+    /// by the packet body
+    pub fn unwrap_frame(
+        &self,
+        mut frame: MinecraftPacketBuffer,
+    ) -> io::Result<MinecraftPacketBuffer> {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return Ok(frame),
+        };
+
+        let data_length = frame.read_varint()?;
+        if data_length == 0 {
+            let data = frame.buffer.split_off(frame.cursor);
+            return Ok(MinecraftPacketBuffer::from_bytes(data));
+        }
+        let data_length = usize::try_from(data_length)
+            .ok()
+            .filter(|&length| length >= threshold && length <= MAX_BYTE_ARRAY_LENGTH)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid uncompressed packet length: {}", data_length),
+                )
+            })?;
+
+        let mut data = Vec::with_capacity(data_length);
+        // Read one byte past the claimed length so a packet that inflates to more is caught
+        // without decompressing all of it
+        ZlibDecoder::new(frame)
+            .take(data_length as u64 + 1)
+            .read_to_end(&mut data)?;
+        if data.len() != data_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Packet decompressed to {} bytes instead of {}",
+                    data.len(),
+                    data_length
+                ),
+            ));
+        }
+        Ok(MinecraftPacketBuffer::from_bytes(data))
+    }
+}
+
+/// Serializes a packet framed for the given compression state
+pub fn frame_packet_compressed<T: Packet>(
+    packet: &T,
+    compression: CompressionState,
+) -> io::Result<Vec<u8>> {
+    let mut response_buffer = MinecraftPacketBuffer::new();
+    packet.write_to_buffer(&mut response_buffer)?;
+    compression.frame(&response_buffer.buffer)
+}
+
+/// Sends a packet to a client that compression has been set for
+pub async fn send_packet_compressed<T: Packet, W: AsyncWriteExt + Unpin>(
+    packet: T,
+    writer: &mut W,
+    compression: CompressionState,
+) -> io::Result<()> {
+    send_raw(&frame_packet_compressed(&packet, compression)?, writer).await
+}
+
+/// Reads a single packet framed for the given compression state. Like [`read_packet`], the
+/// returned buffer holds the packet ID followed by the packet body.
+pub async fn read_packet_compressed<R: AsyncReadExt + Unpin>(
+    reader: &mut R,
+    compression: CompressionState,
+) -> io::Result<MinecraftPacketBuffer> {
+    compression.unwrap_frame(read_packet(reader).await?)
+}
+
+/// Packet framed by [`frame_packet`], shared by every session it is queued for
+pub type FramedPacket = Arc<Vec<u8>>;
+
+/// Sends a packet already framed by [`frame_packet`]
+pub async fn send_raw<W: AsyncWriteExt + Unpin>(framed: &[u8], writer: &mut W) -> io::Result<()> {
+    writer.write_all(framed).await?;
+    writer.flush().await
+}
+
+/// Reads a single length-prefixed packet from the reader. The returned buffer holds the packet ID
+/// followed by the packet body; the length prefix itself is consumed.
+pub async fn read_packet<R: AsyncReadExt + Unpin>(
+    reader: &mut R,
+) -> io::Result<MinecraftPacketBuffer> {
+    let mut length_buffer = MinecraftPacketBuffer::with_capacity(3);
+    loop {
+        let byte = reader.read_u8().await?;
+        length_buffer.write_u8(byte);
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if length_buffer.buffer.len() >= 3 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Packet length prefix too big",
+            ));
+        }
+    }
+
+    let length = length_buffer.read_varint()?;
+    if length <= 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid packet length: {}", length),
+        ));
+    }
+
+    let mut packet = vec![0u8; length as usize];
+    reader.read_exact(&mut packet).await?;
+    Ok(MinecraftPacketBuffer::from_bytes(packet))
+}
+
+/// Reads whole packets from a connection, however their bytes are split across or packed into
+/// the underlying reads. Reads are buffered, so the length prefix doesn't take a read per byte.
+pub struct FramedReader<R> {
+    reader: BufReader<R>,
+    compression: CompressionState,
+}
+
+impl<R: AsyncRead + Unpin> FramedReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            compression: CompressionState::default(),
+        }
+    }
+
+    /// Switches to the framing set by a Set Compression packet, for every packet read after it
+    pub fn set_compression(&mut self, compression: CompressionState) {
+        self.compression = compression;
+    }
+
+    /// Reads the next packet. The returned buffer holds the packet ID followed by the packet body.
+    pub async fn read_packet(&mut self) -> io::Result<MinecraftPacketBuffer> {
+        read_packet_compressed(&mut self.reader, self.compression).await
+    }
+}
+
+/// Returns the number of bytes `value` takes up when encoded as a VarInt.
+pub fn varint_size(value: i32) -> usize {
+    let value = value as u32;
+    match value {
+        0..=0x7F => 1,
+        0x80..=0x3FFF => 2,
+        0x4000..=0x1F_FFFF => 3,
+        0x20_0000..=0xFFF_FFFF => 4,
+        _ => 5,
+    }
+}
+
+/// Maximum length accepted for a length-prefixed byte array. This is the largest payload a single
+/// packet can carry (2^21 - 1 bytes, the largest value of a three byte VarInt).
+pub const MAX_BYTE_ARRAY_LENGTH: usize = 2097151;
+
+/// Most bytes a single NBT tag in a packet may take, as in vanilla
+pub const MAX_NBT_BYTES: u64 = 2097152;
+
+/// Rotation in steps of 1/256 of a full turn, the way entity packets carry yaw and pitch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    /// Closest step to an angle in degrees, wrapping around a full turn
+    pub fn from_degrees(degrees: f32) -> Self {
+        Angle(((degrees / 360.0 * 256.0).round() as i32).rem_euclid(256) as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 * 360.0 / 256.0
+    }
 }
 
 /// Minecraft packet buffer. Contains the buffer and the cursor.
@@ -56,6 +290,12 @@ pub struct MinecraftPacketBuffer {
     cursor: usize,
 }
 
+impl Default for MinecraftPacketBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Minecraft packet buffer impl.
 impl MinecraftPacketBuffer {
     /// Creates a new Minecraft packet buffer.
@@ -68,6 +308,15 @@ impl MinecraftPacketBuffer {
         }
     }
 
+    /// Creates a new, empty Minecraft packet buffer that can hold `capacity` bytes before
+    /// reallocating. Useful for large packets whose size is known up front.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+            cursor: 0,
+        }
+    }
+
     /// Creates a new Minecraft packet buffer from a byte array.
     /// The buffer is initialized with the given byte array.
     /// The cursor is initialized to 0.
@@ -80,7 +329,7 @@ impl MinecraftPacketBuffer {
         }
     }
 
-    /// Returns the current cursor position for the buffer.
+    /// Returns the byte at the current cursor position without advancing the cursor.
     pub fn peek_byte(&self) -> Option<u8> {
         if self.cursor < self.buffer.len() {
             Some(self.buffer[self.cursor])
@@ -89,12 +338,96 @@ impl MinecraftPacketBuffer {
         }
     }
 
+    /// Returns the underlying bytes, including those already read.
+    pub fn get_buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns the current cursor position for the buffer.
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Moves the cursor forward by `count` bytes without reading them.
+    /// Fails if that would move the cursor past the end of the buffer.
+    pub fn advance_cursor(&mut self, count: usize) -> io::Result<()> {
+        if count > self.remaining() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Cannot advance cursor past the end of the buffer",
+            ));
+        }
+        self.cursor += count;
+        Ok(())
+    }
+
+    /// Moves the cursor back to the start of the buffer, so its contents can be read again.
+    pub fn reset_cursor(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Returns the number of bytes left to read after the cursor.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.cursor
+    }
+
+    /// Hex dump of the bytes around the cursor, with a `|` marking the cursor, such as
+    /// `bytes 0..6: 0a 05 | 48 65 6c 6c`
+    pub fn hex_dump(&self) -> String {
+        let start = self.cursor.saturating_sub(HEX_DUMP_RADIUS);
+        let end = (self.cursor + HEX_DUMP_RADIUS).min(self.buffer.len());
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        format!(
+            "bytes {}..{}: {} | {}",
+            start,
+            end,
+            hex(&self.buffer[start..self.cursor]),
+            hex(&self.buffer[self.cursor..end])
+        )
+    }
+
+    /// Returns true if every byte in the buffer has been read.
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Reads a VarInt element count, such as the length of an array that follows. Counts that
+    /// are negative, or too big for the rest of the buffer when each element takes at least
+    /// `min_element_size` bytes, are rejected before anything is allocated for them.
+    pub fn read_length(&mut self, min_element_size: usize) -> io::Result<usize> {
+        let length = self.read_varint()?;
+        if length < 0 || (length as usize).saturating_mul(min_element_size) > self.remaining() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Length {} doesn't fit in the {} bytes left",
+                    length,
+                    self.remaining()
+                ),
+            ));
+        }
+        Ok(length as usize)
+    }
+
+    /// Writes bytes to the buffer as they are, without a length prefix.
+    pub fn write_bytes_raw(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
     /// Writes a VarInt to the buffer.
     /// A VarInt is a variable-length integer. It is encoded using 7 bits per byte, with the most
     /// significant bit of each byte set to 1 unless it is the final byte in the encoded
     /// representation.
     /// The VarInt is written to the buffer in network (big-endian) order.
-    pub fn write_varint(&mut self, mut value: i32) {
+    pub fn write_varint(&mut self, value: i32) {
+        // Shift as unsigned so negative values terminate after five bytes
+        let mut value = value as u32;
         while (value & !0x7F) != 0 {
             self.buffer.push(((value & 0x7F) as u8) | 0x80);
             value >>= 7;
@@ -137,6 +470,50 @@ impl MinecraftPacketBuffer {
         Ok(result)
     }
 
+    /// Writes a VarLong to the buffer, the 64-bit counterpart of a VarInt taking up to ten bytes
+    pub fn write_varlong(&mut self, value: i64) {
+        let mut value = value as u64;
+        while (value & !0x7F) != 0 {
+            self.buffer.push(((value & 0x7F) as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push((value & 0x7F) as u8);
+    }
+
+    /// Reads a VarLong from the buffer
+    pub fn read_varlong(&mut self) -> io::Result<i64> {
+        let mut result = 0;
+        let mut shift = 0;
+
+        loop {
+            if self.cursor >= self.buffer.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "EOF while reading VarLong",
+                ));
+            }
+
+            let byte = self.buffer[self.cursor];
+            self.cursor += 1;
+
+            result |= ((byte & 0x7F) as i64) << shift;
+            shift += 7;
+
+            if (byte & 0x80) == 0 {
+                break;
+            }
+
+            if shift >= 64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "VarLong too big",
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+
     /// Writes a u16 to the buffer.
     /// The u16 is written to the buffer in network (big-endian) order.
     pub fn write_string(&mut self, value: &str) {
@@ -145,6 +522,13 @@ impl MinecraftPacketBuffer {
         self.buffer.extend_from_slice(bytes);
     }
 
+    /// Reads a named NBT tag, rejecting tags nested deeper than vanilla allows or larger than
+    /// [`MAX_NBT_BYTES`] so a crafted packet can't overflow the stack
+    pub fn read_nbt(&mut self) -> io::Result<Tag> {
+        let (_, tag) = Tag::read_limited(self, DEFAULT_MAX_DEPTH, MAX_NBT_BYTES)?;
+        Ok(tag)
+    }
+
     /// Reads a string from the buffer.
     /// The string is read from the buffer in network (big-endian) order.
     pub fn read_string(&mut self) -> io::Result<String> {
@@ -165,6 +549,35 @@ impl MinecraftPacketBuffer {
         })
     }
 
+    /// Writes a byte array to the buffer, prefixed with its length as a VarInt.
+    pub fn write_byte_array(&mut self, bytes: &[u8]) {
+        self.write_varint(bytes.len() as i32);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Reads a VarInt length-prefixed byte array from the buffer.
+    /// Lengths that are negative or larger than `MAX_BYTE_ARRAY_LENGTH` are rejected.
+    pub fn read_byte_array(&mut self) -> io::Result<Vec<u8>> {
+        let length = self.read_varint()?;
+        if length < 0 || length as usize > MAX_BYTE_ARRAY_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid byte array length: {}", length),
+            ));
+        }
+
+        let length = length as usize;
+        if self.cursor + length > self.buffer.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Not enough bytes to read the full byte array",
+            ));
+        }
+        let bytes = self.buffer[self.cursor..self.cursor + length].to_vec();
+        self.cursor += length;
+        Ok(bytes)
+    }
+
     /// Writes a UUID to the buffer.
     /// The UUID is written as two longs in big-endian order.
     pub fn write_uuid(&mut self, value: uuid::Uuid) {
@@ -253,10 +666,40 @@ impl MinecraftPacketBuffer {
         Ok(value)
     }
 
+    pub fn write_i16(&mut self, value: i16) {
+        self.buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn read_i16(&mut self) -> io::Result<i16> {
+        if self.cursor + 2 > self.buffer.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Not enough bytes to read i16",
+            ));
+        }
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(&self.buffer[self.cursor..self.cursor + 2]);
+        self.cursor += 2;
+        Ok(i16::from_be_bytes(bytes))
+    }
+
     pub fn write_i32(&mut self, value: i32) {
         self.buffer.extend_from_slice(&value.to_be_bytes());
     }
 
+    pub fn read_i32(&mut self) -> io::Result<i32> {
+        if self.cursor + 4 > self.buffer.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Not enough bytes to read i32",
+            ));
+        }
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&self.buffer[self.cursor..self.cursor + 4]);
+        self.cursor += 4;
+        Ok(i32::from_be_bytes(bytes))
+    }
+
     pub fn write_i64(&mut self, value: i64) {
         self.buffer.extend_from_slice(&value.to_be_bytes());
     }
@@ -274,6 +717,31 @@ impl MinecraftPacketBuffer {
         Ok(i64::from_be_bytes(bytes))
     }
 
+    /// Writes a block position packed into a long: 26 bits of x, 26 bits of z, then 12 bits of y
+    pub fn write_position(&mut self, x: i32, y: i32, z: i32) {
+        let packed =
+            ((x as i64 & 0x3FFFFFF) << 38) | ((z as i64 & 0x3FFFFFF) << 12) | (y as i64 & 0xFFF);
+        self.write_i64(packed);
+    }
+
+    /// Reads a block position as (x, y, z)
+    pub fn read_position(&mut self) -> io::Result<(i32, i32, i32)> {
+        let packed = self.read_i64()?;
+        // Arithmetic shifts sign-extend each field
+        let x = (packed >> 38) as i32;
+        let y = (packed << 52 >> 52) as i32;
+        let z = (packed << 26 >> 38) as i32;
+        Ok((x, y, z))
+    }
+
+    pub fn write_angle(&mut self, angle: Angle) {
+        self.write_u8(angle.0);
+    }
+
+    pub fn read_angle(&mut self) -> io::Result<Angle> {
+        self.read_u8().map(Angle)
+    }
+
     pub fn write_f64(&mut self, value: f64) -> io::Result<()> {
         self.buffer.extend_from_slice(&value.to_be_bytes());
         Ok(())
@@ -311,6 +779,16 @@ impl MinecraftPacketBuffer {
     }
 }
 
+/// Reads from the cursor position onwards, so the buffer can be handed to readers such as NBT.
+impl std::io::Read for MinecraftPacketBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = buf.len().min(self.remaining());
+        buf[..count].copy_from_slice(&self.buffer[self.cursor..self.cursor + count]);
+        self.cursor += count;
+        Ok(count)
+    }
+}
+
 impl std::io::Write for MinecraftPacketBuffer {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.buffer.extend_from_slice(buf);
@@ -350,6 +828,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_failure_includes_hex_dump() {
+        let bytes = vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        let mut buffer = MinecraftPacketBuffer::from_bytes(bytes.clone());
+        let plain_error = read_packet_body::<TestPacket>(&mut buffer, false)
+            .err()
+            .unwrap();
+        assert!(!plain_error.to_string().contains("ff ff"));
+
+        let mut buffer = MinecraftPacketBuffer::from_bytes(bytes);
+        let error = read_packet_body::<TestPacket>(&mut buffer, true)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), plain_error.kind());
+        assert!(error.to_string().contains("ff ff ff ff ff | 7f"));
+    }
+
     #[test]
     fn test_packet_buffer_new() {
         let buffer = MinecraftPacketBuffer::new();
@@ -374,6 +869,52 @@ mod tests {
         assert_eq!(empty_buffer.peek_byte(), None);
     }
 
+    #[test]
+    fn test_cursor_accessors() {
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![1, 2, 3, 4, 5]);
+        assert_eq!(buffer.get_cursor(), 0);
+        assert_eq!(buffer.remaining(), 5);
+        assert!(!buffer.is_empty());
+
+        // Partially read the buffer
+        assert_eq!(buffer.read_u8().unwrap(), 1);
+        buffer.advance_cursor(2).unwrap();
+        assert_eq!(buffer.get_cursor(), 3);
+        assert_eq!(buffer.remaining(), 2);
+        assert_eq!(buffer.peek_byte(), Some(4));
+
+        // Advancing past the end fails and leaves the cursor untouched
+        assert!(buffer.advance_cursor(3).is_err());
+        assert_eq!(buffer.get_cursor(), 3);
+
+        buffer.advance_cursor(2).unwrap();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.get_buffer(), &[1, 2, 3, 4, 5]);
+
+        // Resetting makes the whole buffer readable again
+        buffer.reset_cursor();
+        assert_eq!(buffer.get_cursor(), 0);
+        assert_eq!(buffer.remaining(), 5);
+        assert_eq!(buffer.read_u8().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_write_bytes_raw_and_read() {
+        use std::io::Read;
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_u8(0xAA);
+        buffer.write_bytes_raw(&[1, 2, 3]);
+        assert_eq!(buffer.get_buffer(), &[0xAA, 1, 2, 3]);
+
+        // Read picks up from the cursor
+        buffer.advance_cursor(1).unwrap();
+        let mut bytes = [0u8; 8];
+        assert_eq!(buffer.read(&mut bytes).unwrap(), 3);
+        assert_eq!(&bytes[..3], &[1, 2, 3]);
+        assert_eq!(buffer.read(&mut bytes).unwrap(), 0);
+    }
+
     #[test]
     fn test_varint() {
         let test_cases = vec![0, 1, 127, 128, 255, 2147483647, -1, -2147483648];
@@ -387,6 +928,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_varlong() {
+        let test_cases = vec![0, 1, 127, 128, i32::MAX as i64 + 1, i64::MAX, -1, i64::MIN];
+
+        for value in test_cases {
+            let mut buffer = MinecraftPacketBuffer::new();
+            buffer.write_varlong(value);
+            assert!(buffer.buffer.len() <= 10);
+
+            let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+            assert_eq!(read_buffer.read_varlong().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_position() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_position(18357644, 831, -20882616);
+        // Example from the protocol documentation
+        assert_eq!(buffer.buffer, 0x4607_632C_15B4_833Fu64.to_be_bytes());
+
+        for position in [(0, 0, 0), (-1, 255, -1), (-30_000_000, -2048, 29_999_999)] {
+            let mut buffer = MinecraftPacketBuffer::new();
+            buffer.write_position(position.0, position.1, position.2);
+            let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+            assert_eq!(read_buffer.read_position().unwrap(), position);
+        }
+    }
+
+    #[test]
+    fn test_position_sign_extension_at_field_boundaries() {
+        let min = -(1 << 25);
+        let max = (1 << 25) - 1;
+        for position in [
+            (min, -2048, min),
+            (max, 2047, max),
+            (min, 2047, max),
+            (max, -2048, min),
+            (min + 1, -1, -1),
+            (-1, 0, min + 1),
+        ] {
+            let mut buffer = MinecraftPacketBuffer::new();
+            buffer.write_position(position.0, position.1, position.2);
+            let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+            assert_eq!(read_buffer.read_position().unwrap(), position);
+        }
+
+        // Only the sign bit of x set
+        let mut buffer = MinecraftPacketBuffer::from_bytes((1u64 << 63).to_be_bytes().to_vec());
+        assert_eq!(buffer.read_position().unwrap(), (min, 0, 0));
+    }
+
+    #[test]
+    fn test_varint_size() {
+        let test_cases = vec![
+            0, 1, 127, 128, 16383, 16384, 2097151, 2097152, 2147483647, -1,
+        ];
+
+        for value in test_cases {
+            let mut buffer = MinecraftPacketBuffer::new();
+            buffer.write_varint(value);
+            assert_eq!(varint_size(value), buffer.buffer.len());
+        }
+    }
+
     #[test]
     fn test_string() {
         let test_strings = vec![
@@ -406,6 +1012,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_byte_array() {
+        let test_arrays: Vec<Vec<u8>> =
+            vec![vec![], vec![0x00], vec![1, 2, 3, 0xFF], vec![0x42; 300]];
+
+        for bytes in test_arrays {
+            let mut buffer = MinecraftPacketBuffer::new();
+            buffer.write_byte_array(&bytes);
+
+            let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+            assert_eq!(read_buffer.read_byte_array().unwrap(), bytes);
+            assert_eq!(read_buffer.peek_byte(), None);
+        }
+    }
+
+    #[test]
+    fn test_byte_array_error_handling() {
+        // Test length above the maximum
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_varint(MAX_BYTE_ARRAY_LENGTH as i32 + 1);
+        let result = buffer.read_byte_array();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Test negative length
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_varint(-1);
+        let result = buffer.read_byte_array();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Test array too long for buffer
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_varint(10);
+        buffer.buffer.extend_from_slice(&[1, 2, 3]);
+        let result = buffer.read_byte_array();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
     #[test]
     fn test_uuid() {
         let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, "wow".as_ref());
@@ -429,6 +1072,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_primitives() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_bool(true);
+        buffer.write_bool(false);
+        buffer.write_i8(i8::MIN);
+        buffer.write_u8(u8::MAX);
+        buffer.write_i16(-2);
+        buffer.write_i32(i32::MIN);
+        buffer.write_i64(i64::MIN);
+        buffer.write_i64(i64::MAX);
+        buffer.write_f32(-0.5).unwrap();
+        buffer.write_f32(f32::NAN).unwrap();
+        buffer.write_f64(f64::MIN_POSITIVE).unwrap();
+        buffer.write_f64(f64::NAN).unwrap();
+        assert_eq!(
+            buffer.get_buffer().len(),
+            2 + 1 + 1 + 2 + 4 + 8 + 8 + 4 + 4 + 8 + 8
+        );
+        // Big-endian, like every fixed-size number in the protocol
+        assert_eq!(&buffer.get_buffer()[10..18], &i64::MIN.to_be_bytes());
+
+        assert!(buffer.read_bool().unwrap());
+        assert!(!buffer.read_bool().unwrap());
+        assert_eq!(buffer.read_i8().unwrap(), i8::MIN);
+        assert_eq!(buffer.read_u8().unwrap(), u8::MAX);
+        assert_eq!(buffer.read_i16().unwrap(), -2);
+        assert_eq!(buffer.read_i32().unwrap(), i32::MIN);
+        assert_eq!(buffer.read_i64().unwrap(), i64::MIN);
+        assert_eq!(buffer.read_i64().unwrap(), i64::MAX);
+        assert_eq!(buffer.read_f32().unwrap(), -0.5);
+        assert!(buffer.read_f32().unwrap().is_nan());
+        assert_eq!(buffer.read_f64().unwrap(), f64::MIN_POSITIVE);
+        assert!(buffer.read_f64().unwrap().is_nan());
+        assert!(buffer.is_empty());
+        assert!(buffer.read_u8().is_err());
+    }
+
     #[test]
     fn test_string_error_handling() {
         // Test invalid UTF-8
@@ -468,6 +1149,18 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_varlong_error_handling() {
+        // Ten bytes is the most a VarLong can take
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![0xFF; 10]);
+        let error = buffer.read_varlong().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![0x80]);
+        let error = buffer.read_varlong().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
     #[test]
     fn test_uuid_error_handling() {
         let mut buffer = MinecraftPacketBuffer::new();
@@ -486,6 +1179,21 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_read_nbt_rejects_deep_nesting() {
+        // A compound holding a compound holding a compound, far deeper than vanilla allows
+        let mut bytes = vec![10, 0, 0];
+        for _ in 0..10000 {
+            bytes.extend_from_slice(&[10, 0, 0]);
+        }
+        let mut buffer = MinecraftPacketBuffer::from_bytes(bytes);
+        let error = buffer.read_nbt().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![1, 0, 1, b'a', 7]);
+        assert_eq!(buffer.read_nbt().unwrap(), Tag::Byte(7));
+    }
+
     #[tokio::test]
     async fn test_send_packet() {
         use tokio::net::{TcpListener, TcpStream};
@@ -514,4 +1222,136 @@ mod tests {
         // Wait for client to complete
         client_task.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_compressed_packets_round_trip() {
+        struct BlobPacket(Vec<u8>);
+
+        impl Packet for BlobPacket {
+            fn packet_id() -> i32 {
+                0x17
+            }
+
+            fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+                Ok(BlobPacket(buffer.read_byte_array()?))
+            }
+
+            fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+                buffer.write_varint(Self::packet_id());
+                buffer.write_byte_array(&self.0);
+                Ok(())
+            }
+        }
+
+        let compression = CompressionState {
+            threshold: Some(256),
+        };
+        let large: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+        let mut stream = Vec::new();
+        send_packet_compressed(BlobPacket(large.clone()), &mut stream, compression)
+            .await
+            .unwrap();
+        send_packet_compressed(BlobPacket(vec![1, 2, 3]), &mut stream, compression)
+            .await
+            .unwrap();
+
+        // The large packet shrinks and records its uncompressed size, the small one is sent as is
+        let mut reader = stream.as_slice();
+        let mut frame = read_packet(&mut reader).await.unwrap();
+        assert!(frame.remaining() < large.len());
+        assert_eq!(frame.read_varint().unwrap(), 1 + 2 + 1000);
+        let mut frame = read_packet(&mut reader).await.unwrap();
+        assert_eq!(frame.read_varint().unwrap(), 0);
+        assert_eq!(frame.get_buffer()[1..], [0x17, 3, 1, 2, 3]);
+
+        let mut reader = stream.as_slice();
+        for expected in [large, vec![1, 2, 3]] {
+            let mut packet = read_packet_compressed(&mut reader, compression)
+                .await
+                .unwrap();
+            assert_eq!(packet.read_varint().unwrap(), BlobPacket::packet_id());
+            assert_eq!(
+                BlobPacket::read_from_buffer(&mut packet).unwrap().0,
+                expected
+            );
+            assert!(packet.is_empty());
+        }
+        assert!(reader.is_empty());
+
+        // A frame claiming a different uncompressed size than it inflates to is rejected
+        let mut tampered = compression.frame(&[0u8; 300]).unwrap();
+        tampered[2] += 1;
+        let error = read_packet_compressed(&mut tampered.as_slice(), compression)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_framed_reader_joins_split_packets() {
+        let mut stream = Vec::new();
+        for value in [300, 7] {
+            let mut body = MinecraftPacketBuffer::new();
+            body.write_varint(TestPacket::packet_id());
+            TestPacket { value }.write_to_buffer(&mut body).unwrap();
+            stream.extend(prefix_length(&body.buffer));
+        }
+
+        // Every byte arrives in a read of its own
+        let mut mock = tokio_test::io::Builder::new();
+        for byte in &stream {
+            mock.read(std::slice::from_ref(byte));
+        }
+        let mut reader = FramedReader::new(mock.build());
+        for value in [300, 7] {
+            let mut packet = reader.read_packet().await.unwrap();
+            assert_eq!(packet.read_varint().unwrap(), TestPacket::packet_id());
+            assert_eq!(
+                TestPacket::read_from_buffer(&mut packet).unwrap().value,
+                value
+            );
+            assert!(packet.is_empty());
+        }
+        let end = reader.read_packet().await.err().unwrap();
+        assert_eq!(end.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn test_read_packet_splits_coalesced_packets() {
+        let mut stream = Vec::new();
+        for value in [1, 300] {
+            let mut body = MinecraftPacketBuffer::new();
+            body.write_varint(TestPacket::packet_id());
+            TestPacket { value }.write_to_buffer(&mut body).unwrap();
+            stream.push(body.buffer.len() as u8);
+            stream.extend_from_slice(&body.buffer);
+        }
+
+        let mut reader = stream.as_slice();
+        for value in [1, 300] {
+            let mut packet = read_packet(&mut reader).await.unwrap();
+            assert_eq!(packet.read_varint().unwrap(), TestPacket::packet_id());
+            assert_eq!(
+                TestPacket::read_from_buffer(&mut packet).unwrap().value,
+                value
+            );
+            assert!(packet.is_empty());
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_packet_rejects_bad_lengths() {
+        let mut zero_length: &[u8] = &[0x00];
+        let err = read_packet(&mut zero_length).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut oversized_prefix: &[u8] = &[0xFF, 0xFF, 0xFF, 0x01];
+        let err = read_packet(&mut oversized_prefix).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut truncated: &[u8] = &[0x05, 0x00, 0x01];
+        let err = read_packet(&mut truncated).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
 }
@@ -0,0 +1,94 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use elytra_common::types::GameMode;
+use std::io;
+
+/// Player Abilities (clientbound)
+/// Tells the client whether the player may fly, takes damage and breaks blocks instantly, and
+/// how fast they fly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerAbilitiesPacket {
+    pub flags: u8,
+    /// 0.05 in vanilla
+    pub flying_speed: f32,
+    /// Scales the field of view with the walking speed, 0.1 in vanilla
+    pub fov_modifier: f32,
+}
+
+impl PlayerAbilitiesPacket {
+    pub const INVULNERABLE: u8 = 0x01;
+    pub const FLYING: u8 = 0x02;
+    pub const ALLOW_FLYING: u8 = 0x04;
+    pub const INSTANT_BREAK: u8 = 0x08;
+
+    pub const DEFAULT_FLYING_SPEED: f32 = 0.05;
+    pub const DEFAULT_FOV_MODIFIER: f32 = 0.1;
+
+    pub fn new(flags: u8) -> Self {
+        Self {
+            flags,
+            flying_speed: Self::DEFAULT_FLYING_SPEED,
+            fov_modifier: Self::DEFAULT_FOV_MODIFIER,
+        }
+    }
+
+    /// The abilities vanilla gives a player in the given game mode. Spectators are always flying.
+    pub fn for_gamemode(gamemode: GameMode) -> Self {
+        Self::new(match gamemode {
+            GameMode::Survival | GameMode::Adventure => 0,
+            GameMode::Creative => Self::INVULNERABLE | Self::ALLOW_FLYING | Self::INSTANT_BREAK,
+            GameMode::Spectator => Self::INVULNERABLE | Self::FLYING | Self::ALLOW_FLYING,
+        })
+    }
+}
+
+impl Packet for PlayerAbilitiesPacket {
+    fn packet_id() -> i32 {
+        0x30
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            flags: buffer.read_u8()?,
+            flying_speed: buffer.read_f32()?,
+            fov_modifier: buffer.read_f32()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_u8(self.flags);
+        buffer.write_f32(self.flying_speed)?;
+        buffer.write_f32(self.fov_modifier)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_creative_abilities() {
+        let packet = PlayerAbilitiesPacket::for_gamemode(GameMode::Creative);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let mut expected = vec![0x30, 0x0D];
+        expected.extend_from_slice(&0.05f32.to_be_bytes());
+        expected.extend_from_slice(&0.1f32.to_be_bytes());
+        assert_eq!(buffer.get_buffer(), &expected[..]);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            PlayerAbilitiesPacket::packet_id()
+        );
+        assert_eq!(
+            PlayerAbilitiesPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+        assert_eq!(
+            PlayerAbilitiesPacket::for_gamemode(GameMode::Survival).flags,
+            0
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use elytra_common::types::GameMode;
+use std::io;
+use uuid::Uuid;
+
+/// Player Info (clientbound)
+/// Updates the entries of the tab list. Every packet carries a single action applied to one or
+/// more players.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInfoPacket {
+    pub action: PlayerInfoAction,
+}
+
+/// Tab list entry sent with the Add Player action
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInfoEntry {
+    pub uuid: Uuid,
+    pub name: String,
+    pub gamemode: GameMode,
+    /// Ping in milliseconds
+    pub ping: i32,
+}
+
+/// Action of a Player Info packet, along with the players it applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerInfoAction {
+    /// Adds players to the tab list. Offline mode players have no skin properties or display name.
+    AddPlayer(Vec<PlayerInfoEntry>),
+    /// Changes the game mode shown for players, such as after `/gamemode`
+    UpdateGameMode(Vec<(Uuid, GameMode)>),
+    /// Sets the ping, in milliseconds, shown as connection bars next to each player
+    UpdateLatency(Vec<(Uuid, i32)>),
+    /// Takes players off the tab list, such as when they disconnect
+    RemovePlayer(Vec<Uuid>),
+}
+
+impl PlayerInfoAction {
+    /// Action ID written before the player list
+    pub fn id(&self) -> i32 {
+        match self {
+            PlayerInfoAction::AddPlayer(_) => 0,
+            PlayerInfoAction::UpdateGameMode(_) => 1,
+            PlayerInfoAction::UpdateLatency(_) => 2,
+            PlayerInfoAction::RemovePlayer(_) => 4,
+        }
+    }
+}
+
+impl PlayerInfoPacket {
+    /// Creates an Add Player packet for the given entries
+    pub fn add_player(entries: Vec<PlayerInfoEntry>) -> Self {
+        Self {
+            action: PlayerInfoAction::AddPlayer(entries),
+        }
+    }
+
+    /// Creates an Update Game Mode packet for the given players
+    pub fn update_gamemode(gamemodes: Vec<(Uuid, GameMode)>) -> Self {
+        Self {
+            action: PlayerInfoAction::UpdateGameMode(gamemodes),
+        }
+    }
+
+    /// Creates an Update Latency packet for the given players
+    pub fn update_latency(latencies: Vec<(Uuid, i32)>) -> Self {
+        Self {
+            action: PlayerInfoAction::UpdateLatency(latencies),
+        }
+    }
+
+    /// Creates a Remove Player packet for the given players
+    pub fn remove_player(uuids: Vec<Uuid>) -> Self {
+        Self {
+            action: PlayerInfoAction::RemovePlayer(uuids),
+        }
+    }
+}
+
+impl Packet for PlayerInfoPacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x32
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let action_id = buffer.read_varint()?;
+        let count = buffer.read_varint()?;
+
+        let action = match action_id {
+            0 => {
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    entries.push(read_entry(buffer)?);
+                }
+                PlayerInfoAction::AddPlayer(entries)
+            }
+            1 => {
+                let mut gamemodes = Vec::new();
+                for _ in 0..count {
+                    gamemodes.push((buffer.read_uuid()?, read_gamemode(buffer)?));
+                }
+                PlayerInfoAction::UpdateGameMode(gamemodes)
+            }
+            2 => {
+                let mut latencies = Vec::new();
+                for _ in 0..count {
+                    latencies.push((buffer.read_uuid()?, buffer.read_varint()?));
+                }
+                PlayerInfoAction::UpdateLatency(latencies)
+            }
+            4 => {
+                let mut uuids = Vec::new();
+                for _ in 0..count {
+                    uuids.push(buffer.read_uuid()?);
+                }
+                PlayerInfoAction::RemovePlayer(uuids)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown Player Info action: {}", action_id),
+                ))
+            }
+        };
+
+        Ok(Self { action })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.action.id());
+
+        match &self.action {
+            PlayerInfoAction::AddPlayer(entries) => {
+                buffer.write_varint(entries.len() as i32);
+                for entry in entries {
+                    buffer.write_uuid(entry.uuid);
+                    buffer.write_string(&entry.name);
+                    // No skin properties
+                    buffer.write_varint(0);
+                    buffer.write_varint(entry.gamemode.id() as i32);
+                    buffer.write_varint(entry.ping);
+                    // No display name
+                    buffer.write_bool(false);
+                }
+            }
+            PlayerInfoAction::UpdateGameMode(gamemodes) => {
+                buffer.write_varint(gamemodes.len() as i32);
+                for (uuid, gamemode) in gamemodes {
+                    buffer.write_uuid(*uuid);
+                    buffer.write_varint(gamemode.id() as i32);
+                }
+            }
+            PlayerInfoAction::UpdateLatency(latencies) => {
+                buffer.write_varint(latencies.len() as i32);
+                for (uuid, latency) in latencies {
+                    buffer.write_uuid(*uuid);
+                    buffer.write_varint(*latency);
+                }
+            }
+            PlayerInfoAction::RemovePlayer(uuids) => {
+                buffer.write_varint(uuids.len() as i32);
+                for uuid in uuids {
+                    buffer.write_uuid(*uuid);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a single Add Player entry. Skin properties and display names are skipped.
+fn read_entry(buffer: &mut MinecraftPacketBuffer) -> io::Result<PlayerInfoEntry> {
+    let uuid = buffer.read_uuid()?;
+    let name = buffer.read_string()?;
+
+    let property_count = buffer.read_varint()?;
+    for _ in 0..property_count {
+        let _name = buffer.read_string()?;
+        let _value = buffer.read_string()?;
+        if buffer.read_bool()? {
+            let _signature = buffer.read_string()?;
+        }
+    }
+
+    let gamemode = read_gamemode(buffer)?;
+    let ping = buffer.read_varint()?;
+
+    if buffer.read_bool()? {
+        let _display_name = buffer.read_string()?;
+    }
+
+    Ok(PlayerInfoEntry {
+        uuid,
+        name,
+        gamemode,
+        ping,
+    })
+}
+
+fn read_gamemode(buffer: &mut MinecraftPacketBuffer) -> io::Result<GameMode> {
+    let gamemode_id = buffer.read_varint()?;
+    u8::try_from(gamemode_id)
+        .ok()
+        .and_then(GameMode::from_id)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown game mode: {}", gamemode_id),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_latency_round_trip() {
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let packet = PlayerInfoPacket::update_latency(vec![(uuid, 42)]);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+        let read = PlayerInfoPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, packet);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_update_gamemode_round_trip() {
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let packet = PlayerInfoPacket::update_gamemode(vec![(uuid, GameMode::Spectator)]);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+        let read = PlayerInfoPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, packet);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_remove_player_round_trip() {
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let packet = PlayerInfoPacket::remove_player(vec![uuid]);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+        let read = PlayerInfoPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, packet);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_add_player_round_trip() {
+        let entry = PlayerInfoEntry {
+            uuid: Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Alex"),
+            name: "Alex".to_owned(),
+            gamemode: GameMode::Creative,
+            ping: 0,
+        };
+        let packet = PlayerInfoPacket::add_player(vec![entry]);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+        let read = PlayerInfoPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(read, packet);
+        assert!(buffer.is_empty());
+    }
+}
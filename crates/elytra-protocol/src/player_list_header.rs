@@ -0,0 +1,68 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use crate::text_component::TextComponent;
+use std::io;
+
+/// Player List Header And Footer (clientbound)
+/// Sets the text shown above and below the player list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerListHeaderFooterPacket {
+    /// Header as a chat component. An empty text component removes it.
+    pub header: String,
+    /// Footer as a chat component. An empty text component removes it.
+    pub footer: String,
+}
+
+impl PlayerListHeaderFooterPacket {
+    pub fn new(header: &TextComponent, footer: &TextComponent) -> Self {
+        Self {
+            header: header.to_json(),
+            footer: footer.to_json(),
+        }
+    }
+}
+
+impl Packet for PlayerListHeaderFooterPacket {
+    fn packet_id() -> i32 {
+        0x53
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            header: buffer.read_string()?,
+            footer: buffer.read_string()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.header);
+        buffer.write_string(&self.footer);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_footer_serialization() {
+        let packet = PlayerListHeaderFooterPacket::new(
+            &TextComponent::new("Elytra").bold(),
+            &TextComponent::new("play.example.com").color("gray"),
+        );
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(buffer.read_varint().unwrap(), 0x53);
+        assert_eq!(
+            buffer.read_string().unwrap(),
+            r#"{"text":"Elytra","bold":true}"#
+        );
+        assert_eq!(
+            buffer.read_string().unwrap(),
+            r#"{"text":"play.example.com","color":"gray"}"#
+        );
+        assert!(buffer.is_empty());
+    }
+}
@@ -29,6 +29,18 @@ impl Packet for PlayerPositionAndLook {
         0x34
     }
 
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            x: buffer.read_f64()?,
+            y: buffer.read_f64()?,
+            z: buffer.read_f64()?,
+            yaw: buffer.read_f32()?,
+            pitch: buffer.read_f32()?,
+            flags: buffer.read_u8()?,
+            teleport_id: buffer.read_varint()?,
+        })
+    }
+
     fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
         buffer.write_varint(Self::packet_id());
 
@@ -71,3 +83,103 @@ impl PlayerPositionAndLook {
     pub const RELATIVE_Y_ROT: u8 = 0x08;
     pub const RELATIVE_X_ROT: u8 = 0x10;
 }
+
+/// Teleport Confirm (serverbound)
+/// Sent by the client once it has applied a Player Position And Look.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeleportConfirmPacket {
+    /// Teleport ID from the Player Position And Look being confirmed
+    pub teleport_id: i32,
+}
+
+impl Packet for TeleportConfirmPacket {
+    fn packet_id() -> i32 {
+        0x00
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            teleport_id: buffer.read_varint()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.teleport_id);
+        Ok(())
+    }
+}
+
+/// Player Position And Rotation (serverbound)
+/// Sent by the client when it moves and turns within the same tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerPositionAndLookServerbound {
+    pub x: f64,
+    /// Position of the player's feet
+    pub y: f64,
+    pub z: f64,
+    /// Absolute rotation on the X axis, in degrees
+    pub yaw: f32,
+    /// Absolute rotation on the Y axis, in degrees
+    pub pitch: f32,
+    /// Whether the player is standing on a block
+    pub on_ground: bool,
+}
+
+impl Packet for PlayerPositionAndLookServerbound {
+    fn packet_id() -> i32 {
+        0x13
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            x: buffer.read_f64()?,
+            y: buffer.read_f64()?,
+            z: buffer.read_f64()?,
+            yaw: buffer.read_f32()?,
+            pitch: buffer.read_f32()?,
+            on_ground: buffer.read_bool()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_f64(self.x)?;
+        buffer.write_f64(self.y)?;
+        buffer.write_f64(self.z)?;
+        buffer.write_f32(self.yaw)?;
+        buffer.write_f32(self.pitch)?;
+        buffer.write_bool(self.on_ground);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serverbound_position_and_look_round_trip() {
+        let packet = PlayerPositionAndLookServerbound {
+            x: 12.5,
+            y: 64.0,
+            z: -3.25,
+            yaw: 90.0,
+            pitch: -15.0,
+            on_ground: true,
+        };
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.buffer.len(), 1 + 3 * 8 + 2 * 4 + 1);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            PlayerPositionAndLookServerbound::packet_id()
+        );
+        assert_eq!(
+            PlayerPositionAndLookServerbound::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+        assert!(buffer.is_empty());
+    }
+}
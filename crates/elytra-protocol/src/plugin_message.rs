@@ -0,0 +1,156 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Channel clients and servers announce their brand on
+pub const BRAND_CHANNEL: &str = "minecraft:brand";
+/// Largest payload the client may send in a plugin message
+pub const MAX_SERVERBOUND_DATA_LENGTH: usize = 32767;
+
+/// Plugin Message (clientbound)
+/// Sends mod or plugin data on a named channel. The data takes up the rest of the packet, with
+/// no length prefix of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginMessagePacket {
+    /// Namespaced channel, like `minecraft:brand`
+    pub channel: String,
+    pub data: Vec<u8>,
+}
+
+impl PluginMessagePacket {
+    /// Announces the server brand, shown in the client's debug screen
+    pub fn brand(brand: &str) -> Self {
+        let mut data = MinecraftPacketBuffer::new();
+        data.write_string(brand);
+        Self {
+            channel: BRAND_CHANNEL.to_owned(),
+            data: data.buffer,
+        }
+    }
+}
+
+impl Packet for PluginMessagePacket {
+    fn packet_id() -> i32 {
+        0x17
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            channel: buffer.read_string()?,
+            data: read_rest(buffer)?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.channel);
+        buffer.write_bytes_raw(&self.data);
+        Ok(())
+    }
+}
+
+/// Plugin Message (serverbound)
+/// Mod or plugin data from the client. Vanilla clients send their brand on `minecraft:brand`
+/// after joining.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginMessageServerboundPacket {
+    pub channel: String,
+    pub data: Vec<u8>,
+}
+
+impl PluginMessageServerboundPacket {
+    /// The client's brand, like `vanilla` or `fabric`, if this message carries it
+    pub fn brand(&self) -> Option<String> {
+        if self.channel != BRAND_CHANNEL {
+            return None;
+        }
+        MinecraftPacketBuffer::from_bytes(self.data.clone())
+            .read_string()
+            .ok()
+    }
+}
+
+impl Packet for PluginMessageServerboundPacket {
+    fn packet_id() -> i32 {
+        0x0B
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let channel = buffer.read_string()?;
+        if buffer.remaining() > MAX_SERVERBOUND_DATA_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Plugin message on {} is longer than {} bytes",
+                    channel, MAX_SERVERBOUND_DATA_LENGTH
+                ),
+            ));
+        }
+        Ok(Self {
+            channel,
+            data: read_rest(buffer)?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.channel);
+        buffer.write_bytes_raw(&self.data);
+        Ok(())
+    }
+}
+
+/// Reads everything left in the packet
+fn read_rest(buffer: &mut MinecraftPacketBuffer) -> io::Result<Vec<u8>> {
+    let data = buffer.get_buffer()[buffer.get_cursor()..].to_vec();
+    buffer.advance_cursor(data.len())?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brand() {
+        let packet = PluginMessagePacket::brand("Elytra");
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let mut expected = vec![0x17, 15];
+        expected.extend_from_slice(b"minecraft:brand");
+        expected.push(6);
+        expected.extend_from_slice(b"Elytra");
+        assert_eq!(buffer.get_buffer(), &expected[..]);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            PluginMessagePacket::packet_id()
+        );
+        assert_eq!(
+            PluginMessagePacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_client_brand() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_string(BRAND_CHANNEL);
+        buffer.write_string("vanilla");
+        let packet = PluginMessageServerboundPacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(packet.brand().as_deref(), Some("vanilla"));
+
+        let other = PluginMessageServerboundPacket {
+            channel: "fabric:registry/sync".to_owned(),
+            data: vec![1, 2, 3],
+        };
+        assert_eq!(other.brand(), None);
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_string("mod:big");
+        buffer.write_bytes_raw(&vec![0; MAX_SERVERBOUND_DATA_LENGTH + 1]);
+        let error = PluginMessageServerboundPacket::read_from_buffer(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,79 @@
+/// Protocol versions the server can talk to, sent by the client in the handshake.
+///
+/// 1.16.4 and 1.16.5 share protocol 754, and 1.16.3 (753) uses the same packet ids and formats
+/// as 754, so every packet is currently written the same way for all of them. Packets that come
+/// to differ should match on the session's version instead of assuming the latest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ProtocolVersion {
+    /// 1.16.3
+    V753,
+    /// 1.16.4 and 1.16.5
+    #[default]
+    V754,
+}
+
+impl ProtocolVersion {
+    /// Newest version the server supports, advertised in the server list
+    pub const LATEST: ProtocolVersion = ProtocolVersion::V754;
+    /// Oldest version the server supports
+    pub const OLDEST: ProtocolVersion = ProtocolVersion::V753;
+
+    /// Looks up the version with the given protocol number, if it is supported
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            753 => Some(ProtocolVersion::V753),
+            754 => Some(ProtocolVersion::V754),
+            _ => None,
+        }
+    }
+
+    /// Protocol number sent in the handshake
+    pub fn id(self) -> i32 {
+        match self {
+            ProtocolVersion::V753 => 753,
+            ProtocolVersion::V754 => 754,
+        }
+    }
+
+    /// Newest game version using this protocol
+    pub fn name(self) -> &'static str {
+        match self {
+            ProtocolVersion::V753 => "1.16.3",
+            ProtocolVersion::V754 => "1.16.5",
+        }
+    }
+
+    /// Reason shown to a client whose protocol isn't supported, telling it which side is out of
+    /// date
+    pub fn unsupported_reason(client_id: i32) -> String {
+        if client_id < Self::OLDEST.id() {
+            format!("Outdated client! Please use {}", Self::LATEST.name())
+        } else {
+            format!("Outdated server! I'm still on {}", Self::LATEST.name())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_versions() {
+        assert_eq!(ProtocolVersion::from_id(753), Some(ProtocolVersion::V753));
+        assert_eq!(ProtocolVersion::from_id(754), Some(ProtocolVersion::V754));
+        assert_eq!(ProtocolVersion::from_id(340), None);
+        assert_eq!(ProtocolVersion::from_id(755), None);
+        assert_eq!(ProtocolVersion::V753.name(), "1.16.3");
+        assert_eq!(ProtocolVersion::LATEST.id(), 754);
+
+        assert_eq!(
+            ProtocolVersion::unsupported_reason(340),
+            "Outdated client! Please use 1.16.5"
+        );
+        assert_eq!(
+            ProtocolVersion::unsupported_reason(755),
+            "Outdated server! I'm still on 1.16.5"
+        );
+    }
+}
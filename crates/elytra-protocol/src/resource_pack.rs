@@ -0,0 +1,140 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Resource Pack Send (clientbound)
+/// Asks the client to download and apply a resource pack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourcePackSendPacket {
+    pub url: String,
+    /// Lowercase hex SHA-1 of the pack, at most 40 characters. The client uses it to skip
+    /// downloading a pack it already has cached.
+    pub hash: String,
+}
+
+impl ResourcePackSendPacket {
+    pub fn new(url: &str, hash: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            hash: hash.to_ascii_lowercase(),
+        }
+    }
+}
+
+impl Packet for ResourcePackSendPacket {
+    fn packet_id() -> i32 {
+        0x38
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            url: buffer.read_string()?,
+            hash: buffer.read_string()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.url);
+        buffer.write_string(&self.hash);
+        Ok(())
+    }
+}
+
+/// What the client did with a resource pack it was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourcePackStatus {
+    SuccessfullyLoaded = 0,
+    Declined = 1,
+    FailedDownload = 2,
+    Accepted = 3,
+}
+
+impl ResourcePackStatus {
+    fn from_id(id: i32) -> io::Result<Self> {
+        match id {
+            0 => Ok(ResourcePackStatus::SuccessfullyLoaded),
+            1 => Ok(ResourcePackStatus::Declined),
+            2 => Ok(ResourcePackStatus::FailedDownload),
+            3 => Ok(ResourcePackStatus::Accepted),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown resource pack status {}", id),
+            )),
+        }
+    }
+}
+
+/// Resource Pack Status (serverbound)
+/// Sent when the client accepts or declines a resource pack, and again once it has loaded it or
+/// failed to download it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourcePackStatusPacket {
+    pub status: ResourcePackStatus,
+}
+
+impl Packet for ResourcePackStatusPacket {
+    fn packet_id() -> i32 {
+        0x21
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            status: ResourcePackStatus::from_id(buffer.read_varint()?)?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.status as i32);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_resource_pack_send() {
+        let packet = ResourcePackSendPacket::new(
+            "https://example.com/pack.zip",
+            "2FD4E1C67A2D28FCED849EE1BB76E7391B93EB12",
+        );
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let mut expected = vec![0x38, 28];
+        expected.extend_from_slice(b"https://example.com/pack.zip");
+        expected.push(40);
+        expected.extend_from_slice(b"2fd4e1c67a2d28fced849ee1bb76e7391b93eb12");
+        assert_eq!(buffer.get_buffer(), &expected[..]);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            ResourcePackSendPacket::packet_id()
+        );
+        assert_eq!(
+            ResourcePackSendPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+
+    #[test]
+    fn test_read_resource_pack_status() {
+        for (id, status) in [
+            (0, ResourcePackStatus::SuccessfullyLoaded),
+            (1, ResourcePackStatus::Declined),
+            (2, ResourcePackStatus::FailedDownload),
+            (3, ResourcePackStatus::Accepted),
+        ] {
+            let mut buffer = MinecraftPacketBuffer::from_bytes(vec![id]);
+            assert_eq!(
+                ResourcePackStatusPacket::read_from_buffer(&mut buffer).unwrap(),
+                ResourcePackStatusPacket { status }
+            );
+        }
+
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![4]);
+        assert!(ResourcePackStatusPacket::read_from_buffer(&mut buffer).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use crate::session::PlayerSession;
+use crate::session_manager::SessionManager;
+use crate::text_component::TextComponent;
+use std::collections::BTreeMap;
+use std::io;
+
+/// Slot of the screen an objective is displayed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplaySlot {
+    List = 0,
+    Sidebar = 1,
+    BelowName = 2,
+}
+
+impl DisplaySlot {
+    fn from_id(id: u8) -> io::Result<Self> {
+        match id {
+            0 => Ok(DisplaySlot::List),
+            1 => Ok(DisplaySlot::Sidebar),
+            2 => Ok(DisplaySlot::BelowName),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported display slot {}", id),
+            )),
+        }
+    }
+}
+
+/// Scoreboard Objective (clientbound)
+/// Creates, removes or renames an objective.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreboardObjectivePacket {
+    /// Unique name of the objective, at most 16 characters
+    pub name: String,
+    pub mode: u8,
+    /// Title as a chat component, with the render type (0 for integers, 1 for hearts). Only
+    /// sent when creating or updating.
+    pub display: Option<(String, i32)>,
+}
+
+impl ScoreboardObjectivePacket {
+    pub const CREATE: u8 = 0;
+    pub const REMOVE: u8 = 1;
+    pub const UPDATE: u8 = 2;
+
+    pub fn create(name: &str, title: &TextComponent) -> Self {
+        Self::with_display(name, Self::CREATE, title)
+    }
+
+    pub fn update(name: &str, title: &TextComponent) -> Self {
+        Self::with_display(name, Self::UPDATE, title)
+    }
+
+    pub fn remove(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            mode: Self::REMOVE,
+            display: None,
+        }
+    }
+
+    fn with_display(name: &str, mode: u8, title: &TextComponent) -> Self {
+        Self {
+            name: name.to_owned(),
+            mode,
+            display: Some((title.to_json(), 0)),
+        }
+    }
+}
+
+impl Packet for ScoreboardObjectivePacket {
+    fn packet_id() -> i32 {
+        0x4A
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let name = buffer.read_string()?;
+        let mode = buffer.read_u8()?;
+        let display = match mode {
+            Self::REMOVE => None,
+            _ => Some((buffer.read_string()?, buffer.read_varint()?)),
+        };
+        Ok(Self {
+            name,
+            mode,
+            display,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.name);
+        buffer.write_u8(self.mode);
+        if let Some((title, render_type)) = &self.display {
+            buffer.write_string(title);
+            buffer.write_varint(*render_type);
+        }
+        Ok(())
+    }
+}
+
+/// Display Scoreboard (clientbound)
+/// Shows an objective in one of the display slots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayScoreboardPacket {
+    pub slot: DisplaySlot,
+    /// Objective to show, or an empty string to clear the slot
+    pub objective_name: String,
+}
+
+impl Packet for DisplayScoreboardPacket {
+    fn packet_id() -> i32 {
+        0x43
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            slot: DisplaySlot::from_id(buffer.read_u8()?)?,
+            objective_name: buffer.read_string()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_u8(self.slot as u8);
+        buffer.write_string(&self.objective_name);
+        Ok(())
+    }
+}
+
+/// Update Score (clientbound)
+/// Sets or removes the score of an entry, usually a player name, in an objective.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateScorePacket {
+    /// Entry whose score changed, at most 40 characters
+    pub entity_name: String,
+    pub objective_name: String,
+    /// New score, or `None` to remove the entry
+    pub value: Option<i32>,
+}
+
+impl UpdateScorePacket {
+    const UPDATE: u8 = 0;
+    const REMOVE: u8 = 1;
+}
+
+impl Packet for UpdateScorePacket {
+    fn packet_id() -> i32 {
+        0x4D
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let entity_name = buffer.read_string()?;
+        let action = buffer.read_u8()?;
+        let objective_name = buffer.read_string()?;
+        let value = match action {
+            Self::REMOVE => None,
+            _ => Some(buffer.read_varint()?),
+        };
+        Ok(Self {
+            entity_name,
+            objective_name,
+            value,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_string(&self.entity_name);
+        match self.value {
+            Some(value) => {
+                buffer.write_u8(Self::UPDATE);
+                buffer.write_string(&self.objective_name);
+                buffer.write_varint(value);
+            }
+            None => {
+                buffer.write_u8(Self::REMOVE);
+                buffer.write_string(&self.objective_name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sidebar objective with a score per entry. Changes are broadcast to everyone online; the
+/// objective is created on the clients with the first change.
+#[derive(Debug, Clone)]
+pub struct Scoreboard {
+    objective_name: String,
+    title: TextComponent,
+    scores: BTreeMap<String, i32>,
+    /// Whether the objective has been created on the clients
+    shown: bool,
+}
+
+impl Scoreboard {
+    pub fn new(objective_name: impl Into<String>, title: TextComponent) -> Self {
+        Self {
+            objective_name: objective_name.into(),
+            title,
+            scores: BTreeMap::new(),
+            shown: false,
+        }
+    }
+
+    pub fn score(&self, name: &str) -> Option<i32> {
+        self.scores.get(name).copied()
+    }
+
+    pub fn set_title(&mut self, sessions: &SessionManager, title: TextComponent) -> io::Result<()> {
+        self.title = title;
+        if !self.shown {
+            return self.show(sessions);
+        }
+        let packet = ScoreboardObjectivePacket::update(&self.objective_name, &self.title);
+        sessions.broadcast_packet(packet, None)
+    }
+
+    pub fn set_score(
+        &mut self,
+        sessions: &SessionManager,
+        name: &str,
+        value: i32,
+    ) -> io::Result<()> {
+        self.show(sessions)?;
+        if self.scores.insert(name.to_owned(), value) == Some(value) {
+            return Ok(());
+        }
+        sessions.broadcast_packet(self.score_packet(name, Some(value)), None)
+    }
+
+    pub fn remove_score(&mut self, sessions: &SessionManager, name: &str) -> io::Result<()> {
+        if self.scores.remove(name).is_none() {
+            return Ok(());
+        }
+        sessions.broadcast_packet(self.score_packet(name, None), None)
+    }
+
+    /// Sends the whole scoreboard to a player who has just joined
+    pub async fn send_to(&self, session: &mut PlayerSession) -> io::Result<()> {
+        if !self.shown {
+            return Ok(());
+        }
+        session.send_packet(self.objective_packet()).await?;
+        session.send_packet(self.display_packet()).await?;
+        for (name, value) in &self.scores {
+            session
+                .send_packet(self.score_packet(name, Some(*value)))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Creates the objective on the clients and puts it in the sidebar, if that hasn't been done
+    fn show(&mut self, sessions: &SessionManager) -> io::Result<()> {
+        if self.shown {
+            return Ok(());
+        }
+        self.shown = true;
+        sessions.broadcast_packet(self.objective_packet(), None)?;
+        sessions.broadcast_packet(self.display_packet(), None)
+    }
+
+    fn objective_packet(&self) -> ScoreboardObjectivePacket {
+        ScoreboardObjectivePacket::create(&self.objective_name, &self.title)
+    }
+
+    fn display_packet(&self) -> DisplayScoreboardPacket {
+        DisplayScoreboardPacket {
+            slot: DisplaySlot::Sidebar,
+            objective_name: self.objective_name.clone(),
+        }
+    }
+
+    fn score_packet(&self, name: &str, value: Option<i32>) -> UpdateScorePacket {
+        UpdateScorePacket {
+            entity_name: name.to_owned(),
+            objective_name: self.objective_name.clone(),
+            value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::read_packet;
+    use tokio::io::DuplexStream;
+    use uuid::Uuid;
+
+    async fn recv<P: Packet>(client: &mut DuplexStream) -> P {
+        let mut packet = read_packet(client).await.unwrap();
+        assert_eq!(packet.read_varint().unwrap(), P::packet_id());
+        P::read_from_buffer(&mut packet).unwrap()
+    }
+
+    #[test]
+    fn test_remove_score_omits_value() {
+        let packet = UpdateScorePacket {
+            entity_name: "Steve".to_owned(),
+            objective_name: "kills".to_owned(),
+            value: None,
+        };
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.get_buffer(),
+            b"\x4D\x05Steve\x01\x05kills".as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_scores_emits_packet_sequence() {
+        let sessions = SessionManager::new();
+        let mut players = Vec::new();
+        let mut clients = Vec::new();
+        for (id, username) in ["Steve", "Alex"].into_iter().enumerate() {
+            let (client, server) = tokio::io::duplex(4096);
+            let uuid = Uuid::from_u128(id as u128);
+            let (session, _reader) = PlayerSession::from_stream(username.to_owned(), uuid, server);
+            sessions.add_session(&session);
+            players.push(session);
+            clients.push(client);
+        }
+
+        let mut scoreboard = Scoreboard::new("kills", TextComponent::new("Kills"));
+        scoreboard.set_score(&sessions, "Steve", 3).unwrap();
+        scoreboard.set_score(&sessions, "Alex", 5).unwrap();
+        scoreboard.remove_score(&sessions, "Steve").unwrap();
+
+        for client in &mut clients {
+            assert_eq!(
+                recv::<ScoreboardObjectivePacket>(client).await,
+                ScoreboardObjectivePacket::create("kills", &TextComponent::new("Kills"))
+            );
+            assert_eq!(
+                recv::<DisplayScoreboardPacket>(client).await,
+                DisplayScoreboardPacket {
+                    slot: DisplaySlot::Sidebar,
+                    objective_name: "kills".to_owned(),
+                }
+            );
+            for (name, value) in [("Steve", Some(3)), ("Alex", Some(5)), ("Steve", None)] {
+                assert_eq!(
+                    recv::<UpdateScorePacket>(client).await,
+                    UpdateScorePacket {
+                        entity_name: name.to_owned(),
+                        objective_name: "kills".to_owned(),
+                        value,
+                    }
+                );
+            }
+        }
+        assert_eq!(scoreboard.score("Alex"), Some(5));
+        assert_eq!(scoreboard.score("Steve"), None);
+    }
+}
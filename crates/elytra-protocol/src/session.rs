@@ -1,53 +1,750 @@
-use crate::packet::{send_packet, Packet};
+use crate::change_game_state::ChangeGameStatePacket;
+use crate::chat::ChatMessagePacket;
+use crate::disconnect::DisconnectPacket;
+use crate::encryption::EncryptedStream;
+use crate::entity_action::EntityActionPacket;
+use crate::entity_effect::{EntityEffectPacket, RemoveEntityEffectPacket};
+use crate::entity_metadata::{
+    EntityMetadataPacket, MetadataValue, Pose, ENTITY_FLAGS_INDEX, FLAG_CROUCHING, FLAG_SPRINTING,
+    POSE_INDEX,
+};
+use crate::entity_rotation::{EntityHeadLookPacket, EntityRotationPacket};
+use crate::experience::{experience_to_next_level, SetExperiencePacket};
+use crate::held_item_change::HeldItemChangePacket;
+use crate::packet::{frame_packet, Angle, FramedPacket, Packet};
+use crate::player_info::PlayerInfoEntry;
+use crate::player_position_and_look::PlayerPositionAndLook;
+use crate::protocol_version::ProtocolVersion;
+use crate::text_component::TextComponent;
+use elytra_common::types::{Direction, GameMode};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
 use tokio::io;
-use tokio::io::{BufWriter, ReadHalf, WriteHalf};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufWriter, ReadHalf};
 use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Notify;
 use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Health of a player at full health
+pub const MAX_HEALTH: f32 = 20.0;
+
+/// View distance used until the client says otherwise
+pub const DEFAULT_VIEW_DISTANCE: u8 = 10;
+
+/// How long to wait for a teleport to be confirmed before sending it again
+pub const TELEPORT_RESEND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Packets queued for a session before it counts as not keeping up. Its own connection waits
+/// for room at that point; anyone else sending to it disconnects it.
+pub const OUTBOUND_QUEUE_CAPACITY: usize = 1024;
+
+/// Write side of a session's transport. Boxed so sessions over TCP and in-memory pipes can be
+/// stored side by side.
+pub type SessionWriter = BufWriter<Box<dyn AsyncWrite + Send + Sync + Unpin>>;
+
+/// Queues packets for a session from outside the task that owns it, such as another player's
+/// broadcast. It never waits for room, and doesn't keep the connection open once the session is
+/// dropped.
+#[derive(Clone)]
+pub struct PacketSender {
+    outbound: mpsc::WeakSender<FramedPacket>,
+    kick: Arc<Notify>,
+}
+
+impl PacketSender {
+    /// Queues a packet without waiting, disconnecting a client whose queue is full like
+    /// [`PlayerSession::push_packet`]. Fails once the connection is closed.
+    pub fn push_packet<T: Packet>(&self, packet: T) -> io::Result<()> {
+        self.push_raw(Arc::new(frame_packet(&packet)?))
+    }
+
+    /// Queues a framed packet without waiting, like [`PacketSender::push_packet`]
+    pub fn push_raw(&self, framed: FramedPacket) -> io::Result<()> {
+        match self.outbound.upgrade() {
+            Some(outbound) if push_queued(&outbound, &self.kick, framed) => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Connection is closed",
+            )),
+        }
+    }
+
+    /// Whether the session is still around and its connection can be written to
+    pub fn is_connected(&self) -> bool {
+        self.outbound
+            .upgrade()
+            .is_some_and(|outbound| !outbound.is_closed())
+    }
+}
+
+/// Status effect a player has, counting down to when it wears off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveEffect {
+    pub amplifier: i8,
+    pub remaining_ticks: i32,
+}
 
 pub struct PlayerSession {
     pub username: String,
-    pub writer: BufWriter<WriteHalf<TcpStream>>,
+    pub uuid: Uuid,
+    /// Entity id of the player, unique among the entities the server sends
+    pub entity_id: i32,
+    /// Operator permission level, 0 for regular players up to 4
+    pub op_level: u8,
+    /// Packets waiting for the session's writer task, which owns the write side of the transport.
+    /// Sending only queues a packet, so a slow client doesn't hold up whoever sends to it.
+    outbound: mpsc::Sender<FramedPacket>,
+    /// Tells the writer task to stop and drop the connection
+    kick: Arc<Notify>,
     pub last_keep_alive_id: i64,
     pub last_keep_alive_time: Instant,
     pub last_keep_alive_response: Instant,
+    /// Round-trip time of the last answered keep-alive, in milliseconds
+    ping_ms: Option<u32>,
     pub position: (f64, f64, f64),
     pub yaw: f32,
     pub pitch: f32,
+    /// Rotation other players were last sent, in the steps entity packets use. Rotation is only
+    /// re-sent once it moves to another step, so changes too small to show don't send anything.
+    sent_yaw: Angle,
+    sent_pitch: Angle,
+    /// Currently selected hotbar slot (0-8)
+    pub selected_hotbar_slot: u8,
+    pub gamemode: GameMode,
+    /// Health points, 20 being full health
+    pub health: f32,
+    pub experience_level: i32,
+    /// Progress towards the next level, from 0 to 1
+    pub experience_progress: f32,
+    /// Experience points collected in total, which the level doesn't take away from
+    pub total_experience: i32,
+    /// Status effects the player has, by effect id
+    pub effects: HashMap<i8, ActiveEffect>,
+    pub sneaking: bool,
+    pub sprinting: bool,
+    /// Entity id of the vehicle the player is riding
+    pub vehicle_id: Option<i32>,
+    /// Protocol version the client sent in its handshake
+    pub protocol_version: ProtocolVersion,
+    /// Radius, in chunks, of the area sent to the player
+    pub view_distance: u8,
+    /// Whether the dimension the player is in has sky light, which decides if light updates carry
+    /// sky light arrays
+    pub has_skylight: bool,
+    /// Teleport the client hasn't confirmed yet. Movement from the client is ignored until it
+    /// does, since it was sent before the client knew about the teleport.
+    pub pending_teleport_id: Option<i32>,
+    /// When the pending teleport was last sent
+    pub teleport_sent_at: Instant,
+    /// Last teleport id handed out
+    last_teleport_id: i32,
 }
 
 impl PlayerSession {
-    pub fn new(username: String, socket: TcpStream) -> (Self, ReadHalf<TcpStream>) {
-        let (read, write) = tokio::io::split(socket);
+    /// Creates a session over a client connection, encrypted with `shared_secret` if the login
+    /// negotiated one
+    pub fn new(
+        username: String,
+        uuid: Uuid,
+        socket: TcpStream,
+        shared_secret: Option<[u8; 16]>,
+    ) -> (Self, ReadHalf<EncryptedStream<TcpStream>>) {
+        Self::from_stream(username, uuid, EncryptedStream::new(socket, shared_secret))
+    }
+
+    /// Creates a session over any bidirectional stream, such as a `tokio::io::DuplexStream` in
+    /// tests. Returns the session along with the read half of the stream. The write half is
+    /// handed to a writer task, so this must be called from within a Tokio runtime.
+    pub fn from_stream<S>(username: String, uuid: Uuid, stream: S) -> (Self, ReadHalf<S>)
+    where
+        S: AsyncRead + AsyncWrite + Send + Sync + 'static,
+    {
+        let (read, write) = tokio::io::split(stream);
+        let (outbound, queued) = mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
+        let kick = Arc::new(Notify::new());
+        tokio::spawn(write_queued(
+            queued,
+            BufWriter::new(Box::new(write)),
+            kick.clone(),
+        ));
         (
             Self {
                 username,
-                writer: BufWriter::new(write),
+                uuid,
+                entity_id: 0,
+                op_level: 0,
+                outbound,
+                kick,
                 last_keep_alive_id: 0,
                 last_keep_alive_time: Instant::now(),
                 last_keep_alive_response: Instant::now(),
+                ping_ms: None,
                 position: (0.0, 64.0, 0.0),
                 yaw: 0.0,
                 pitch: 0.0,
+                sent_yaw: Angle::default(),
+                sent_pitch: Angle::default(),
+                selected_hotbar_slot: 0,
+                gamemode: GameMode::default(),
+                health: MAX_HEALTH,
+                experience_level: 0,
+                experience_progress: 0.0,
+                total_experience: 0,
+                effects: HashMap::new(),
+                sneaking: false,
+                sprinting: false,
+                vehicle_id: None,
+                protocol_version: ProtocolVersion::default(),
+                view_distance: DEFAULT_VIEW_DISTANCE,
+                has_skylight: true,
+                pending_teleport_id: None,
+                teleport_sent_at: Instant::now(),
+                last_teleport_id: 0,
             },
             read,
         )
     }
 
+    /// Queues a packet for the client, waiting for room if the queue is full. Fails once the
+    /// connection is closed.
     pub async fn send_packet<T: Packet>(&mut self, packet: T) -> io::Result<()> {
-        send_packet(packet, &mut self.writer).await
+        self.send_raw(Arc::new(frame_packet(&packet)?)).await
+    }
+
+    /// Queues a packet already framed by [`crate::packet::frame_packet`], such as a cached chunk
+    pub async fn send_raw(&mut self, framed: FramedPacket) -> io::Result<()> {
+        self.outbound
+            .send(framed)
+            .await
+            .map_err(|_| self.closed_error())
+    }
+
+    /// Queues a packet without waiting, for packets sent on someone else's behalf such as
+    /// broadcasts. A client whose queue is full isn't keeping up, so it is disconnected rather
+    /// than allowed to hold up the sender; that doesn't count as a failed send.
+    pub fn push_packet<T: Packet>(&mut self, packet: T) -> io::Result<()> {
+        self.push_raw(Arc::new(frame_packet(&packet)?))
+    }
+
+    /// Queues a framed packet without waiting, like [`PlayerSession::push_packet`]
+    pub fn push_raw(&mut self, framed: FramedPacket) -> io::Result<()> {
+        if push_queued(&self.outbound, &self.kick, framed) {
+            Ok(())
+        } else {
+            Err(self.closed_error())
+        }
+    }
+
+    /// Handle other tasks can queue packets for this session through
+    pub fn packet_sender(&self) -> PacketSender {
+        PacketSender {
+            outbound: self.outbound.downgrade(),
+            kick: self.kick.clone(),
+        }
+    }
+
+    /// Kicks the player, showing the reason on their disconnect screen. The connection is closed
+    /// once everything queued before the Disconnect packet has been written.
+    pub fn disconnect(&mut self, reason: &str) -> io::Result<()> {
+        self.push_packet(DisconnectPacket::new(reason))?;
+        self.push_raw(Arc::new(Vec::new()))
+    }
+
+    /// Whether the connection can still be written to. It closes when writing to the client
+    /// fails or the client falls too far behind.
+    pub fn is_connected(&self) -> bool {
+        !self.outbound.is_closed()
+    }
+
+    /// Completes once the connection can no longer be written to
+    pub fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let outbound = self.outbound.clone();
+        async move { outbound.closed().await }
+    }
+
+    fn closed_error(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            format!("Connection to {} is closed", self.username),
+        )
+    }
+
+    /// Reasserts the selected hotbar slot on the client. Sent after join and respawn, where the
+    /// client would otherwise keep whatever slot it had selected locally.
+    pub async fn sync_held_item(&mut self) -> io::Result<()> {
+        self.send_packet(HeldItemChangePacket::new(self.selected_hotbar_slot))
+            .await
+    }
+
+    /// Switches the player to another game mode and tells the client about it. The packet is
+    /// queued without waiting, like a broadcast, so the session manager can do this along with
+    /// updating everyone's tab list.
+    pub fn set_gamemode(&mut self, gamemode: GameMode) -> io::Result<()> {
+        self.gamemode = gamemode;
+        self.push_packet(ChangeGameStatePacket::change_gamemode(gamemode))
+    }
+
+    /// Gives the player experience points, levelling them up as the bar fills, and updates their
+    /// experience bar. Negative amounts take points away, down to an empty bar at level 0.
+    pub fn add_experience(&mut self, points: i32) -> io::Result<()> {
+        self.total_experience = self.total_experience.saturating_add(points).max(0);
+        let needed = |level| experience_to_next_level(level) as f32;
+        self.experience_progress += points as f32 / needed(self.experience_level);
+        while self.experience_progress >= 1.0 {
+            let left_over = (self.experience_progress - 1.0) * needed(self.experience_level);
+            self.experience_level += 1;
+            self.experience_progress = left_over / needed(self.experience_level);
+        }
+        while self.experience_progress < 0.0 && self.experience_level > 0 {
+            let missing = self.experience_progress * needed(self.experience_level);
+            self.experience_level -= 1;
+            self.experience_progress = 1.0 + missing / needed(self.experience_level);
+        }
+        self.experience_progress = self.experience_progress.clamp(0.0, 1.0);
+        self.push_packet(self.experience_packet())
+    }
+
+    /// Gives the player whole levels, keeping the progress towards the next one
+    pub fn add_experience_levels(&mut self, levels: i32) -> io::Result<()> {
+        self.experience_level = self.experience_level.saturating_add(levels).max(0);
+        self.push_packet(self.experience_packet())
+    }
+
+    /// Set Experience showing the player's current level and bar
+    pub fn experience_packet(&self) -> SetExperiencePacket {
+        SetExperiencePacket::new(
+            self.experience_progress,
+            self.experience_level,
+            self.total_experience,
+        )
+    }
+
+    /// Gives the player a status effect lasting `duration` ticks, replacing any they already have
+    /// with the same id. Returns the Entity Effect showing it, for everyone to be sent.
+    pub fn add_effect(
+        &mut self,
+        effect_id: i8,
+        amplifier: i8,
+        duration: i32,
+    ) -> EntityEffectPacket {
+        self.effects.insert(
+            effect_id,
+            ActiveEffect {
+                amplifier,
+                remaining_ticks: duration,
+            },
+        );
+        EntityEffectPacket::new(self.entity_id, effect_id, amplifier, duration)
+    }
+
+    /// Counts the player's effects down by a tick, removing those that wear off. Returns the
+    /// packets removing them.
+    pub fn tick_effects(&mut self) -> Vec<RemoveEntityEffectPacket> {
+        let entity_id = self.entity_id;
+        let mut expired = Vec::new();
+        self.effects.retain(|&effect_id, effect| {
+            effect.remaining_ticks -= 1;
+            if effect.remaining_ticks > 0 {
+                return true;
+            }
+            expired.push(RemoveEntityEffectPacket {
+                entity_id,
+                effect_id,
+            });
+            false
+        });
+        expired
+    }
+
+    /// Shows a message from the server in the player's chat box
+    pub fn send_system_message(&mut self, message: &TextComponent) -> io::Result<()> {
+        self.push_packet(ChatMessagePacket::system(message))
+    }
+
+    /// Tab list entry describing this player
+    pub fn player_info_entry(&self) -> PlayerInfoEntry {
+        PlayerInfoEntry {
+            uuid: self.uuid,
+            name: self.username.clone(),
+            gamemode: self.gamemode,
+            ping: self
+                .ping_ms
+                .map_or(0, |ping| i32::try_from(ping).unwrap_or(i32::MAX)),
+        }
+    }
+
+    /// Updates the sneaking and sprinting flags from an Entity Action. Returns whether anything
+    /// other players can see has changed.
+    pub fn apply_entity_action(&mut self, action_id: i32) -> bool {
+        let (flag, value) = match action_id {
+            EntityActionPacket::START_SNEAKING => (&mut self.sneaking, true),
+            EntityActionPacket::STOP_SNEAKING => (&mut self.sneaking, false),
+            EntityActionPacket::START_SPRINTING => (&mut self.sprinting, true),
+            EntityActionPacket::STOP_SPRINTING => (&mut self.sprinting, false),
+            _ => return false,
+        };
+        std::mem::replace(flag, value) != value
+    }
+
+    /// Entity Metadata with the player's flags and pose, for showing other players a crouch
+    pub fn pose_metadata(&self) -> EntityMetadataPacket {
+        let mut flags = 0;
+        if self.sneaking {
+            flags |= FLAG_CROUCHING;
+        }
+        if self.sprinting {
+            flags |= FLAG_SPRINTING;
+        }
+        let pose = if self.sneaking {
+            Pose::Sneaking
+        } else {
+            Pose::Standing
+        };
+        EntityMetadataPacket::new(self.entity_id)
+            .with(ENTITY_FLAGS_INDEX, MetadataValue::Byte(flags))
+            .with(POSE_INDEX, MetadataValue::Pose(pose))
     }
 
     pub fn should_send_keep_alive(&self) -> bool {
         self.last_keep_alive_time.elapsed() >= Duration::from_secs(10)
     }
 
+    /// Records a keep-alive response from the client. If it answers the outstanding keep-alive,
+    /// the round trip becomes the player's ping, which is returned.
+    pub fn record_keep_alive_response(&mut self, keep_alive_id: i64) -> Option<u32> {
+        if keep_alive_id != self.last_keep_alive_id {
+            return None;
+        }
+        self.last_keep_alive_response = Instant::now();
+        let round_trip = self
+            .last_keep_alive_response
+            .duration_since(self.last_keep_alive_time);
+        let ping_ms = u32::try_from(round_trip.as_millis()).unwrap_or(u32::MAX);
+        self.ping_ms = Some(ping_ms);
+        Some(ping_ms)
+    }
+
+    /// Latency measured by the last answered keep-alive, or `None` before the client has
+    /// answered one
+    pub fn ping_ms(&self) -> Option<u32> {
+        self.ping_ms
+    }
+
     pub fn has_timed_out(&self) -> bool {
         self.last_keep_alive_response.elapsed() >= Duration::from_secs(30)
     }
 
+    /// Unit vector pointing where the player is looking
+    pub fn facing(&self) -> (f64, f64, f64) {
+        let yaw = (self.yaw as f64).to_radians();
+        let pitch = (self.pitch as f64).to_radians();
+        (
+            -pitch.cos() * yaw.sin(),
+            -pitch.sin(),
+            pitch.cos() * yaw.cos(),
+        )
+    }
+
+    /// Horizontal direction the player is facing, ignoring whether they look up or down
+    pub fn facing_cardinal(&self) -> Direction {
+        Direction::from_yaw(self.yaw)
+    }
+
+    /// Entity Rotation and Head Look showing other players where this player now looks, if that
+    /// has moved to another angle step since they were last sent
+    pub fn rotation_update(&mut self) -> Option<(EntityRotationPacket, EntityHeadLookPacket)> {
+        let yaw = Angle::from_degrees(self.yaw);
+        let pitch = Angle::from_degrees(self.pitch);
+        if (yaw, pitch) == (self.sent_yaw, self.sent_pitch) {
+            return None;
+        }
+        self.sent_yaw = yaw;
+        self.sent_pitch = pitch;
+        Some((
+            EntityRotationPacket {
+                entity_id: self.entity_id,
+                yaw,
+                pitch,
+                // Whether the player is on the ground isn't tracked yet
+                on_ground: true,
+            },
+            EntityHeadLookPacket {
+                entity_id: self.entity_id,
+                head_yaw: yaw,
+            },
+        ))
+    }
+
     pub fn update_position(&mut self, x: f64, y: f64, z: f64, yaw: f32, pitch: f32) {
         self.position = (x, y, z);
         self.yaw = yaw;
         self.pitch = pitch;
     }
+
+    /// Moves the player and sends them a Player Position And Look with a new teleport id, which
+    /// stays pending until the client confirms it
+    pub async fn teleport(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+    ) -> io::Result<()> {
+        self.update_position(x, y, z, yaw, pitch);
+        self.last_teleport_id = self.last_teleport_id.wrapping_add(1);
+        self.pending_teleport_id = Some(self.last_teleport_id);
+        match self.pending_teleport() {
+            Some(packet) => self.send_packet(packet).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Player Position And Look for the pending teleport, marking it as sent now
+    fn pending_teleport(&mut self) -> Option<PlayerPositionAndLook> {
+        let teleport_id = self.pending_teleport_id?;
+        self.teleport_sent_at = Instant::now();
+        let (x, y, z) = self.position;
+        Some(PlayerPositionAndLook::new(
+            x,
+            y,
+            z,
+            self.yaw,
+            self.pitch,
+            0,
+            teleport_id,
+        ))
+    }
+
+    /// Clears the pending teleport if the client confirmed that one. Returns whether it did.
+    pub fn confirm_teleport(&mut self, teleport_id: i32) -> bool {
+        if self.pending_teleport_id != Some(teleport_id) {
+            return false;
+        }
+        self.pending_teleport_id = None;
+        true
+    }
+
+    /// Whether movement from the client can be applied. While a teleport is pending it is
+    /// ignored, and the teleport is sent again, without waiting, if the client has taken too
+    /// long to confirm it.
+    pub fn accepts_movement(&mut self) -> io::Result<bool> {
+        if self.pending_teleport_id.is_none() {
+            return Ok(true);
+        }
+        if self.teleport_sent_at.elapsed() >= TELEPORT_RESEND_INTERVAL {
+            if let Some(packet) = self.pending_teleport() {
+                self.push_packet(packet)?;
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Queues a framed packet without waiting, kicking the session if its queue is full. Returns
+/// false if the queue is closed.
+fn push_queued(outbound: &mpsc::Sender<FramedPacket>, kick: &Notify, framed: FramedPacket) -> bool {
+    match outbound.try_send(framed) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            kick.notify_one();
+            true
+        }
+        Err(TrySendError::Closed(_)) => false,
+    }
+}
+
+/// Writes the packets queued for a session until every sender is gone, writing fails or the
+/// session is kicked. Returning drops the queue and the write side of the transport.
+async fn write_queued(
+    mut queued: mpsc::Receiver<FramedPacket>,
+    mut writer: SessionWriter,
+    kick: Arc<Notify>,
+) {
+    tokio::select! {
+        _ = kick.notified() => {}
+        _ = write_until_closed(&mut queued, &mut writer) => {}
+    }
+}
+
+/// Packets that are already queued are written together, with one flush for the lot. An empty
+/// packet, which framing never produces, asks for the connection to be closed after everything
+/// before it.
+async fn write_until_closed(queued: &mut mpsc::Receiver<FramedPacket>, writer: &mut SessionWriter) {
+    while let Some(framed) = queued.recv().await {
+        let mut closing = framed.is_empty();
+        if writer.write_all(&framed).await.is_err() {
+            return;
+        }
+        while !closing {
+            let Ok(framed) = queued.try_recv() else {
+                break;
+            };
+            closing = framed.is_empty();
+            if writer.write_all(&framed).await.is_err() {
+                return;
+            }
+        }
+        if writer.flush().await.is_err() {
+            return;
+        }
+        if closing {
+            let _ = writer.shutdown().await;
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::read_packet;
+
+    #[tokio::test]
+    async fn test_teleport_stays_pending_until_confirmed() {
+        let (mut client, server) = tokio::io::duplex(256);
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let (mut session, _reader) = PlayerSession::from_stream("Steve".to_owned(), uuid, server);
+        assert!(session.accepts_movement().unwrap());
+
+        session.teleport(4.5, 70.0, -2.5, 90.0, 0.0).await.unwrap();
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            PlayerPositionAndLook::packet_id()
+        );
+        let teleport = PlayerPositionAndLook::read_from_buffer(&mut packet).unwrap();
+        assert_eq!((teleport.x, teleport.y, teleport.z), (4.5, 70.0, -2.5));
+        assert_eq!(session.pending_teleport_id, Some(teleport.teleport_id));
+        assert!(!session.accepts_movement().unwrap());
+
+        // Only the latest teleport id clears it
+        assert!(!session.confirm_teleport(teleport.teleport_id + 1));
+        assert!(!session.accepts_movement().unwrap());
+        assert!(session.confirm_teleport(teleport.teleport_id));
+        assert!(session.accepts_movement().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_teleport_is_resent() {
+        let (mut client, server) = tokio::io::duplex(256);
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let (mut session, _reader) = PlayerSession::from_stream("Steve".to_owned(), uuid, server);
+        session.teleport(0.0, 64.0, 0.0, 0.0, 0.0).await.unwrap();
+        read_packet(&mut client).await.unwrap();
+
+        session.teleport_sent_at = Instant::now() - TELEPORT_RESEND_INTERVAL;
+        assert!(!session.accepts_movement().unwrap());
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            PlayerPositionAndLook::packet_id()
+        );
+        let teleport = PlayerPositionAndLook::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(session.pending_teleport_id, Some(teleport.teleport_id));
+    }
+
+    #[tokio::test]
+    async fn test_facing() {
+        let (_client, server) = tokio::io::duplex(64);
+        let (mut session, _reader) =
+            PlayerSession::from_stream("Steve".to_owned(), Uuid::nil(), server);
+        for (yaw, direction) in [
+            (0.0, Direction::South),
+            (90.0, Direction::West),
+            (180.0, Direction::North),
+            (270.0, Direction::East),
+            (-90.0, Direction::East),
+            (400.0, Direction::South),
+        ] {
+            session.yaw = yaw;
+            assert_eq!(session.facing_cardinal(), direction, "yaw {}", yaw);
+        }
+
+        session.yaw = 0.0;
+        session.pitch = 0.0;
+        let (x, y, z) = session.facing();
+        assert!(x.abs() < 1e-9 && y.abs() < 1e-9 && (z - 1.0).abs() < 1e-9);
+
+        // Looking straight down
+        session.pitch = 90.0;
+        let (_, y, _) = session.facing();
+        assert!((y + 1.0).abs() < 1e-9);
+
+        session.yaw = 123.0;
+        session.pitch = -37.5;
+        let (x, y, z) = session.facing();
+        assert!((x * x + y * y + z * z - 1.0).abs() < 1e-9);
+        assert!(x < 0.0 && y > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_round_trip_sets_ping() {
+        let (_client, server) = tokio::io::duplex(64);
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let (mut session, _reader) = PlayerSession::from_stream("Steve".to_owned(), uuid, server);
+        assert_eq!(session.ping_ms(), None);
+
+        session.last_keep_alive_id = 42;
+        session.last_keep_alive_time = Instant::now();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // A stale id doesn't count as an answer
+        assert_eq!(session.record_keep_alive_response(41), None);
+        assert_eq!(session.ping_ms(), None);
+
+        let ping_ms = session.record_keep_alive_response(42).unwrap();
+        assert!((50..500).contains(&ping_ms), "ping was {}ms", ping_ms);
+        assert_eq!(session.ping_ms(), Some(ping_ms));
+        assert_eq!(session.player_info_entry().ping, ping_ms as i32);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_sends_reason_and_closes() {
+        let (mut client, server) = tokio::io::duplex(256);
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let (mut session, _reader) = PlayerSession::from_stream("Steve".to_owned(), uuid, server);
+        let closed = session.closed();
+
+        session.push_packet(HeldItemChangePacket::new(3)).unwrap();
+        session.disconnect("Timed out").unwrap();
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            HeldItemChangePacket::packet_id()
+        );
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(packet.read_varint().unwrap(), DisconnectPacket::packet_id());
+        let disconnect = DisconnectPacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(disconnect.reason, r#"{"text":"Timed out"}"#);
+
+        let error = read_packet(&mut client).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        closed.await;
+    }
+
+    #[tokio::test]
+    async fn test_set_gamemode_sends_change_game_state() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let uuid = Uuid::new_v3(&Uuid::NAMESPACE_DNS, b"OfflinePlayer:Steve");
+        let (mut session, _reader) = PlayerSession::from_stream("Steve".to_owned(), uuid, server);
+
+        session.set_gamemode(GameMode::Spectator).unwrap();
+        assert_eq!(session.gamemode, GameMode::Spectator);
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            ChangeGameStatePacket::packet_id()
+        );
+        let change_game_state = ChangeGameStatePacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(
+            change_game_state,
+            ChangeGameStatePacket::change_gamemode(GameMode::Spectator)
+        );
+    }
 }
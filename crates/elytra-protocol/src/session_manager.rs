@@ -1,138 +1,988 @@
-use crate::packet::Packet;
+use crate::destroy_entities::DestroyEntitiesPacket;
+use crate::entity_action::EntityActionPacket;
+use crate::entity_velocity::EntityVelocityPacket;
+use crate::experience::CollectItemPacket;
+use crate::packet::{frame_packet, FramedPacket, Packet};
+use crate::player_info::{PlayerInfoEntry, PlayerInfoPacket};
 use crate::player_position_and_look::PlayerPositionAndLook;
-use crate::session::PlayerSession;
+use crate::session::{PacketSender, PlayerSession};
+use crate::vehicle::SetPassengersPacket;
+use elytra_common::types::GameMode;
 use std::collections::{HashMap, HashSet};
 use std::io;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use uuid::Uuid;
 
+/// Horizontal speed, in blocks per tick, a player is knocked back with when hit
+const KNOCKBACK_HORIZONTAL: f64 = 0.4;
+/// Upward speed, in blocks per tick, a player is knocked back with when hit
+const KNOCKBACK_VERTICAL: f64 = 0.4;
+
+/// Index of the players online, by username. Each player's [`PlayerSession`] is owned by the
+/// task running their connection, which passes it to the methods that change it. The index only
+/// holds what other tasks need to reach a player: a [`PacketSender`] for their queue, along with
+/// the little of their state that other players see.
+///
+/// The index is locked inside these methods only, and none of them wait, so the lock is never
+/// held while a slow player is waited for.
 pub struct SessionManager {
-    sessions: HashMap<String, PlayerSession>,
+    players: RwLock<Players>,
+}
+
+#[derive(Default)]
+struct Players {
+    by_name: HashMap<String, OnlinePlayer>,
+    /// Secondary index from player UUID to username, kept in sync with `by_name`
+    uuid_index: HashMap<Uuid, String>,
+    /// Places held by [`JoinSlot`]s for players who are still joining
+    joining: usize,
+}
+
+impl Players {
+    fn add(&mut self, session: &PlayerSession) -> Vec<PlayerInfoEntry> {
+        let online = self
+            .by_name
+            .values()
+            .map(|player| player.info.clone())
+            .collect();
+        let uuid = session.uuid;
+        self.uuid_index.insert(uuid, session.username.clone());
+        if let Some(replaced) = self
+            .by_name
+            .insert(session.username.clone(), OnlinePlayer::new(session))
+        {
+            // The username was previously bound to another uuid, drop its stale index entry
+            if replaced.info.uuid != uuid {
+                self.uuid_index.remove(&replaced.info.uuid);
+            }
+        }
+        online
+    }
+}
+
+/// The server already has as many players as it allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerFull;
+
+/// Place on the player list held for a player who is still joining, so players logging in at
+/// the same time can't take more places than there are. The place is given up when the slot is
+/// dropped without being filled.
+pub struct JoinSlot<'a> {
+    sessions: Option<&'a SessionManager>,
+}
+
+impl JoinSlot<'_> {
+    /// Lists the player the slot was held for, like [`SessionManager::add_session`]
+    pub fn add_session(mut self, session: &PlayerSession) -> Vec<PlayerInfoEntry> {
+        let sessions = self.sessions.take().expect("slot is only filled once");
+        let mut players = sessions.players_mut();
+        players.joining -= 1;
+        players.add(session)
+    }
+}
+
+impl Drop for JoinSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(sessions) = self.sessions.take() {
+            sessions.players_mut().joining -= 1;
+        }
+    }
+}
+
+/// What the index knows about an online player
+#[derive(Clone)]
+pub struct OnlinePlayer {
+    pub entity_id: i32,
+    /// Tab list entry, with the player's UUID, game mode and latency
+    pub info: PlayerInfoEntry,
+    /// Entity id of the vehicle the player is riding
+    pub vehicle_id: Option<i32>,
+    pub sender: PacketSender,
+}
+
+impl OnlinePlayer {
+    fn new(session: &PlayerSession) -> Self {
+        Self {
+            entity_id: session.entity_id,
+            info: session.player_info_entry(),
+            vehicle_id: session.vehicle_id,
+            sender: session.packet_sender(),
+        }
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SessionManager {
     pub fn new() -> Self {
         Self {
-            sessions: HashMap::new(),
+            players: RwLock::new(Players::default()),
+        }
+    }
+
+    /// Lists a player as online, replacing anyone listed under the same name. Returns the tab
+    /// list entries of the players who were already online, taken together with the change so
+    /// a player joining at the same time can't be missed.
+    pub fn add_session(&self, session: &PlayerSession) -> Vec<PlayerInfoEntry> {
+        self.players_mut().add(session)
+    }
+
+    /// Holds a place on the player list for a player who is logging in, unless `max_players`
+    /// are already online or joining. Players who may `bypass` the limit always get a place.
+    pub fn try_reserve_slot(
+        &self,
+        max_players: usize,
+        bypass: bool,
+    ) -> Result<JoinSlot<'_>, ServerFull> {
+        let mut players = self.players_mut();
+        if !bypass && players.by_name.len() + players.joining >= max_players {
+            return Err(ServerFull);
         }
+        players.joining += 1;
+        Ok(JoinSlot {
+            sessions: Some(self),
+        })
     }
 
-    pub fn add_session(&mut self, session: PlayerSession) {
-        self.sessions.insert(session.username.clone(), session);
+    /// Takes a player off the list. Returns whether they were on it.
+    pub fn remove_session(&self, username: &str) -> bool {
+        let mut players = self.players_mut();
+        let Some(player) = players.by_name.remove(username) else {
+            return false;
+        };
+        players.uuid_index.remove(&player.info.uuid);
+        true
     }
 
-    pub fn remove_session(&mut self, username: &str) -> Option<PlayerSession> {
-        self.sessions.remove(username)
+    /// Takes a player who disconnected off the list, then removes them from everyone else's tab
+    /// list and world
+    pub fn handle_disconnect(&self, session: &PlayerSession) -> io::Result<()> {
+        if !self.remove_session(&session.username) {
+            return Ok(());
+        }
+        self.broadcast_packet(PlayerInfoPacket::remove_player(vec![session.uuid]), None)?;
+        self.broadcast_packet(DestroyEntitiesPacket::new(vec![session.entity_id]), None)
     }
 
-    pub fn get_session(&mut self, username: &str) -> Option<&mut PlayerSession> {
-        self.sessions.get_mut(username)
+    /// Refreshes what other players see of a player, after their session has changed
+    pub fn update_session(&self, session: &PlayerSession) {
+        if let Some(player) = self.players_mut().by_name.get_mut(&session.username) {
+            *player = OnlinePlayer::new(session);
+        }
     }
 
-    /// Broadcast a packet to all players except those specified
-    pub async fn broadcast_packet_except<T: Packet + Clone>(
-        &mut self,
+    /// What the index knows about the player with the given username
+    pub fn get_session(&self, username: &str) -> Option<OnlinePlayer> {
+        self.players().by_name.get(username).cloned()
+    }
+
+    /// Get a player by their UUID
+    pub fn get_session_by_uuid(&self, uuid: &Uuid) -> Option<OnlinePlayer> {
+        let players = self.players();
+        let username = players.uuid_index.get(uuid)?;
+        players.by_name.get(username).cloned()
+    }
+
+    /// Handles a player attacking the entity with the given id. Damage isn't applied yet; a hit
+    /// player is only knocked back in the direction the attacker is facing.
+    pub fn handle_attack(&self, attacker: &PlayerSession, target_entity_id: i32) -> io::Result<()> {
+        let target_is_player = self.players().by_name.iter().any(|(username, player)| {
+            player.entity_id == target_entity_id && *username != attacker.username
+        });
+        if !target_is_player {
+            return Ok(());
+        }
+
+        let yaw = (attacker.yaw as f64).to_radians();
+        let knockback = EntityVelocityPacket::new(
+            target_entity_id,
+            (
+                -yaw.sin() * KNOCKBACK_HORIZONTAL,
+                KNOCKBACK_VERTICAL,
+                yaw.cos() * KNOCKBACK_HORIZONTAL,
+            ),
+        );
+        self.broadcast_packet(knockback, None)
+    }
+
+    /// Broadcast a packet to all players except those specified. A failed send doesn't stop the
+    /// packet from reaching the remaining players; the first error is returned afterwards.
+    /// Players too far behind to take the packet are disconnected instead of waited for, and
+    /// players whose connection already closed are skipped; their own connection task cleans
+    /// them up.
+    pub fn broadcast_packet_except<T: Packet>(
+        &self,
         packet: T,
         excluded_players: &HashSet<String>,
     ) -> io::Result<()> {
-        for (username, session) in self.sessions.iter_mut() {
-            if !excluded_players.contains(username) {
-                session.send_packet(packet.clone()).await?;
-            }
-        }
-        Ok(())
+        let framed = Arc::new(frame_packet(&packet)?);
+        self.push_to_each(framed, |username| !excluded_players.contains(username))
     }
 
-    /// Broadcast a packet only to specified players
-    pub async fn broadcast_packet_only<T: Packet + Clone>(
-        &mut self,
+    /// Broadcast a packet only to specified players. A failed send doesn't stop the packet from
+    /// reaching the remaining players; the first error is returned afterwards.
+    pub fn broadcast_packet_only<T: Packet>(
+        &self,
         packet: T,
         included_players: &HashSet<String>,
     ) -> io::Result<()> {
-        for username in included_players {
-            if let Some(session) = self.sessions.get_mut(username) {
-                session.send_packet(packet.clone()).await?;
-            }
-        }
-        Ok(())
+        let framed = Arc::new(frame_packet(&packet)?);
+        self.push_to_each(framed, |username| included_players.contains(username))
     }
 
     /// Broadcast a packet to all players except one
-    pub async fn broadcast_packet<T: Packet + Clone>(
-        &mut self,
+    pub fn broadcast_packet<T: Packet>(
+        &self,
         packet: T,
         except_username: Option<&str>,
     ) -> io::Result<()> {
-        if let Some(username) = except_username {
-            let mut excluded = HashSet::new();
-            excluded.insert(username.to_string());
-            self.broadcast_packet_except(packet, &excluded).await
-        } else {
-            let empty_set = HashSet::new();
-            self.broadcast_packet_except(packet, &empty_set).await
+        self.broadcast_raw(Arc::new(frame_packet(&packet)?), except_username)
+    }
+
+    /// Sends a packet already framed by [`crate::packet::frame_packet`] to all players except
+    /// one, so the packet is serialized once however many players receive it. A failed send
+    /// doesn't stop the packet from reaching the remaining players.
+    pub fn broadcast_raw(
+        &self,
+        framed: FramedPacket,
+        except_username: Option<&str>,
+    ) -> io::Result<()> {
+        self.push_to_each(framed, |username| Some(username) != except_username)
+    }
+
+    /// Queues a framed packet for every connected player `include` accepts, without waiting
+    fn push_to_each(&self, framed: FramedPacket, include: impl Fn(&str) -> bool) -> io::Result<()> {
+        let mut result = Ok(());
+        for (username, player) in &self.players().by_name {
+            if include(username) && player.sender.is_connected() {
+                let sent = player.sender.push_raw(framed.clone());
+                result = result.and(sent);
+            }
         }
+        result
     }
 
     /// Broadcast position updates to specific players
-    pub async fn broadcast_position_updates_to(
-        &mut self,
-        source_username: &str,
+    pub fn broadcast_position_updates_to(
+        &self,
+        source: &PlayerSession,
         target_players: &HashSet<String>,
     ) -> io::Result<()> {
-        if let Some(source_session) = self.sessions.get(source_username) {
-            let (x, y, z) = source_session.position;
-            let position_packet = PlayerPositionAndLook::new(
-                x,
-                y,
-                z,
-                source_session.yaw,
-                source_session.pitch,
-                0, // flags - absolute position
-                0, // teleport ID
-            );
-            self.broadcast_packet_only(position_packet, target_players)
-                .await?;
+        self.broadcast_packet_only(position_packet(source), target_players)
+    }
+
+    pub fn broadcast_position_updates(&self, source: &PlayerSession) -> io::Result<()> {
+        self.broadcast_packet(position_packet(source), Some(&source.username))
+    }
+
+    /// Applies movement sent by a player and shows it to everyone else, along with their rotation
+    /// if it changed enough to show. Movement sent before the
+    /// player confirmed a teleport is dropped; returns whether it was applied.
+    pub fn handle_player_position(
+        &self,
+        session: &mut PlayerSession,
+        (x, y, z): (f64, f64, f64),
+        yaw: f32,
+        pitch: f32,
+    ) -> io::Result<bool> {
+        if !session.accepts_movement()? {
+            return Ok(false);
         }
-        Ok(())
+        session.update_position(x, y, z, yaw, pitch);
+        let rotation = session.rotation_update();
+        self.broadcast_position_updates(session)?;
+        if let Some((rotation, head_look)) = rotation {
+            self.broadcast_packet(rotation, Some(&session.username))?;
+            self.broadcast_packet(head_look, Some(&session.username))?;
+        }
+        Ok(true)
     }
 
-    pub async fn broadcast_position_updates(&mut self, source_username: &str) -> io::Result<()> {
-        if let Some(source_session) = self.sessions.get(source_username) {
-            let (x, y, z) = source_session.position;
-            let position_packet = PlayerPositionAndLook::new(
-                x,
-                y,
-                z,
-                source_session.yaw,
-                source_session.pitch,
-                0, // flags - absolute position
-                0, // teleport ID
-            );
-            let mut excluded = HashSet::new();
-            excluded.insert(source_username.to_string());
-            self.broadcast_packet_except(position_packet, &excluded)
-                .await?;
+    /// Switches a player to another game mode and updates it in everyone's tab list
+    pub fn set_gamemode(&self, session: &mut PlayerSession, gamemode: GameMode) -> io::Result<()> {
+        session.set_gamemode(gamemode)?;
+        self.update_session(session);
+        let update = PlayerInfoPacket::update_gamemode(vec![(session.uuid, gamemode)]);
+        self.broadcast_packet(update, None)
+    }
+
+    /// Has a player pick up an experience orb worth `value` points: everyone sees the orb fly to
+    /// them and the points are added to their experience. Nothing spawns orbs yet, so this is
+    /// only the pickup half.
+    pub fn collect_experience_orb(
+        &self,
+        session: &mut PlayerSession,
+        orb_entity_id: i32,
+        value: i32,
+    ) -> io::Result<()> {
+        let collect = CollectItemPacket {
+            collected_entity_id: orb_entity_id,
+            collector_entity_id: session.entity_id,
+            pickup_item_count: 1,
+        };
+        session.add_experience(value)?;
+        self.broadcast_packet(collect, None)
+    }
+
+    /// Gives a player a status effect and shows it to everyone
+    pub fn add_effect(
+        &self,
+        session: &mut PlayerSession,
+        effect_id: i8,
+        amplifier: i8,
+        duration: i32,
+    ) -> io::Result<()> {
+        let effect = session.add_effect(effect_id, amplifier, duration);
+        self.broadcast_packet(effect, None)
+    }
+
+    /// Counts a player's status effects down by a tick, telling everyone about effects that wear
+    /// off
+    pub fn tick_effects(&self, session: &mut PlayerSession) -> io::Result<()> {
+        for removal in session.tick_effects() {
+            self.broadcast_packet(removal, None)?;
         }
         Ok(())
     }
 
-    pub async fn check_keep_alives(&mut self) -> Vec<String> {
-        let mut to_remove = Vec::new();
+    /// Applies a player's Entity Action and, if their pose or flags changed, shows the change to
+    /// everyone else
+    pub fn handle_entity_action(
+        &self,
+        session: &mut PlayerSession,
+        action: &EntityActionPacket,
+    ) -> io::Result<()> {
+        if !session.apply_entity_action(action.action_id) {
+            return Ok(());
+        }
+        self.broadcast_packet(session.pose_metadata(), Some(&session.username))
+    }
 
-        for (username, session) in self.sessions.iter() {
-            if session.has_timed_out() {
-                to_remove.push(username.clone());
-            }
+    /// Seats a player on a vehicle and shows everyone its new passengers
+    pub fn mount(&self, session: &mut PlayerSession, vehicle_id: i32) -> io::Result<()> {
+        let previous_vehicle = session.vehicle_id.replace(vehicle_id);
+        self.update_session(session);
+        if let Some(previous_vehicle) = previous_vehicle.filter(|&id| id != vehicle_id) {
+            self.broadcast_passengers(previous_vehicle)?;
         }
+        self.broadcast_passengers(vehicle_id)
+    }
+
+    /// Takes a player off whatever they are riding
+    pub fn dismount(&self, session: &mut PlayerSession) -> io::Result<()> {
+        let Some(vehicle_id) = session.vehicle_id.take() else {
+            return Ok(());
+        };
+        self.update_session(session);
+        self.broadcast_passengers(vehicle_id)
+    }
 
-        to_remove
+    /// Sends everyone the players riding a vehicle. They are listed by entity id so the order,
+    /// and with it the driver, stays the same as others get on and off.
+    fn broadcast_passengers(&self, vehicle_id: i32) -> io::Result<()> {
+        let mut passengers: Vec<i32> = self
+            .players()
+            .by_name
+            .values()
+            .filter(|player| player.vehicle_id == Some(vehicle_id))
+            .map(|player| player.entity_id)
+            .collect();
+        passengers.sort();
+        self.broadcast_packet(SetPassengersPacket::new(vehicle_id, passengers), None)
     }
 
     /// Get a set of all online players
     pub fn get_all_players(&self) -> HashSet<String> {
-        self.sessions.keys().cloned().collect()
+        self.players().by_name.keys().cloned().collect()
+    }
+
+    /// Tab list entries of all online players
+    pub fn get_player_info_entries(&self) -> Vec<PlayerInfoEntry> {
+        self.players()
+            .by_name
+            .values()
+            .map(|player| player.info.clone())
+            .collect()
     }
 
     /// Get online player names
     pub fn get_player_names(&self) -> Vec<String> {
-        self.sessions.keys().cloned().collect()
+        self.players().by_name.keys().cloned().collect()
+    }
+
+    /// Number of players online
+    pub fn player_count(&self) -> usize {
+        self.players().by_name.len()
+    }
+
+    /// Names and UUIDs of up to `limit` online players, in alphabetical order
+    pub fn player_sample(&self, limit: usize) -> Vec<(String, Uuid)> {
+        let mut sample: Vec<_> = self
+            .players()
+            .by_name
+            .iter()
+            .map(|(username, player)| (username.clone(), player.info.uuid))
+            .collect();
+        sample.sort();
+        sample.truncate(limit);
+        sample
+    }
+
+    // Critical sections are short and don't panic, so a poisoned lock is still consistent
+    fn players(&self) -> RwLockReadGuard<'_, Players> {
+        self.players
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn players_mut(&self) -> RwLockWriteGuard<'_, Players> {
+        self.players
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Player Position And Look showing other players where `source` is
+fn position_packet(source: &PlayerSession) -> PlayerPositionAndLook {
+    let (x, y, z) = source.position;
+    PlayerPositionAndLook::new(
+        x,
+        y,
+        z,
+        source.yaw,
+        source.pitch,
+        0, // flags - absolute position
+        0, // teleport ID
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity_effect::{EntityEffectPacket, RemoveEntityEffectPacket};
+    use crate::entity_rotation::{EntityHeadLookPacket, EntityRotationPacket};
+    use crate::keep_alive::KeepAlivePacket;
+    use crate::packet::read_packet;
+    use crate::packet::Angle;
+    use crate::session::OUTBOUND_QUEUE_CAPACITY;
+    use tokio::io::{AsyncReadExt, DuplexStream};
+
+    /// Creates a session backed by an in-memory pipe, returning the client end of the pipe
+    fn duplex_session(username: &str) -> (PlayerSession, DuplexStream) {
+        let (client, server) = tokio::io::duplex(4096);
+        let uuid = Uuid::new_v3(
+            &Uuid::NAMESPACE_DNS,
+            format!("OfflinePlayer:{}", username).as_bytes(),
+        );
+        let (session, _reader) = PlayerSession::from_stream(username.to_string(), uuid, server);
+        (session, client)
+    }
+
+    #[tokio::test]
+    async fn test_effect_is_removed_when_it_expires() {
+        let session_manager = SessionManager::new();
+        let (mut session, mut client) = duplex_session("Steve");
+        session.entity_id = 7;
+        session_manager.add_session(&session);
+
+        // Speed for two ticks
+        session_manager.add_effect(&mut session, 1, 0, 2).unwrap();
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            EntityEffectPacket::packet_id()
+        );
+        let effect = EntityEffectPacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(
+            (effect.entity_id, effect.effect_id, effect.duration),
+            (7, 1, 2)
+        );
+
+        session_manager.tick_effects(&mut session).unwrap();
+        assert!(session.effects.contains_key(&1));
+        session_manager.tick_effects(&mut session).unwrap();
+        assert!(session.effects.is_empty());
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            RemoveEntityEffectPacket::packet_id()
+        );
+        assert_eq!(
+            RemoveEntityEffectPacket::read_from_buffer(&mut packet).unwrap(),
+            RemoveEntityEffectPacket {
+                entity_id: 7,
+                effect_id: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_session_by_uuid() {
+        let session_manager = SessionManager::new();
+        let (session, _client) = duplex_session("Steve");
+        let uuid = session.uuid;
+        session_manager.add_session(&session);
+
+        let player = session_manager.get_session_by_uuid(&uuid).unwrap();
+        assert_eq!(player.info.name, "Steve");
+        assert_eq!(player.info.uuid, uuid);
+
+        assert!(session_manager.remove_session("Steve"));
+        assert!(session_manager.get_session_by_uuid(&uuid).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_removes_player_for_everyone_else() {
+        let session_manager = SessionManager::new();
+        let (mut steve, _steve_client) = duplex_session("Steve");
+        steve.entity_id = 7;
+        let (alex, mut alex_client) = duplex_session("Alex");
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        session_manager.handle_disconnect(&steve).unwrap();
+        assert!(session_manager.get_session("Steve").is_none());
+
+        let mut packet = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(packet.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+        assert_eq!(
+            PlayerInfoPacket::read_from_buffer(&mut packet).unwrap(),
+            PlayerInfoPacket::remove_player(vec![steve.uuid])
+        );
+        let mut packet = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            DestroyEntitiesPacket::packet_id()
+        );
+        assert_eq!(
+            DestroyEntitiesPacket::read_from_buffer(&mut packet).unwrap(),
+            DestroyEntitiesPacket::new(vec![7])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_join_slots_count_towards_the_player_limit() {
+        let session_manager = SessionManager::new();
+        let (steve, _steve_client) = duplex_session("Steve");
+        session_manager.add_session(&steve);
+
+        // Alex's place is held while they join, so nobody else gets it meanwhile
+        let slot = session_manager.try_reserve_slot(2, false).unwrap();
+        assert_eq!(
+            session_manager.try_reserve_slot(2, false).err(),
+            Some(ServerFull)
+        );
+        assert!(session_manager.try_reserve_slot(2, true).is_ok());
+
+        let (alex, _alex_client) = duplex_session("Alex");
+        let online = slot.add_session(&alex);
+        assert_eq!(online.len(), 1);
+        assert_eq!(session_manager.player_count(), 2);
+        assert!(session_manager.try_reserve_slot(2, false).is_err());
+
+        // A slot given up without joining frees its place
+        session_manager.remove_session("Alex");
+        drop(session_manager.try_reserve_slot(2, false).unwrap());
+        assert!(session_manager.try_reserve_slot(2, false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_add_session_returns_players_already_online() {
+        let session_manager = SessionManager::new();
+        let (steve, _steve_client) = duplex_session("Steve");
+        let (alex, _alex_client) = duplex_session("Alex");
+
+        assert!(session_manager.add_session(&steve).is_empty());
+        let online = session_manager.add_session(&alex);
+        assert_eq!(online, vec![steve.player_info_entry()]);
+        assert_eq!(session_manager.get_player_names().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_position_updates_reach_other_players() {
+        let session_manager = SessionManager::new();
+        let (mut steve, mut steve_client) = duplex_session("Steve");
+        let (alex, mut alex_client) = duplex_session("Alex");
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        steve.update_position(1.5, 70.0, -3.0, 90.0, 10.0);
+        session_manager.broadcast_position_updates(&steve).unwrap();
+
+        let mut packet = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            PlayerPositionAndLook::packet_id()
+        );
+        let position = PlayerPositionAndLook::read_from_buffer(&mut packet).unwrap();
+        assert_eq!((position.x, position.y, position.z), (1.5, 70.0, -3.0));
+        assert_eq!((position.yaw, position.pitch), (90.0, 10.0));
+
+        // Dropping the sessions closes the pipes, so the source sees EOF without any packet
+        drop(steve);
+        drop(alex);
+        let mut remaining = Vec::new();
+        steve_client.read_to_end(&mut remaining).await.unwrap();
+        assert!(remaining.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sub_step_rotation_sends_no_head_look() {
+        let session_manager = SessionManager::new();
+        let (mut steve, _steve_client) = duplex_session("Steve");
+        let (alex, mut alex_client) = duplex_session("Alex");
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        async fn next_packet_id(client: &mut DuplexStream) -> i32 {
+            read_packet(client).await.unwrap().read_varint().unwrap()
+        }
+
+        // An angle step is 1.40625 degrees, so turning by a fraction of a degree at a time only
+        // sends rotation when it crosses into the next step
+        let mut rotations = 0;
+        for step in 0..10 {
+            let yaw = 10.0 + step as f32 * 0.1;
+            session_manager
+                .handle_player_position(&mut steve, (0.0, 64.0, 0.0), yaw, 0.0)
+                .unwrap();
+            assert_eq!(
+                next_packet_id(&mut alex_client).await,
+                PlayerPositionAndLook::packet_id()
+            );
+            if step == 0 || Angle::from_degrees(yaw) != Angle::from_degrees(yaw - 0.1) {
+                rotations += 1;
+                assert_eq!(
+                    next_packet_id(&mut alex_client).await,
+                    EntityRotationPacket::packet_id()
+                );
+                assert_eq!(
+                    next_packet_id(&mut alex_client).await,
+                    EntityHeadLookPacket::packet_id()
+                );
+            }
+        }
+        assert_eq!(rotations, 2);
+    }
+
+    #[tokio::test]
+    async fn test_position_ignored_until_teleport_confirmed() {
+        let session_manager = SessionManager::new();
+        let (mut steve, _steve_client) = duplex_session("Steve");
+        session_manager.add_session(&steve);
+        steve.teleport(0.5, 64.0, 0.5, 0.0, 0.0).await.unwrap();
+        let teleport_id = steve.pending_teleport_id.unwrap();
+
+        let applied = session_manager
+            .handle_player_position(&mut steve, (30.0, 64.0, 30.0), 0.0, 0.0)
+            .unwrap();
+        assert!(!applied);
+        assert_eq!(steve.position, (0.5, 64.0, 0.5));
+
+        assert!(steve.confirm_teleport(teleport_id));
+        let applied = session_manager
+            .handle_player_position(&mut steve, (1.0, 64.0, 0.5), 45.0, 0.0)
+            .unwrap();
+        assert!(applied);
+        assert_eq!(steve.position, (1.0, 64.0, 0.5));
+        assert_eq!(steve.yaw, 45.0);
+    }
+
+    #[tokio::test]
+    async fn test_start_sneaking_broadcasts_metadata() {
+        use crate::entity_metadata::{
+            EntityMetadataPacket, MetadataValue, Pose, ENTITY_FLAGS_INDEX, FLAG_CROUCHING,
+            POSE_INDEX,
+        };
+
+        let session_manager = SessionManager::new();
+        let (mut steve, _steve_client) = duplex_session("Steve");
+        let (alex, mut alex_client) = duplex_session("Alex");
+        steve.entity_id = 12;
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        let action = EntityActionPacket::new(12, EntityActionPacket::START_SNEAKING);
+        session_manager
+            .handle_entity_action(&mut steve, &action)
+            .unwrap();
+        assert!(steve.sneaking);
+
+        let mut packet = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            EntityMetadataPacket::packet_id()
+        );
+        let metadata = EntityMetadataPacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(metadata.entity_id, 12);
+        match metadata.get(ENTITY_FLAGS_INDEX) {
+            Some(MetadataValue::Byte(flags)) => assert_ne!(flags & FLAG_CROUCHING, 0),
+            value => panic!("Expected entity flags, got {:?}", value),
+        }
+        assert_eq!(
+            metadata.get(POSE_INDEX),
+            Some(&MetadataValue::Pose(Pose::Sneaking))
+        );
+
+        // Only actual changes need to be shown to others
+        assert!(!steve.apply_entity_action(EntityActionPacket::START_SNEAKING));
+        assert!(steve.apply_entity_action(EntityActionPacket::STOP_SNEAKING));
+    }
+
+    #[tokio::test]
+    async fn test_attack_knocks_target_back() {
+        let session_manager = SessionManager::new();
+        let (mut steve, _steve_client) = duplex_session("Steve");
+        let (mut alex, mut alex_client) = duplex_session("Alex");
+        steve.entity_id = 1;
+        // Facing west, towards negative x
+        steve.yaw = 90.0;
+        alex.entity_id = 2;
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        session_manager.handle_attack(&steve, 2).unwrap();
+
+        let mut packet = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            EntityVelocityPacket::packet_id()
+        );
+        let velocity = EntityVelocityPacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(velocity.entity_id, 2);
+        assert!(velocity.velocity_x < -3000);
+        assert!(velocity.velocity_y > 0);
+        assert_eq!(velocity.velocity_z, 0);
+    }
+
+    #[tokio::test]
+    async fn test_mount_broadcasts_passengers() {
+        let session_manager = SessionManager::new();
+        let (mut steve, mut steve_client) = duplex_session("Steve");
+        let (mut alex, _alex_client) = duplex_session("Alex");
+        steve.entity_id = 1;
+        alex.entity_id = 2;
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        async fn read_passengers(client: &mut DuplexStream) -> SetPassengersPacket {
+            let mut packet = read_packet(client).await.unwrap();
+            assert_eq!(
+                packet.read_varint().unwrap(),
+                SetPassengersPacket::packet_id()
+            );
+            SetPassengersPacket::read_from_buffer(&mut packet).unwrap()
+        }
+
+        session_manager.mount(&mut alex, 100).unwrap();
+        session_manager.mount(&mut steve, 100).unwrap();
+        read_passengers(&mut steve_client).await;
+        assert_eq!(
+            read_passengers(&mut steve_client).await,
+            SetPassengersPacket::new(100, vec![1, 2])
+        );
+
+        session_manager.dismount(&mut steve).unwrap();
+        assert_eq!(
+            read_passengers(&mut steve_client).await,
+            SetPassengersPacket::new(100, vec![2])
+        );
+        assert_eq!(steve.vehicle_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_raw_sends_framed_bytes() {
+        let session_manager = SessionManager::new();
+        let (steve, mut steve_client) = duplex_session("Steve");
+        let (alex, mut alex_client) = duplex_session("Alex");
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        let packet = PlayerPositionAndLook::new(2.0, 65.0, -1.0, 0.0, 0.0, 0, 0);
+        let framed = Arc::new(frame_packet(&packet).unwrap());
+        session_manager
+            .broadcast_raw(framed, Some("Steve"))
+            .unwrap();
+        session_manager.remove_session("Steve");
+        drop(steve);
+
+        let mut received = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(
+            received.read_varint().unwrap(),
+            PlayerPositionAndLook::packet_id()
+        );
+        let received = PlayerPositionAndLook::read_from_buffer(&mut received).unwrap();
+        assert_eq!((received.x, received.y, received.z), (2.0, 65.0, -1.0));
+        // Nothing was sent to the excluded player before the session closed
+        assert!(read_packet(&mut steve_client).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_continues_past_closed_session() {
+        let session_manager = SessionManager::new();
+        let (steve, steve_client) = duplex_session("Steve");
+        let (alex, mut alex_client) = duplex_session("Alex");
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+        drop(steve_client);
+
+        // The first packet is only queued; writing it shows the connection is gone
+        let packet = PlayerPositionAndLook::new(0.0, 64.0, 0.0, 0.0, 0.0, 0, 0);
+        session_manager
+            .broadcast_packet(packet.clone(), None)
+            .unwrap();
+        while steve.is_connected() {
+            tokio::task::yield_now().await;
+        }
+        // Steve is skipped from then on rather than failing the broadcast
+        session_manager.broadcast_packet(packet, None).unwrap();
+
+        for _ in 0..2 {
+            let mut received = read_packet(&mut alex_client).await.unwrap();
+            assert_eq!(
+                received.read_varint().unwrap(),
+                PlayerPositionAndLook::packet_id()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_skips_dropped_session() {
+        let session_manager = SessionManager::new();
+        let (steve, _steve_client) = duplex_session("Steve");
+        let (alex, mut alex_client) = duplex_session("Alex");
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        // Steve's task ended without taking him off the list
+        drop(steve);
+        session_manager
+            .broadcast_packet(KeepAlivePacket::new(1), None)
+            .unwrap();
+
+        let mut received = read_packet(&mut alex_client).await.unwrap();
+        assert_eq!(
+            received.read_varint().unwrap(),
+            KeepAlivePacket::packet_id()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_player_falling_behind_is_kicked() {
+        let session_manager = SessionManager::new();
+        let (steve_client, steve_server) = tokio::io::duplex(1 << 20);
+        let (steve, _reader) =
+            PlayerSession::from_stream("Steve".to_owned(), Uuid::nil(), steve_server);
+        // Alex never reads, so his queue fills up behind a full pipe
+        let (_alex_client, alex_server) = tokio::io::duplex(64);
+        let (alex, _reader) =
+            PlayerSession::from_stream("Alex".to_owned(), Uuid::from_u128(1), alex_server);
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        let packet = PlayerPositionAndLook::new(0.0, 64.0, 0.0, 0.0, 0.0, 0, 0);
+        let sent = OUTBOUND_QUEUE_CAPACITY + 100;
+        for _ in 0..sent {
+            session_manager
+                .broadcast_packet(packet.clone(), None)
+                .unwrap();
+            // Lets the writer tasks catch up, as they would on a busy server
+            tokio::task::yield_now().await;
+        }
+        while alex.is_connected() {
+            tokio::task::yield_now().await;
+        }
+        assert!(steve.is_connected());
+
+        let mut steve_client = steve_client;
+        for _ in 0..sent {
+            let mut received = read_packet(&mut steve_client).await.unwrap();
+            assert_eq!(
+                received.read_varint().unwrap(),
+                PlayerPositionAndLook::packet_id()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_player_does_not_hold_up_movement() {
+        let session_manager = SessionManager::new();
+        let (mut steve, _steve_client) = duplex_session("Steve");
+        // Alex never reads, and his pipe fills up after a few packets
+        let (alex_client, alex_server) = tokio::io::duplex(64);
+        let uuid = Uuid::from_u128(1);
+        let (alex, _reader) = PlayerSession::from_stream("Alex".to_owned(), uuid, alex_server);
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+
+        for step in 0..100 {
+            let moved = session_manager.handle_player_position(
+                &mut steve,
+                (step as f64, 64.0, 0.0),
+                0.0,
+                0.0,
+            );
+            assert!(moved.unwrap());
+        }
+        drop(alex_client);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_broadcast_does_not_hold_the_lock_while_a_peer_waits() {
+        let session_manager = Arc::new(SessionManager::new());
+        let (steve, mut steve_client) = duplex_session("Steve");
+        // Alex never reads, so his own task ends up waiting for room in his queue
+        let (_alex_client, alex_server) = tokio::io::duplex(64);
+        let (mut alex, _reader) =
+            PlayerSession::from_stream("Alex".to_owned(), Uuid::from_u128(1), alex_server);
+        session_manager.add_session(&steve);
+        session_manager.add_session(&alex);
+        let alex_task = tokio::spawn(async move {
+            let mut keep_alive_id = 0;
+            while alex
+                .send_packet(KeepAlivePacket::new(keep_alive_id))
+                .await
+                .is_ok()
+            {
+                keep_alive_id += 1;
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!alex_task.is_finished());
+
+        // Other tasks keep using the index meanwhile, and broadcasts still reach everyone else
+        let broadcasting = session_manager.clone();
+        let broadcast =
+            tokio::spawn(
+                async move { broadcasting.broadcast_packet(KeepAlivePacket::new(-1), None) },
+            );
+        assert_eq!(session_manager.get_player_names().len(), 2);
+        tokio::time::timeout(std::time::Duration::from_secs(1), broadcast)
+            .await
+            .expect("Broadcasting waited for Alex")
+            .unwrap()
+            .unwrap();
+        let mut received = read_packet(&mut steve_client).await.unwrap();
+        assert_eq!(
+            received.read_varint().unwrap(),
+            KeepAlivePacket::packet_id()
+        );
+        assert_eq!(
+            KeepAlivePacket::read_from_buffer(&mut received)
+                .unwrap()
+                .keep_alive_id,
+            -1
+        );
+
+        // Alex couldn't take the broadcast, so he was disconnected, which ends his wait
+        tokio::time::timeout(std::time::Duration::from_secs(1), alex_task)
+            .await
+            .expect("Alex wasn't disconnected")
+            .unwrap();
     }
 }
@@ -0,0 +1,56 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Spawn Position (clientbound)
+/// Sets the world spawn, which compasses point to and players respawn at without a bed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnPositionPacket {
+    pub position: (i32, i32, i32),
+}
+
+impl SpawnPositionPacket {
+    pub fn new(position: (i32, i32, i32)) -> Self {
+        Self { position }
+    }
+}
+
+impl Packet for SpawnPositionPacket {
+    fn packet_id() -> i32 {
+        0x42
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            position: buffer.read_position()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        let (x, y, z) = self.position;
+        buffer.write_position(x, y, z);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_position_round_trip() {
+        let packet = SpawnPositionPacket::new((-120, 65, 36));
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.buffer.len(), 1 + 8);
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            SpawnPositionPacket::packet_id()
+        );
+        assert_eq!(
+            SpawnPositionPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+}
@@ -1,7 +1,14 @@
 ﻿use crate::packet::{MinecraftPacketBuffer, Packet};
+use crate::protocol_version::ProtocolVersion;
+use crate::session_manager::SessionManager;
 use serde_json::json;
 use tokio::io::*;
 
+/// Message of the day shown under the server's name when none is configured
+pub const DEFAULT_MOTD: &str = "An Elytra Server";
+/// Most players listed when hovering over the player count, as in vanilla
+pub const MAX_PLAYER_SAMPLE: usize = 12;
+
 pub struct StatusRequestPacket;
 
 impl Packet for StatusRequestPacket {
@@ -11,6 +18,11 @@ impl Packet for StatusRequestPacket {
     {
         Ok(StatusRequestPacket)
     }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> std::io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        Ok(())
+    }
 }
 
 pub struct StatusResponsePacket {
@@ -19,19 +31,55 @@ pub struct StatusResponsePacket {
 
 impl StatusResponsePacket {
     pub fn new() -> Self {
+        Self::for_version(ProtocolVersion::LATEST, DEFAULT_MOTD, 100)
+    }
+
+    /// Status advertising the given version, so clients on any supported version see the server
+    /// as compatible, along with the message of the day and player cap shown in the server list
+    pub fn for_version(version: ProtocolVersion, motd: &str, max_players: usize) -> Self {
+        Self::with_players(version, motd, max_players, 0, &[])
+    }
+
+    /// Status listing the players currently online, a sample of whom is shown when hovering over
+    /// the player count
+    pub fn from_session_manager(
+        session_manager: &SessionManager,
+        version: ProtocolVersion,
+        motd: &str,
+        max_players: usize,
+    ) -> Self {
+        Self::with_players(
+            version,
+            motd,
+            max_players,
+            session_manager.player_count(),
+            &session_manager.player_sample(MAX_PLAYER_SAMPLE),
+        )
+    }
+
+    fn with_players(
+        version: ProtocolVersion,
+        motd: &str,
+        max_players: usize,
+        online: usize,
+        sample: &[(String, uuid::Uuid)],
+    ) -> Self {
+        let sample: Vec<_> = sample
+            .iter()
+            .map(|(name, id)| json!({ "name": name, "id": id.hyphenated().to_string() }))
+            .collect();
         let status_json = json!({
             "version": {
-                "name": "1.16.5",
-                "protocol": 754
+                "name": version.name(),
+                "protocol": version.id()
             },
             "players": {
-                "max": 100,
-                // TODO: Online players should be fetched dynamically
-                "online": 0,
-                "sample": []
+                "max": max_players,
+                "online": online,
+                "sample": sample
             },
             "description": {
-                "text": "An Elytra Server"
+                "text": motd
             }
         });
 
@@ -41,6 +89,12 @@ impl StatusResponsePacket {
     }
 }
 
+impl Default for StatusResponsePacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Packet for StatusResponsePacket {
     fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> Result<()> {
         buffer.write_varint(Self::packet_id());
@@ -48,3 +102,86 @@ impl Packet for StatusResponsePacket {
         Ok(())
     }
 }
+
+/// Ping (serverbound)
+/// Sent after the status response. The server echoes the payload back in a [`PongPacket`], and
+/// the client shows the time that took as the server's latency.
+pub struct PingPacket {
+    pub payload: i64,
+}
+
+impl Packet for PingPacket {
+    fn packet_id() -> i32 {
+        0x01
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> Result<Self> {
+        Ok(PingPacket {
+            payload: buffer.read_i64()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i64(self.payload);
+        Ok(())
+    }
+}
+
+/// Pong (clientbound)
+/// Answers a [`PingPacket`] with the same payload. The status exchange ends here.
+pub struct PongPacket {
+    pub payload: i64,
+}
+
+impl Packet for PongPacket {
+    fn packet_id() -> i32 {
+        0x01
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> Result<Self> {
+        Ok(PongPacket {
+            payload: buffer.read_i64()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i64(self.payload);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::PlayerSession;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_status_lists_online_players() {
+        let session_manager = SessionManager::new();
+        for id in 0..14 {
+            let (_client, server) = tokio::io::duplex(64);
+            let (session, _reader) =
+                PlayerSession::from_stream(format!("Player{:02}", id), Uuid::from_u128(id), server);
+            session_manager.add_session(&session);
+        }
+
+        let response = StatusResponsePacket::from_session_manager(
+            &session_manager,
+            ProtocolVersion::LATEST,
+            DEFAULT_MOTD,
+            20,
+        );
+        let status: Value = serde_json::from_str(&response.response_json).unwrap();
+        assert_eq!(status["players"]["max"], 20);
+        assert_eq!(status["players"]["online"], 14);
+
+        let sample = status["players"]["sample"].as_array().unwrap();
+        assert_eq!(sample.len(), MAX_PLAYER_SAMPLE);
+        assert_eq!(sample[0]["name"], "Player00");
+        assert_eq!(sample[1]["id"], Uuid::from_u128(1).hyphenated().to_string());
+    }
+}
@@ -12,6 +12,12 @@ struct Tag {
     entries: Vec<i32>,
 }
 
+impl Default for TagsPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Packet for TagsPacket {
     fn packet_id() -> i32
     where
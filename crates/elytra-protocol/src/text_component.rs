@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+/// Chat component, the JSON text format used for chat messages, kick reasons and titles.
+/// Formatting that is left unset is inherited from the parent component.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextComponent {
+    pub text: String,
+    /// Named color such as "red", or a "#RRGGBB" hex color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    /// Components rendered after this one, inheriting its formatting
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<TextComponent>,
+}
+
+impl TextComponent {
+    /// Creates an unformatted component
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = Some(true);
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = Some(true);
+        self
+    }
+
+    pub fn underlined(mut self) -> Self {
+        self.underlined = Some(true);
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = Some(true);
+        self
+    }
+
+    pub fn obfuscated(mut self) -> Self {
+        self.obfuscated = Some(true);
+        self
+    }
+
+    /// Appends a child component
+    pub fn append(mut self, component: TextComponent) -> Self {
+        self.extra.push(component);
+        self
+    }
+
+    /// Serializes the component to the JSON string sent on the wire
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("text components always serialize")
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        TextComponent::new(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plain_component_json() {
+        assert_eq!(TextComponent::new("Hello").to_json(), r#"{"text":"Hello"}"#);
+    }
+
+    #[test]
+    fn test_formatted_component_json() {
+        let component = TextComponent::new("Banned")
+            .color("red")
+            .bold()
+            .append(TextComponent::new("\nAppeal on the forums").italic());
+
+        let value: serde_json::Value = serde_json::from_str(&component.to_json()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "text": "Banned",
+                "color": "red",
+                "bold": true,
+                "extra": [{"text": "\nAppeal on the forums", "italic": true}]
+            })
+        );
+
+        let read: TextComponent = serde_json::from_value(value).unwrap();
+        assert_eq!(read, component);
+    }
+}
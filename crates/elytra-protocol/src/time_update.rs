@@ -0,0 +1,62 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Time Update (clientbound)
+/// Keeps the client's clock in sync with the server. Sent every second and whenever the time is
+/// changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeUpdatePacket {
+    /// Total ticks the world has run for; not changed by commands
+    pub world_age: i64,
+    /// Ticks since the start of the first day, 24000 per day. A negative value freezes the sun
+    /// at the absolute value.
+    pub time_of_day: i64,
+}
+
+impl TimeUpdatePacket {
+    pub fn new(world_age: i64, time_of_day: i64) -> Self {
+        Self {
+            world_age,
+            time_of_day,
+        }
+    }
+}
+
+impl Packet for TimeUpdatePacket {
+    fn packet_id() -> i32 {
+        0x4E
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            world_age: buffer.read_i64()?,
+            time_of_day: buffer.read_i64()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i64(self.world_age);
+        buffer.write_i64(self.time_of_day);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_update_round_trip() {
+        let packet = TimeUpdatePacket::new(72000, 13000);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.buffer.len(), 1 + 8 + 8);
+
+        assert_eq!(buffer.read_varint().unwrap(), TimeUpdatePacket::packet_id());
+        assert_eq!(
+            TimeUpdatePacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use crate::bit_set::BitSet;
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Length in bytes of a single light array (one nibble per block in a 16x16x16 section)
+pub const LIGHT_ARRAY_LENGTH: usize = 2048;
+
+/// Update Light (clientbound)
+/// Updates light levels for a chunk column. Each mask has one bit per section, starting with the
+/// section below the world (bit 0) up to the section above it (bit 17). A light array is sent for
+/// every bit set in the sky/block light masks, in ascending bit order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateLightPacket {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// If edges should be trusted for light updates
+    pub trust_edges: bool,
+    /// Sections with sky light arrays present
+    pub sky_light_mask: i32,
+    /// Sections with block light arrays present
+    pub block_light_mask: i32,
+    /// Sections whose sky light is all zero
+    pub empty_sky_light_mask: i32,
+    /// Sections whose block light is all zero
+    pub empty_block_light_mask: i32,
+    /// One 2048 byte array per bit set in `sky_light_mask`
+    pub sky_light_arrays: Vec<Vec<u8>>,
+    /// One 2048 byte array per bit set in `block_light_mask`
+    pub block_light_arrays: Vec<Vec<u8>>,
+}
+
+/// Number of light sections in a chunk column: the 16 world sections plus one below and one above
+pub const LIGHT_SECTIONS: usize = 18;
+
+impl UpdateLightPacket {
+    /// Creates a light update with full sky light in every section and no block light. This is a
+    /// stand-in until light is actually computed.
+    pub fn full_sky_light(chunk_x: i32, chunk_z: i32) -> Self {
+        Self::for_dimension(chunk_x, chunk_z, true)
+    }
+
+    /// Creates the stand-in light update for a dimension. Dimensions without sky light, like the
+    /// Nether and the End, get no sky light arrays and an empty sky light mask.
+    pub fn for_dimension(chunk_x: i32, chunk_z: i32, has_skylight: bool) -> Self {
+        let all_sections = (1 << LIGHT_SECTIONS) - 1;
+        let (sky_light_mask, sky_light_arrays) = if has_skylight {
+            (
+                all_sections,
+                vec![vec![0xFF; LIGHT_ARRAY_LENGTH]; LIGHT_SECTIONS],
+            )
+        } else {
+            (0, Vec::new())
+        };
+        Self {
+            chunk_x,
+            chunk_z,
+            trust_edges: true,
+            sky_light_mask,
+            block_light_mask: 0,
+            empty_sky_light_mask: 0,
+            empty_block_light_mask: all_sections,
+            sky_light_arrays,
+            block_light_arrays: Vec::new(),
+        }
+    }
+
+    /// Sections with sky light arrays, as the bit set later protocols send
+    pub fn sky_light_sections(&self) -> BitSet {
+        BitSet::from(self.sky_light_mask)
+    }
+
+    /// Sections with block light arrays, as the bit set later protocols send
+    pub fn block_light_sections(&self) -> BitSet {
+        BitSet::from(self.block_light_mask)
+    }
+
+    /// Sets which sections have sky and block light arrays from bit sets. 1.16 sends the masks as
+    /// VarInts, so sections past the 32nd are rejected.
+    pub fn set_light_sections(
+        &mut self,
+        sky_light: &BitSet,
+        block_light: &BitSet,
+    ) -> io::Result<()> {
+        let to_mask = |sections: &BitSet| {
+            sections.to_mask().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "1.16 light masks only hold 32 sections",
+                )
+            })
+        };
+        self.sky_light_mask = to_mask(sky_light)?;
+        self.block_light_mask = to_mask(block_light)?;
+        Ok(())
+    }
+}
+
+impl Packet for UpdateLightPacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x23
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let chunk_x = buffer.read_varint()?;
+        let chunk_z = buffer.read_varint()?;
+        let trust_edges = buffer.read_bool()?;
+        let sky_light_mask = buffer.read_varint()?;
+        let block_light_mask = buffer.read_varint()?;
+        let empty_sky_light_mask = buffer.read_varint()?;
+        let empty_block_light_mask = buffer.read_varint()?;
+
+        let sky_light_arrays = read_light_arrays(buffer, &BitSet::from(sky_light_mask))?;
+        let block_light_arrays = read_light_arrays(buffer, &BitSet::from(block_light_mask))?;
+
+        Ok(Self {
+            chunk_x,
+            chunk_z,
+            trust_edges,
+            sky_light_mask,
+            block_light_mask,
+            empty_sky_light_mask,
+            empty_block_light_mask,
+            sky_light_arrays,
+            block_light_arrays,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        validate_light_arrays(&self.sky_light_sections(), &self.sky_light_arrays)?;
+        validate_light_arrays(&self.block_light_sections(), &self.block_light_arrays)?;
+
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.chunk_x);
+        buffer.write_varint(self.chunk_z);
+        buffer.write_bool(self.trust_edges);
+        buffer.write_varint(self.sky_light_mask);
+        buffer.write_varint(self.block_light_mask);
+        buffer.write_varint(self.empty_sky_light_mask);
+        buffer.write_varint(self.empty_block_light_mask);
+
+        for array in &self.sky_light_arrays {
+            buffer.write_byte_array(array);
+        }
+        for array in &self.block_light_arrays {
+            buffer.write_byte_array(array);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads one light array for every bit set in the mask, checking each is exactly 2048 bytes long
+fn read_light_arrays(
+    buffer: &mut MinecraftPacketBuffer,
+    mask: &BitSet,
+) -> io::Result<Vec<Vec<u8>>> {
+    let mut arrays = Vec::with_capacity(mask.count_ones());
+    for _ in 0..mask.count_ones() {
+        let length = buffer.read_varint()?;
+        if length as usize != LIGHT_ARRAY_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid light array length: {} (expected {})",
+                    length, LIGHT_ARRAY_LENGTH
+                ),
+            ));
+        }
+
+        let mut array = vec![0u8; LIGHT_ARRAY_LENGTH];
+        io::Read::read_exact(buffer, &mut array)?;
+        arrays.push(array);
+    }
+    Ok(arrays)
+}
+
+/// Makes sure there is one 2048 byte light array per bit set in the mask
+fn validate_light_arrays(mask: &BitSet, arrays: &[Vec<u8>]) -> io::Result<()> {
+    if arrays.len() != mask.count_ones() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Light mask has {} sections set but {} arrays were given",
+                mask.count_ones(),
+                arrays.len()
+            ),
+        ));
+    }
+    if arrays.iter().any(|array| array.len() != LIGHT_ARRAY_LENGTH) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Light arrays must be {} bytes long", LIGHT_ARRAY_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_packet() -> UpdateLightPacket {
+        UpdateLightPacket {
+            chunk_x: -3,
+            chunk_z: 7,
+            trust_edges: true,
+            sky_light_mask: 0b110,
+            block_light_mask: 0b1,
+            empty_sky_light_mask: 0b1,
+            empty_block_light_mask: 0b110,
+            sky_light_arrays: vec![
+                vec![0xFF; LIGHT_ARRAY_LENGTH],
+                vec![0x12; LIGHT_ARRAY_LENGTH],
+            ],
+            block_light_arrays: vec![vec![0x34; LIGHT_ARRAY_LENGTH]],
+        }
+    }
+
+    #[test]
+    fn test_update_light_round_trip() {
+        let packet = test_packet();
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        let mut read_buffer = MinecraftPacketBuffer::from_bytes(buffer.buffer);
+        assert_eq!(
+            read_buffer.read_varint().unwrap(),
+            UpdateLightPacket::packet_id()
+        );
+        let read_packet = UpdateLightPacket::read_from_buffer(&mut read_buffer).unwrap();
+        assert_eq!(read_packet, packet);
+        assert!(read_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_update_light_rejects_wrong_array_length() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_varint(0); // chunk x
+        buffer.write_varint(0); // chunk z
+        buffer.write_bool(true);
+        buffer.write_varint(0b1); // sky light mask
+        buffer.write_varint(0); // block light mask
+        buffer.write_varint(0); // empty sky light mask
+        buffer.write_varint(0); // empty block light mask
+        buffer.write_byte_array(&[0xFF; 16]);
+
+        let result = UpdateLightPacket::read_from_buffer(&mut buffer);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_dimension_without_sky_light_writes_no_sky_arrays() {
+        let packet = UpdateLightPacket::for_dimension(2, -1, false);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer.read_varint().unwrap(),
+            UpdateLightPacket::packet_id()
+        );
+        assert_eq!(buffer.read_varint().unwrap(), 2);
+        assert_eq!(buffer.read_varint().unwrap(), -1);
+        assert!(buffer.read_bool().unwrap());
+        assert_eq!(buffer.read_varint().unwrap(), 0); // sky light mask
+        assert_eq!(buffer.read_varint().unwrap(), 0); // block light mask
+        assert_eq!(buffer.read_varint().unwrap(), 0); // empty sky light mask
+        assert_eq!(buffer.read_varint().unwrap(), (1 << LIGHT_SECTIONS) - 1);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_light_sections_as_bit_sets() {
+        let mut packet = test_packet();
+        assert_eq!(
+            packet.sky_light_sections().iter().collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let sky_light: BitSet = [0, 17].into_iter().collect();
+        packet
+            .set_light_sections(&sky_light, &BitSet::from(0b1))
+            .unwrap();
+        assert_eq!(packet.sky_light_mask, 1 | (1 << 17));
+        assert_eq!(packet.sky_light_sections(), sky_light);
+
+        let too_tall: BitSet = [40].into_iter().collect();
+        assert!(packet
+            .set_light_sections(&too_tall, &BitSet::new())
+            .is_err());
+        assert_eq!(packet.sky_light_mask, 1 | (1 << 17));
+    }
+
+    #[test]
+    fn test_update_light_rejects_mismatched_mask() {
+        let mut packet = test_packet();
+        packet.sky_light_mask = 0b111;
+
+        let mut buffer = MinecraftPacketBuffer::new();
+        assert!(packet.write_to_buffer(&mut buffer).is_err());
+    }
+}
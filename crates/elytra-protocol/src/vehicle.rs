@@ -0,0 +1,295 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Vehicle Move (serverbound)
+/// Sent by the client when the vehicle it is driving moves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleMovePacket {
+    /// Absolute position of the vehicle
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Absolute rotation of the vehicle, in degrees
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Packet for VehicleMovePacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x16
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            x: buffer.read_f64()?,
+            y: buffer.read_f64()?,
+            z: buffer.read_f64()?,
+            yaw: buffer.read_f32()?,
+            pitch: buffer.read_f32()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_f64(self.x)?;
+        buffer.write_f64(self.y)?;
+        buffer.write_f64(self.z)?;
+        buffer.write_f32(self.yaw)?;
+        buffer.write_f32(self.pitch)?;
+        Ok(())
+    }
+}
+
+/// Steer Boat (serverbound)
+/// Sent while the player is rowing a boat, to animate its paddles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SteerBoatPacket {
+    pub left_paddle_turning: bool,
+    pub right_paddle_turning: bool,
+}
+
+impl Packet for SteerBoatPacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x17
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            left_paddle_turning: buffer.read_bool()?,
+            right_paddle_turning: buffer.read_bool()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_bool(self.left_paddle_turning);
+        buffer.write_bool(self.right_paddle_turning);
+        Ok(())
+    }
+}
+
+/// Steer Vehicle (serverbound)
+/// Sent with the movement input of a player riding a vehicle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SteerVehiclePacket {
+    /// Positive to the left of the player
+    pub sideways: f32,
+    /// Positive forward
+    pub forward: f32,
+    /// Bit field of `JUMP` and `UNMOUNT`
+    pub flags: u8,
+}
+
+impl SteerVehiclePacket {
+    /// Flag constants for the flags field
+    pub const JUMP: u8 = 0x01;
+    pub const UNMOUNT: u8 = 0x02;
+
+    pub fn is_jumping(&self) -> bool {
+        self.flags & Self::JUMP != 0
+    }
+
+    pub fn is_unmounting(&self) -> bool {
+        self.flags & Self::UNMOUNT != 0
+    }
+}
+
+impl Packet for SteerVehiclePacket {
+    fn packet_id() -> i32
+    where
+        Self: Sized,
+    {
+        0x1D
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            sideways: buffer.read_f32()?,
+            forward: buffer.read_f32()?,
+            flags: buffer.read_u8()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_f32(self.sideways)?;
+        buffer.write_f32(self.forward)?;
+        buffer.write_u8(self.flags);
+        Ok(())
+    }
+}
+
+/// Set Passengers (clientbound)
+/// Seats entities on a vehicle. Always lists every passenger; entities left out are dismounted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetPassengersPacket {
+    pub vehicle_id: i32,
+    /// Entity ids of the passengers, the first one controlling the vehicle
+    pub passengers: Vec<i32>,
+}
+
+impl SetPassengersPacket {
+    pub fn new(vehicle_id: i32, passengers: Vec<i32>) -> Self {
+        Self {
+            vehicle_id,
+            passengers,
+        }
+    }
+}
+
+impl Packet for SetPassengersPacket {
+    fn packet_id() -> i32 {
+        0x4B
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let vehicle_id = buffer.read_varint()?;
+        let count = buffer.read_varint()?;
+        if count < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Negative passenger count",
+            ));
+        }
+        let passengers = (0..count)
+            .map(|_| buffer.read_varint())
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            vehicle_id,
+            passengers,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.vehicle_id);
+        buffer.write_varint(self.passengers.len() as i32);
+        for passenger in &self.passengers {
+            buffer.write_varint(*passenger);
+        }
+        Ok(())
+    }
+}
+
+/// Attach Entity (clientbound)
+/// Ties an entity to another with a lead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachEntityPacket {
+    pub attached_entity_id: i32,
+    /// Entity holding the lead, or -1 to remove it
+    pub holding_entity_id: i32,
+}
+
+impl AttachEntityPacket {
+    pub fn detach(attached_entity_id: i32) -> Self {
+        Self {
+            attached_entity_id,
+            holding_entity_id: -1,
+        }
+    }
+}
+
+impl Packet for AttachEntityPacket {
+    fn packet_id() -> i32 {
+        0x45
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        Ok(Self {
+            attached_entity_id: buffer.read_i32()?,
+            holding_entity_id: buffer.read_i32()?,
+        })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_i32(self.attached_entity_id);
+        buffer.write_i32(self.holding_entity_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vehicle_move() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_f64(10.5).unwrap();
+        buffer.write_f64(63.0).unwrap();
+        buffer.write_f64(-4.25).unwrap();
+        buffer.write_f32(180.0).unwrap();
+        buffer.write_f32(-15.0).unwrap();
+
+        let packet = VehicleMovePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!((packet.x, packet.y, packet.z), (10.5, 63.0, -4.25));
+        assert_eq!((packet.yaw, packet.pitch), (180.0, -15.0));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_steer_boat() {
+        let mut buffer = MinecraftPacketBuffer::from_bytes(vec![0x01, 0x00]);
+
+        let packet = SteerBoatPacket::read_from_buffer(&mut buffer).unwrap();
+        assert!(packet.left_paddle_turning);
+        assert!(!packet.right_paddle_turning);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_steer_vehicle() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_f32(0.98).unwrap();
+        buffer.write_f32(-0.98).unwrap();
+        buffer.write_u8(SteerVehiclePacket::UNMOUNT);
+
+        let packet = SteerVehiclePacket::read_from_buffer(&mut buffer).unwrap();
+        assert_eq!(packet.sideways, 0.98);
+        assert_eq!(packet.forward, -0.98);
+        assert!(!packet.is_jumping());
+        assert!(packet.is_unmounting());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_set_passengers_keeps_order() {
+        let packet = SetPassengersPacket::new(300, vec![7, 2]);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        assert_eq!(buffer.get_buffer(), &[0x4B, 0xAC, 0x02, 2, 7, 2]);
+
+        buffer.read_varint().unwrap();
+        assert_eq!(
+            SetPassengersPacket::read_from_buffer(&mut buffer).unwrap(),
+            packet
+        );
+    }
+
+    #[test]
+    fn test_attach_entity() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        AttachEntityPacket::detach(5)
+            .write_to_buffer(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            buffer.get_buffer(),
+            &[0x45, 0, 0, 0, 5, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_read_vehicle_move_truncated() {
+        let mut buffer = MinecraftPacketBuffer::new();
+        buffer.write_f64(10.5).unwrap();
+
+        assert!(VehicleMovePacket::read_from_buffer(&mut buffer).is_err());
+    }
+}
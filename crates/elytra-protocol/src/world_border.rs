@@ -0,0 +1,173 @@
+use crate::packet::{MinecraftPacketBuffer, Packet};
+use std::io;
+
+/// Change made by a World Border packet
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldBorderAction {
+    /// Resizes the border at once to a side length in blocks
+    SetSize {
+        diameter: f64,
+    },
+    SetCenter {
+        x: f64,
+        z: f64,
+    },
+    /// Sends the whole border to a player who has just joined
+    Initialize {
+        x: f64,
+        z: f64,
+        diameter: f64,
+        /// Furthest from spawn, in blocks, a nether portal can take a player
+        portal_teleport_boundary: i32,
+        warning_time: i32,
+        warning_blocks: i32,
+    },
+    /// Seconds before a shrinking border reaches the player that the screen starts turning red
+    SetWarningTime {
+        seconds: i32,
+    },
+    /// Distance from the border, in blocks, at which the screen starts turning red
+    SetWarningBlocks {
+        blocks: i32,
+    },
+}
+
+impl WorldBorderAction {
+    fn id(&self) -> i32 {
+        match self {
+            WorldBorderAction::SetSize { .. } => 0,
+            WorldBorderAction::SetCenter { .. } => 2,
+            WorldBorderAction::Initialize { .. } => 3,
+            WorldBorderAction::SetWarningTime { .. } => 4,
+            WorldBorderAction::SetWarningBlocks { .. } => 5,
+        }
+    }
+}
+
+/// World Border (clientbound)
+/// Moves, resizes or sets up the world border. Shrinking or growing the border over time isn't
+/// supported, so sizes always change at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldBorderPacket {
+    pub action: WorldBorderAction,
+}
+
+impl WorldBorderPacket {
+    pub fn new(action: WorldBorderAction) -> Self {
+        Self { action }
+    }
+}
+
+impl Packet for WorldBorderPacket {
+    fn packet_id() -> i32 {
+        0x3D
+    }
+
+    fn read_from_buffer(buffer: &mut MinecraftPacketBuffer) -> io::Result<Self> {
+        let action = match buffer.read_varint()? {
+            0 => WorldBorderAction::SetSize {
+                diameter: buffer.read_f64()?,
+            },
+            2 => WorldBorderAction::SetCenter {
+                x: buffer.read_f64()?,
+                z: buffer.read_f64()?,
+            },
+            3 => {
+                let x = buffer.read_f64()?;
+                let z = buffer.read_f64()?;
+                let _old_diameter = buffer.read_f64()?;
+                let diameter = buffer.read_f64()?;
+                let _speed = buffer.read_varlong()?;
+                WorldBorderAction::Initialize {
+                    x,
+                    z,
+                    diameter,
+                    portal_teleport_boundary: buffer.read_varint()?,
+                    warning_time: buffer.read_varint()?,
+                    warning_blocks: buffer.read_varint()?,
+                }
+            }
+            4 => WorldBorderAction::SetWarningTime {
+                seconds: buffer.read_varint()?,
+            },
+            5 => WorldBorderAction::SetWarningBlocks {
+                blocks: buffer.read_varint()?,
+            },
+            action => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unsupported world border action {}", action),
+                ))
+            }
+        };
+        Ok(Self { action })
+    }
+
+    fn write_to_buffer(&self, buffer: &mut MinecraftPacketBuffer) -> io::Result<()> {
+        buffer.write_varint(Self::packet_id());
+        buffer.write_varint(self.action.id());
+        match &self.action {
+            WorldBorderAction::SetSize { diameter } => buffer.write_f64(*diameter)?,
+            WorldBorderAction::SetCenter { x, z } => {
+                buffer.write_f64(*x)?;
+                buffer.write_f64(*z)?;
+            }
+            WorldBorderAction::Initialize {
+                x,
+                z,
+                diameter,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks,
+            } => {
+                buffer.write_f64(*x)?;
+                buffer.write_f64(*z)?;
+                // Old and new diameter, the same since the border isn't moving
+                buffer.write_f64(*diameter)?;
+                buffer.write_f64(*diameter)?;
+                // Milliseconds until the new diameter is reached, 0 for a border that isn't moving
+                buffer.write_varlong(0);
+                buffer.write_varint(*portal_teleport_boundary);
+                buffer.write_varint(*warning_time);
+                buffer.write_varint(*warning_blocks);
+            }
+            WorldBorderAction::SetWarningTime { seconds } => buffer.write_varint(*seconds),
+            WorldBorderAction::SetWarningBlocks { blocks } => buffer.write_varint(*blocks),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_border_round_trip() {
+        for action in [
+            WorldBorderAction::SetSize { diameter: 100.0 },
+            WorldBorderAction::SetCenter { x: 8.5, z: -20.0 },
+            WorldBorderAction::Initialize {
+                x: 0.0,
+                z: 0.0,
+                diameter: 500.0,
+                portal_teleport_boundary: 29_999_984,
+                warning_time: 15,
+                warning_blocks: 5,
+            },
+            WorldBorderAction::SetWarningBlocks { blocks: 10 },
+        ] {
+            let packet = WorldBorderPacket::new(action);
+            let mut buffer = MinecraftPacketBuffer::new();
+            packet.write_to_buffer(&mut buffer).unwrap();
+            assert_eq!(
+                buffer.read_varint().unwrap(),
+                WorldBorderPacket::packet_id()
+            );
+            assert_eq!(
+                WorldBorderPacket::read_from_buffer(&mut buffer).unwrap(),
+                packet
+            );
+        }
+    }
+}
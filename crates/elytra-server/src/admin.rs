@@ -0,0 +1,154 @@
+use elytra_logger::log::log;
+use elytra_logger::severity::LogSeverity::{Debug, Error, Info};
+use elytra_protocol::chat::ChatMessagePacket;
+use elytra_protocol::session_manager::SessionManager;
+use elytra_protocol::text_component::TextComponent;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// Request sent to the admin socket, one JSON object per line
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum AdminRequest {
+    /// Names of the players online
+    List,
+    /// Shows a message from the server to every player
+    Broadcast { text: String },
+}
+
+/// Serves the admin socket until the server shuts down. Every line received is answered with one
+/// line of JSON holding `"ok"` and either the result or an `"error"`.
+///
+/// The socket has no authentication, so it should only be bound to an address operators trust,
+/// such as localhost.
+pub async fn serve(
+    listener: TcpListener,
+    sessions: Arc<SessionManager>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, addr)) => {
+                    log(format!("Admin connection from: {}", addr), Info);
+                    tokio::spawn(handle_admin_connection(stream, sessions.clone()));
+                }
+                Err(accept_error) => log(
+                    format!("Failed to accept admin connection: {}", accept_error),
+                    Error,
+                ),
+            },
+        }
+    }
+}
+
+async fn handle_admin_connection(stream: TcpStream, sessions: Arc<SessionManager>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(read_error) => {
+                log(
+                    format!("Failed to read admin request: {}", read_error),
+                    Error,
+                );
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        log(format!("Admin request: {}", line), Debug);
+
+        let response = match serde_json::from_str::<AdminRequest>(&line) {
+            Ok(request) => match handle_request(request, &sessions) {
+                Ok(response) => response,
+                Err(request_error) => json!({ "ok": false, "error": request_error.to_string() }),
+            },
+            Err(parse_error) => json!({ "ok": false, "error": parse_error.to_string() }),
+        };
+        if let Err(write_error) = write_line(&mut writer, &response).await {
+            log(
+                format!("Failed to answer admin request: {}", write_error),
+                Error,
+            );
+            break;
+        }
+    }
+}
+
+async fn write_line<W: AsyncWriteExt + Unpin>(writer: &mut W, response: &Value) -> io::Result<()> {
+    let mut line = response.to_string();
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await
+}
+
+/// Carries out a request and returns the response to send back
+pub fn handle_request(request: AdminRequest, sessions: &SessionManager) -> io::Result<Value> {
+    match request {
+        AdminRequest::List => {
+            let mut players = sessions.get_player_names();
+            players.sort();
+            Ok(json!({ "ok": true, "players": players }))
+        }
+        AdminRequest::Broadcast { text } => {
+            let message = ChatMessagePacket::system(&TextComponent::new(text));
+            sessions.broadcast_packet(message, None)?;
+            Ok(json!({ "ok": true }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elytra_protocol::packet::{read_packet, Packet};
+    use elytra_protocol::session::PlayerSession;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_parse_requests() {
+        assert_eq!(
+            serde_json::from_str::<AdminRequest>(r#"{"cmd":"list"}"#).unwrap(),
+            AdminRequest::List
+        );
+        assert_eq!(
+            serde_json::from_str::<AdminRequest>(r#"{"cmd":"broadcast","text":"Hi"}"#).unwrap(),
+            AdminRequest::Broadcast {
+                text: "Hi".to_owned()
+            }
+        );
+        assert!(serde_json::from_str::<AdminRequest>(r#"{"cmd":"stop"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_reaches_players() {
+        let sessions = SessionManager::new();
+        let (mut client, server) = tokio::io::duplex(256);
+        let (session, _reader) =
+            PlayerSession::from_stream("Steve".to_owned(), Uuid::nil(), server);
+        sessions.add_session(&session);
+
+        let request = AdminRequest::Broadcast {
+            text: "Restarting soon".to_owned(),
+        };
+        let response = handle_request(request, &sessions).unwrap();
+        assert_eq!(response, json!({ "ok": true }));
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            ChatMessagePacket::packet_id()
+        );
+        let message = ChatMessagePacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(message.json, r#"{"text":"Restarting soon"}"#);
+        assert_eq!(message.position, ChatMessagePacket::SYSTEM);
+    }
+}
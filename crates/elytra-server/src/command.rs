@@ -0,0 +1,277 @@
+use elytra_protocol::session::PlayerSession;
+use elytra_protocol::session_manager::SessionManager;
+use elytra_protocol::text_component::TextComponent;
+use elytra_wotra::blocks;
+use elytra_wotra::world::{BlockPos, World};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+pub mod fill;
+pub mod gamemode;
+pub mod setblock;
+pub mod time;
+pub mod weather;
+pub mod worldborder;
+pub mod xp;
+
+/// What a command handler can act on. Commands run on the task of the player who sent them, so
+/// the sender's session is borrowed from there.
+pub struct CommandContext<'a> {
+    /// Session of the player who ran the command
+    pub sender: &'a mut PlayerSession,
+    pub world: Arc<World>,
+    pub sessions: Arc<SessionManager>,
+}
+
+impl CommandContext<'_> {
+    /// Borrows the context again, for running another command with it
+    pub fn reborrow(&mut self) -> CommandContext<'_> {
+        CommandContext {
+            sender: self.sender,
+            world: self.world.clone(),
+            sessions: self.sessions.clone(),
+        }
+    }
+}
+
+pub type CommandResult = Result<(), CommandError>;
+
+/// Runs a command with the arguments that followed its name
+pub type CommandHandler =
+    for<'a> fn(CommandContext<'a>, Vec<String>) -> BoxFuture<'a, CommandResult>;
+
+#[derive(Debug)]
+pub enum CommandError {
+    /// No command is registered under the name
+    UnknownCommand(String),
+    /// The arguments don't fit the command; holds its usage
+    InvalidArguments(&'static str),
+    /// The sender's operator level is below what the command requires
+    PermissionDenied,
+    /// The arguments parsed but the command can't carry them out; holds the reason
+    Failed(String),
+    /// The command ran but sending its results failed
+    Io(io::Error),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::UnknownCommand(name) => write!(f, "Unknown command: {}", name),
+            CommandError::InvalidArguments(usage) => write!(f, "Usage: {}", usage),
+            CommandError::PermissionDenied => {
+                write!(f, "You do not have permission to use this command")
+            }
+            CommandError::Failed(reason) => write!(f, "{}", reason),
+            CommandError::Io(io_error) => write!(f, "{}", io_error),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<io::Error> for CommandError {
+    fn from(io_error: io::Error) -> Self {
+        CommandError::Io(io_error)
+    }
+}
+
+/// Parses a block position from three coordinates. A coordinate starting with `~` is relative
+/// to `origin`, the sender's position.
+pub fn parse_block_pos(args: &[String], origin: (f64, f64, f64)) -> Option<BlockPos> {
+    let [x, y, z] = args else {
+        return None;
+    };
+    Some((
+        parse_coordinate(x, origin.0)?,
+        parse_coordinate(y, origin.1)?,
+        parse_coordinate(z, origin.2)?,
+    ))
+}
+
+fn parse_coordinate(arg: &str, origin: f64) -> Option<i32> {
+    match arg.strip_prefix('~') {
+        Some("") => Some(origin.floor() as i32),
+        Some(offset) => {
+            let offset: f64 = offset.parse().ok()?;
+            Some((origin + offset).floor() as i32)
+        }
+        None => arg.parse().ok(),
+    }
+}
+
+/// Resolves a block name, such as `stone` or `minecraft:stone`, to its default state
+pub fn parse_block_state(arg: &str) -> Result<u32, CommandError> {
+    blocks::block_state_by_name(arg)
+        .ok_or_else(|| CommandError::Failed(format!("Unknown block type: {}", arg)))
+}
+
+/// A command players can run from the chat box
+pub struct Command {
+    /// Name typed after the `/`
+    pub name: &'static str,
+    pub usage: &'static str,
+    /// Operator level (0-4) a player needs to run the command
+    pub required_level: u8,
+    pub handler: CommandHandler,
+}
+
+/// Looks up commands by name and runs them
+pub struct CommandDispatcher {
+    commands: HashMap<&'static str, Command>,
+}
+
+impl Default for CommandDispatcher {
+    /// A dispatcher with all built-in commands registered
+    fn default() -> Self {
+        let mut dispatcher = Self::new();
+        dispatcher.register(fill::command());
+        dispatcher.register(gamemode::command());
+        dispatcher.register(setblock::command());
+        dispatcher.register(time::command());
+        dispatcher.register(weather::command());
+        dispatcher.register(worldborder::command());
+        dispatcher.register(xp::command());
+        dispatcher
+    }
+}
+
+impl CommandDispatcher {
+    /// Creates a dispatcher without any commands
+    pub fn new() -> Self {
+        Self {
+            commands: HashMap::new(),
+        }
+    }
+
+    /// Registers a command, replacing any command with the same name
+    pub fn register(&mut self, command: Command) {
+        self.commands.insert(command.name, command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// Parses and runs a command line, given without its leading `/`. The command only runs if
+    /// the sender's operator level is high enough.
+    pub async fn dispatch(&self, context: CommandContext<'_>, command_line: &str) -> CommandResult {
+        let mut parts = command_line.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let command = self
+            .get(name)
+            .ok_or_else(|| CommandError::UnknownCommand(name.to_owned()))?;
+
+        if context.sender.op_level < command.required_level {
+            return Err(CommandError::PermissionDenied);
+        }
+
+        (command.handler)(context, parts.map(str::to_owned).collect()).await
+    }
+
+    /// Runs a command like `dispatch` and tells the sender in chat when it was rejected
+    pub async fn execute(
+        &self,
+        mut context: CommandContext<'_>,
+        command_line: &str,
+    ) -> CommandResult {
+        let result = self.dispatch(context.reborrow(), command_line).await;
+
+        if let Err(command_error) = &result {
+            if !matches!(command_error, CommandError::Io(_)) {
+                let message = TextComponent::new(command_error.to_string()).color("red");
+                context.sender.send_system_message(&message)?;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use elytra_protocol::chat::ChatMessagePacket;
+    use elytra_protocol::login::LoginSuccessPacket;
+    use elytra_protocol::packet::{read_packet, Packet};
+    use elytra_wotra::generator::FlatGenerator;
+    use elytra_wotra::world::NIGHT;
+    use tokio::io::DuplexStream;
+
+    /// A single online player running commands, with what their commands act on
+    pub(crate) struct TestSender {
+        pub session: PlayerSession,
+        pub world: Arc<World>,
+        pub sessions: Arc<SessionManager>,
+    }
+
+    impl TestSender {
+        pub(crate) fn context(&mut self) -> CommandContext<'_> {
+            CommandContext {
+                sender: &mut self.session,
+                world: self.world.clone(),
+                sessions: self.sessions.clone(),
+            }
+        }
+    }
+
+    /// A player who is online by themselves, along with their end of the connection. The player
+    /// is a full operator so every command is allowed.
+    pub(crate) fn test_sender(username: &str) -> (TestSender, DuplexStream) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let uuid = LoginSuccessPacket::new(username.to_owned()).uuid;
+        let (mut session, _reader) = PlayerSession::from_stream(username.to_owned(), uuid, server);
+        session.op_level = 4;
+        let sessions = Arc::new(SessionManager::new());
+        sessions.add_session(&session);
+
+        let sender = TestSender {
+            session,
+            world: Arc::new(World::new(FlatGenerator::default())),
+            sessions,
+        };
+        (sender, client)
+    }
+
+    #[tokio::test]
+    async fn test_op_command_requires_permission() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let dispatcher = CommandDispatcher::default();
+        assert_eq!(dispatcher.get("time").unwrap().required_level, 2);
+
+        sender.session.op_level = 1;
+        let result = dispatcher.execute(sender.context(), "time set night").await;
+        assert!(matches!(result, Err(CommandError::PermissionDenied)));
+        assert_eq!(sender.world.world_time(), 0);
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            ChatMessagePacket::packet_id()
+        );
+        let message = ChatMessagePacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(message.position, ChatMessagePacket::SYSTEM);
+        assert!(message.json.contains("do not have permission"));
+
+        sender.session.op_level = 2;
+        dispatcher
+            .execute(sender.context(), "time set night")
+            .await
+            .unwrap();
+        assert_eq!(sender.world.world_time(), NIGHT);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_command() {
+        let (mut sender, _client) = test_sender("Steve");
+        let result = CommandDispatcher::default()
+            .dispatch(sender.context(), "fly away")
+            .await;
+        match result {
+            Err(CommandError::UnknownCommand(name)) => assert_eq!(name, "fly"),
+            result => panic!("Expected an unknown command error, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use super::{
+    parse_block_pos, parse_block_state, Command, CommandContext, CommandError, CommandResult,
+};
+use elytra_protocol::block_change::MultiBlockChangePacket;
+use elytra_wotra::world::{BlockPos, World};
+use futures::future::BoxFuture;
+use std::collections::BTreeMap;
+
+const USAGE: &str = "/fill <x1> <y1> <z1> <x2> <y2> <z2> <block>";
+
+/// Most blocks a single `/fill` may change, the same limit as vanilla
+pub const MAX_FILL_VOLUME: i64 = 32768;
+
+/// `/fill <x1> <y1> <z1> <x2> <y2> <z2> <block>`
+pub fn command() -> Command {
+    Command {
+        name: "fill",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    if args.len() != 7 {
+        return Err(CommandError::InvalidArguments(USAGE));
+    }
+    let origin = context.sender.position;
+    let from = parse_block_pos(&args[0..3], origin).ok_or(CommandError::InvalidArguments(USAGE))?;
+    let to = parse_block_pos(&args[3..6], origin).ok_or(CommandError::InvalidArguments(USAGE))?;
+    let state = parse_block_state(&args[6])?;
+
+    let min = (from.0.min(to.0), from.1.min(to.1), from.2.min(to.2));
+    let max = (from.0.max(to.0), from.1.max(to.1), from.2.max(to.2));
+    if !World::is_in_height_range(min.1) || !World::is_in_height_range(max.1) {
+        return Err(CommandError::Failed(
+            "Cannot place blocks outside of the world".to_owned(),
+        ));
+    }
+    let volume = (max.0 as i64 - min.0 as i64 + 1)
+        * (max.1 as i64 - min.1 as i64 + 1)
+        * (max.2 as i64 - min.2 as i64 + 1);
+    if volume > MAX_FILL_VOLUME {
+        return Err(CommandError::Failed(format!(
+            "Too many blocks in the specified area (maximum {}, specified {})",
+            MAX_FILL_VOLUME, volume
+        )));
+    }
+
+    let changes: Vec<(BlockPos, u32)> = (min.0..=max.0)
+        .flat_map(|x| (min.1..=max.1).flat_map(move |y| (min.2..=max.2).map(move |z| (x, y, z))))
+        .map(|position| (position, state))
+        .collect();
+    context.world.set_block_states(&changes).await?;
+
+    for packet in multi_block_changes(&changes) {
+        context.sessions.broadcast_packet(packet, None)?;
+    }
+    Ok(())
+}
+
+/// Groups block changes into one Multi Block Change packet per chunk section
+pub fn multi_block_changes(changes: &[(BlockPos, u32)]) -> Vec<MultiBlockChangePacket> {
+    let mut sections: BTreeMap<(i32, i32, i32), MultiBlockChangePacket> = BTreeMap::new();
+    for &((x, y, z), state) in changes {
+        let (section_x, section_y, section_z) = (x >> 4, y >> 4, z >> 4);
+        sections
+            .entry((section_x, section_y, section_z))
+            .or_insert_with(|| MultiBlockChangePacket::new(section_x, section_y, section_z))
+            .push((x & 15) as u8, (y & 15) as u8, (z & 15) as u8, state);
+    }
+    sections.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::packet::{read_packet, Packet};
+    use elytra_wotra::blocks::{AIR, GRASS_BLOCK, STONE};
+    use tokio::io::DuplexStream;
+
+    async fn read_multi_block_change(client: &mut DuplexStream) -> MultiBlockChangePacket {
+        let mut packet = read_packet(client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            MultiBlockChangePacket::packet_id()
+        );
+        MultiBlockChangePacket::read_from_buffer(&mut packet).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fill_changes_box() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let world = sender.world.clone();
+
+        // Crosses from section y 0 into section y 1
+        CommandDispatcher::default()
+            .dispatch(sender.context(), "fill 1 14 1 0 17 2 stone")
+            .await
+            .unwrap();
+
+        for x in 0..=1 {
+            for y in 14..=17 {
+                for z in 1..=2 {
+                    assert_eq!(world.get_block_state((x, y, z)).await.unwrap(), STONE);
+                }
+            }
+        }
+        assert_eq!(world.get_block_state((2, 14, 1)).await.unwrap(), AIR);
+        assert_eq!(world.get_block_state((0, 3, 1)).await.unwrap(), GRASS_BLOCK);
+
+        let lower = read_multi_block_change(&mut client).await;
+        assert_eq!(
+            (lower.section_x, lower.section_y, lower.section_z),
+            (0, 0, 0)
+        );
+        assert_eq!(lower.blocks.len(), 2 * 2 * 2);
+        assert!(lower.blocks.contains(&((1, 15, 2), STONE)));
+
+        let upper = read_multi_block_change(&mut client).await;
+        assert_eq!(
+            (upper.section_x, upper.section_y, upper.section_z),
+            (0, 1, 0)
+        );
+        assert_eq!(upper.blocks.len(), 2 * 2 * 2);
+        assert!(upper.blocks.contains(&((0, 1, 1), STONE)));
+    }
+
+    #[tokio::test]
+    async fn test_fill_rejects_large_area() {
+        let (mut sender, _client) = test_sender("Steve");
+        let world = sender.world.clone();
+        let result = CommandDispatcher::default()
+            .dispatch(sender.context(), "fill 0 0 0 99 99 99 stone")
+            .await;
+        assert!(matches!(result, Err(CommandError::Failed(_))));
+        assert_eq!(world.loaded_chunk_count(), 0);
+    }
+}
@@ -0,0 +1,82 @@
+use super::{Command, CommandContext, CommandError, CommandResult};
+use elytra_common::types::GameMode;
+use futures::future::BoxFuture;
+
+const USAGE: &str = "/gamemode (survival|creative|adventure|spectator)";
+
+/// `/gamemode <mode>`, which switches the sender to another game mode
+pub fn command() -> Command {
+    Command {
+        name: "gamemode",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    let gamemode = match args.as_slice() {
+        [mode] => GameMode::from_name(mode).ok_or(CommandError::InvalidArguments(USAGE))?,
+        _ => return Err(CommandError::InvalidArguments(USAGE)),
+    };
+
+    context.sessions.set_gamemode(context.sender, gamemode)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::change_game_state::ChangeGameStatePacket;
+    use elytra_protocol::packet::{read_packet, Packet};
+    use elytra_protocol::player_info::PlayerInfoPacket;
+    use elytra_protocol::session::PlayerSession;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_gamemode_broadcasts_tab_list_update() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let (mut other_client, server) = tokio::io::duplex(4096);
+        let (other, _reader) =
+            PlayerSession::from_stream("Alex".to_owned(), Uuid::from_u128(1), server);
+        sender.sessions.add_session(&other);
+
+        CommandDispatcher::default()
+            .dispatch(sender.context(), "gamemode creative")
+            .await
+            .unwrap();
+
+        assert_eq!(sender.session.gamemode, GameMode::Creative);
+        let uuid = sender.session.uuid;
+        let expected = PlayerInfoPacket::update_gamemode(vec![(uuid, GameMode::Creative)]);
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            ChangeGameStatePacket::packet_id()
+        );
+        for client in [&mut client, &mut other_client] {
+            let mut packet = read_packet(client).await.unwrap();
+            assert_eq!(packet.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+            assert_eq!(
+                PlayerInfoPacket::read_from_buffer(&mut packet).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_gamemode_is_rejected() {
+        let (mut sender, _client) = test_sender("Steve");
+        let result = CommandDispatcher::default()
+            .dispatch(sender.context(), "gamemode hardcore")
+            .await;
+        assert!(matches!(result, Err(CommandError::InvalidArguments(_))));
+    }
+}
@@ -0,0 +1,123 @@
+use super::{
+    parse_block_pos, parse_block_state, Command, CommandContext, CommandError, CommandResult,
+};
+use elytra_protocol::block_change::BlockChangePacket;
+use elytra_protocol::effect::EffectPacket;
+use elytra_wotra::blocks::AIR;
+use elytra_wotra::world::World;
+use futures::future::BoxFuture;
+
+const USAGE: &str = "/setblock <x> <y> <z> <block>";
+
+/// `/setblock <x> <y> <z> <block>`
+pub fn command() -> Command {
+    Command {
+        name: "setblock",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    let [coordinates @ .., block] = args.as_slice() else {
+        return Err(CommandError::InvalidArguments(USAGE));
+    };
+    let origin = context.sender.position;
+    let (x, y, z) =
+        parse_block_pos(coordinates, origin).ok_or(CommandError::InvalidArguments(USAGE))?;
+    let state = parse_block_state(block)?;
+    if !World::is_in_height_range(y) {
+        return Err(CommandError::Failed(
+            "Cannot place blocks outside of the world".to_owned(),
+        ));
+    }
+
+    let previous = context.world.get_block_state((x, y, z)).await?;
+    context.world.set_block_state((x, y, z), state).await?;
+    let sessions = &context.sessions;
+    sessions.broadcast_packet(BlockChangePacket::new(x, y, z, state), None)?;
+    // Replacing a block with air breaks it
+    if state == AIR && previous != AIR {
+        sessions.broadcast_packet(EffectPacket::block_break((x, y, z), previous), None)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::packet::{read_packet, Packet};
+    use elytra_wotra::blocks::{GRASS_BLOCK, STONE};
+
+    #[tokio::test]
+    async fn test_setblock_changes_one_block() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let world = sender.world.clone();
+        sender.session.position = (10.5, 4.0, -3.2);
+
+        CommandDispatcher::default()
+            .dispatch(sender.context(), "setblock ~1 ~ ~ minecraft:stone")
+            .await
+            .unwrap();
+
+        assert_eq!(world.get_block_state((11, 4, -4)).await.unwrap(), STONE);
+        // Neighbours are untouched
+        assert_eq!(world.get_block_state((10, 4, -4)).await.unwrap(), AIR);
+        assert_eq!(
+            world.get_block_state((11, 3, -4)).await.unwrap(),
+            GRASS_BLOCK
+        );
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            BlockChangePacket::packet_id()
+        );
+        assert_eq!(
+            BlockChangePacket::read_from_buffer(&mut packet).unwrap(),
+            BlockChangePacket::new(11, 4, -4, STONE)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_setblock_air_plays_break_effect() {
+        let (mut sender, mut client) = test_sender("Steve");
+        CommandDispatcher::default()
+            .dispatch(sender.context(), "setblock 0 3 0 air")
+            .await
+            .unwrap();
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            BlockChangePacket::packet_id()
+        );
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(packet.read_varint().unwrap(), EffectPacket::packet_id());
+        assert_eq!(
+            EffectPacket::read_from_buffer(&mut packet).unwrap(),
+            EffectPacket::block_break((0, 3, 0), GRASS_BLOCK)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_setblock_rejects_unknown_block() {
+        let (mut sender, _client) = test_sender("Steve");
+        let result = CommandDispatcher::default()
+            .dispatch(sender.context(), "setblock 0 64 0 notablock")
+            .await;
+        match result {
+            Err(CommandError::Failed(reason)) => {
+                assert_eq!(reason, "Unknown block type: notablock")
+            }
+            result => panic!("Expected the command to fail, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use super::{Command, CommandContext, CommandError, CommandResult};
+use elytra_protocol::time_update::TimeUpdatePacket;
+use elytra_wotra::world::{World, DAY, NIGHT};
+use futures::future::BoxFuture;
+
+const USAGE: &str = "/time set (day|night|<ticks>) | /time add <ticks>";
+
+/// `/time set day`, `/time set night`, `/time set <ticks>` and `/time add <ticks>`
+pub fn command() -> Command {
+    Command {
+        name: "time",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["set", "day"] => context.world.set_world_time(DAY),
+        ["set", "night"] => context.world.set_world_time(NIGHT),
+        ["set", ticks] => context.world.set_world_time(parse_ticks(ticks)?),
+        ["add", ticks] => {
+            context.world.add_world_time(parse_ticks(ticks)?);
+        }
+        _ => return Err(CommandError::InvalidArguments(USAGE)),
+    }
+
+    context
+        .sessions
+        .broadcast_packet(time_update(&context.world), None)?;
+    Ok(())
+}
+
+fn parse_ticks(ticks: &str) -> Result<i64, CommandError> {
+    ticks
+        .parse()
+        .map_err(|_| CommandError::InvalidArguments(USAGE))
+}
+
+/// Time Update describing the world's current clock
+pub fn time_update(world: &World) -> TimeUpdatePacket {
+    TimeUpdatePacket::new(world.world_age(), world.world_time())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::packet::{read_packet, Packet};
+
+    #[tokio::test]
+    async fn test_time_set_night_broadcasts_time_update() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let world = sender.world.clone();
+        world.tick();
+
+        CommandDispatcher::default()
+            .dispatch(sender.context(), "time set night")
+            .await
+            .unwrap();
+        assert_eq!(world.world_time(), NIGHT);
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(packet.read_varint().unwrap(), TimeUpdatePacket::packet_id());
+        let time_update = TimeUpdatePacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(time_update, TimeUpdatePacket::new(1, NIGHT));
+    }
+
+    #[tokio::test]
+    async fn test_time_add_and_invalid_arguments() {
+        let (mut sender, _client) = test_sender("Steve");
+        let world = sender.world.clone();
+        let dispatcher = CommandDispatcher::default();
+
+        dispatcher
+            .dispatch(sender.context(), "time set day")
+            .await
+            .unwrap();
+        dispatcher
+            .dispatch(sender.context(), "time add 250")
+            .await
+            .unwrap();
+        assert_eq!(world.world_time(), DAY + 250);
+
+        let result = dispatcher.dispatch(sender.context(), "time add soon").await;
+        assert!(matches!(result, Err(CommandError::InvalidArguments(USAGE))));
+        assert_eq!(world.world_time(), DAY + 250);
+    }
+}
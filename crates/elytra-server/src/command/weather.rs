@@ -0,0 +1,127 @@
+use super::{Command, CommandContext, CommandError, CommandResult};
+use elytra_protocol::change_game_state::ChangeGameStatePacket;
+use elytra_wotra::world::{Weather, World};
+use futures::future::BoxFuture;
+
+const USAGE: &str = "/weather (clear|rain|thunder)";
+
+/// `/weather clear`, `/weather rain` and `/weather thunder`
+pub fn command() -> Command {
+    Command {
+        name: "weather",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    let weather = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["clear"] => Weather::Clear,
+        ["rain"] => Weather::Rain,
+        ["thunder"] => Weather::Thunder,
+        _ => return Err(CommandError::InvalidArguments(USAGE)),
+    };
+
+    let previous = context.world.set_weather(weather);
+    for packet in weather_change(previous, weather) {
+        context.sessions.broadcast_packet(packet, None)?;
+    }
+    Ok(())
+}
+
+/// Change Game State packets that move a client from one weather to another
+pub fn weather_change(previous: Weather, weather: Weather) -> Vec<ChangeGameStatePacket> {
+    let mut packets = Vec::new();
+    match (previous.is_raining(), weather.is_raining()) {
+        (false, true) => packets.push(ChangeGameStatePacket::begin_raining()),
+        (true, false) => packets.push(ChangeGameStatePacket::end_raining()),
+        _ => {}
+    }
+    packets.push(ChangeGameStatePacket::rain_level(weather.rain_strength()));
+    packets.push(ChangeGameStatePacket::thunder_level(
+        weather.thunder_strength(),
+    ));
+    packets
+}
+
+/// Packets that show a joining player the world's current weather. Nothing needs to be sent
+/// when it is clear.
+pub fn current_weather(world: &World) -> Vec<ChangeGameStatePacket> {
+    match world.weather() {
+        Weather::Clear => Vec::new(),
+        weather => weather_change(Weather::Clear, weather),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::packet::{read_packet, Packet};
+    use tokio::io::DuplexStream;
+
+    async fn read_game_state(client: &mut DuplexStream) -> ChangeGameStatePacket {
+        let mut packet = read_packet(client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            ChangeGameStatePacket::packet_id()
+        );
+        ChangeGameStatePacket::read_from_buffer(&mut packet).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_weather_rain_broadcasts_begin_raining() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let world = sender.world.clone();
+
+        CommandDispatcher::default()
+            .dispatch(sender.context(), "weather rain")
+            .await
+            .unwrap();
+        assert_eq!(world.weather(), Weather::Rain);
+
+        assert_eq!(
+            read_game_state(&mut client).await,
+            ChangeGameStatePacket::begin_raining()
+        );
+        assert_eq!(
+            read_game_state(&mut client).await,
+            ChangeGameStatePacket::rain_level(1.0)
+        );
+        assert_eq!(
+            read_game_state(&mut client).await,
+            ChangeGameStatePacket::thunder_level(0.0)
+        );
+    }
+
+    #[test]
+    fn test_weather_change_packets() {
+        let reasons = |packets: Vec<ChangeGameStatePacket>| -> Vec<u8> {
+            packets.into_iter().map(|packet| packet.reason).collect()
+        };
+        assert_eq!(
+            reasons(weather_change(Weather::Rain, Weather::Clear)),
+            vec![
+                ChangeGameStatePacket::END_RAINING,
+                ChangeGameStatePacket::RAIN_LEVEL_CHANGE,
+                ChangeGameStatePacket::THUNDER_LEVEL_CHANGE
+            ]
+        );
+        // Already raining, only the levels change
+        assert_eq!(
+            weather_change(Weather::Rain, Weather::Thunder)[1],
+            ChangeGameStatePacket::thunder_level(1.0)
+        );
+    }
+}
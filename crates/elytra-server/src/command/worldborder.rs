@@ -0,0 +1,136 @@
+use super::{Command, CommandContext, CommandError, CommandResult};
+use elytra_protocol::world_border::{WorldBorderAction, WorldBorderPacket};
+use elytra_wotra::world::{World, MAX_WORLD_BORDER_DIAMETER};
+use futures::future::BoxFuture;
+
+const USAGE: &str =
+    "/worldborder (set <size>|center <x> <z>|warning distance <blocks>|warning time <seconds>)";
+
+/// `/worldborder set <size>`, `/worldborder center <x> <z>` and
+/// `/worldborder warning (distance|time) <value>`
+pub fn command() -> Command {
+    Command {
+        name: "worldborder",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    let mut border = context.world.world_border();
+    let action = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["set", size] => {
+            let diameter: f64 = parse(size)?;
+            if !(1.0..=MAX_WORLD_BORDER_DIAMETER).contains(&diameter) {
+                return Err(CommandError::Failed(format!(
+                    "World border size must be between 1 and {}",
+                    MAX_WORLD_BORDER_DIAMETER
+                )));
+            }
+            border.diameter = diameter;
+            WorldBorderAction::SetSize { diameter }
+        }
+        ["center", x, z] => {
+            border.center = (parse(x)?, parse(z)?);
+            WorldBorderAction::SetCenter {
+                x: border.center.0,
+                z: border.center.1,
+            }
+        }
+        ["warning", "distance", blocks] => {
+            border.warning_blocks = parse_non_negative(blocks)?;
+            WorldBorderAction::SetWarningBlocks {
+                blocks: border.warning_blocks,
+            }
+        }
+        ["warning", "time", seconds] => {
+            border.warning_time = parse_non_negative(seconds)?;
+            WorldBorderAction::SetWarningTime {
+                seconds: border.warning_time,
+            }
+        }
+        _ => return Err(CommandError::InvalidArguments(USAGE)),
+    };
+
+    context.world.set_world_border(border);
+    context
+        .sessions
+        .broadcast_packet(WorldBorderPacket::new(action), None)?;
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(arg: &str) -> Result<T, CommandError> {
+    arg.parse()
+        .map_err(|_| CommandError::InvalidArguments(USAGE))
+}
+
+fn parse_non_negative(arg: &str) -> Result<i32, CommandError> {
+    parse::<u32>(arg)
+        .and_then(|value| i32::try_from(value).map_err(|_| CommandError::InvalidArguments(USAGE)))
+}
+
+/// World Border packet setting up the world's border for a player who has just joined
+pub fn initialize(world: &World) -> WorldBorderPacket {
+    let border = world.world_border();
+    WorldBorderPacket::new(WorldBorderAction::Initialize {
+        x: border.center.0,
+        z: border.center.1,
+        diameter: border.diameter,
+        portal_teleport_boundary: (MAX_WORLD_BORDER_DIAMETER / 2.0) as i32,
+        warning_time: border.warning_time,
+        warning_blocks: border.warning_blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::packet::{read_packet, Packet};
+
+    #[tokio::test]
+    async fn test_worldborder_set_broadcasts_size() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let world = sender.world.clone();
+        let dispatcher = CommandDispatcher::default();
+
+        dispatcher
+            .dispatch(sender.context(), "worldborder set 100")
+            .await
+            .unwrap();
+        assert_eq!(world.world_border().diameter, 100.0);
+
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            WorldBorderPacket::packet_id()
+        );
+        assert_eq!(
+            WorldBorderPacket::read_from_buffer(&mut packet).unwrap(),
+            WorldBorderPacket::new(WorldBorderAction::SetSize { diameter: 100.0 })
+        );
+
+        dispatcher
+            .dispatch(sender.context(), "worldborder center 10 -20.5")
+            .await
+            .unwrap();
+        assert_eq!(world.world_border().center, (10.0, -20.5));
+
+        let result = dispatcher
+            .dispatch(sender.context(), "worldborder set 0")
+            .await;
+        assert!(matches!(result, Err(CommandError::Failed(_))));
+        assert_eq!(world.world_border().diameter, 100.0);
+    }
+}
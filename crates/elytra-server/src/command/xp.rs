@@ -0,0 +1,84 @@
+use super::{Command, CommandContext, CommandError, CommandResult};
+use futures::future::BoxFuture;
+
+const USAGE: &str = "/xp add <amount> [points|levels]";
+
+/// `/xp add <amount> [points|levels]`, which gives the sender experience points, or whole levels
+pub fn command() -> Command {
+    Command {
+        name: "xp",
+        usage: USAGE,
+        required_level: 2,
+        handler,
+    }
+}
+
+fn handler(context: CommandContext<'_>, args: Vec<String>) -> BoxFuture<'_, CommandResult> {
+    Box::pin(run(context, args))
+}
+
+async fn run(context: CommandContext<'_>, args: Vec<String>) -> CommandResult {
+    let (amount, levels) = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["add", amount] | ["add", amount, "points"] => (*amount, false),
+        ["add", amount, "levels"] => (*amount, true),
+        _ => return Err(CommandError::InvalidArguments(USAGE)),
+    };
+    let amount: i32 = amount
+        .parse()
+        .map_err(|_| CommandError::InvalidArguments(USAGE))?;
+
+    if levels {
+        context.sender.add_experience_levels(amount)?;
+    } else {
+        context.sender.add_experience(amount)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::test_sender;
+    use crate::command::CommandDispatcher;
+    use elytra_protocol::experience::SetExperiencePacket;
+    use elytra_protocol::packet::{read_packet, Packet};
+
+    #[tokio::test]
+    async fn test_xp_add_points_levels_up() {
+        let (mut sender, mut client) = test_sender("Steve");
+        let dispatcher = CommandDispatcher::default();
+        dispatcher
+            .dispatch(sender.context(), "xp add 100")
+            .await
+            .unwrap();
+
+        // Levels 0 to 6 take 7 + 9 + ... + 19 = 91 points, leaving 9 of the 21 needed for level 8
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            packet.read_varint().unwrap(),
+            SetExperiencePacket::packet_id()
+        );
+        let experience = SetExperiencePacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(experience.level, 7);
+        assert_eq!(experience.total_experience, 100);
+        assert!((experience.experience_bar - 9.0 / 21.0).abs() < 1e-4);
+
+        dispatcher
+            .dispatch(sender.context(), "xp add 3 levels")
+            .await
+            .unwrap();
+        let mut packet = read_packet(&mut client).await.unwrap();
+        packet.read_varint().unwrap();
+        let experience = SetExperiencePacket::read_from_buffer(&mut packet).unwrap();
+        assert_eq!(experience.level, 10);
+        assert_eq!(experience.total_experience, 100);
+
+        let result = dispatcher.dispatch(sender.context(), "xp add lots").await;
+        assert!(matches!(result, Err(CommandError::InvalidArguments(_))));
+    }
+}
@@ -0,0 +1,152 @@
+use elytra_common::types::GameMode;
+use elytra_logger::severity::LogSeverity;
+use elytra_nbt::Compression;
+use elytra_protocol::session::DEFAULT_VIEW_DISTANCE;
+use elytra_protocol::status::DEFAULT_MOTD;
+use elytra_protocol::text_component::TextComponent;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+/// Default port the server listens on
+pub const DEFAULT_PORT: u16 = 25565;
+/// Default cap on connections open at the same time
+pub const DEFAULT_MAX_CONNECTIONS: usize = 100;
+/// Default cap on players online at the same time
+pub const DEFAULT_MAX_PLAYERS: usize = 20;
+/// Default radius, in chunks, of the area around spawn generated at startup
+pub const DEFAULT_SPAWN_RADIUS: u8 = 2;
+
+/// Kind of terrain generated for new chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LevelType {
+    /// A few flat layers of bedrock, dirt and grass
+    #[default]
+    Flat,
+    /// Rolling hills generated from the world seed
+    Default,
+}
+
+/// Resource pack offered to players when they join
+#[derive(Debug, Clone)]
+pub struct ResourcePackConfig {
+    /// Where the client downloads the pack from
+    pub url: String,
+    /// Hex SHA-1 of the pack file
+    pub sha1: String,
+    /// Kicks players who decline the pack
+    pub required: bool,
+}
+
+/// Server configuration
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address the server listens on. Port 0 lets the OS pick a free port.
+    pub bind_addr: SocketAddr,
+    /// Threads the runtime runs tasks on. `None` uses one per CPU core.
+    pub worker_threads: Option<usize>,
+    /// Connections, in any state, that can be open at once. Connections beyond this are closed
+    /// as soon as they are accepted.
+    pub max_connections: usize,
+    /// Players that can be online at once. Further players are turned away when they log in,
+    /// unless they are operators.
+    pub max_players: usize,
+    /// Encrypts every login with an RSA key generated at startup, as a vanilla online-mode server
+    /// does. Players aren't checked against Mojang's session servers, so this doesn't keep anyone
+    /// from joining under someone else's name.
+    pub online_mode: bool,
+    /// Message of the day shown under the server's name in the server list
+    pub motd: String,
+    /// Address of the admin socket, which answers line-based JSON queries such as
+    /// `{"cmd":"list"}`. It has no authentication, so bind it to localhost. `None` disables it.
+    pub admin_addr: Option<SocketAddr>,
+    /// Logs a hex dump of the bytes around the failure when a packet from a player can't be
+    /// read. The dump holds whatever the client sent, so it is only on by default in debug
+    /// builds.
+    pub packet_hex_dumps: bool,
+    /// Least severe messages written to the log. Debug output is only on by default in debug
+    /// builds.
+    pub min_log_severity: LogSeverity,
+    /// File the log is appended to as well as printed to the console. `None` only prints it.
+    pub log_file: Option<PathBuf>,
+    /// Game mode given to players when they join
+    pub default_gamemode: GameMode,
+    /// Terrain generated for new chunks
+    pub level_type: LevelType,
+    /// Furthest, in chunks, the server sends chunks to players. Players asking for a shorter view
+    /// distance in their settings get fewer chunks.
+    pub view_distance: u8,
+    /// Radius, in chunks, of the area around spawn generated before the server starts accepting
+    /// players, so the first to join doesn't wait for it. 0 only generates the spawn chunk.
+    pub spawn_radius: u8,
+    /// World seed. The same seed always generates the same terrain. Replaced by the seed in the
+    /// level file, if it has one.
+    pub seed: i64,
+    /// Name of the world, shown in the server log. Replaced by the name in the level file, if it
+    /// has one.
+    pub level_name: String,
+    /// Vanilla `level.dat` the world's name, seed and spawn are read from at startup. `None`, or
+    /// a file that doesn't exist, keeps the configured seed and spawns players at (0, 64, 0).
+    pub level_file: Option<PathBuf>,
+    /// Directory player data is saved to when players leave and restored from when they join.
+    /// `None` keeps players from being saved at all.
+    pub player_data_dir: Option<PathBuf>,
+    /// Directory of the region files chunks are loaded from and, on shutdown, saved to. `None`
+    /// regenerates the world on every start.
+    pub region_dir: Option<PathBuf>,
+    /// Compression level of saved data. `Compression::fast()` (level 1) spends the least CPU time
+    /// per save, `Compression::best()` (level 9) makes the smallest files.
+    pub compression_level: Compression,
+    /// `ops.json` file listing operators, loaded at startup. `None` makes nobody an operator.
+    pub ops_file: Option<PathBuf>,
+    /// NBT file, gzip compressed or not, holding the dimension codec sent on login. `None` uses
+    /// the built-in overworld codec.
+    pub dimension_codec_file: Option<PathBuf>,
+    /// Hides coordinates and other details from the F3 debug screen
+    pub reduced_debug_info: bool,
+    /// Shows the death screen when a player dies instead of respawning them right away
+    pub enable_respawn_screen: bool,
+    /// Text shown above the player list. Nothing is sent if neither this nor the footer is set.
+    pub player_list_header: Option<TextComponent>,
+    /// Text shown below the player list
+    pub player_list_footer: Option<TextComponent>,
+    /// Resource pack sent to players when they join. `None` leaves them on their own packs.
+    pub resource_pack: Option<ResourcePackConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, DEFAULT_PORT)),
+            worker_threads: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_players: DEFAULT_MAX_PLAYERS,
+            online_mode: false,
+            motd: DEFAULT_MOTD.to_owned(),
+            admin_addr: None,
+            packet_hex_dumps: cfg!(debug_assertions),
+            min_log_severity: if cfg!(debug_assertions) {
+                LogSeverity::Debug
+            } else {
+                LogSeverity::Info
+            },
+            log_file: Some(PathBuf::from("logs/latest.log")),
+            default_gamemode: GameMode::default(),
+            level_type: LevelType::default(),
+            view_distance: DEFAULT_VIEW_DISTANCE,
+            spawn_radius: DEFAULT_SPAWN_RADIUS,
+            seed: 0,
+            level_name: "world".to_owned(),
+            level_file: Some(PathBuf::from("world/level.dat")),
+            player_data_dir: Some(PathBuf::from("world/playerdata")),
+            region_dir: Some(PathBuf::from("world/region")),
+            compression_level: Compression::default(),
+            ops_file: Some(PathBuf::from("ops.json")),
+            dimension_codec_file: None,
+            reduced_debug_info: false,
+            enable_respawn_screen: true,
+            player_list_header: None,
+            player_list_footer: None,
+            resource_pack: None,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use elytra_nbt::{Compound, NBTFile, Tag};
+use elytra_wotra::world::BlockPos;
+use std::io;
+use std::path::Path;
+
+/// World settings read from a vanilla `level.dat`. Anything the file doesn't hold is left as
+/// `None`, for the configured value or built-in default to be used instead.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelData {
+    pub level_name: Option<String>,
+    pub spawn: Option<BlockPos>,
+    pub seed: Option<i64>,
+}
+
+impl LevelData {
+    /// Reads the `Data` compound at the root of a `level.dat`. The seed is taken from
+    /// `WorldGenSettings`, as saved since 1.16, or from `RandomSeed` in older worlds.
+    pub fn from_nbt(tag: &Tag) -> io::Result<Self> {
+        let data = tag
+            .as_compound()
+            .and_then(|root| root.get("Data"))
+            .and_then(Tag::as_compound)
+            .ok_or_else(|| invalid_data("level.dat has no Data compound"))?;
+
+        let level_name = match data.get("LevelName") {
+            Some(name) => Some(
+                name.as_string()
+                    .ok_or_else(|| invalid_data("Invalid LevelName"))?
+                    .clone(),
+            ),
+            None => None,
+        };
+
+        let spawn = match (
+            get_i32(data, "SpawnX")?,
+            get_i32(data, "SpawnY")?,
+            get_i32(data, "SpawnZ")?,
+        ) {
+            (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+            (None, None, None) => None,
+            _ => return Err(invalid_data("level.dat has an incomplete spawn position")),
+        };
+
+        let world_gen_seed = data
+            .get("WorldGenSettings")
+            .and_then(Tag::as_compound)
+            .and_then(|settings| settings.get("seed"));
+        let seed = match world_gen_seed.or_else(|| data.get("RandomSeed")) {
+            Some(seed) => Some(seed.as_i64().ok_or_else(|| invalid_data("Invalid seed"))?),
+            None => None,
+        };
+
+        Ok(Self {
+            level_name,
+            spawn,
+            seed,
+        })
+    }
+}
+
+/// Reads the level settings from a `level.dat`, gzip compressed or not. A missing file gives
+/// empty settings, as for a world that has never been saved.
+pub fn load_level(path: &Path) -> io::Result<LevelData> {
+    let file = match NBTFile::open(path) {
+        Ok(file) => file,
+        Err(open_error) if open_error.kind() == io::ErrorKind::NotFound => {
+            return Ok(LevelData::default())
+        }
+        Err(open_error) => return Err(open_error),
+    };
+    LevelData::from_nbt(&file.root)
+}
+
+fn get_i32(compound: &Compound, name: &str) -> io::Result<Option<i32>> {
+    match compound.get(name) {
+        Some(tag) => tag
+            .as_i32()
+            .map(Some)
+            .ok_or_else(|| invalid_data(format!("Invalid {}", name))),
+        None => Ok(None),
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_dat(data: Vec<(&str, Tag)>) -> Tag {
+        let data = data
+            .into_iter()
+            .map(|(name, tag)| (name.to_owned(), tag))
+            .collect();
+        Tag::Compound(Compound::from([("Data".to_owned(), Tag::Compound(data))]))
+    }
+
+    #[test]
+    fn test_load_level() {
+        let dir = std::env::temp_dir().join(format!("elytra-level-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("level.dat");
+        assert_eq!(load_level(&path).unwrap(), LevelData::default());
+
+        let root = level_dat(vec![
+            ("LevelName", Tag::String("Survival".to_owned())),
+            ("SpawnX", Tag::Int(-120)),
+            ("SpawnY", Tag::Int(71)),
+            ("SpawnZ", Tag::Int(36)),
+            (
+                "WorldGenSettings",
+                Tag::Compound(Compound::from([(
+                    "seed".to_owned(),
+                    Tag::Long(-4_172_144_997_902_289_642),
+                )])),
+            ),
+        ]);
+        NBTFile::new(String::new(), root).save(&path).unwrap();
+
+        assert_eq!(
+            load_level(&path).unwrap(),
+            LevelData {
+                level_name: Some("Survival".to_owned()),
+                spawn: Some((-120, 71, 36)),
+                seed: Some(-4_172_144_997_902_289_642),
+            }
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_level_seed_falls_back_to_random_seed() {
+        let level = LevelData::from_nbt(&level_dat(vec![("RandomSeed", Tag::Long(42))])).unwrap();
+        assert_eq!(level.seed, Some(42));
+        assert_eq!(level.spawn, None);
+
+        let partial_spawn = level_dat(vec![("SpawnX", Tag::Int(0))]);
+        assert!(LevelData::from_nbt(&partial_spawn).is_err());
+    }
+}
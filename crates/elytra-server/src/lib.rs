@@ -1 +1,8 @@
-pub mod server; 
\ No newline at end of file
+pub mod admin;
+pub mod command;
+pub mod config;
+pub mod level;
+pub mod ops;
+pub mod playerdata;
+pub mod registries;
+pub mod server;
\ No newline at end of file
@@ -1,9 +1,29 @@
-use elytra_logger::severity::LogSeverity::Info;
-use elytra_logger::log::log;
+use elytra_logger::log::{log, log_to_file};
+use elytra_logger::panic::install_panic_hook;
+use elytra_logger::severity::LogSeverity;
+use elytra_logger::severity::LogSeverity::{Info, Warning};
+use elytra_server::config::ServerConfig;
 use elytra_server::server;
 
-#[tokio::main]
-async fn main() {
+fn main() {
+    install_panic_hook();
+    let mut config = ServerConfig::default();
+    if let Some(path) = &config.log_file {
+        if let Err(open_error) = log_to_file(path) {
+            log(
+                format!("Failed to open log file {}: {}", path.display(), open_error),
+                Warning,
+            );
+        }
+    }
     log("Elytra init".to_owned(), Info);
-    server::run().await;
+    // ELYTRA_LOG_LEVEL=info quiets debug output without rebuilding
+    if let Ok(level) = std::env::var("ELYTRA_LOG_LEVEL") {
+        match LogSeverity::from_name(&level) {
+            Some(severity) => config.min_log_severity = severity,
+            None => log(format!("Unknown log level {}", level), Warning),
+        }
+    }
+    let runtime = server::build_runtime(&config).expect("Failed to start the async runtime");
+    runtime.block_on(server::run(config));
 }
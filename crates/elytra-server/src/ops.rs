@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Highest operator permission level
+pub const MAX_OP_LEVEL: u8 = 4;
+
+/// Entry of an `ops.json` file, as written by vanilla servers
+#[derive(Debug, Deserialize)]
+struct OpEntry {
+    uuid: String,
+    level: u8,
+}
+
+/// Operator permission levels of players, by UUID. Players that aren't listed have level 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpList {
+    levels: HashMap<Uuid, u8>,
+}
+
+impl OpList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads an `ops.json` file. A missing file is treated as an empty list.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(read_error) if read_error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self::new())
+            }
+            Err(read_error) => return Err(read_error),
+        };
+        Self::from_json(&contents)
+    }
+
+    /// Parses the contents of an `ops.json` file
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let entries: Vec<OpEntry> = serde_json::from_str(json)
+            .map_err(|json_error| io::Error::new(io::ErrorKind::InvalidData, json_error))?;
+
+        let mut ops = Self::new();
+        for entry in entries {
+            let uuid = Uuid::parse_str(&entry.uuid)
+                .map_err(|uuid_error| io::Error::new(io::ErrorKind::InvalidData, uuid_error))?;
+            ops.set_level(uuid, entry.level);
+        }
+        Ok(ops)
+    }
+
+    /// Sets a player's level, capped to `MAX_OP_LEVEL`
+    pub fn set_level(&mut self, uuid: Uuid, level: u8) {
+        self.levels.insert(uuid, level.min(MAX_OP_LEVEL));
+    }
+
+    pub fn level(&self, uuid: &Uuid) -> u8 {
+        self.levels.get(uuid).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ops_json() {
+        let ops = OpList::from_json(
+            r#"[
+                {"uuid": "069a79f4-44e9-4726-a5be-fca90e38aaf5", "name": "Notch", "level": 4, "bypassesPlayerLimit": false},
+                {"uuid": "853c80ef3c3749fdaa49938b674adae6", "name": "jeb_", "level": 9}
+            ]"#,
+        )
+        .unwrap();
+
+        let notch = Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+        let jeb = Uuid::parse_str("853c80ef-3c37-49fd-aa49-938b674adae6").unwrap();
+        assert_eq!(ops.level(&notch), 4);
+        assert_eq!(ops.level(&jeb), MAX_OP_LEVEL);
+        assert_eq!(ops.level(&Uuid::nil()), 0);
+
+        assert!(OpList::load(Path::new("does/not/exist/ops.json"))
+            .unwrap()
+            .levels
+            .is_empty());
+        assert!(OpList::from_json("{}").is_err());
+    }
+}
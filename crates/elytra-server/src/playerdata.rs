@@ -0,0 +1,188 @@
+use elytra_common::types::GameMode;
+use elytra_nbt::{Compound, Compression, NBTFile, Tag};
+use elytra_protocol::session::PlayerSession;
+use std::io;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Player state that outlives a connection, stored with the tag names of vanilla player data
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerData {
+    pub position: (f64, f64, f64),
+    pub yaw: f32,
+    pub pitch: f32,
+    pub gamemode: GameMode,
+    pub health: f32,
+    pub selected_hotbar_slot: u8,
+}
+
+impl PlayerData {
+    pub fn from_session(session: &PlayerSession) -> Self {
+        Self {
+            position: session.position,
+            yaw: session.yaw,
+            pitch: session.pitch,
+            gamemode: session.gamemode,
+            health: session.health,
+            selected_hotbar_slot: session.selected_hotbar_slot,
+        }
+    }
+
+    pub fn apply_to(&self, session: &mut PlayerSession) {
+        session.position = self.position;
+        session.yaw = self.yaw;
+        session.pitch = self.pitch;
+        session.gamemode = self.gamemode;
+        session.health = self.health;
+        session.selected_hotbar_slot = self.selected_hotbar_slot;
+    }
+
+    pub fn to_nbt(&self) -> Tag {
+        let (x, y, z) = self.position;
+        let mut compound = Compound::new();
+        compound.insert(
+            "Pos".to_string(),
+            Tag::List(vec![Tag::Double(x), Tag::Double(y), Tag::Double(z)]),
+        );
+        compound.insert(
+            "Rotation".to_string(),
+            Tag::List(vec![Tag::Float(self.yaw), Tag::Float(self.pitch)]),
+        );
+        compound.insert(
+            "playerGameType".to_string(),
+            Tag::Int(self.gamemode.id() as i32),
+        );
+        compound.insert("Health".to_string(), Tag::Float(self.health));
+        compound.insert(
+            "SelectedItemSlot".to_string(),
+            Tag::Int(self.selected_hotbar_slot as i32),
+        );
+        // Items aren't tracked yet, but vanilla tools expect the list to be there
+        compound.insert("Inventory".to_string(), Tag::List(Vec::new()));
+        Tag::Compound(compound)
+    }
+
+    pub fn from_nbt(tag: &Tag) -> io::Result<Self> {
+        let compound = tag
+            .as_compound()
+            .ok_or_else(|| invalid_data("Player data is not a compound"))?;
+        let get = |name: &str| {
+            compound
+                .get(name)
+                .ok_or_else(|| invalid_data(format!("Player data is missing {}", name)))
+        };
+
+        let position = match get("Pos")?.as_list().map(Vec::as_slice) {
+            Some([x, y, z]) => (
+                x.as_f64().ok_or_else(|| invalid_data("Invalid Pos"))?,
+                y.as_f64().ok_or_else(|| invalid_data("Invalid Pos"))?,
+                z.as_f64().ok_or_else(|| invalid_data("Invalid Pos"))?,
+            ),
+            _ => return Err(invalid_data("Invalid Pos")),
+        };
+        let (yaw, pitch) = match get("Rotation")?.as_list().map(Vec::as_slice) {
+            Some([yaw, pitch]) => (
+                yaw.as_f32()
+                    .ok_or_else(|| invalid_data("Invalid Rotation"))?,
+                pitch
+                    .as_f32()
+                    .ok_or_else(|| invalid_data("Invalid Rotation"))?,
+            ),
+            _ => return Err(invalid_data("Invalid Rotation")),
+        };
+        let gamemode = get("playerGameType")?
+            .as_i32()
+            .and_then(|id| u8::try_from(id).ok())
+            .and_then(GameMode::from_id)
+            .ok_or_else(|| invalid_data("Invalid playerGameType"))?;
+        let health = get("Health")?
+            .as_f32()
+            .ok_or_else(|| invalid_data("Invalid Health"))?;
+        let selected_hotbar_slot = get("SelectedItemSlot")?
+            .as_i32()
+            .and_then(|slot| u8::try_from(slot).ok())
+            .filter(|&slot| slot <= 8)
+            .ok_or_else(|| invalid_data("Invalid SelectedItemSlot"))?;
+
+        Ok(Self {
+            position,
+            yaw,
+            pitch,
+            gamemode,
+            health,
+            selected_hotbar_slot,
+        })
+    }
+}
+
+/// File the data of the player with the given UUID is stored in
+pub fn player_data_path(dir: &Path, uuid: &Uuid) -> PathBuf {
+    dir.join(format!("{}.dat", uuid.hyphenated()))
+}
+
+/// Saves a player's state to `<dir>/<uuid>.dat` at the given compression level, creating the
+/// directory if needed
+pub fn save_player(dir: &Path, session: &PlayerSession, level: Compression) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let file = NBTFile::new(String::new(), PlayerData::from_session(session).to_nbt());
+    file.save_with(player_data_path(dir, &session.uuid), level)
+}
+
+/// Restores a player's saved state into their session. Returns false, leaving the session's
+/// spawn defaults alone, if the player has never been saved.
+pub fn load_player(dir: &Path, session: &mut PlayerSession) -> io::Result<bool> {
+    let file = match NBTFile::open(player_data_path(dir, &session.uuid)) {
+        Ok(file) => file,
+        Err(open_error) if open_error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(open_error) => return Err(open_error),
+    };
+    PlayerData::from_nbt(&file.root)?.apply_to(session);
+    Ok(true)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elytra_protocol::login::LoginSuccessPacket;
+
+    fn session(username: &str) -> PlayerSession {
+        let (_client, server) = tokio::io::duplex(1024);
+        let uuid = LoginSuccessPacket::new(username.to_owned()).uuid;
+        PlayerSession::from_stream(username.to_owned(), uuid, server).0
+    }
+
+    #[tokio::test]
+    async fn test_save_and_restore_player() {
+        let dir = std::env::temp_dir().join(format!("elytra-playerdata-{}", std::process::id()));
+
+        let mut steve = session("Steve");
+        // Nothing saved yet, the spawn defaults stay
+        assert!(!load_player(&dir, &mut steve).unwrap());
+        assert_eq!(steve.position, (0.0, 64.0, 0.0));
+
+        steve.position = (12.5, 70.0, -3.25);
+        steve.yaw = 90.0;
+        steve.pitch = -15.0;
+        steve.gamemode = GameMode::Creative;
+        steve.health = 7.5;
+        steve.selected_hotbar_slot = 6;
+        save_player(&dir, &steve, Compression::default()).unwrap();
+
+        let mut restored = session("Steve");
+        assert!(load_player(&dir, &mut restored).unwrap());
+        assert_eq!(
+            PlayerData::from_session(&restored),
+            PlayerData::from_session(&steve)
+        );
+
+        // Other players aren't affected
+        let mut alex = session("Alex");
+        assert!(!load_player(&dir, &mut alex).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use elytra_nbt::{NBTFile, Tag};
+use elytra_protocol::join_game::default_dimension_codec;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Registries sent to players when they log in, cached so they aren't rebuilt for every login.
+/// Players already online keep whatever they were sent until they relog.
+pub struct Registries {
+    /// NBT file the dimension codec is read from. `None` uses the built-in codec.
+    source: Option<PathBuf>,
+    dimension_codec: RwLock<Arc<Tag>>,
+}
+
+impl Registries {
+    /// Loads the registries, failing if the source file can't be read
+    pub fn load(source: Option<PathBuf>) -> io::Result<Self> {
+        let dimension_codec = read_dimension_codec(&source)?;
+        Ok(Self {
+            source,
+            dimension_codec: RwLock::new(Arc::new(dimension_codec)),
+        })
+    }
+
+    /// Dimension codec for the next player to log in
+    pub fn dimension_codec(&self) -> Arc<Tag> {
+        self.dimension_codec
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Reads the source again. If that fails, the registries loaded before stay in use.
+    pub fn reload(&self) -> io::Result<()> {
+        let dimension_codec = read_dimension_codec(&self.source)?;
+        *self
+            .dimension_codec
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(dimension_codec);
+        Ok(())
+    }
+}
+
+fn read_dimension_codec(source: &Option<PathBuf>) -> io::Result<Tag> {
+    let Some(path) = source else {
+        return Ok(default_dimension_codec());
+    };
+    let codec = NBTFile::open(path)?.root;
+    if codec.as_compound().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} does not hold a compound tag", path.display()),
+        ));
+    }
+    Ok(codec)
+}
@@ -1,207 +1,1148 @@
-use elytra_logger::severity::LogSeverity::{Debug, Error, Info};
+use crate::admin;
+use crate::command::{time, weather, worldborder, CommandContext, CommandDispatcher};
+use crate::config::{LevelType, ServerConfig};
+use crate::level::{self, LevelData};
+use crate::ops::OpList;
+use crate::playerdata;
+use crate::registries::Registries;
+use elytra_logger::severity::LogSeverity::{Debug, Error, Info, Warning};
 use elytra_logger::systime;
-use elytra_logger::log::log;
+use elytra_logger::log::{log, set_min_severity, ContextLogger};
+use elytra_protocol::chat::{ChatMessagePacket, ChatMessageServerboundPacket};
 use elytra_protocol::client_settings::ClientSettingsPacket;
 use elytra_protocol::declare_commands::{CommandNode, DeclareCommandsPacket, Parser, StringType};
+use elytra_protocol::disconnect::DisconnectPacket;
+use elytra_protocol::encryption::{EncryptedStream, EncryptionResponsePacket, ServerKey};
+use elytra_protocol::entity_action::EntityActionPacket;
+use elytra_protocol::entity_status::EntityStatusPacket;
 use elytra_protocol::handshake::*;
-use elytra_protocol::join_game::JoinGamePacket;
+use elytra_protocol::held_item_change::HeldItemChangeServerboundPacket;
+use elytra_protocol::interact_entity::InteractEntityPacket;
+use elytra_protocol::join_game::{hash_seed, JoinGamePacket};
 use elytra_protocol::keep_alive::KeepAlivePacket;
-use elytra_protocol::login::{LoginStartPacket, LoginSuccessPacket};
+use elytra_protocol::legacy_ping::{LegacyStatusResponse, LEGACY_PING_ID, LEGACY_PING_PAYLOAD};
+use elytra_protocol::login::{LoginDisconnectPacket, LoginStartPacket, LoginSuccessPacket};
 use elytra_protocol::packet::*;
-use elytra_protocol::player_position_and_look::PlayerPositionAndLook;
+use elytra_protocol::player_abilities::PlayerAbilitiesPacket;
+use elytra_protocol::player_info::PlayerInfoPacket;
+use elytra_protocol::player_list_header::PlayerListHeaderFooterPacket;
+use elytra_protocol::player_position_and_look::{
+    PlayerPositionAndLookServerbound, TeleportConfirmPacket,
+};
+use elytra_protocol::plugin_message::{PluginMessagePacket, PluginMessageServerboundPacket};
+use elytra_protocol::protocol_version::ProtocolVersion;
+use elytra_protocol::resource_pack::{
+    ResourcePackSendPacket, ResourcePackStatus, ResourcePackStatusPacket,
+};
 use elytra_protocol::session::PlayerSession;
-use elytra_protocol::session_manager::SessionManager;
-use elytra_protocol::status::StatusResponsePacket;
+use elytra_protocol::session_manager::{JoinSlot, SessionManager};
+use elytra_protocol::spawn_position::SpawnPositionPacket;
+use elytra_protocol::status::{PingPacket, PongPacket, StatusResponsePacket};
+use elytra_protocol::text_component::TextComponent;
+use elytra_protocol::update_light::UpdateLightPacket;
+use elytra_protocol::vehicle::{SteerBoatPacket, SteerVehiclePacket, VehicleMovePacket};
+use elytra_wotra::generator::{CaveCarver, FlatGenerator, NoiseGenerator};
+use elytra_wotra::lighting::light_packet;
+use elytra_wotra::world::{ChunkPos, World};
 use once_cell::sync;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 use tokio::io;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadHalf};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock;
-use tokio::time::{interval, Duration, Instant};
+use tokio::runtime::{self, Runtime};
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, interval_at, Duration, Instant};
+
+/// Shortest view distance chunks are sent for, whatever the client asks for
+const MIN_VIEW_DISTANCE: u8 = 2;
+/// How long to wait for a joining client's settings before sending the world with the defaults
+const CLIENT_SETTINGS_TIMEOUT: Duration = Duration::from_secs(1);
+/// How often players are sent a keep-alive
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+/// Decoded packets queued between a player's read task and their play loop
+const PLAYER_EVENT_QUEUE_CAPACITY: usize = 64;
+/// Brand announced to clients, shown in their debug screen
+const SERVER_BRAND: &str = "Elytra";
+/// Reason shown to players turned away because the server is full
+const SERVER_FULL_MESSAGE: &str = "Server is full";
+/// Reason shown to players whose Encryption Response doesn't hold the verify token they were sent
+const VERIFY_TOKEN_MISMATCH_MESSAGE: &str = "Invalid verify token";
+/// Length of a server tick
+const TICK_DURATION: Duration = Duration::from_millis(50);
+/// Number of ticks between Time Updates sent to keep clients' clocks in sync
+const TIME_UPDATE_INTERVAL_TICKS: u64 = 20;
 
 // Global session manager
-static SESSION_MANAGER: sync::Lazy<Arc<RwLock<SessionManager>>> =
-    sync::Lazy::new(|| Arc::new(RwLock::new(SessionManager::new())));
+static SESSION_MANAGER: sync::Lazy<Arc<SessionManager>> =
+    sync::Lazy::new(|| Arc::new(SessionManager::new()));
 
-/// Starts the server and listens for incoming connections.
-/// The server will listen on port 25565 by default.
-pub async fn run() {
-    // TODO: Should be an option for manually setting IP and Port
-    let listener = TcpListener::bind("0.0.0.0:25565").await.unwrap();
-    log("Listening on port 25565".to_owned(), Info);
+// Key pair online-mode logins are encrypted with, generated by the first online-mode server
+static SERVER_KEY: sync::OnceCell<ServerKey> = sync::OnceCell::new();
 
-    // Spawn keep-alive checker task
-    tokio::spawn(keep_alive_checker());
+// Entity id handed to the next player that logs in
+static NEXT_ENTITY_ID: AtomicI32 = AtomicI32::new(1);
 
-    loop {
-        let (socket, addr) = listener.accept().await.unwrap();
-        log(format!("New connection from: {}", addr), Info);
-        tokio::spawn(handle_connection(socket));
+// Commands players can run from the chat box
+static COMMAND_DISPATCHER: sync::Lazy<CommandDispatcher> =
+    sync::Lazy::new(CommandDispatcher::default);
+
+/// Builds the multi-threaded runtime the server runs on, with as many workers as the config asks
+/// for
+pub fn build_runtime(config: &ServerConfig) -> io::Result<Runtime> {
+    let mut builder = runtime::Builder::new_multi_thread();
+    if let Some(worker_threads) = config.worker_threads {
+        builder.worker_threads(worker_threads);
     }
+    builder.enable_all().build()
 }
 
-/// Task that checks for timed-out connections
-async fn keep_alive_checker() {
-    let mut interval = interval(Duration::from_secs(1));
-    loop {
-        interval.tick().await;
-        let mut session_manager = SESSION_MANAGER.write().await;
-
-        // Check for timed-out sessions
-        let to_remove = session_manager.check_keep_alives().await;
-        for username in to_remove {
-            if let Some(session) = session_manager.remove_session(&username) {
-                log(format!("Player {} timed out", session.username), Info);
+/// Starts the server with the given configuration and serves connections until Ctrl-C is
+/// pressed, then shuts it down and saves the world.
+pub async fn run(config: ServerConfig) {
+    let handle = start(config).await.unwrap();
+    if let Err(signal_error) = tokio::signal::ctrl_c().await {
+        log(
+            format!("Failed to listen for Ctrl-C: {}", signal_error),
+            Error,
+        );
+        return handle.wait().await;
+    }
+    log("Stopping the server".to_owned(), Info);
+    handle.shutdown().await;
+}
+
+/// Handle to a running server, returned by [`start`]. Dropping the handle stops the server.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    admin_addr: Option<SocketAddr>,
+    shutdown_sender: watch::Sender<bool>,
+    accept_task: JoinHandle<()>,
+    registries: Arc<Registries>,
+    world: Arc<World>,
+}
+
+impl ServerHandle {
+    /// Address the server is actually bound to, including the OS-assigned port
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Address the admin socket is bound to, if it is enabled
+    pub fn admin_addr(&self) -> Option<SocketAddr> {
+        self.admin_addr
+    }
+
+    /// Stops accepting connections, waits for the listener to close and saves the chunks that
+    /// changed to their region files. Connections that were already accepted are left to finish
+    /// on their own.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown_sender.send(true);
+        let _ = self.accept_task.await;
+
+        let world = self.world.clone();
+        match tokio::task::spawn_blocking(move || world.save_dirty_chunks()).await {
+            Ok(Ok(saved)) => log(format!("Saved {} chunks", saved), Info),
+            Ok(Err(save_error)) => log(format!("Failed to save chunks: {}", save_error), Error),
+            Err(join_error) => log(format!("Failed to save chunks: {}", join_error), Error),
+        }
+    }
+
+    /// Reads the registries, such as the dimension codec, from their source again. Players who
+    /// log in afterwards get the new registries; players already online keep theirs until they
+    /// relog.
+    pub fn reload_registries(&self) -> io::Result<()> {
+        self.registries.reload()
+    }
+
+    /// World the server is running
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Waits until the server stops accepting connections
+    pub async fn wait(self) {
+        let _ = self.accept_task.await;
+    }
+}
+
+/// Binds the listener described by `config` and starts accepting connections in the background.
+pub async fn start(mut config: ServerConfig) -> io::Result<ServerHandle> {
+    set_min_severity(config.min_log_severity);
+    let registries = Arc::new(Registries::load(config.dimension_codec_file.clone())?);
+    let listener = TcpListener::bind(config.bind_addr).await?;
+    let local_addr = listener.local_addr()?;
+    log(format!("Listening on {}", local_addr), Info);
+
+    let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+    let admin_addr = match config.admin_addr {
+        Some(admin_addr) => {
+            let admin_listener = TcpListener::bind(admin_addr).await?;
+            let admin_addr = admin_listener.local_addr()?;
+            log(format!("Admin socket listening on {}", admin_addr), Info);
+            tokio::spawn(admin::serve(
+                admin_listener,
+                SESSION_MANAGER.clone(),
+                shutdown_receiver.clone(),
+            ));
+            Some(admin_addr)
+        }
+        None => None,
+    };
+    let level = match &config.level_file {
+        Some(path) => level::load_level(path)?,
+        None => LevelData::default(),
+    };
+    if let Some(level_name) = level.level_name {
+        config.level_name = level_name;
+    }
+    if let Some(seed) = level.seed {
+        config.seed = seed;
+    }
+    log(format!("Loading level {}", config.level_name), Info);
+    let world = Arc::new(create_world(&config));
+    if let Some(spawn) = level.spawn {
+        world.set_spawn(spawn);
+    }
+    prepare_spawn_area(&world, config.spawn_radius).await?;
+    let ops = Arc::new(load_ops(&config));
+    if config.online_mode {
+        // Generated up front so the first player to join doesn't wait for it
+        SERVER_KEY.get_or_try_init(ServerKey::generate)?;
+    }
+
+    let config = Arc::new(config);
+    tokio::spawn(tick_loop(world.clone(), shutdown_receiver.clone()));
+    let max_connections = config.max_connections;
+    let connect = {
+        let world = world.clone();
+        let registries = registries.clone();
+        move |socket, logger| {
+            handle_connection(
+                socket,
+                config.clone(),
+                world.clone(),
+                ops.clone(),
+                registries.clone(),
+                logger,
+            )
+        }
+    };
+    let accept_task = tokio::spawn(accept_connections(
+        listener,
+        max_connections,
+        connect,
+        shutdown_receiver,
+    ));
+
+    Ok(ServerHandle {
+        local_addr,
+        admin_addr,
+        shutdown_sender,
+        accept_task,
+        registries,
+        world,
+    })
+}
+
+/// Creates the world with the generator selected by the configuration, saved to the configured
+/// region directory
+fn create_world(config: &ServerConfig) -> World {
+    let world = match config.level_type {
+        LevelType::Flat => World::new(FlatGenerator::default()),
+        LevelType::Default => World::new(CaveCarver::new(
+            NoiseGenerator::new(config.seed),
+            config.seed.wrapping_add(1),
+        )),
+    };
+    match &config.region_dir {
+        Some(region_dir) => world.with_region_dir(region_dir),
+        None => world,
+    }
+}
+
+/// Generates the chunks within `spawn_radius` of spawn, in parallel, so they are already loaded
+/// when the first player joins
+async fn prepare_spawn_area(world: &World, spawn_radius: u8) -> io::Result<()> {
+    let started = Instant::now();
+    let (spawn_x, _, spawn_z) = world.spawn();
+    let positions = chunks_around((spawn_x >> 4, spawn_z >> 4), spawn_radius as i32);
+    world.get_or_generate(&positions).await?;
+    log(
+        format!(
+            "Prepared {} spawn chunks in {} ms",
+            positions.len(),
+            started.elapsed().as_millis()
+        ),
+        Info,
+    );
+    Ok(())
+}
+
+/// Chunks within `radius` chunks of `center`, in a square spiral going outward from it, so the
+/// nearest chunks come first
+fn chunks_around(center: ChunkPos, radius: i32) -> Vec<ChunkPos> {
+    let side = 2 * radius + 1;
+    let total = (side * side) as usize;
+    let mut positions = Vec::with_capacity(total);
+    let (mut x, mut z) = center;
+    positions.push(center);
+
+    // Legs of the spiral grow by one every two turns: 1, 1, 2, 2, 3, 3, ...
+    let (mut step_x, mut step_z) = (1, 0);
+    let mut leg_length = 1;
+    while positions.len() < total {
+        for _ in 0..2 {
+            for _ in 0..leg_length {
+                x += step_x;
+                z += step_z;
+                // The last leg runs past the square before the spiral is complete
+                if (x - center.0).abs() <= radius && (z - center.1).abs() <= radius {
+                    positions.push((x, z));
+                }
             }
+            (step_x, step_z) = (-step_z, step_x);
         }
+        leg_length += 1;
     }
+    positions
 }
 
-async fn handle_connection(mut socket: TcpStream) {
-    let mut buffer = [0u8; 1024];
-    match socket.read(&mut buffer).await {
-        Ok(size) if size > 0 => {
-            let mut handshake_packet_buffer =
-                MinecraftPacketBuffer::from_bytes(buffer[..size].to_vec());
-            match HandshakePacket::read_from_buffer(&mut handshake_packet_buffer) {
-                Ok(handshake_packet) => {
-                    log(format!("Received handshake: {:?}", handshake_packet), Debug);
-                    if let Err(handshake_error) =
-                        handle_handshake_next_state(socket, handshake_packet).await
-                    {
-                        log(
-                            format!("Failed to handle handshake: {}", handshake_error),
-                            Error,
+/// Loads the operators listed in the configured ops file. A broken file is reported and leaves
+/// nobody opped rather than keeping the server from starting.
+fn load_ops(config: &ServerConfig) -> OpList {
+    let Some(path) = &config.ops_file else {
+        return OpList::new();
+    };
+    OpList::load(path).unwrap_or_else(|load_error| {
+        log(
+            format!("Failed to load ops from {}: {}", path.display(), load_error),
+            Error,
+        );
+        OpList::new()
+    })
+}
+
+/// Accepts incoming connections until shutdown is requested
+/// Accepts connections until shutdown, handing each one to `connect` as long as fewer than
+/// `max_connections` are open
+async fn accept_connections<C, F>(
+    listener: TcpListener,
+    max_connections: usize,
+    connect: C,
+    mut shutdown: watch::Receiver<bool>,
+) where
+    C: Fn(TcpStream, ContextLogger) -> F,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let mut next_connection_id = 1;
+    let connection_slots = Arc::new(Semaphore::new(max_connections));
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((socket, addr)) => {
+                    let logger = ContextLogger::for_connection(next_connection_id, addr);
+                    next_connection_id += 1;
+                    // Dropping the socket closes the connection
+                    let Ok(slot) = connection_slots.clone().try_acquire_owned() else {
+                        logger.log(
+                            format!(
+                                "Refused connection from {}, already at the limit of {} connections",
+                                addr, max_connections
+                            ),
+                            Warning,
                         );
-                    }
+                        continue;
+                    };
+                    logger.log(format!("New connection from: {}", addr), Info);
+                    // Each connection runs in its own task, so a panic while handling it only
+                    // ends that connection and is reported through the panic hook. The slot is
+                    // freed when the task ends either way.
+                    let connection = connect(socket, logger);
+                    tokio::spawn(async move {
+                        connection.await;
+                        drop(slot);
+                    });
                 }
-                Err(handshake_parse_error) => log(
-                    format!("Failed to parse handshake: {}", handshake_parse_error),
+                Err(accept_error) => log(
+                    format!("Failed to accept connection: {}", accept_error),
                     Error,
                 ),
+            },
+        }
+    }
+    log(
+        format!("Stopped listening on {:?}", listener.local_addr()),
+        Info,
+    );
+}
+
+/// Task that advances the world every tick and periodically syncs the time with all players.
+/// Players' status effects are ticked by their own play loops.
+async fn tick_loop(world: Arc<World>, mut shutdown: watch::Receiver<bool>) {
+    let mut interval = interval(TICK_DURATION);
+    let mut ticks: u64 = 0;
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = interval.tick() => {}
+        }
+        world.tick();
+        ticks += 1;
+
+        if ticks.is_multiple_of(TIME_UPDATE_INTERVAL_TICKS) {
+            if let Err(broadcast_error) =
+                SESSION_MANAGER.broadcast_packet(time::time_update(&world), None)
+            {
+                log(
+                    format!("Failed to send time update: {}", broadcast_error),
+                    Warning,
+                );
             }
         }
-        Err(socket_read_error) => log(
-            format!("Failed to read from socket: {}", socket_read_error),
+    }
+}
+
+async fn handle_connection(
+    mut socket: TcpStream,
+    config: Arc<ServerConfig>,
+    world: Arc<World>,
+    ops: Arc<OpList>,
+    registries: Arc<Registries>,
+    logger: ContextLogger,
+) {
+    // Clients before 1.7 open with a legacy ping where a handshake's length would be
+    let mut first_byte = [0; 1];
+    if matches!(socket.peek(&mut first_byte).await, Ok(1)) && first_byte[0] == LEGACY_PING_ID {
+        if let Err(ping_error) = handle_legacy_ping(socket, &config).await {
+            logger.log(
+                format!("Failed to answer legacy ping: {}", ping_error),
+                Debug,
+            );
+        }
+        return;
+    }
+
+    let mut handshake_packet_buffer = match read_packet(&mut socket).await {
+        Ok(packet_buffer) => packet_buffer,
+        Err(socket_read_error) => {
+            logger.log(
+                format!("Failed to read from socket: {}", socket_read_error),
+                Error,
+            );
+            return;
+        }
+    };
+
+    match read_handshake(&mut handshake_packet_buffer) {
+        Ok(handshake_packet) => {
+            logger.log(format!("Received handshake: {:?}", handshake_packet), Debug);
+            if let Err(handshake_error) = handle_handshake_next_state(
+                socket,
+                handshake_packet,
+                &config,
+                &world,
+                &ops,
+                &registries,
+                &logger,
+            )
+            .await
+            {
+                logger.log(
+                    format!("Failed to handle handshake: {}", handshake_error),
+                    Error,
+                );
+            }
+        }
+        Err(handshake_parse_error) => logger.log(
+            format!("Failed to parse handshake: {}", handshake_parse_error),
             Error,
         ),
-        Ok(_) => panic!("This should never happen"),
     }
 }
 
-/// Handles the play state after login and join game
-async fn handle_play_state(socket: TcpStream, username: String) -> io::Result<()> {
-    let mut raw_buffer = [0u8; 1024];
-    let mut last_keep_alive_time = Instant::now();
+/// Answers a pre-1.7 server list ping with the legacy status, then closes the connection
+async fn handle_legacy_ping(mut socket: TcpStream, config: &ServerConfig) -> io::Result<()> {
+    // 1.6 clients follow the ping with a plugin message that the response doesn't depend on
+    let mut ping = [0; 256];
+    let read = socket.read(&mut ping).await?;
+    let with_version = read > 1 && ping[1] == LEGACY_PING_PAYLOAD;
 
-    // Create session with split socket
-    let (session, mut reader) = PlayerSession::new(username.clone(), socket);
+    let response = LegacyStatusResponse {
+        protocol: ProtocolVersion::LATEST.id(),
+        version: ProtocolVersion::LATEST.name().to_owned(),
+        motd: config.motd.clone(),
+        online: SESSION_MANAGER.player_count(),
+        max_players: config.max_players,
+    };
+    socket.write_all(&response.to_bytes(with_version)).await?;
+    socket.shutdown().await
+}
 
-    // Add session to manager
-    {
-        let mut session_manager = SESSION_MANAGER.write().await;
-        session_manager.add_session(session);
+/// Reads the packet ID and body of a handshake packet
+fn read_handshake(buffer: &mut MinecraftPacketBuffer) -> io::Result<HandshakePacket> {
+    let packet_id = buffer.read_varint()?;
+    if packet_id != HandshakePacket::packet_id() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid packet ID",
+        ));
     }
+    HandshakePacket::read_from_buffer(buffer)
+}
 
-    loop {
-        // Send keep-alive packet every 10 seconds
-        if last_keep_alive_time.elapsed() >= Duration::from_secs(10) {
-            let keep_alive_id = systime::unix_timestamp();
-            let keep_alive_packet = KeepAlivePacket::new(keep_alive_id);
+/// Sends Join Game and the player state that goes with it. The world follows in [`send_world`]
+/// once the client has sent its settings.
+async fn send_join_game(
+    session: &mut PlayerSession,
+    config: &ServerConfig,
+    world: &World,
+    registries: &Registries,
+) -> io::Result<()> {
+    let join_game = join_game_packet(session, config, world, registries);
+    session.has_skylight = join_game.has_skylight();
+    session.send_packet(join_game).await?;
+    session
+        .send_packet(PluginMessagePacket::brand(SERVER_BRAND))
+        .await?;
+    session
+        .send_packet(PlayerAbilitiesPacket::for_gamemode(session.gamemode))
+        .await?;
 
-            {
-                let mut session_manager = SESSION_MANAGER.write().await;
-                if let Some(session) = session_manager.get_session(&username) {
-                    session.last_keep_alive_id = keep_alive_id;
-                    session.last_keep_alive_time = Instant::now();
-                    session.send_packet(keep_alive_packet).await?;
-                }
+    // Reassert the selected hotbar slot so the client doesn't desync from the server
+    session.sync_held_item().await?;
+
+    // Lets the client know which operator features, such as the gamemode switcher, to offer
+    session
+        .send_packet(EntityStatusPacket::op_permission_level(
+            session.entity_id,
+            session.op_level,
+        ))
+        .await?;
+
+    // let declare_recipes_packet = DeclareRecipesPacket::new();
+    // send_packet(declare_recipes_packet, &mut socket).await?;
+
+    // Send command graph
+    session.send_packet(create_command_graph()).await?;
+    Ok(())
+}
+
+/// Waits for the Client Settings a client sends right after Join Game, skipping any other
+/// packets that arrive first. Returns `None` if they don't arrive in time.
+async fn await_client_settings<R: AsyncRead + Unpin>(
+    reader: &mut FramedReader<R>,
+    logger: &ContextLogger,
+) -> io::Result<Option<ClientSettingsPacket>> {
+    let wait_for_settings = async {
+        loop {
+            let mut packet = reader.read_packet().await?;
+            let packet_id = packet.read_varint()?;
+            if packet_id == ClientSettingsPacket::packet_id() {
+                return ClientSettingsPacket::read_from_buffer(&mut packet);
             }
+            logger.log(
+                format!("Skipped packet 0x{:02x} while joining", packet_id),
+                Debug,
+            );
+        }
+    };
+    match tokio::time::timeout(CLIENT_SETTINGS_TIMEOUT, wait_for_settings).await {
+        Ok(settings) => settings.map(Some),
+        Err(_) => Ok(None),
+    }
+}
 
-            last_keep_alive_time = Instant::now();
-        }
-
-        match reader.read(&mut raw_buffer).await {
-            Ok(size) if size > 0 => {
-                let mut packet_buffer =
-                    MinecraftPacketBuffer::from_bytes(raw_buffer[..size].to_vec());
-                let packet_id = packet_buffer.read_varint()?;
-
-                match packet_id {
-                    // Keep-alive response
-                    0x0F => {
-                        if let Ok(keep_alive) =
-                            KeepAlivePacket::read_from_buffer(&mut packet_buffer)
-                        {
-                            let mut session_manager = SESSION_MANAGER.write().await;
-                            if let Some(session) = session_manager.get_session(&username) {
-                                if keep_alive.keep_alive_id == session.last_keep_alive_id {
-                                    session.last_keep_alive_response = Instant::now();
-                                }
-                            }
-
-                            log(format!("Received keep alive packet from player: {}", username), Debug);
-                        }
-                    }
-                    // Player Position
-                    0x11 => {
-                        let x = packet_buffer.read_f64()?;
-                        let y = packet_buffer.read_f64()?;
-                        let z = packet_buffer.read_f64()?;
-                        let yaw = packet_buffer.read_f32()?;
-                        let pitch = packet_buffer.read_f32()?;
-
-                        let mut session_manager = SESSION_MANAGER.write().await;
-                        if let Some(session) = session_manager.get_session(&username) {
-                            session.update_position(x, y, z, yaw, pitch);
-                            session_manager
-                                .broadcast_position_updates(&username)
-                                .await?;
-                        }
-                    }
-                    // Client Settings packet
-                    0x05 => {
-                        if let Ok(settings) =
-                            ClientSettingsPacket::read_from_buffer(&mut packet_buffer)
-                        {
-                            log(
-                                format!(
-                                    "Received packet 0x{:02x} (Client Settings): {:?}",
-                                    packet_id, settings
-                                ),
-                                Debug,
-                            );
-                        }
-                    }
-                    _ => {
-                        log(
-                            format!("Received unknown packet 0x{:02x}", packet_id),
+/// Sends the world around the player, sized to their view distance, followed by their position
+/// and the state of the world
+async fn send_world(
+    session: &mut PlayerSession,
+    config: &ServerConfig,
+    world: &World,
+) -> io::Result<()> {
+    send_spawn_chunks(session, world).await?;
+
+    // Points compasses at the world spawn
+    session
+        .send_packet(SpawnPositionPacket::new(world.spawn()))
+        .await?;
+
+    // Send initial position and look, either spawn or where the player last left
+    let (x, y, z) = session.position;
+    let (yaw, pitch) = (session.yaw, session.pitch);
+    session.teleport(x, y, z, yaw, pitch).await?;
+
+    session.send_packet(time::time_update(world)).await?;
+    for packet in weather::current_weather(world) {
+        session.send_packet(packet).await?;
+    }
+    session.send_packet(worldborder::initialize(world)).await?;
+
+    if let Some(packet) = player_list_header_footer(config) {
+        session.send_packet(packet).await?;
+    }
+
+    if let Some(resource_pack) = &config.resource_pack {
+        session
+            .send_packet(ResourcePackSendPacket::new(
+                &resource_pack.url,
+                &resource_pack.sha1,
+            ))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Player list header and footer from the config, if either is set
+fn player_list_header_footer(config: &ServerConfig) -> Option<PlayerListHeaderFooterPacket> {
+    if config.player_list_header.is_none() && config.player_list_footer.is_none() {
+        return None;
+    }
+    let empty = TextComponent::default();
+    Some(PlayerListHeaderFooterPacket::new(
+        config.player_list_header.as_ref().unwrap_or(&empty),
+        config.player_list_footer.as_ref().unwrap_or(&empty),
+    ))
+}
+
+/// Join Game packet for a player, with the seed and world flags taken from the config and the
+/// generator
+fn join_game_packet(
+    session: &PlayerSession,
+    config: &ServerConfig,
+    world: &World,
+    registries: &Registries,
+) -> JoinGamePacket {
+    JoinGamePacket {
+        dimension_codec: registries.dimension_codec().as_ref().clone(),
+        reduced_debug_info: config.reduced_debug_info,
+        enable_respawn_screen: config.enable_respawn_screen,
+        is_flat: world.is_flat(),
+        hashed_seed: hash_seed(config.seed),
+        view_distance: config.view_distance as i32,
+        max_players: i32::try_from(config.max_players).unwrap_or(i32::MAX),
+        ..JoinGamePacket::new(
+            session.entity_id,
+            session.gamemode,
+            vec!["minecraft:overworld".to_owned()],
+            "minecraft:overworld".to_owned(),
+        )
+    }
+}
+
+/// Sends the chunks within the player's view distance, each preceded by its light, starting with
+/// the chunk the player is in and spiralling outward so the world doesn't fill in from the edges.
+/// Chunks that aren't loaded yet are generated in parallel before any of them is sent.
+async fn send_spawn_chunks(session: &mut PlayerSession, world: &World) -> io::Result<()> {
+    let (x, _, z) = session.position;
+    let player_chunk = ((x.floor() as i32) >> 4, (z.floor() as i32) >> 4);
+    let positions = chunks_around(player_chunk, session.view_distance as i32);
+
+    let encoded_chunks = world.get_or_encode(&positions).await?;
+    for (&(chunk_x, chunk_z), encoded) in positions.iter().zip(encoded_chunks) {
+        let light = match world.get_chunk(chunk_x, chunk_z) {
+            Some(chunk) => light_packet(&chunk, session.has_skylight),
+            None => UpdateLightPacket::for_dimension(chunk_x, chunk_z, session.has_skylight),
+        };
+        session.send_packet(light).await?;
+        session.send_raw(encoded).await?;
+    }
+    Ok(())
+}
+
+/// Reads the body of a packet a player sent. Packets that can't be read are logged and skipped,
+/// with a hex dump of their bytes if `hex_dumps` is set.
+fn read_play_packet<T: Packet>(
+    buffer: &mut MinecraftPacketBuffer,
+    hex_dumps: bool,
+    logger: &ContextLogger,
+) -> Option<T> {
+    match read_packet_body(buffer, hex_dumps) {
+        Ok(packet) => Some(packet),
+        Err(read_error) => {
+            logger.log(
+                format!(
+                    "Failed to read packet 0x{:02x}: {}",
+                    T::packet_id(),
+                    read_error
+                ),
+                Debug,
+            );
+            None
+        }
+    }
+}
+
+/// Packet a player sent during play, decoded by the connection's read task and handled by the
+/// player's play loop
+enum PlayerEvent {
+    TeleportConfirm(TeleportConfirmPacket),
+    ChatMessage(ChatMessageServerboundPacket),
+    PluginMessage(PluginMessageServerboundPacket),
+    InteractEntity(InteractEntityPacket),
+    KeepAlive(KeepAlivePacket),
+    Position {
+        position: (f64, f64, f64),
+        yaw: f32,
+        pitch: f32,
+    },
+    VehicleMove(VehicleMovePacket),
+    SteerBoat(SteerBoatPacket),
+    EntityAction(EntityActionPacket),
+    SteerVehicle(SteerVehiclePacket),
+    HeldItemChange(HeldItemChangeServerboundPacket),
+    ClientSettings(ClientSettingsPacket),
+    ResourcePackStatus(ResourcePackStatusPacket),
+}
+
+/// Handles the play state after login and join game. The player's session belongs to this task;
+/// other players only reach it through the sender listed in the session manager, in the `slot`
+/// reserved for them at login. Packets are read and decoded by a separate task and queued for
+/// the play loop.
+async fn handle_play_state(
+    mut session: PlayerSession,
+    slot: JoinSlot<'_>,
+    reader: FramedReader<ReadHalf<EncryptedStream<TcpStream>>>,
+    config: &ServerConfig,
+    world: Arc<World>,
+    sessions: &Arc<SessionManager>,
+    logger: ContextLogger,
+) -> io::Result<()> {
+    let username = session.username.clone();
+    let connection_closed = session.closed();
+
+    // List the player as online and introduce them to the tab list of everyone already there
+    let online_players = slot.add_session(&session);
+    if let Err(broadcast_error) = sessions.broadcast_packet(
+        PlayerInfoPacket::add_player(vec![session.player_info_entry()]),
+        None,
+    ) {
+        logger.log(
+            format!(
+                "Failed to announce player {}: {}",
+                username, broadcast_error
+            ),
+            Warning,
+        );
+    }
+    let introduced = if online_players.is_empty() {
+        Ok(())
+    } else {
+        session
+            .send_packet(PlayerInfoPacket::add_player(online_players))
+            .await
+    };
+
+    // The session is listed from here on, so every way out goes through the cleanup below
+    let result = match introduced {
+        Ok(()) => {
+            let (event_sender, events) = mpsc::channel(PLAYER_EVENT_QUEUE_CAPACITY);
+            let read_task = tokio::spawn(read_player_events(
+                reader,
+                event_sender,
+                config.packet_hex_dumps,
+                logger.clone(),
+            ));
+            let result = run_play_loop(
+                &mut session,
+                events,
+                connection_closed,
+                config,
+                &world,
+                sessions,
+                &logger,
+            )
+            .await;
+            read_task.abort();
+            result
+        }
+        Err(introduce_error) => Err(introduce_error),
+    };
+
+    // Remove session when connection ends
+    if let Err(broadcast_error) = sessions.handle_disconnect(&session) {
+        logger.log(
+            format!(
+                "Failed to tell players that {} left: {}",
+                username, broadcast_error
+            ),
+            Warning,
+        );
+    }
+    save_player_data(config, &session);
+    logger.log(format!("Player {} disconnected", username), Info);
+
+    result
+}
+
+/// Reads packets from a player and queues them for the play loop until the connection closes.
+/// Waits whenever the queue is full, so a player flooding packets is slowed down rather than
+/// buffered without limit.
+async fn read_player_events(
+    mut reader: FramedReader<ReadHalf<EncryptedStream<TcpStream>>>,
+    events: mpsc::Sender<PlayerEvent>,
+    hex_dumps: bool,
+    logger: ContextLogger,
+) {
+    loop {
+        let mut packet_buffer = match reader.read_packet().await {
+            Ok(packet_buffer) => packet_buffer,
+            // Connection closed
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(e) => {
+                logger.log(format!("Error reading from socket: {}", e), Error);
+                return;
+            }
+        };
+        let event = match decode_player_event(&mut packet_buffer, hex_dumps, &logger) {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
+            Err(decode_error) => {
+                logger.log(format!("Failed to read packet: {}", decode_error), Error);
+                return;
+            }
+        };
+        if events.send(event).await.is_err() {
+            // The play loop has ended
+            return;
+        }
+    }
+}
+
+/// Decodes a packet sent during play. Packets that can't be read or aren't handled are logged and
+/// skipped.
+fn decode_player_event(
+    packet_buffer: &mut MinecraftPacketBuffer,
+    hex_dumps: bool,
+    logger: &ContextLogger,
+) -> io::Result<Option<PlayerEvent>> {
+    let packet_id = packet_buffer.read_varint()?;
+    let event = match packet_id {
+        // Teleport Confirm
+        0x00 => {
+            read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::TeleportConfirm)
+        }
+        // Chat Message (serverbound)
+        0x03 => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::ChatMessage),
+        // Client Settings packet
+        0x05 => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::ClientSettings),
+        // Plugin Message (serverbound)
+        0x0B => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::PluginMessage),
+        // Interact Entity
+        0x0E => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::InteractEntity),
+        // Keep-alive response
+        0x10 => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::KeepAlive),
+        // Player Position And Rotation (serverbound)
+        0x13 => read_play_packet(packet_buffer, hex_dumps, logger).map(
+            |packet: PlayerPositionAndLookServerbound| PlayerEvent::Position {
+                position: (packet.x, packet.y, packet.z),
+                yaw: packet.yaw,
+                pitch: packet.pitch,
+            },
+        ),
+        // Vehicle Move
+        0x16 => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::VehicleMove),
+        // Steer Boat
+        0x17 => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::SteerBoat),
+        // Entity Action
+        0x1C => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::EntityAction),
+        // Steer Vehicle
+        0x1D => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::SteerVehicle),
+        // Resource Pack Status
+        0x21 => {
+            read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::ResourcePackStatus)
+        }
+        // Held Item Change (serverbound)
+        0x25 => read_play_packet(packet_buffer, hex_dumps, logger).map(PlayerEvent::HeldItemChange),
+        _ => {
+            logger.log(
+                format!("Received unknown packet 0x{:02x}", packet_id),
+                Debug,
+            );
+            None
+        }
+    };
+    Ok(event)
+}
+
+/// Handles a player's events as they arrive, sends them a keep-alive every so often and ticks
+/// their status effects, until their connection closes, from either side, they stop answering
+/// keep-alives or they are kicked
+async fn run_play_loop(
+    session: &mut PlayerSession,
+    mut events: mpsc::Receiver<PlayerEvent>,
+    connection_closed: impl Future<Output = ()>,
+    config: &ServerConfig,
+    world: &Arc<World>,
+    sessions: &Arc<SessionManager>,
+    logger: &ContextLogger,
+) -> io::Result<()> {
+    let mut keep_alive = interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL);
+    let mut tick = interval(TICK_DURATION);
+    tokio::pin!(connection_closed);
+    loop {
+        tokio::select! {
+            _ = &mut connection_closed => {
+                logger.log(
+                    format!("Dropped {}, who couldn't be written to or fell behind", session.username),
+                    Warning,
+                );
+                return Ok(());
+            }
+            _ = keep_alive.tick() => send_keep_alive(session)?,
+            _ = tick.tick() => {
+                if session.has_timed_out() {
+                    logger.log(format!("Player {} timed out", session.username), Info);
+                    if let Err(disconnect_error) = session.disconnect("Timed out") {
+                        logger.log(
+                            format!("Failed to disconnect {}: {}", session.username, disconnect_error),
                             Debug,
                         );
                     }
+                    return Ok(());
+                }
+                if let Err(broadcast_error) = sessions.tick_effects(session) {
+                    logger.log(
+                        format!("Failed to remove expired effects: {}", broadcast_error),
+                        Warning,
+                    );
                 }
             }
-            Ok(_) => break, // Connection closed
-            Err(e) => {
-                log(format!("Error reading from socket: {}", e), Error);
-                break;
+            event = events.recv() => match event {
+                Some(event) => {
+                    if handle_player_event(session, event, config, world, sessions, logger)
+                        .await?
+                        .is_break()
+                    {
+                        return Ok(());
+                    }
+                }
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Sends a player a new keep-alive, which they have to answer before they time out. It is
+/// queued without waiting, so a client that has stopped reading is disconnected rather than
+/// stalling their play loop.
+fn send_keep_alive(session: &mut PlayerSession) -> io::Result<()> {
+    let keep_alive_id = systime::unix_timestamp();
+    session.last_keep_alive_id = keep_alive_id;
+    session.last_keep_alive_time = Instant::now();
+    session.push_packet(KeepAlivePacket::new(keep_alive_id))
+}
+
+/// Carries out a packet a player sent. Breaks if the player has been kicked.
+async fn handle_player_event(
+    session: &mut PlayerSession,
+    event: PlayerEvent,
+    config: &ServerConfig,
+    world: &Arc<World>,
+    sessions: &Arc<SessionManager>,
+    logger: &ContextLogger,
+) -> io::Result<ControlFlow<()>> {
+    match event {
+        PlayerEvent::TeleportConfirm(teleport_confirm) => {
+            if !session.confirm_teleport(teleport_confirm.teleport_id) {
+                logger.log(
+                    format!(
+                        "Player {} confirmed teleport {}, expected {:?}",
+                        session.username, teleport_confirm.teleport_id, session.pending_teleport_id
+                    ),
+                    Warning,
+                );
+            }
+        }
+        PlayerEvent::ChatMessage(chat_message) => {
+            if let Some(command_line) = chat_message.command() {
+                let context = CommandContext {
+                    sender: session,
+                    world: world.clone(),
+                    sessions: sessions.clone(),
+                };
+                logger.log(format!("Running command /{}", command_line), Info);
+                if let Err(command_error) = COMMAND_DISPATCHER.execute(context, command_line).await
+                {
+                    logger.log(
+                        format!("Command /{} failed: {}", command_line, command_error),
+                        Warning,
+                    );
+                }
+            } else {
+                logger.log(
+                    format!("<{}> {}", session.username, chat_message.message),
+                    Info,
+                );
+                let text =
+                    TextComponent::new(format!("<{}> {}", session.username, chat_message.message));
+                sessions.broadcast_packet(
+                    ChatMessagePacket::new(&text, ChatMessagePacket::CHAT, session.uuid),
+                    None,
+                )?;
+            }
+        }
+        PlayerEvent::PluginMessage(plugin_message) => match plugin_message.brand() {
+            Some(brand) => logger.log(
+                format!("Player {} is using the {} client", session.username, brand),
+                Debug,
+            ),
+            None => logger.log(
+                format!(
+                    "Ignoring plugin message from {} on {}",
+                    session.username, plugin_message.channel
+                ),
+                Debug,
+            ),
+        },
+        PlayerEvent::InteractEntity(interact_entity) => {
+            if interact_entity.is_attack() {
+                sessions.handle_attack(session, interact_entity.entity_id)?;
+            } else {
+                logger.log(
+                    format!(
+                        "Player {} interacted with entity {}",
+                        session.username, interact_entity.entity_id
+                    ),
+                    Debug,
+                );
+            }
+        }
+        PlayerEvent::KeepAlive(keep_alive) => {
+            handle_keep_alive_response(sessions, session, keep_alive)?;
+
+            logger.log(
+                format!(
+                    "Received keep alive packet from player: {}",
+                    session.username
+                ),
+                Debug,
+            );
+        }
+        PlayerEvent::Position {
+            position,
+            yaw,
+            pitch,
+        } => {
+            if !sessions.handle_player_position(session, position, yaw, pitch)? {
+                logger.log(
+                    "Ignored movement sent before a teleport was confirmed".to_owned(),
+                    Debug,
+                );
+            }
+        }
+        PlayerEvent::VehicleMove(vehicle_move) => {
+            // The rider moves along with the vehicle it is driving
+            let (yaw, pitch) = (session.yaw, session.pitch);
+            session.update_position(vehicle_move.x, vehicle_move.y, vehicle_move.z, yaw, pitch);
+        }
+        PlayerEvent::SteerBoat(steer_boat) => {
+            logger.log(
+                format!("Player {} steered boat: {:?}", session.username, steer_boat),
+                Debug,
+            );
+        }
+        PlayerEvent::EntityAction(entity_action) => {
+            sessions.handle_entity_action(session, &entity_action)?;
+        }
+        PlayerEvent::SteerVehicle(steer_vehicle) => {
+            if steer_vehicle.is_unmounting() {
+                sessions.dismount(session)?;
+            }
+            logger.log(
+                format!(
+                    "Player {} steered vehicle: {:?}",
+                    session.username, steer_vehicle
+                ),
+                Debug,
+            );
+        }
+        PlayerEvent::HeldItemChange(held_item_change) => {
+            match u8::try_from(held_item_change.slot) {
+                Ok(slot) if slot <= 8 => session.selected_hotbar_slot = slot,
+                _ => logger.log(
+                    format!(
+                        "Player {} selected invalid hotbar slot {}",
+                        session.username, held_item_change.slot
+                    ),
+                    Warning,
+                ),
+            }
+        }
+        PlayerEvent::ClientSettings(settings) => {
+            logger.log(format!("Received Client Settings: {:?}", settings), Debug);
+        }
+        PlayerEvent::ResourcePackStatus(resource_pack_status) => {
+            let status = resource_pack_status.status;
+            logger.log(
+                format!(
+                    "Player {} resource pack status: {:?}",
+                    session.username, status
+                ),
+                Info,
+            );
+            let required = config
+                .resource_pack
+                .as_ref()
+                .is_some_and(|resource_pack| resource_pack.required);
+            if required && status == ResourcePackStatus::Declined {
+                session
+                    .send_packet(DisconnectPacket::new(
+                        "This server requires its resource pack",
+                    ))
+                    .await?;
+                logger.log(
+                    format!(
+                        "Kicked {} for declining the resource pack",
+                        session.username
+                    ),
+                    Info,
+                );
+                return Ok(ControlFlow::Break(()));
             }
         }
     }
+    Ok(ControlFlow::Continue(()))
+}
 
-    // Remove session when connection ends
-    {
-        let mut session_manager = SESSION_MANAGER.write().await;
-        session_manager.remove_session(&username);
-        log(format!("Player {} disconnected", username), Info);
+/// Saves a leaving player's state, if player data is enabled. Failures are logged rather than
+/// returned since the player is gone either way.
+fn save_player_data(config: &ServerConfig, session: &PlayerSession) {
+    if let Some(dir) = &config.player_data_dir {
+        if let Err(save_error) = playerdata::save_player(dir, session, config.compression_level) {
+            log(
+                format!(
+                    "Failed to save player data of {}: {}",
+                    session.username, save_error
+                ),
+                Error,
+            );
+        }
     }
+}
 
-    Ok(())
+/// Records a keep-alive response and, if it answers the outstanding keep-alive, broadcasts the
+/// measured round-trip time as the player's latency in the tab list
+fn handle_keep_alive_response(
+    session_manager: &SessionManager,
+    session: &mut PlayerSession,
+    keep_alive: KeepAlivePacket,
+) -> io::Result<()> {
+    let Some(ping_ms) = session.record_keep_alive_response(keep_alive.keep_alive_id) else {
+        return Ok(());
+    };
+    session_manager.update_session(session);
+    let latency_ms = i32::try_from(ping_ms).unwrap_or(i32::MAX);
+    let latency_update = PlayerInfoPacket::update_latency(vec![(session.uuid, latency_ms)]);
+    session_manager.broadcast_packet(latency_update, None)
 }
 
 /// Creates a command graph with basic commands
-#[allow(dead_code)]
 fn create_command_graph() -> DeclareCommandsPacket {
     let mut declare_commands_packet = DeclareCommandsPacket::new();
 
@@ -235,12 +1176,42 @@ fn create_command_graph() -> DeclareCommandsPacket {
     tp_target_node.set_suggestions("minecraft:ask_server");
     let tp_target_index = declare_commands_packet.add_node(tp_target_node);
 
+    // Add /setblock <pos> <block>
+    let setblock_index =
+        declare_commands_packet.add_node(CommandNode::new_literal("setblock", false));
+    let setblock_block_index = declare_commands_packet.add_node(CommandNode::new_argument(
+        "block",
+        Parser::BlockState,
+        true,
+    ));
+    let mut setblock_pos_node = CommandNode::new_argument("pos", Parser::BlockPos, false);
+    setblock_pos_node.add_child(setblock_block_index);
+    let setblock_pos_index = declare_commands_packet.add_node(setblock_pos_node);
+
+    // Add /fill <from> <to> <block>
+    let fill_index = declare_commands_packet.add_node(CommandNode::new_literal("fill", false));
+    let fill_block_index = declare_commands_packet.add_node(CommandNode::new_argument(
+        "block",
+        Parser::BlockState,
+        true,
+    ));
+    let mut fill_to_node = CommandNode::new_argument("to", Parser::BlockPos, false);
+    fill_to_node.add_child(fill_block_index);
+    let fill_to_index = declare_commands_packet.add_node(fill_to_node);
+    let mut fill_from_node = CommandNode::new_argument("from", Parser::BlockPos, false);
+    fill_from_node.add_child(fill_to_index);
+    let fill_from_index = declare_commands_packet.add_node(fill_from_node);
+
     // Connect the nodes
     declare_commands_packet.get_root_mut().add_child(help_index);
     declare_commands_packet
         .get_root_mut()
         .add_child(gamemode_index);
     declare_commands_packet.get_root_mut().add_child(tp_index);
+    declare_commands_packet
+        .get_root_mut()
+        .add_child(setblock_index);
+    declare_commands_packet.get_root_mut().add_child(fill_index);
 
     if let Some(gamemode_node) = declare_commands_packet.get_node_mut(gamemode_index) {
         gamemode_node.add_child(gamemode_arg_index);
@@ -250,77 +1221,524 @@ fn create_command_graph() -> DeclareCommandsPacket {
         tp_node.add_child(tp_target_index);
     }
 
+    if let Some(setblock_node) = declare_commands_packet.get_node_mut(setblock_index) {
+        setblock_node.add_child(setblock_pos_index);
+    }
+
+    if let Some(fill_node) = declare_commands_packet.get_node_mut(fill_index) {
+        fill_node.add_child(fill_from_index);
+    }
+
     declare_commands_packet
 }
 
+/// Sends an Encryption Request and reads the client's response. Returns the shared secret
+/// everything after the response is encrypted with, or `None` if the client was disconnected
+/// for sending back the wrong verify token.
+async fn negotiate_encryption(
+    socket: &mut TcpStream,
+    username: &str,
+    logger: &ContextLogger,
+) -> io::Result<Option<[u8; 16]>> {
+    let server_key = SERVER_KEY.get_or_try_init(ServerKey::generate)?;
+    let request = server_key.encryption_request();
+    let verify_token = request.verify_token.clone();
+    send_packet(request, socket).await?;
+
+    let mut response_buffer = read_packet(socket).await?;
+    let packet_id = response_buffer.read_varint()?;
+    if packet_id != EncryptionResponsePacket::packet_id() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Expected an Encryption Response, got packet ID {:#04x}",
+                packet_id
+            ),
+        ));
+    }
+    let response = EncryptionResponsePacket::read_from_buffer(&mut response_buffer)?;
+    let shared_secret: [u8; 16] = server_key
+        .decrypt(&response.shared_secret)?
+        .try_into()
+        .map_err(|secret: Vec<u8>| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Shared secret is {} bytes, expected 16", secret.len()),
+            )
+        })?;
+
+    if server_key.decrypt(&response.verify_token).ok() != Some(verify_token) {
+        logger.log(
+            format!("Player {} sent back the wrong verify token", username),
+            Info,
+        );
+        // The client encrypts from its response on, so it can only read the reason encrypted
+        let mut encrypted = EncryptedStream::new(socket, Some(shared_secret));
+        send_packet(
+            LoginDisconnectPacket::new(VERIFY_TOKEN_MISMATCH_MESSAGE.to_owned()),
+            &mut encrypted,
+        )
+        .await?;
+        encrypted.flush().await?;
+        return Ok(None);
+    }
+    Ok(Some(shared_secret))
+}
+
 /// Handles the handshake packet next state
 async fn handle_handshake_next_state(
     mut socket: TcpStream,
     handshake: HandshakePacket,
+    config: &ServerConfig,
+    world: &Arc<World>,
+    ops: &OpList,
+    registries: &Registries,
+    logger: &ContextLogger,
 ) -> io::Result<()> {
-    let mut raw_buffer = [0u8; 1024];
+    let protocol_version = ProtocolVersion::from_id(handshake.protocol_version);
     match handshake.next_state {
         // Status request
         1 => {
-            socket.read(&mut raw_buffer).await?;
+            // The status request carries no fields, so its contents are not inspected
+            let _status_request = read_packet(&mut socket).await?;
 
-            let response = StatusResponsePacket::new();
+            // Unsupported clients are shown the latest version so they know what to update to
+            let response = StatusResponsePacket::from_session_manager(
+                &SESSION_MANAGER,
+                protocol_version.unwrap_or(ProtocolVersion::LATEST),
+                &config.motd,
+                config.max_players,
+            );
             send_packet(response, &mut socket).await?;
+
+            // Clients that only want the status may hang up without pinging
+            let mut ping_buffer = match read_packet(&mut socket).await {
+                Ok(ping_buffer) => ping_buffer,
+                Err(read_error) if read_error.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                Err(read_error) => return Err(read_error),
+            };
+            let packet_id = ping_buffer.read_varint()?;
+            if packet_id != PingPacket::packet_id() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Expected a status ping, got packet ID {:#04x}", packet_id),
+                ));
+            }
+            let ping = PingPacket::read_from_buffer(&mut ping_buffer)?;
+            send_packet(
+                PongPacket {
+                    payload: ping.payload,
+                },
+                &mut socket,
+            )
+            .await?;
+            socket.shutdown().await?;
         }
         // Login request
         2 => {
-            socket.read(&mut raw_buffer).await?;
-
-            let mut login_start_packet_buffer =
-                MinecraftPacketBuffer::from_bytes(raw_buffer.to_vec());
+            let mut login_start_packet_buffer = read_packet(&mut socket).await?;
+            let _packet_id = login_start_packet_buffer.read_varint()?;
 
             if let Ok(login_start) =
                 LoginStartPacket::read_from_buffer(&mut login_start_packet_buffer)
             {
-                log(
+                logger.log(
                     format!("Player {} attempting to login", login_start.username),
                     Debug,
                 );
 
-                // TODO: Implement login checks
+                let Some(protocol_version) = protocol_version else {
+                    logger.log(
+                        format!(
+                            "Player {} uses unsupported protocol {}",
+                            login_start.username, handshake.protocol_version
+                        ),
+                        Info,
+                    );
+                    let reason = ProtocolVersion::unsupported_reason(handshake.protocol_version);
+                    send_packet(LoginDisconnectPacket::new(reason), &mut socket).await?;
+                    return Ok(());
+                };
 
                 let login_success_packet = LoginSuccessPacket::new(login_start.username.clone());
-                send_packet(login_success_packet, &mut socket).await?;
+                let uuid = login_success_packet.uuid;
+                let op_level = ops.level(&uuid);
+                // Operators can always join
+                let Ok(slot) = SESSION_MANAGER.try_reserve_slot(config.max_players, op_level > 0)
+                else {
+                    logger.log(
+                        format!(
+                            "Player {} turned away, the server is full",
+                            login_start.username
+                        ),
+                        Info,
+                    );
+                    send_packet(
+                        LoginDisconnectPacket::new(SERVER_FULL_MESSAGE.to_owned()),
+                        &mut socket,
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                let shared_secret = if config.online_mode {
+                    match negotiate_encryption(&mut socket, &login_start.username, logger).await? {
+                        Some(shared_secret) => Some(shared_secret),
+                        None => return Ok(()),
+                    }
+                } else {
+                    None
+                };
 
-                let join_game_packet = JoinGamePacket::new(
-                    1,
-                    vec!["minecraft:overworld".to_owned()],
-                    "minecraft:overworld".to_owned(),
-                );
-                send_packet(join_game_packet, &mut socket).await?;
-
-                // let held_item_change_packet = HeldItemChangePacket::new(0);
-                // send_packet(held_item_change_packet, &mut socket).await?;
-
-                // let declare_recipes_packet = DeclareRecipesPacket::new();
-                // send_packet(declare_recipes_packet, &mut socket).await?;
-
-                // Send command graph
-                // let declare_commands_packet = create_command_graph();
-                // send_packet(declare_commands_packet, &mut socket).await?;
-
-                // Send initial position and look
-                let player_position = PlayerPositionAndLook::new(
-                    0.0,  // x - spawn at origin
-                    64.0, // y - reasonable spawn height
-                    0.0,  // z - spawn at origin
-                    0.0,  // yaw - looking straight ahead
-                    0.0,  // pitch - looking straight ahead
-                    0,    // flags - all values are absolute
-                    0,    // teleport ID - first teleport
-                );
-                send_packet(player_position, &mut socket).await?;
+                let player_logger = logger.with_player(&login_start.username);
+                let (mut session, reader) =
+                    PlayerSession::new(login_start.username, uuid, socket, shared_secret);
+                session.send_packet(login_success_packet).await?;
+                let mut reader = FramedReader::new(reader);
+                session.entity_id = NEXT_ENTITY_ID.fetch_add(1, Ordering::Relaxed);
+                session.protocol_version = protocol_version;
+                session.op_level = op_level;
+                session.gamemode = config.default_gamemode;
+                let (spawn_x, spawn_y, spawn_z) = world.spawn();
+                session.position = (spawn_x as f64, spawn_y as f64, spawn_z as f64);
+                if let Some(dir) = &config.player_data_dir {
+                    if let Err(load_error) = playerdata::load_player(dir, &mut session) {
+                        player_logger.log(
+                            format!("Failed to load player data, using defaults: {}", load_error),
+                            Warning,
+                        );
+                    }
+                }
+                send_join_game(&mut session, config, world, registries).await?;
+
+                // Chunks are only sent once the client has told us how far it can see
+                let requested = match await_client_settings(&mut reader, &player_logger).await? {
+                    Some(settings) => settings.view_distance(),
+                    None => {
+                        player_logger.log(
+                            "Client Settings didn't arrive in time, using the defaults".to_owned(),
+                            Debug,
+                        );
+                        config.view_distance
+                    }
+                };
+                session.view_distance = requested.min(config.view_distance).max(MIN_VIEW_DISTANCE);
+                send_world(&mut session, config, world).await?;
 
                 // After sending join game packet, transition to play state
-                handle_play_state(socket, login_start.username).await?;
+                handle_play_state(
+                    session,
+                    slot,
+                    reader,
+                    config,
+                    world.clone(),
+                    &SESSION_MANAGER,
+                    player_logger,
+                )
+                .await?;
             }
         }
-        _ => panic!("Unknown next state: {}", handshake.next_state),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown next state: {}", handshake.next_state),
+            ))
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elytra_logger::panic::panic_hook;
+    use elytra_logger::severity::LogSeverity;
+    use elytra_protocol::chunk_data::ChunkDataPacket;
+    use elytra_protocol::held_item_change::HeldItemChangePacket;
+    use elytra_protocol::player_info::PlayerInfoAction;
+    use elytra_protocol::status::StatusRequestPacket;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_server_keeps_accepting_after_connection_panic() {
+        // Other tests in this binary can panic while the hook is replaced, so it still passes
+        // every panic on to the hook that was installed before
+        let fatal_messages = Arc::new(Mutex::new(Vec::new()));
+        let captured = fatal_messages.clone();
+        let report_fatal = panic_hook(move |message, severity| {
+            if matches!(severity, LogSeverity::Fatal) {
+                captured.lock().unwrap().push(message);
+            }
+        });
+        let previous_hook = Arc::new(std::panic::take_hook());
+        let chained_hook = previous_hook.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            report_fatal(info);
+            chained_hook(info);
+        }));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_sender, shutdown) = watch::channel(false);
+        let config = Arc::new(ServerConfig::default());
+        let world = Arc::new(World::new(FlatGenerator::default()));
+        let ops = Arc::new(OpList::new());
+        let registries = Arc::new(Registries::load(None).unwrap());
+        let panicked = Arc::new(AtomicBool::new(false));
+        // The first connection's task panics; the ones after it are handled as usual
+        let connect = move |socket, logger| {
+            let panics = !panicked.swap(true, Ordering::Relaxed);
+            let connection = handle_connection(
+                socket,
+                config.clone(),
+                world.clone(),
+                ops.clone(),
+                registries.clone(),
+                logger,
+            );
+            async move {
+                if panics {
+                    panic!("Connection task panicked");
+                }
+                connection.await;
+            }
+        };
+        let accept_task = tokio::spawn(accept_connections(listener, 2, connect, shutdown));
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut byte = [0; 1];
+        assert_eq!(client.read(&mut byte).await.unwrap(), 0);
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let handshake = HandshakePacket {
+            protocol_version: ProtocolVersion::LATEST.id(),
+            server_address: "localhost".to_owned(),
+            server_port: addr.port(),
+            next_state: 1,
+        };
+        send_packet(handshake, &mut client).await.unwrap();
+        send_packet(StatusRequestPacket, &mut client).await.unwrap();
+        let mut response = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            response.read_varint().unwrap(),
+            StatusResponsePacket::packet_id()
+        );
+
+        shutdown_sender.send(true).unwrap();
+        accept_task.await.unwrap();
+        drop(std::panic::take_hook());
+        std::panic::set_hook(Arc::into_inner(previous_hook).unwrap());
+
+        let fatal_messages = fatal_messages.lock().unwrap();
+        assert!(fatal_messages
+            .iter()
+            .any(|message| message.ends_with("Connection task panicked")));
+    }
+
+    #[tokio::test]
+    async fn test_join_sends_held_item_change_for_stored_slot() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+
+        let uuid = LoginSuccessPacket::new("Steve".to_owned()).uuid;
+        let (mut session, _reader) = PlayerSession::new("Steve".to_owned(), uuid, socket, None);
+        session.selected_hotbar_slot = 4;
+        // The join sequence is larger than the socket buffers, so it has to be read concurrently
+        let world = World::new(FlatGenerator::default());
+        tokio::spawn(async move {
+            let registries = Registries::load(None).unwrap();
+            send_join_game(&mut session, &ServerConfig::default(), &world, &registries).await
+        });
+
+        let mut join_game = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            join_game.read_varint().unwrap(),
+            JoinGamePacket::packet_id()
+        );
+
+        let mut brand = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            brand.read_varint().unwrap(),
+            PluginMessagePacket::packet_id()
+        );
+        assert_eq!(
+            PluginMessagePacket::read_from_buffer(&mut brand).unwrap(),
+            PluginMessagePacket::brand("Elytra")
+        );
+
+        let mut abilities = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            abilities.read_varint().unwrap(),
+            PlayerAbilitiesPacket::packet_id()
+        );
+
+        let mut held_item_change = read_packet(&mut client).await.unwrap();
+        assert_eq!(
+            held_item_change.read_varint().unwrap(),
+            HeldItemChangePacket::packet_id()
+        );
+        let packet = HeldItemChangePacket::read_from_buffer(&mut held_item_change).unwrap();
+        assert_eq!(packet.slot(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_join_game_world_flags() {
+        let uuid = LoginSuccessPacket::new("Steve".to_owned()).uuid;
+        let (session, _reader) =
+            PlayerSession::from_stream("Steve".to_owned(), uuid, tokio::io::duplex(64).1);
+        let config = ServerConfig {
+            enable_respawn_screen: false,
+            reduced_debug_info: true,
+            ..ServerConfig::default()
+        };
+        assert_eq!(config.level_type, LevelType::Flat);
+        let registries = Registries::load(None).unwrap();
+
+        let packet = join_game_packet(&session, &config, &create_world(&config), &registries);
+        assert!(packet.is_flat);
+        let mut buffer = MinecraftPacketBuffer::new();
+        packet.write_to_buffer(&mut buffer).unwrap();
+        // The packet ends with reduced debug info, respawn screen, debug world and flat world
+        let bytes = buffer.get_buffer();
+        assert_eq!(&bytes[bytes.len() - 4..], &[1, 0, 0, 1]);
+
+        let config = ServerConfig {
+            level_type: LevelType::Default,
+            ..ServerConfig::default()
+        };
+        let packet = join_game_packet(&session, &config, &create_world(&config), &registries);
+        assert!(!packet.is_flat);
+        assert!(packet.enable_respawn_screen);
+        assert_eq!(packet.hashed_seed, hash_seed(config.seed));
+    }
+
+    #[test]
+    fn test_chunks_around_spiral_outward() {
+        assert_eq!(
+            chunks_around((0, 0), 1),
+            vec![
+                (0, 0),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+            ]
+        );
+        assert_eq!(chunks_around((5, -3), 0), vec![(5, -3)]);
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_sent_nearest_first() {
+        let (mut client, server) = tokio::io::duplex(1 << 16);
+        let (mut session, _reader) =
+            PlayerSession::from_stream("Steve".to_owned(), uuid::Uuid::nil(), server);
+        session.position = (40.0, 64.0, -20.0);
+        session.view_distance = 2;
+        tokio::spawn(async move {
+            let world = World::new(FlatGenerator::default());
+            send_spawn_chunks(&mut session, &world).await
+        });
+
+        let mut sent = Vec::new();
+        while sent.len() < 25 {
+            let mut packet = read_packet(&mut client).await.unwrap();
+            if packet.read_varint().unwrap() == ChunkDataPacket::packet_id() {
+                let chunk = ChunkDataPacket::read_from_buffer(&mut packet).unwrap();
+                sent.push((chunk.chunk_x, chunk.chunk_z));
+            }
+        }
+        assert_eq!(sent, chunks_around((2, -2), 2));
+        let distances: Vec<i32> = sent
+            .iter()
+            .map(|&(x, z)| (x - 2).abs().max((z + 2).abs()))
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_response_broadcasts_latency() {
+        let session_manager = SessionManager::new();
+        let (client, server) = tokio::io::duplex(4096);
+        let uuid = LoginSuccessPacket::new("Steve".to_owned()).uuid;
+        let (mut session, _reader) = PlayerSession::from_stream("Steve".to_owned(), uuid, server);
+        session.last_keep_alive_id = 1234;
+        session.last_keep_alive_time = Instant::now() - Duration::from_millis(50);
+        session_manager.add_session(&session);
+
+        // A response with a stale id is ignored
+        handle_keep_alive_response(&session_manager, &mut session, KeepAlivePacket::new(1))
+            .unwrap();
+        handle_keep_alive_response(&session_manager, &mut session, KeepAlivePacket::new(1234))
+            .unwrap();
+
+        let mut client = client;
+        let mut packet = read_packet(&mut client).await.unwrap();
+        assert_eq!(packet.read_varint().unwrap(), PlayerInfoPacket::packet_id());
+        let player_info = PlayerInfoPacket::read_from_buffer(&mut packet).unwrap();
+        match player_info.action {
+            PlayerInfoAction::UpdateLatency(latencies) => {
+                assert_eq!(latencies.len(), 1);
+                assert_eq!(latencies[0].0, uuid);
+                assert!((50..5000).contains(&latencies[0].1));
+            }
+            action => panic!("Expected Update Latency, got {:?}", action),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_join_does_not_leave_session_behind() {
+        let sessions = Arc::new(SessionManager::new());
+        // Someone already online, so the joining player is sent the tab list
+        let (online, _reader) = PlayerSession::from_stream(
+            "Alex".to_owned(),
+            uuid::Uuid::from_u128(2198),
+            tokio::io::duplex(4096).1,
+        );
+        sessions.add_session(&online);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        let uuid = LoginSuccessPacket::new("Steve".to_owned()).uuid;
+        let (mut session, reader) = PlayerSession::new("Steve".to_owned(), uuid, socket, None);
+        // Close the connection before the tab list can be sent
+        drop(client);
+        while session.is_connected() {
+            let _ = session.send_packet(KeepAlivePacket::new(0)).await;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let config = ServerConfig {
+            player_data_dir: None,
+            ..ServerConfig::default()
+        };
+        let slot = sessions
+            .try_reserve_slot(config.max_players, false)
+            .unwrap();
+        let result = handle_play_state(
+            session,
+            slot,
+            FramedReader::new(reader),
+            &config,
+            Arc::new(World::new(FlatGenerator::default())),
+            &sessions,
+            ContextLogger::for_connection(0, "test"),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(sessions.get_session("Steve").is_none());
+        assert!(sessions.get_session("Alex").is_some());
+    }
+}
@@ -1,17 +1,55 @@
 use elytra_protocol::handshake::HandshakePacket;
-use elytra_protocol::packet::{MinecraftPacketBuffer, Packet};
-use tokio::io::{self as io, AsyncReadExt, AsyncWriteExt};
+use elytra_protocol::packet::{read_packet, MinecraftPacketBuffer, Packet};
+use elytra_server::config::ServerConfig;
+use elytra_server::server::{self, ServerHandle};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::io::{self as io, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-pub async fn connect_to_server() -> TcpStream {
-    TcpStream::connect("127.0.0.1:25565").await.unwrap()
+mod test_client;
+
+pub use test_client::TestClient;
+
+/// Default configuration bound to a localhost port picked by the OS, without saving player data
+/// or chunks or reading the ops list or level file
+pub fn local_config() -> ServerConfig {
+    ServerConfig {
+        bind_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        player_data_dir: None,
+        ops_file: None,
+        level_file: None,
+        region_dir: None,
+        ..ServerConfig::default()
+    }
+}
+
+/// Starts a server with the default configuration on a localhost port picked by the OS
+pub async fn start_server() -> ServerHandle {
+    start_server_with(local_config()).await
+}
+
+/// Starts a server with the given configuration
+pub async fn start_server_with(config: ServerConfig) -> ServerHandle {
+    server::start(config).await.unwrap()
+}
+
+pub async fn connect_to_server(addr: SocketAddr) -> TcpStream {
+    TcpStream::connect(addr).await.unwrap()
 }
 
 pub async fn send_handshake(client: &mut TcpStream, next_state: i32) -> io::Result<()> {
+    send_handshake_with_version(client, 754, next_state).await // Minecraft 1.16.5
+}
+
+pub async fn send_handshake_with_version(
+    client: &mut TcpStream,
+    protocol_version: i32,
+    next_state: i32,
+) -> io::Result<()> {
     let handshake = HandshakePacket {
-        protocol_version: 754, // Minecraft 1.16.5
+        protocol_version,
         server_address: "localhost".to_string(),
-        server_port: 25565,
+        server_port: client.peer_addr()?.port(),
         next_state,
     };
 
@@ -29,10 +67,10 @@ pub async fn send_packet<T: Packet>(client: &mut TcpStream, packet: T) -> io::Re
     client.write_all(&packet_with_length.buffer).await
 }
 
+/// Reads a single packet and returns its contents as lossy UTF-8
 pub async fn read_response(client: &mut TcpStream) -> io::Result<String> {
-    let mut response_buffer = vec![0u8; 1024];
-    let n = client.read(&mut response_buffer).await?;
-    Ok(String::from_utf8_lossy(&response_buffer[..n]).to_string())
+    let packet = read_packet(client).await?;
+    Ok(String::from_utf8_lossy(&packet.buffer).to_string())
 }
 
 pub fn assert_response_contains_status_fields(response: &str) {
@@ -0,0 +1,101 @@
+use super::send_handshake;
+use elytra_protocol::client_settings::ClientSettingsPacket;
+use elytra_protocol::login::{LoginStartPacket, LoginSuccessPacket};
+use elytra_protocol::packet::{read_packet, send_packet, MinecraftPacketBuffer, Packet};
+use std::net::SocketAddr;
+use tokio::io::{self, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Next state requested in the handshake to log in
+const LOGIN_STATE: i32 = 2;
+/// View distance test clients ask for, kept small so joining is quick
+const TEST_VIEW_DISTANCE: u8 = 2;
+
+/// Client side of a connection, used to script packet exchanges with the server in tests
+pub struct TestClient {
+    stream: TcpStream,
+}
+
+impl TestClient {
+    /// Connects to the server and sends a handshake asking for `next_state`
+    pub async fn connect(addr: SocketAddr, next_state: i32) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr).await?;
+        send_handshake(&mut stream, next_state).await?;
+        Ok(TestClient { stream })
+    }
+
+    /// Connects and logs in as `username`, leaving the connection in the play state
+    pub async fn login(addr: SocketAddr, username: &str) -> io::Result<(Self, LoginSuccessPacket)> {
+        Self::login_with_view_distance(addr, username, TEST_VIEW_DISTANCE).await
+    }
+
+    /// Logs in and sends Client Settings asking for `view_distance`, as a game client does once
+    /// it joins
+    pub async fn login_with_view_distance(
+        addr: SocketAddr,
+        username: &str,
+        view_distance: u8,
+    ) -> io::Result<(Self, LoginSuccessPacket)> {
+        let mut client = Self::connect(addr, LOGIN_STATE).await?;
+        client
+            .send(LoginStartPacket {
+                username: username.to_owned(),
+            })
+            .await?;
+        let login_success = client.recv::<LoginSuccessPacket>().await?;
+        client
+            .send(ClientSettingsPacket::new(
+                "en_us".to_owned(),
+                view_distance,
+                0,
+                true,
+                0x7F,
+                1,
+            ))
+            .await?;
+        Ok((client, login_success))
+    }
+
+    /// Sends a packet to the server
+    pub async fn send<P: Packet>(&mut self, packet: P) -> io::Result<()> {
+        send_packet(packet, &mut self.stream).await
+    }
+
+    /// Writes bytes as they are, such as part of a framed packet
+    pub async fn send_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.write_all(bytes).await?;
+        self.stream.flush().await
+    }
+
+    /// Reads the next packet, failing if it isn't a `P`
+    pub async fn recv<P: Packet>(&mut self) -> io::Result<P> {
+        let mut packet = read_packet(&mut self.stream).await?;
+        let packet_id = packet.read_varint()?;
+        if packet_id != P::packet_id() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected packet 0x{:02x} but received 0x{:02x}",
+                    P::packet_id(),
+                    packet_id
+                ),
+            ));
+        }
+        P::read_from_buffer(&mut packet)
+    }
+
+    /// Reads the next packet whatever it is, starting at its packet ID
+    pub async fn recv_raw(&mut self) -> io::Result<MinecraftPacketBuffer> {
+        read_packet(&mut self.stream).await
+    }
+
+    /// Skips packets until a `P` arrives and returns it
+    pub async fn recv_until<P: Packet>(&mut self) -> io::Result<P> {
+        loop {
+            let mut packet = read_packet(&mut self.stream).await?;
+            if packet.read_varint()? == P::packet_id() {
+                return P::read_from_buffer(&mut packet);
+            }
+        }
+    }
+}
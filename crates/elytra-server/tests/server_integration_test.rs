@@ -1,16 +1,37 @@
 mod common;
 
 use common::*;
-use elytra_protocol::login::LoginStartPacket;
-use elytra_protocol::packet::Packet;
-use elytra_protocol::status::StatusRequestPacket;
+use elytra_common::types::GameMode;
+use elytra_nbt::{NBTFile, Tag};
+use elytra_protocol::chat::{ChatMessagePacket, ChatMessageServerboundPacket};
+use elytra_protocol::chunk_data::ChunkDataPacket;
+use elytra_protocol::encryption::{
+    EncryptedStream, EncryptionRequestPacket, EncryptionResponsePacket,
+};
+use elytra_protocol::join_game::{default_dimension_codec, JoinGamePacket};
+use elytra_protocol::login::{LoginDisconnectPacket, LoginStartPacket, LoginSuccessPacket};
+use elytra_protocol::packet::{frame_packet, read_packet, MinecraftPacketBuffer, Packet};
+use elytra_protocol::player_info::{PlayerInfoAction, PlayerInfoPacket};
+use elytra_protocol::player_position_and_look::PlayerPositionAndLook;
+use elytra_protocol::spawn_position::SpawnPositionPacket;
+use elytra_protocol::status::{PingPacket, StatusRequestPacket};
+use elytra_server::config::ServerConfig;
+use elytra_server::server::ServerHandle;
 use futures::future::join_all;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pkcs1v15Encrypt, RsaPublicKey};
+use serde_json::Value;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
 use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
 use tokio::time::sleep;
 
 #[tokio::test]
 async fn test_server_handshake_status() {
-    let mut client = connect_to_server().await;
+    let server = start_server().await;
+    let mut client = connect_to_server(server.local_addr()).await;
 
     // Send handshake and status request
     send_handshake(&mut client, 1).await.unwrap();
@@ -19,11 +40,110 @@ async fn test_server_handshake_status() {
     // Read and verify response
     let response = read_response(&mut client).await.unwrap();
     assert_response_contains_status_fields(&response);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_status_shows_configured_motd_and_player_cap() {
+    let server = start_server_with(ServerConfig {
+        motd: "Integration MOTD".to_owned(),
+        max_players: 7,
+        ..local_config()
+    })
+    .await;
+    let mut client = connect_to_server(server.local_addr()).await;
+
+    send_handshake(&mut client, 1).await.unwrap();
+    send_packet(&mut client, StatusRequestPacket).await.unwrap();
+    let response = read_response(&mut client).await.unwrap();
+    assert!(
+        response.contains(r#""text":"Integration MOTD""#),
+        "{}",
+        response
+    );
+    assert!(response.contains(r#""max":7"#), "{}", response);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_status_ping_is_echoed() {
+    let server = start_server().await;
+    let mut client = connect_to_server(server.local_addr()).await;
+
+    send_handshake(&mut client, 1).await.unwrap();
+    send_packet(&mut client, StatusRequestPacket).await.unwrap();
+    read_response(&mut client).await.unwrap();
+
+    let payload = 0x0123_4567_89AB_CDEF;
+    send_packet(&mut client, PingPacket { payload })
+        .await
+        .unwrap();
+    let pong = read_packet(&mut client).await.unwrap();
+    let mut expected = vec![0x01];
+    expected.extend_from_slice(&payload.to_be_bytes());
+    assert_eq!(pong.get_buffer(), &expected[..]);
+
+    // The server closes the connection once it has answered
+    let mut rest = Vec::new();
+    client.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_legacy_ping_gets_legacy_status() {
+    let server = start_server_with(ServerConfig {
+        motd: "Legacy MOTD".to_owned(),
+        max_players: 9,
+        ..local_config()
+    })
+    .await;
+    let mut client = connect_to_server(server.local_addr()).await;
+
+    client.write_all(&[0xFE, 0x01]).await.unwrap();
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+
+    assert_eq!(response[0], 0xFF);
+    let units: Vec<u16> = response[3..]
+        .chunks(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+    assert_eq!(
+        u16::from_be_bytes([response[1], response[2]]) as usize,
+        units.len()
+    );
+    let status = String::from_utf16(&units).unwrap();
+    let fields: Vec<&str> = status.split('\0').collect();
+    assert_eq!(fields[..4], ["§1", "754", "1.16.5", "Legacy MOTD"]);
+    assert_eq!(fields[5], "9");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_server_stops_listening_after_shutdown() {
+    let server = start_server().await;
+    let addr = server.local_addr();
+    assert_ne!(addr.port(), 0);
+
+    let mut client = connect_to_server(addr).await;
+    send_handshake(&mut client, 1).await.unwrap();
+    send_packet(&mut client, StatusRequestPacket).await.unwrap();
+    let response = read_response(&mut client).await.unwrap();
+    assert_response_contains_status_fields(&response);
+
+    server.shutdown().await;
+    assert!(TcpStream::connect(addr).await.is_err());
 }
 
 #[tokio::test]
 async fn test_server_handshake_login() {
-    let mut client = connect_to_server().await;
+    let server = start_server().await;
+    let mut client = connect_to_server(server.local_addr()).await;
 
     // Send handshake and login start
     send_handshake(&mut client, 2).await.unwrap();
@@ -36,14 +156,535 @@ async fn test_server_handshake_login() {
     .await
     .unwrap();
 
-    // Read response and verify disconnect message
+    // Offline mode logins are accepted straight away
+    let mut response = read_packet(&mut client).await.unwrap();
+    assert_eq!(
+        response.read_varint().unwrap(),
+        LoginSuccessPacket::packet_id()
+    );
+    let login_success = LoginSuccessPacket::read_from_buffer(&mut response).unwrap();
+    assert_eq!(login_success.username, "TestPlayer");
+
+    server.shutdown().await;
+}
+
+/// Logs in to an online-mode server the way a client does, but answers the Encryption Request
+/// with `verify_token` instead of the token it was sent, if one is given. Returns the first
+/// packet the server sends after the response, decrypted.
+async fn online_mode_login(
+    server: &ServerHandle,
+    username: &str,
+    verify_token: Option<Vec<u8>>,
+) -> MinecraftPacketBuffer {
+    let mut client = connect_to_server(server.local_addr()).await;
+    send_handshake(&mut client, 2).await.unwrap();
+    send_packet(
+        &mut client,
+        LoginStartPacket {
+            username: username.to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut request_buffer = read_packet(&mut client).await.unwrap();
+    assert_eq!(
+        request_buffer.read_varint().unwrap(),
+        EncryptionRequestPacket::packet_id()
+    );
+    let request = EncryptionRequestPacket::read_from_buffer(&mut request_buffer).unwrap();
+    assert_eq!(request.server_id, "");
+
+    let public_key = RsaPublicKey::from_public_key_der(&request.public_key).unwrap();
+    let shared_secret = [0x5A; 16];
+    let mut rng = rand::thread_rng();
+    let verify_token = verify_token.unwrap_or(request.verify_token);
+    let response = EncryptionResponsePacket {
+        shared_secret: public_key
+            .encrypt(&mut rng, Pkcs1v15Encrypt, &shared_secret)
+            .unwrap(),
+        verify_token: public_key
+            .encrypt(&mut rng, Pkcs1v15Encrypt, &verify_token)
+            .unwrap(),
+    };
+    send_packet(&mut client, response).await.unwrap();
+
+    let mut encrypted = EncryptedStream::new(client, Some(shared_secret));
+    read_packet(&mut encrypted).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_online_mode_login_is_encrypted() {
+    let server = start_server_with(ServerConfig {
+        online_mode: true,
+        ..local_config()
+    })
+    .await;
+
+    let mut response = online_mode_login(&server, "OnlinePlayer", None).await;
+    assert_eq!(
+        response.read_varint().unwrap(),
+        LoginSuccessPacket::packet_id()
+    );
+    let login_success = LoginSuccessPacket::read_from_buffer(&mut response).unwrap();
+    assert_eq!(login_success.username, "OnlinePlayer");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_online_mode_rejects_wrong_verify_token() {
+    let server = start_server_with(ServerConfig {
+        online_mode: true,
+        ..local_config()
+    })
+    .await;
+
+    let mut response = online_mode_login(&server, "WrongToken", Some(vec![0; 4])).await;
+    assert_eq!(
+        response.read_varint().unwrap(),
+        LoginDisconnectPacket::packet_id()
+    );
+    let disconnect = LoginDisconnectPacket::read_from_buffer(&mut response).unwrap();
+    assert!(disconnect.reason.contains("Invalid verify token"));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_client_login_receives_world() {
+    let server = start_server().await;
+
+    let (mut client, login_success) = TestClient::login(server.local_addr(), "Scripted")
+        .await
+        .unwrap();
+    assert_eq!(login_success.username, "Scripted");
+
+    let join_game = client.recv::<JoinGamePacket>().await.unwrap();
+    assert_eq!(join_game.world_name, "minecraft:overworld");
+
+    let chunk = client.recv_until::<ChunkDataPacket>().await.unwrap();
+    assert!(chunk.full_chunk);
+    assert!(chunk.has_section(0));
+
+    let position = client.recv_until::<PlayerPositionAndLook>().await.unwrap();
+    assert_eq!(position.y, 64.0);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_level_file_sets_spawn_and_seed() {
+    let dir = std::env::temp_dir().join(format!("elytra-level-dat-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let level_file = dir.join("level.dat");
+    let data = Tag::Compound(
+        [
+            ("LevelName", Tag::String("Fixture".to_owned())),
+            ("SpawnX", Tag::Int(-120)),
+            ("SpawnY", Tag::Int(71)),
+            ("SpawnZ", Tag::Int(36)),
+            ("RandomSeed", Tag::Long(8_675_309)),
+        ]
+        .into_iter()
+        .map(|(name, tag)| (name.to_owned(), tag))
+        .collect(),
+    );
+    let root = Tag::Compound([("Data".to_owned(), data)].into_iter().collect());
+    NBTFile::new(String::new(), root).save(&level_file).unwrap();
+
+    let server = start_server_with(ServerConfig {
+        level_file: Some(level_file),
+        ..local_config()
+    })
+    .await;
+    assert_eq!(server.world().spawn(), (-120, 71, 36));
+
+    let (mut client, _) = TestClient::login(server.local_addr(), "Spawner")
+        .await
+        .unwrap();
+    let join_game = client.recv::<JoinGamePacket>().await.unwrap();
+    let spawn_position = client.recv_until::<SpawnPositionPacket>().await.unwrap();
+    assert_eq!(spawn_position.position, (-120, 71, 36));
+    let position = client.recv_until::<PlayerPositionAndLook>().await.unwrap();
+    assert_eq!((position.x, position.y, position.z), (-120.0, 71.0, 36.0));
+
+    // The seed from the file wins over the configured one
+    let seeded = start_server_with(ServerConfig {
+        seed: 8_675_309,
+        ..local_config()
+    })
+    .await;
+    let (mut seeded_client, _) = TestClient::login(seeded.local_addr(), "Seeded")
+        .await
+        .unwrap();
+    let seeded_join_game = seeded_client.recv::<JoinGamePacket>().await.unwrap();
+    assert_eq!(join_game.hashed_seed, seeded_join_game.hashed_seed);
+
+    server.shutdown().await;
+    seeded.shutdown().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_play_packets_split_or_coalesced_are_read_whole() {
+    let server = start_server().await;
+    let (mut client, _) = TestClient::login(server.local_addr(), "Framed")
+        .await
+        .unwrap();
+    client.recv_until::<PlayerPositionAndLook>().await.unwrap();
+
+    // Unknown commands are answered with an error only the sender sees
+    let command = |name: &str| {
+        frame_packet(&ChatMessageServerboundPacket::new(format!("/{}", name))).unwrap()
+    };
+    let split = command("first");
+    client.send_bytes(&split[..4]).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    client.send_bytes(&split[4..]).await.unwrap();
+    let coalesced = [command("second"), command("third")].concat();
+    client.send_bytes(&coalesced).await.unwrap();
+
+    for name in ["first", "second", "third"] {
+        let reply = client.recv_until::<ChatMessagePacket>().await.unwrap();
+        assert!(reply.json.contains(name), "{}", reply.json);
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_chat_is_broadcast_to_every_player() {
+    let server = start_server().await;
+    let (mut talker, talker_login) = TestClient::login(server.local_addr(), "Talker")
+        .await
+        .unwrap();
+    let (mut listener, _) = TestClient::login(server.local_addr(), "Listener")
+        .await
+        .unwrap();
+    talker.recv_until::<PlayerPositionAndLook>().await.unwrap();
+    listener
+        .recv_until::<PlayerPositionAndLook>()
+        .await
+        .unwrap();
+
+    talker
+        .send(ChatMessageServerboundPacket::new("hello there".to_owned()))
+        .await
+        .unwrap();
+    for client in [&mut talker, &mut listener] {
+        let message = loop {
+            let message = client.recv_until::<ChatMessagePacket>().await.unwrap();
+            if message.json.contains("Talker") {
+                break message;
+            }
+        };
+        assert!(
+            message.json.contains("<Talker> hello there"),
+            "{}",
+            message.json
+        );
+        assert_eq!(message.position, ChatMessagePacket::CHAT);
+        assert_eq!(message.sender, talker_login.uuid);
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_changed_chunks_survive_restart() {
+    let dir = std::env::temp_dir().join(format!("elytra-region-dir-{}", std::process::id()));
+    let config = ServerConfig {
+        region_dir: Some(dir.clone()),
+        ..local_config()
+    };
+
+    let server = start_server_with(config.clone()).await;
+    server
+        .world()
+        .set_block_state((5, 10, -70), 1)
+        .await
+        .unwrap();
+    assert!(server.world().is_dirty(0, -5));
+    server.shutdown().await;
+
+    let restarted = start_server_with(config).await;
+    assert_eq!(
+        restarted
+            .world()
+            .get_block_state((5, 10, -70))
+            .await
+            .unwrap(),
+        1
+    );
+    restarted.shutdown().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Counts the chunks sent to a new player, who are sent their position once all of them are
+async fn count_join_chunks(client: &mut TestClient) -> usize {
+    let mut chunks = 0;
+    loop {
+        let mut packet = client.recv_raw().await.unwrap();
+        match packet.read_varint().unwrap() {
+            id if id == ChunkDataPacket::packet_id() => chunks += 1,
+            id if id == PlayerPositionAndLook::packet_id() => return chunks,
+            _ => {}
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_chunks_sent_match_view_distance() {
+    let server = start_server().await;
+
+    let (mut near, _) = TestClient::login_with_view_distance(server.local_addr(), "Near", 2)
+        .await
+        .unwrap();
+    let (mut far, _) = TestClient::login_with_view_distance(server.local_addr(), "Far", 4)
+        .await
+        .unwrap();
+
+    // Chunks are sent in a square reaching the view distance on each side of spawn
+    assert_eq!(count_join_chunks(&mut near).await, 5 * 5);
+    assert_eq!(count_join_chunks(&mut far).await, 9 * 9);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_spawn_area_is_generated_at_startup() {
+    let config = ServerConfig {
+        spawn_radius: 2,
+        ..local_config()
+    };
+    let server = start_server_with(config).await;
+
+    let world = server.world();
+    assert_eq!(world.loaded_chunk_count(), 25);
+    for chunk_x in -2..=2 {
+        for chunk_z in -2..=2 {
+            assert!(world.get_chunk(chunk_x, chunk_z).is_some());
+        }
+    }
+    assert!(world.get_chunk(3, 0).is_none());
+}
+
+#[tokio::test]
+async fn test_configured_gamemode_is_sent_on_join() {
+    let config = ServerConfig {
+        default_gamemode: GameMode::Creative,
+        ..local_config()
+    };
+    let server = start_server_with(config).await;
+
+    let (mut client, login_success) = TestClient::login(server.local_addr(), "Builder")
+        .await
+        .unwrap();
+
+    let join_game = client.recv::<JoinGamePacket>().await.unwrap();
+    assert_eq!(join_game.gamemode, GameMode::Creative);
+
+    let player_info = client.recv_until::<PlayerInfoPacket>().await.unwrap();
+    match player_info.action {
+        PlayerInfoAction::AddPlayer(entries) => {
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].uuid, login_success.uuid);
+            assert_eq!(entries[0].gamemode, GameMode::Creative);
+        }
+        action => panic!("Expected Add Player, got {:?}", action),
+    }
+
+    server.shutdown().await;
+}
+
+/// Writes the built-in dimension codec with an extra marker entry to an NBT file
+fn write_marked_codec(path: &Path, marker: &str) {
+    let mut codec = default_dimension_codec();
+    if let Tag::Compound(entries) = &mut codec {
+        entries.insert("elytra:marker".to_owned(), Tag::String(marker.to_owned()));
+    }
+    NBTFile::new(String::new(), codec).save(path).unwrap();
+}
+
+fn codec_marker(join_game: &JoinGamePacket) -> Option<&str> {
+    join_game
+        .dimension_codec
+        .as_compound()?
+        .get("elytra:marker")?
+        .as_string()
+        .map(String::as_str)
+}
+
+#[tokio::test]
+async fn test_reloaded_dimension_codec_reaches_new_logins() {
+    let dir = std::env::temp_dir().join(format!("elytra-registries-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let codec_path = dir.join("dimension_codec.nbt");
+    write_marked_codec(&codec_path, "first");
+
+    let config = ServerConfig {
+        dimension_codec_file: Some(codec_path.clone()),
+        ..local_config()
+    };
+    let server = start_server_with(config).await;
+
+    let (mut first_client, _) = TestClient::login(server.local_addr(), "Early")
+        .await
+        .unwrap();
+    let join_game = first_client.recv::<JoinGamePacket>().await.unwrap();
+    assert_eq!(codec_marker(&join_game), Some("first"));
+
+    write_marked_codec(&codec_path, "second");
+    server.reload_registries().unwrap();
+
+    let (mut second_client, _) = TestClient::login(server.local_addr(), "Late")
+        .await
+        .unwrap();
+    let join_game = second_client.recv::<JoinGamePacket>().await.unwrap();
+    assert_eq!(codec_marker(&join_game), Some("second"));
+
+    // A broken source leaves the last good codec in place
+    std::fs::write(&codec_path, b"not nbt").unwrap();
+    assert!(server.reload_registries().is_err());
+    let (mut third_client, _) = TestClient::login(server.local_addr(), "Later")
+        .await
+        .unwrap();
+    let join_game = third_client.recv::<JoinGamePacket>().await.unwrap();
+    assert_eq!(codec_marker(&join_game), Some("second"));
+
+    server.shutdown().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_older_supported_protocol_can_join() {
+    let server = start_server().await;
+    let mut client = connect_to_server(server.local_addr()).await;
+
+    // Minecraft 1.16.3
+    send_handshake_with_version(&mut client, 753, 1)
+        .await
+        .unwrap();
+    send_packet(&mut client, StatusRequestPacket).await.unwrap();
     let response = read_response(&mut client).await.unwrap();
-    assert!(response.contains("disconnect") || response.contains("Disconnect"));
+    assert!(response.contains(r#""protocol":753"#));
+
+    let mut client = connect_to_server(server.local_addr()).await;
+    send_handshake_with_version(&mut client, 753, 2)
+        .await
+        .unwrap();
+    send_packet(
+        &mut client,
+        LoginStartPacket {
+            username: "Older".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let mut response = read_packet(&mut client).await.unwrap();
+    assert_eq!(
+        response.read_varint().unwrap(),
+        LoginSuccessPacket::packet_id()
+    );
+    let mut response = read_packet(&mut client).await.unwrap();
+    assert_eq!(response.read_varint().unwrap(), JoinGamePacket::packet_id());
+    let join_game = JoinGamePacket::read_from_buffer(&mut response).unwrap();
+    assert_eq!(join_game.world_name, "minecraft:overworld");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unsupported_protocol_is_disconnected() {
+    let server = start_server().await;
+    let mut client = connect_to_server(server.local_addr()).await;
+
+    // Minecraft 1.12.2
+    send_handshake_with_version(&mut client, 340, 2)
+        .await
+        .unwrap();
+    send_packet(
+        &mut client,
+        LoginStartPacket {
+            username: "Outdated".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let mut response = read_packet(&mut client).await.unwrap();
+    assert_eq!(
+        response.read_varint().unwrap(),
+        LoginDisconnectPacket::packet_id()
+    );
+    let disconnect = LoginDisconnectPacket::read_from_buffer(&mut response).unwrap();
+    assert!(disconnect.reason.contains("Outdated client"));
+
+    let result = read_response(&mut client).await;
+    assert!(result.is_err() || result.unwrap().is_empty());
+
+    server.shutdown().await;
+}
+
+/// Logs in and returns the id of the first packet the server answers with
+async fn login_response_id(server: &ServerHandle, username: &str) -> (i32, String) {
+    let mut client = connect_to_server(server.local_addr()).await;
+    send_handshake(&mut client, 2).await.unwrap();
+    send_packet(
+        &mut client,
+        LoginStartPacket {
+            username: username.to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let mut response = read_packet(&mut client).await.unwrap();
+    let packet_id = response.read_varint().unwrap();
+    let reason = if packet_id == LoginDisconnectPacket::packet_id() {
+        LoginDisconnectPacket::read_from_buffer(&mut response)
+            .unwrap()
+            .reason
+    } else {
+        String::new()
+    };
+    (packet_id, reason)
+}
+
+#[tokio::test]
+async fn test_full_server_turns_away_all_but_operators() {
+    let dir = std::env::temp_dir().join(format!("elytra-full-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let ops_path = dir.join("ops.json");
+    let admin_uuid = LoginSuccessPacket::new("Admin".to_owned()).uuid;
+    std::fs::write(
+        &ops_path,
+        format!(r#"[{{"uuid":"{}","level":4}}]"#, admin_uuid.hyphenated()),
+    )
+    .unwrap();
+
+    // Other tests share the player list, so a cap of 0 is the only one known to be reached
+    let config = ServerConfig {
+        max_players: 0,
+        ops_file: Some(ops_path),
+        ..local_config()
+    };
+    let server = start_server_with(config).await;
+
+    let (packet_id, reason) = login_response_id(&server, "Latecomer").await;
+    assert_eq!(packet_id, LoginDisconnectPacket::packet_id());
+    assert!(reason.contains("Server is full"));
+
+    let (packet_id, _) = login_response_id(&server, "Admin").await;
+    assert_eq!(packet_id, LoginSuccessPacket::packet_id());
+
+    server.shutdown().await;
+    std::fs::remove_dir_all(&dir).unwrap();
 }
 
 #[tokio::test]
 async fn test_server_invalid_handshake() {
-    let mut client = connect_to_server().await;
+    let server = start_server().await;
+    let mut client = connect_to_server(server.local_addr()).await;
 
     // Send invalid handshake (next_state = 3)
     send_handshake(&mut client, 3).await.unwrap();
@@ -51,10 +692,86 @@ async fn test_server_invalid_handshake() {
     // Connection should be closed
     let result = read_response(&mut client).await;
     assert!(result.is_err() || result.unwrap().is_empty());
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_connections_beyond_the_cap_are_refused() {
+    let config = ServerConfig {
+        max_connections: 2,
+        ..local_config()
+    };
+    let server = start_server_with(config).await;
+    let addr = server.local_addr();
+
+    let (first, _) = TestClient::login(addr, "First").await.unwrap();
+    let (_second, _) = TestClient::login(addr, "Second").await.unwrap();
+
+    // The third connection is closed without an answer
+    let mut client = connect_to_server(addr).await;
+    let _ = send_handshake(&mut client, 1).await;
+    let _ = send_packet(&mut client, StatusRequestPacket).await;
+    let result = read_response(&mut client).await;
+    assert!(result.is_err() || result.unwrap().is_empty());
+
+    // Leaving frees a slot for the next connection
+    drop(first);
+    let mut accepted = false;
+    for _ in 0..50 {
+        let mut client = connect_to_server(addr).await;
+        let _ = send_handshake(&mut client, 1).await;
+        let _ = send_packet(&mut client, StatusRequestPacket).await;
+        if let Ok(response) = read_response(&mut client).await {
+            assert_response_contains_status_fields(&response);
+            accepted = true;
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(accepted, "No slot was freed after a player left");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_admin_socket_lists_online_players() {
+    let config = ServerConfig {
+        admin_addr: Some(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))),
+        ..local_config()
+    };
+    let server = start_server_with(config).await;
+    let (mut player, _) = TestClient::login(server.local_addr(), "AdminListed")
+        .await
+        .unwrap();
+    // The tab list is sent once the player is online
+    player.recv_until::<PlayerInfoPacket>().await.unwrap();
+
+    let admin = TcpStream::connect(server.admin_addr().unwrap())
+        .await
+        .unwrap();
+    let (reader, mut writer) = admin.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"{\"cmd\":\"list\"}\n").await.unwrap();
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["ok"], true);
+    assert!(response["players"]
+        .as_array()
+        .unwrap()
+        .contains(&Value::from("AdminListed")));
+
+    writer.write_all(b"{\"cmd\":\"stop\"}\n").await.unwrap();
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["ok"], false);
+
+    server.shutdown().await;
 }
 
 #[tokio::test]
 async fn test_server_concurrent_connections() {
+    let server = start_server().await;
+    let addr = server.local_addr();
+
     // Create multiple concurrent connections
     let mut handles = Vec::new();
     for i in 0..5 {
@@ -62,7 +779,7 @@ async fn test_server_concurrent_connections() {
             // Add small delay to avoid exact simultaneous connections
             sleep(Duration::from_millis(i * 100)).await;
 
-            let mut client = connect_to_server().await;
+            let mut client = connect_to_server(addr).await;
 
             // Send handshake and status request
             send_handshake(&mut client, 1).await.unwrap();
@@ -81,4 +798,6 @@ async fn test_server_concurrent_connections() {
         let response = result.unwrap();
         assert_response_contains_status_fields(&response);
     }
+
+    server.shutdown().await;
 }
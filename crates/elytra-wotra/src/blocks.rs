@@ -0,0 +1,171 @@
+// Global palette ids of the block states and biomes used by the built-in generators (1.16.5)
+
+pub use elytra_protocol::chunk_data::AIR;
+use std::ops::RangeInclusive;
+
+pub const STONE: u32 = 1;
+pub const GRASS_BLOCK: u32 = 9;
+pub const DIRT: u32 = 10;
+pub const BEDROCK: u32 = 33;
+/// Still water source block (level 0)
+pub const WATER: u32 = 34;
+/// Single chest facing north
+pub const CHEST: u32 = 2035;
+/// Standing oak sign facing south
+pub const OAK_SIGN: u32 = 3382;
+
+pub const PLAINS_BIOME: i32 = 1;
+
+/// Default block states by name, as accepted by commands
+const BLOCKS_BY_NAME: &[(&str, u32)] = &[
+    ("air", AIR),
+    ("stone", STONE),
+    ("granite", 2),
+    ("polished_granite", 3),
+    ("diorite", 4),
+    ("polished_diorite", 5),
+    ("andesite", 6),
+    ("polished_andesite", 7),
+    ("grass_block", GRASS_BLOCK),
+    ("dirt", DIRT),
+    ("coarse_dirt", 11),
+    ("podzol", 13),
+    ("cobblestone", 14),
+    ("oak_planks", 15),
+    ("spruce_planks", 16),
+    ("birch_planks", 17),
+    ("jungle_planks", 18),
+    ("acacia_planks", 19),
+    ("dark_oak_planks", 20),
+    ("bedrock", BEDROCK),
+    ("water", WATER),
+    ("lava", 50),
+    ("sand", 66),
+    ("red_sand", 67),
+    ("gravel", 68),
+    ("gold_ore", 69),
+    ("iron_ore", 70),
+    ("coal_ore", 71),
+    ("oak_log", 74),
+    ("spruce_log", 77),
+    ("birch_log", 80),
+    ("jungle_log", 83),
+    ("acacia_log", 86),
+    ("dark_oak_log", 89),
+    ("sponge", 229),
+    ("glass", 231),
+    ("lapis_ore", 232),
+    ("lapis_block", 233),
+    ("chest", CHEST),
+    ("oak_sign", OAK_SIGN),
+    ("spruce_sign", 3414),
+    ("birch_sign", 3446),
+    ("acacia_sign", 3478),
+    ("jungle_sign", 3510),
+    ("dark_oak_sign", 3542),
+];
+
+/// Ranges of block states that have a block entity, with the id of the block entity
+const BLOCK_ENTITY_STATES: &[(RangeInclusive<u32>, &str)] = &[
+    (1953..=1953, "minecraft:mob_spawner"),
+    (2034..=2057, "minecraft:chest"),
+    // Standing signs of all six wood types
+    (3381..=3572, "minecraft:sign"),
+];
+
+/// Ranges of block states that give off light, with their light level
+const LIGHT_EMITTING_STATES: &[(RangeInclusive<u32>, u8)] = &[
+    // Lava of every level
+    (50..=65, 15),
+    // Standing and wall torches
+    (1435..=1439, 14),
+    (1440..=1951, 15),
+    (1952..=1952, 10),
+    (4013..=4013, 15),
+];
+
+/// Ranges of block states light passes through. Every other block except air is treated as
+/// fully opaque.
+const TRANSPARENT_STATES: &[RangeInclusive<u32>] = &[
+    // Water and lava
+    34..=65,
+    231..=231,
+    // Torches and fire
+    1435..=1952,
+    2034..=2057,
+    3381..=3572,
+];
+
+/// Looks up the default state of a block by its name, with or without the `minecraft:`
+/// namespace
+pub fn block_state_by_name(name: &str) -> Option<u32> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    BLOCKS_BY_NAME
+        .iter()
+        .find(|(block_name, _)| *block_name == name)
+        .map(|&(_, state)| state)
+}
+
+/// Name of the block whose default state this is, without the `minecraft:` namespace
+pub fn block_name(state: u32) -> Option<&'static str> {
+    BLOCKS_BY_NAME
+        .iter()
+        .find(|&&(_, block_state)| block_state == state)
+        .map(|&(name, _)| name)
+}
+
+/// Id of the block entity that goes with a block state, if the block has one
+pub fn block_entity_id(state: u32) -> Option<&'static str> {
+    BLOCK_ENTITY_STATES
+        .iter()
+        .find(|(states, _)| states.contains(&state))
+        .map(|&(_, id)| id)
+}
+
+/// Light level a block state gives off, 0 for most blocks
+pub fn light_emission(state: u32) -> u8 {
+    LIGHT_EMITTING_STATES
+        .iter()
+        .find(|(states, _)| states.contains(&state))
+        .map_or(0, |&(_, level)| level)
+}
+
+/// Whether a block state stops light from passing through it
+pub fn is_opaque(state: u32) -> bool {
+    state != AIR
+        && !TRANSPARENT_STATES
+            .iter()
+            .any(|states| states.contains(&state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_state_by_name() {
+        assert_eq!(block_state_by_name("stone"), Some(STONE));
+        assert_eq!(
+            block_state_by_name("minecraft:grass_block"),
+            Some(GRASS_BLOCK)
+        );
+        assert_eq!(block_state_by_name("minecraft:air"), Some(AIR));
+        assert_eq!(block_state_by_name("other:stone"), None);
+        assert_eq!(block_state_by_name("oak_log[axis=x]"), None);
+
+        assert_eq!(block_name(GRASS_BLOCK), Some("grass_block"));
+        assert_eq!(block_name(OAK_SIGN + 1), None);
+    }
+
+    #[test]
+    fn test_block_entity_id() {
+        assert_eq!(block_entity_id(CHEST), Some("minecraft:chest"));
+        assert_eq!(block_entity_id(OAK_SIGN), Some("minecraft:sign"));
+        assert_eq!(
+            block_entity_id(block_state_by_name("dark_oak_sign").unwrap()),
+            Some("minecraft:sign")
+        );
+        assert_eq!(block_entity_id(STONE), None);
+        assert_eq!(block_entity_id(AIR), None);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::blocks::{block_entity_id, block_name, block_state_by_name};
+use elytra_nbt::{Compound, Tag};
+use elytra_protocol::chunk_data::{
+    pack_heightmap, ChunkDataPacket, ChunkSection, AIR, BIOMES_LENGTH, HEIGHTMAPS,
+    SECTIONS_PER_CHUNK, SECTION_VOLUME,
+};
+use std::collections::HashMap;
+use std::io;
+
+/// Height of a chunk column in blocks
+pub const CHUNK_HEIGHT: usize = SECTIONS_PER_CHUNK * 16;
+/// Data version of 1.16.5, stored with saved chunks
+pub const DATA_VERSION: i32 = 2586;
+
+/// A 16x256x16 column of blocks, made of up to 16 sections. Sections that were never written
+/// to are left out and read as air.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkColumn {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// One entry per section, lowest first
+    pub sections: Vec<Option<ChunkSection>>,
+    /// Biome ids, one per 4x4x4 cell
+    pub biomes: Vec<i32>,
+    /// NBT compounds of the block entities in the chunk
+    pub block_entities: Vec<Tag>,
+}
+
+impl ChunkColumn {
+    /// Creates a column filled with air and a single biome
+    pub fn new(chunk_x: i32, chunk_z: i32, biome: i32) -> Self {
+        Self {
+            chunk_x,
+            chunk_z,
+            sections: vec![None; SECTIONS_PER_CHUNK],
+            biomes: vec![biome; BIOMES_LENGTH],
+            block_entities: Vec::new(),
+        }
+    }
+
+    /// Returns the global palette id of the block at the given chunk-relative coordinates
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> u32 {
+        match self.sections.get(y / 16) {
+            Some(Some(section)) => section.get_block_state(x, y % 16, z),
+            _ => AIR,
+        }
+    }
+
+    /// Sets the block at the given chunk-relative coordinates, creating its section if needed.
+    /// The block entity of the old block is removed, and one is created if the new block needs
+    /// it. Writes above the top of the column are ignored.
+    pub fn set_block_state(&mut self, x: usize, y: usize, z: usize, state: u32) {
+        let Some(section) = self.sections.get_mut(y / 16) else {
+            return;
+        };
+        if section.is_none() && state == AIR {
+            return;
+        }
+        section
+            .get_or_insert_with(ChunkSection::new)
+            .set_block_state(x, y % 16, z, state);
+
+        let position = (
+            self.chunk_x * 16 + x as i32,
+            y as i32,
+            self.chunk_z * 16 + z as i32,
+        );
+        if !self.block_entities.is_empty() {
+            self.block_entities
+                .retain(|block_entity| block_entity_position(block_entity) != Some(position));
+        }
+        if let Some(id) = block_entity_id(state) {
+            self.block_entities.push(new_block_entity(id, position));
+        }
+    }
+
+    /// Block entity at the given chunk-relative coordinates, if there is one
+    pub fn get_block_entity(&self, x: usize, y: usize, z: usize) -> Option<&Tag> {
+        let position = (
+            self.chunk_x * 16 + x as i32,
+            y as i32,
+            self.chunk_z * 16 + z as i32,
+        );
+        self.block_entities
+            .iter()
+            .find(|block_entity| block_entity_position(block_entity) == Some(position))
+    }
+
+    /// Y of the highest block in a column that matches the predicate, if any
+    pub fn highest_block(
+        &self,
+        x: usize,
+        z: usize,
+        predicate: impl Fn(u32) -> bool,
+    ) -> Option<usize> {
+        (0..CHUNK_HEIGHT)
+            .rev()
+            .find(|&y| predicate(self.get_block_state(x, y, z)))
+    }
+
+    /// Builds the full Chunk Data packet for this column
+    pub fn to_packet(&self) -> ChunkDataPacket {
+        let mut packet = ChunkDataPacket::new_full_chunk(
+            self.chunk_x,
+            self.chunk_z,
+            self.sections.clone(),
+            self.biomes.clone(),
+        );
+        packet.block_entities = self.block_entities.clone();
+        packet
+    }
+
+    /// Stores the column the way vanilla 1.16 saves a chunk: sections with a palette and packed
+    /// indices into it, the heightmaps, biomes and block entities
+    pub fn to_nbt(&self) -> Tag {
+        let sections = self
+            .sections
+            .iter()
+            .enumerate()
+            .filter_map(|(y, section)| Some((y, section.as_ref()?)))
+            .map(|(y, section)| {
+                let (palette, block_states) = anvil_section(section);
+                let mut compound = Compound::new();
+                compound.insert("Y".to_owned(), Tag::Byte(y as i8));
+                compound.insert("Palette".to_owned(), Tag::List(palette));
+                compound.insert("BlockStates".to_owned(), Tag::LongArray(block_states));
+                Tag::Compound(compound)
+            })
+            .collect();
+
+        let mut heightmaps = Compound::new();
+        for (name, counts) in HEIGHTMAPS {
+            let mut heights = [0u16; 256];
+            for (index, height) in heights.iter_mut().enumerate() {
+                *height = self
+                    .highest_block(index & 15, index >> 4, counts)
+                    .map_or(0, |y| y as u16 + 1);
+            }
+            heightmaps.insert(name.to_owned(), Tag::LongArray(pack_heightmap(&heights)));
+        }
+
+        let mut level = Compound::new();
+        level.insert("xPos".to_owned(), Tag::Int(self.chunk_x));
+        level.insert("zPos".to_owned(), Tag::Int(self.chunk_z));
+        level.insert("Status".to_owned(), Tag::String("full".to_owned()));
+        level.insert("Sections".to_owned(), Tag::List(sections));
+        level.insert("Heightmaps".to_owned(), Tag::Compound(heightmaps));
+        level.insert("Biomes".to_owned(), Tag::IntArray(self.biomes.clone()));
+        level.insert(
+            "TileEntities".to_owned(),
+            Tag::List(self.block_entities.clone()),
+        );
+
+        let mut root = Compound::new();
+        root.insert("DataVersion".to_owned(), Tag::Int(DATA_VERSION));
+        root.insert("Level".to_owned(), Tag::Compound(level));
+        Tag::Compound(root)
+    }
+
+    /// Reads a column stored by [`ChunkColumn::to_nbt`] or saved by vanilla 1.16. Sections
+    /// without block states, like the light-only ones vanilla saves below and above the world,
+    /// are skipped. Sections holding a global id per block, as older Elytra versions saved them,
+    /// are still read.
+    pub fn from_nbt(tag: &Tag) -> io::Result<Self> {
+        let level = tag
+            .as_compound()
+            .and_then(|root| root.get("Level"))
+            .and_then(Tag::as_compound)
+            .ok_or_else(|| invalid_data("Chunk has no Level compound"))?;
+        let get = |name: &str| {
+            level
+                .get(name)
+                .ok_or_else(|| invalid_data(format!("Chunk is missing {}", name)))
+        };
+
+        let chunk_x = get("xPos")?
+            .as_i32()
+            .ok_or_else(|| invalid_data("Invalid xPos"))?;
+        let chunk_z = get("zPos")?
+            .as_i32()
+            .ok_or_else(|| invalid_data("Invalid zPos"))?;
+        let biomes = match get("Biomes")? {
+            Tag::IntArray(biomes) if biomes.len() == BIOMES_LENGTH => biomes.clone(),
+            _ => return Err(invalid_data("Invalid Biomes")),
+        };
+        let block_entities = get("TileEntities")?
+            .as_list()
+            .ok_or_else(|| invalid_data("Invalid TileEntities"))?
+            .clone();
+
+        let mut sections = vec![None; SECTIONS_PER_CHUNK];
+        let saved_sections = get("Sections")?
+            .as_list()
+            .ok_or_else(|| invalid_data("Invalid Sections"))?;
+        for saved in saved_sections {
+            let saved = saved
+                .as_compound()
+                .ok_or_else(|| invalid_data("Invalid section"))?;
+            let states = match (saved.get("Palette"), saved.get("BlockStates")) {
+                (Some(palette), Some(Tag::LongArray(data))) => anvil_section_states(palette, data)?,
+                (None, Some(Tag::IntArray(states))) if states.len() == SECTION_VOLUME => {
+                    states.iter().map(|&state| state as u32).collect()
+                }
+                (None, None) => continue,
+                _ => return Err(invalid_data("Invalid section BlockStates")),
+            };
+            let slot = saved
+                .get("Y")
+                .and_then(Tag::as_i8)
+                .and_then(|y| sections.get_mut(usize::try_from(y).ok()?))
+                .ok_or_else(|| invalid_data("Invalid section Y"))?;
+
+            let mut section = ChunkSection::new();
+            for (index, state) in states.into_iter().enumerate() {
+                if state != AIR {
+                    let (x, y, z) = (index & 15, index >> 8, (index >> 4) & 15);
+                    section.set_block_state(x, y, z, state);
+                }
+            }
+            *slot = Some(section);
+        }
+
+        Ok(Self {
+            chunk_x,
+            chunk_z,
+            sections,
+            biomes,
+            block_entities,
+        })
+    }
+}
+
+/// Builds the palette and packed block states of a saved section, the palette listing states in
+/// the order they first appear
+fn anvil_section(section: &ChunkSection) -> (Vec<Tag>, Vec<i64>) {
+    let mut palette = Vec::new();
+    let mut palette_index = HashMap::new();
+    let indices: Vec<usize> = (0..SECTION_VOLUME)
+        .map(|index| {
+            let state = section.get_block_state(index & 15, index >> 8, (index >> 4) & 15);
+            *palette_index.entry(state).or_insert_with(|| {
+                palette.push(state);
+                palette.len() - 1
+            })
+        })
+        .collect();
+
+    let bits = anvil_bits_per_block(palette.len());
+    let values_per_long = 64 / bits;
+    let mut block_states = vec![0i64; SECTION_VOLUME.div_ceil(values_per_long)];
+    for (index, value) in indices.into_iter().enumerate() {
+        block_states[index / values_per_long] |=
+            ((value as u64) << ((index % values_per_long) * bits)) as i64;
+    }
+    (
+        palette.into_iter().map(palette_entry).collect(),
+        block_states,
+    )
+}
+
+/// Palette entry of a saved section. Not every state has a name yet, so the global id is stored
+/// as well and takes precedence when reading. Vanilla ignores it, loading unnamed states as air.
+fn palette_entry(state: u32) -> Tag {
+    let mut compound = Compound::new();
+    if let Some(name) = block_name(state) {
+        compound.insert(
+            "Name".to_owned(),
+            Tag::String(format!("minecraft:{}", name)),
+        );
+    }
+    compound.insert("GlobalId".to_owned(), Tag::Int(state as i32));
+    Tag::Compound(compound)
+}
+
+/// Bits per block of a saved section, the fewest that fit every palette index but at least 4
+fn anvil_bits_per_block(palette_length: usize) -> usize {
+    ((usize::BITS - palette_length.saturating_sub(1).leading_zeros()) as usize).max(4)
+}
+
+/// Unpacks the blocks of a saved section, stored as indices into its palette at 4 or more bits
+/// per block, never spanning two longs. Properties of vanilla palette entries aren't mapped yet,
+/// so each block gets the default state of its name, and names without a known state load as
+/// air.
+fn anvil_section_states(palette: &Tag, data: &[i64]) -> io::Result<Vec<u32>> {
+    let palette = palette
+        .as_list()
+        .ok_or_else(|| invalid_data("Invalid section Palette"))?
+        .iter()
+        .map(|entry| {
+            let entry = entry
+                .as_compound()
+                .ok_or_else(|| invalid_data("Invalid section Palette entry"))?;
+            if let Some(state) = entry.get("GlobalId") {
+                return state
+                    .as_i32()
+                    .map(|state| state as u32)
+                    .ok_or_else(|| invalid_data("Invalid section Palette GlobalId"));
+            }
+            let name = entry
+                .get("Name")
+                .and_then(Tag::as_string)
+                .ok_or_else(|| invalid_data("Invalid section Palette Name"))?;
+            Ok(block_state_by_name(name).unwrap_or(AIR))
+        })
+        .collect::<io::Result<Vec<u32>>>()?;
+    if palette.is_empty() {
+        return Err(invalid_data("Section has an empty Palette"));
+    }
+
+    let bits = anvil_bits_per_block(palette.len());
+    let values_per_long = 64 / bits;
+    if data.len() != SECTION_VOLUME.div_ceil(values_per_long) {
+        return Err(invalid_data(format!(
+            "Invalid BlockStates length {} for a palette of {}",
+            data.len(),
+            palette.len()
+        )));
+    }
+
+    let mask = (1u64 << bits) - 1;
+    (0..SECTION_VOLUME)
+        .map(|index| {
+            let long = data[index / values_per_long] as u64;
+            let value = (long >> ((index % values_per_long) * bits)) & mask;
+            palette
+                .get(value as usize)
+                .copied()
+                .ok_or_else(|| invalid_data("Section BlockStates index outside of its Palette"))
+        })
+        .collect()
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Empty block entity with the given id, holding only its id and world position
+fn new_block_entity(id: &str, (x, y, z): (i32, i32, i32)) -> Tag {
+    let mut compound = Compound::new();
+    compound.insert("id".to_owned(), Tag::String(id.to_owned()));
+    compound.insert("x".to_owned(), Tag::Int(x));
+    compound.insert("y".to_owned(), Tag::Int(y));
+    compound.insert("z".to_owned(), Tag::Int(z));
+    Tag::Compound(compound)
+}
+
+/// World position stored in a block entity compound
+fn block_entity_position(block_entity: &Tag) -> Option<(i32, i32, i32)> {
+    let compound = block_entity.as_compound()?;
+    let coordinate = |name: &str| compound.get(name).and_then(Tag::as_i32);
+    Some((coordinate("x")?, coordinate("y")?, coordinate("z")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_block_state_across_sections() {
+        let mut column = ChunkColumn::new(2, -3, 1);
+        column.set_block_state(1, 0, 1, 33);
+        column.set_block_state(15, 200, 0, 9);
+        column.set_block_state(0, CHUNK_HEIGHT, 0, 9); // Out of range, ignored
+        column.set_block_state(4, 100, 4, AIR); // Doesn't create an empty section
+
+        assert_eq!(column.get_block_state(1, 0, 1), 33);
+        assert_eq!(column.get_block_state(15, 200, 0), 9);
+        assert_eq!(column.get_block_state(15, 201, 0), AIR);
+        assert!(column.sections[100 / 16].is_none());
+
+        let packet = column.to_packet();
+        assert_eq!((packet.chunk_x, packet.chunk_z), (2, -3));
+        assert_eq!(packet.primary_bit_mask, 1 | (1 << (200 / 16)));
+    }
+
+    #[test]
+    fn test_nbt_round_trip() {
+        let mut column = ChunkColumn::new(-7, 12, 4);
+        column.set_block_state(3, 17, 9, 33);
+        column.set_block_state(15, 255, 15, 1);
+        column.set_block_state(0, 40, 0, crate::blocks::OAK_SIGN);
+
+        let restored = ChunkColumn::from_nbt(&column.to_nbt()).unwrap();
+        assert_eq!(restored.get_block_state(3, 17, 9), 33);
+        assert_eq!(restored.get_block_state(15, 255, 15), 1);
+        assert_eq!(restored.block_entities, column.block_entities);
+        assert_eq!((restored.chunk_x, restored.chunk_z), (-7, 12));
+        assert_eq!(restored.biomes, column.biomes);
+        assert_eq!(
+            restored.to_packet().primary_bit_mask,
+            column.to_packet().primary_bit_mask
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use crate::blocks::{AIR, BEDROCK, DIRT, GRASS_BLOCK, PLAINS_BIOME, STONE, WATER};
+use crate::chunk::{ChunkColumn, CHUNK_HEIGHT};
+use crate::noise::PerlinNoise;
+
+/// Highest y filled with water by the noise generator
+pub const SEA_LEVEL: usize = 62;
+
+/// Fills in new chunks. Generators run on blocking worker threads, possibly several chunks at
+/// once, so they must be shareable between threads.
+pub trait WorldGenerator: Send + Sync {
+    fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> ChunkColumn;
+
+    /// Whether the world is superflat. Clients draw the horizon lower and skip the void fog in
+    /// flat worlds.
+    fn is_flat(&self) -> bool {
+        false
+    }
+}
+
+/// Generates a superflat world out of horizontal layers
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlatGenerator {
+    /// Block state of each layer, starting at y = 0
+    layers: Vec<u32>,
+    biome: i32,
+}
+
+impl Default for FlatGenerator {
+    /// A layer of bedrock, two layers of dirt and grass on top
+    fn default() -> Self {
+        Self::new(vec![BEDROCK, DIRT, DIRT, GRASS_BLOCK], PLAINS_BIOME)
+    }
+}
+
+impl FlatGenerator {
+    pub fn new(layers: Vec<u32>, biome: i32) -> Self {
+        Self { layers, biome }
+    }
+
+    pub fn layers(&self) -> &[u32] {
+        &self.layers
+    }
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> ChunkColumn {
+        let mut column = ChunkColumn::new(chunk_x, chunk_z, self.biome);
+        for (y, &state) in self.layers.iter().enumerate().take(CHUNK_HEIGHT) {
+            for z in 0..16 {
+                for x in 0..16 {
+                    column.set_block_state(x, y, z, state);
+                }
+            }
+        }
+        column
+    }
+
+    fn is_flat(&self) -> bool {
+        true
+    }
+}
+
+/// Generates rolling hills from seeded Perlin noise: bedrock at the bottom, stone, a few layers of
+/// dirt and grass on top, with water filling everything below sea level
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    seed: i64,
+    noise: PerlinNoise,
+}
+
+impl NoiseGenerator {
+    /// Average surface height
+    const BASE_HEIGHT: f64 = 64.0;
+    /// Largest distance of the surface above or below the base height
+    const HEIGHT_VARIATION: f64 = 24.0;
+    /// Horizontal size, in blocks, of the largest hills
+    const HORIZONTAL_SCALE: f64 = 96.0;
+    const OCTAVES: u32 = 4;
+    /// Number of dirt layers between the stone and the surface block
+    const DIRT_DEPTH: usize = 3;
+
+    pub fn new(seed: i64) -> Self {
+        Self {
+            seed,
+            noise: PerlinNoise::new(seed),
+        }
+    }
+
+    pub fn seed(&self) -> i64 {
+        self.seed
+    }
+
+    /// Y of the surface block at the given world coordinates
+    pub fn surface_height(&self, x: i32, z: i32) -> usize {
+        let noise = self.noise.fractal(
+            x as f64 / Self::HORIZONTAL_SCALE,
+            z as f64 / Self::HORIZONTAL_SCALE,
+            Self::OCTAVES,
+        );
+        let height = Self::BASE_HEIGHT + noise * Self::HEIGHT_VARIATION;
+        (height.round() as usize).clamp(1, CHUNK_HEIGHT - 1)
+    }
+}
+
+impl WorldGenerator for NoiseGenerator {
+    fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> ChunkColumn {
+        let mut column = ChunkColumn::new(chunk_x, chunk_z, PLAINS_BIOME);
+        for z in 0..16 {
+            for x in 0..16 {
+                let surface = self.surface_height(chunk_x * 16 + x as i32, chunk_z * 16 + z as i32);
+
+                column.set_block_state(x, 0, z, BEDROCK);
+                for y in 1..surface {
+                    let state = if y + Self::DIRT_DEPTH < surface {
+                        STONE
+                    } else {
+                        DIRT
+                    };
+                    column.set_block_state(x, y, z, state);
+                }
+                // Grass doesn't grow underwater
+                let top = if surface >= SEA_LEVEL {
+                    GRASS_BLOCK
+                } else {
+                    DIRT
+                };
+                column.set_block_state(x, surface, z, top);
+                for y in surface + 1..=SEA_LEVEL {
+                    column.set_block_state(x, y, z, WATER);
+                }
+            }
+        }
+        column
+    }
+}
+
+/// Wraps another generator and carves caves into its terrain wherever 3D noise rises above a
+/// threshold. The top few blocks under the surface are never carved, so caves don't open up into
+/// the sky or drain the sea.
+#[derive(Debug, Clone)]
+pub struct CaveCarver<G> {
+    base: G,
+    noise: PerlinNoise,
+}
+
+impl<G: WorldGenerator> CaveCarver<G> {
+    /// Noise value above which a block is carved out
+    const THRESHOLD: f64 = 0.3;
+    /// Horizontal and vertical size, in blocks, of the noise features
+    const HORIZONTAL_SCALE: f64 = 24.0;
+    const VERTICAL_SCALE: f64 = 12.0;
+    /// Number of blocks under the surface that are left intact
+    const SURFACE_DEPTH: usize = 5;
+
+    /// Carves caves into `base`'s terrain. The seed should differ from the one the base generator
+    /// uses, or the caves end up following the shape of the hills.
+    pub fn new(base: G, seed: i64) -> Self {
+        Self {
+            base,
+            noise: PerlinNoise::new(seed),
+        }
+    }
+
+    fn is_cave(&self, x: i32, y: usize, z: i32) -> bool {
+        let noise = self.noise.sample_3d(
+            x as f64 / Self::HORIZONTAL_SCALE,
+            y as f64 / Self::VERTICAL_SCALE,
+            z as f64 / Self::HORIZONTAL_SCALE,
+        );
+        noise > Self::THRESHOLD
+    }
+}
+
+impl<G: WorldGenerator> WorldGenerator for CaveCarver<G> {
+    fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> ChunkColumn {
+        let mut column = self.base.generate_chunk(chunk_x, chunk_z);
+        for z in 0..16 {
+            for x in 0..16 {
+                let Some(surface) =
+                    column.highest_block(x, z, |state| state != AIR && state != WATER)
+                else {
+                    continue;
+                };
+
+                // Bedrock at y = 0 stays as well
+                for y in 1..surface.saturating_sub(Self::SURFACE_DEPTH - 1) {
+                    if self.is_cave(chunk_x * 16 + x as i32, y, chunk_z * 16 + z as i32) {
+                        column.set_block_state(x, y, z, AIR);
+                    }
+                }
+            }
+        }
+        column
+    }
+
+    fn is_flat(&self) -> bool {
+        self.base.is_flat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_generator_is_reproducible_from_seed() {
+        let generator = NoiseGenerator::new(1234);
+        let same_seed = NoiseGenerator::new(1234);
+        let other_seed = NoiseGenerator::new(4321);
+
+        for (chunk_x, chunk_z) in [(0, 0), (-3, 7)] {
+            let chunk = generator.generate_chunk(chunk_x, chunk_z);
+            assert_eq!(chunk, same_seed.generate_chunk(chunk_x, chunk_z));
+            assert_ne!(chunk, other_seed.generate_chunk(chunk_x, chunk_z));
+        }
+    }
+
+    #[test]
+    fn test_noise_generator_layers() {
+        let generator = NoiseGenerator::new(99);
+        let chunk = generator.generate_chunk(2, -1);
+
+        for (x, z) in [(0, 0), (7, 12), (15, 15)] {
+            let surface = generator.surface_height(2 * 16 + x as i32, -16 + z as i32);
+            assert_eq!(chunk.get_block_state(x, 0, z), BEDROCK);
+            if surface > NoiseGenerator::DIRT_DEPTH + 1 {
+                assert_eq!(chunk.get_block_state(x, 1, z), STONE);
+            }
+            assert_eq!(chunk.get_block_state(x, surface - 1, z), DIRT);
+            let above = chunk.get_block_state(x, surface + 1, z);
+            if surface >= SEA_LEVEL {
+                assert_eq!(chunk.get_block_state(x, surface, z), GRASS_BLOCK);
+                assert_eq!(above, AIR);
+            } else {
+                assert_eq!(above, WATER);
+                assert_eq!(chunk.get_block_state(x, SEA_LEVEL + 1, z), AIR);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cave_carver_keeps_surface() {
+        let base = NoiseGenerator::new(7);
+        let generator = CaveCarver::new(base.clone(), 8);
+
+        let mut cave_blocks = 0;
+        for (chunk_x, chunk_z) in [(0, 0), (1, -2), (-4, 3)] {
+            let original = base.generate_chunk(chunk_x, chunk_z);
+            let carved = generator.generate_chunk(chunk_x, chunk_z);
+
+            for z in 0..16 {
+                for x in 0..16 {
+                    let surface =
+                        base.surface_height(chunk_x * 16 + x as i32, chunk_z * 16 + z as i32);
+                    assert_eq!(carved.get_block_state(x, 0, z), BEDROCK);
+                    for y in surface + 1 - CaveCarver::<NoiseGenerator>::SURFACE_DEPTH..=surface {
+                        assert_eq!(
+                            carved.get_block_state(x, y, z),
+                            original.get_block_state(x, y, z)
+                        );
+                    }
+                    cave_blocks += (1..surface)
+                        .filter(|&y| carved.get_block_state(x, y, z) == AIR)
+                        .count();
+                }
+            }
+        }
+        assert!(cave_blocks > 0);
+    }
+}
@@ -0,0 +1,8 @@
+// World state, storage and generation
+pub mod blocks;
+pub mod chunk;
+pub mod generator;
+pub mod lighting;
+pub mod noise;
+pub mod region;
+pub mod world;
@@ -0,0 +1,277 @@
+use crate::blocks::{is_opaque, light_emission};
+use crate::chunk::ChunkColumn;
+use elytra_protocol::chunk_data::{Palette, SECTION_VOLUME};
+use elytra_protocol::update_light::{UpdateLightPacket, LIGHT_ARRAY_LENGTH, LIGHT_SECTIONS};
+use std::collections::VecDeque;
+
+/// Brightest light level, that of open sky
+pub const MAX_LIGHT: u8 = 15;
+
+/// Light is computed from the section below the world to the one above it
+const LIGHT_HEIGHT: usize = LIGHT_SECTIONS * 16;
+
+/// Light of each section of a column, laid out the way the Update Light packet sends it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightArrays {
+    /// Sections that have a light array, bit 0 being the section below the world
+    pub mask: i32,
+    /// Sections without any light
+    pub empty_mask: i32,
+    /// One 2048 byte array of nibbles per bit set in `mask`
+    pub arrays: Vec<Vec<u8>>,
+}
+
+/// Computes sky light for a column. Sky light comes down every column at full strength until it
+/// reaches an opaque block, then spreads sideways and further down, losing a level per block.
+pub fn compute_sky_light(chunk: &ChunkColumn) -> LightArrays {
+    let opaque = opacity(chunk);
+    let mut levels = vec![0u8; 16 * 16 * LIGHT_HEIGHT];
+
+    // Lowest y reached by direct sky light in each column
+    let mut sky_bottoms = [0usize; 256];
+    for (column, sky_bottom) in sky_bottoms.iter_mut().enumerate() {
+        let (x, z) = (column & 15, column >> 4);
+        *sky_bottom = (0..LIGHT_HEIGHT)
+            .rev()
+            .find(|&light_y| opaque[light_index(x, light_y, z)])
+            .map_or(0, |light_y| light_y + 1);
+        for light_y in *sky_bottom..LIGHT_HEIGHT {
+            levels[light_index(x, light_y, z)] = MAX_LIGHT;
+        }
+    }
+
+    // Only blocks beside a column that is lit less far down can spread any light
+    let mut queue = VecDeque::new();
+    for (column, &sky_bottom) in sky_bottoms.iter().enumerate() {
+        let (x, z) = (column & 15, column >> 4);
+        let neighbour_bottom = [
+            (x.wrapping_sub(1), z),
+            (x + 1, z),
+            (x, z.wrapping_sub(1)),
+            (x, z + 1),
+        ]
+        .into_iter()
+        .filter(|&(x, z)| x < 16 && z < 16)
+        .map(|(x, z)| sky_bottoms[(z << 4) | x])
+        .max()
+        .unwrap_or(0);
+        for light_y in sky_bottom..neighbour_bottom {
+            queue.push_back((x, light_y, z));
+        }
+    }
+    flood_fill(&opaque, &mut levels, queue);
+    into_arrays(&levels)
+}
+
+/// Computes block light for a column, spreading from every block that gives off light and losing
+/// a level per block
+pub fn compute_block_light(chunk: &ChunkColumn) -> LightArrays {
+    let opaque = opacity(chunk);
+    let mut levels = vec![0u8; 16 * 16 * LIGHT_HEIGHT];
+    let mut queue = VecDeque::new();
+    for (section_y, section) in chunk.sections.iter().enumerate() {
+        let Some(section) = section else {
+            continue;
+        };
+        let has_sources = match section.palette() {
+            Palette::Single(state) => light_emission(*state) > 0,
+            Palette::Indirect(palette) => palette.iter().any(|&state| light_emission(state) > 0),
+            Palette::Direct => true,
+        };
+        if !has_sources {
+            continue;
+        }
+        for index in 0..SECTION_VOLUME {
+            let (x, y, z) = (index & 15, index >> 8, (index >> 4) & 15);
+            let emission = light_emission(section.get_block_state(x, y, z));
+            if emission > 0 {
+                let light_y = (section_y + 1) * 16 + y;
+                levels[light_index(x, light_y, z)] = emission;
+                queue.push_back((x, light_y, z));
+            }
+        }
+    }
+    flood_fill(&opaque, &mut levels, queue);
+    into_arrays(&levels)
+}
+
+/// Builds the Update Light packet for a column. Light doesn't cross into neighbouring columns
+/// yet. Dimensions without sky light get no sky light arrays.
+pub fn light_packet(chunk: &ChunkColumn, has_skylight: bool) -> UpdateLightPacket {
+    let sky_light = if has_skylight {
+        compute_sky_light(chunk)
+    } else {
+        LightArrays {
+            mask: 0,
+            empty_mask: 0,
+            arrays: Vec::new(),
+        }
+    };
+    let block_light = compute_block_light(chunk);
+    UpdateLightPacket {
+        chunk_x: chunk.chunk_x,
+        chunk_z: chunk.chunk_z,
+        trust_edges: true,
+        sky_light_mask: sky_light.mask,
+        block_light_mask: block_light.mask,
+        empty_sky_light_mask: sky_light.empty_mask,
+        empty_block_light_mask: block_light.empty_mask,
+        sky_light_arrays: sky_light.arrays,
+        block_light_arrays: block_light.arrays,
+    }
+}
+
+/// Spreads light breadth first from the queued positions to their neighbours, one level less
+/// per block, stopping at opaque blocks and the edges of the column
+fn flood_fill(opaque: &[bool], levels: &mut [u8], mut queue: VecDeque<(usize, usize, usize)>) {
+    while let Some((x, light_y, z)) = queue.pop_front() {
+        let level = levels[light_index(x, light_y, z)];
+        if level <= 1 {
+            continue;
+        }
+        let neighbours = [
+            (x.wrapping_sub(1), light_y, z),
+            (x + 1, light_y, z),
+            (x, light_y.wrapping_sub(1), z),
+            (x, light_y + 1, z),
+            (x, light_y, z.wrapping_sub(1)),
+            (x, light_y, z + 1),
+        ];
+        for (x, light_y, z) in neighbours {
+            if x >= 16 || z >= 16 || light_y >= LIGHT_HEIGHT {
+                continue;
+            }
+            let index = light_index(x, light_y, z);
+            if levels[index] >= level - 1 || opaque[index] {
+                continue;
+            }
+            levels[index] = level - 1;
+            queue.push_back((x, light_y, z));
+        }
+    }
+}
+
+/// Whether each block stops light, indexed like the light levels. Everything outside the world is
+/// air.
+fn opacity(chunk: &ChunkColumn) -> Vec<bool> {
+    let mut opaque = vec![false; 16 * 16 * LIGHT_HEIGHT];
+    for (section_y, section) in chunk.sections.iter().enumerate() {
+        let Some(section) = section else {
+            continue;
+        };
+        // Sections are stored YZX like the light levels, so each one is a contiguous run
+        let section_opaque = &mut opaque[(section_y + 1) * SECTION_VOLUME..][..SECTION_VOLUME];
+        if let Palette::Single(state) = section.palette() {
+            section_opaque.fill(is_opaque(*state));
+            continue;
+        }
+        for (index, opaque) in section_opaque.iter_mut().enumerate() {
+            *opaque = is_opaque(section.get_block_state(index & 15, index >> 8, (index >> 4) & 15));
+        }
+    }
+    opaque
+}
+
+fn light_index(x: usize, light_y: usize, z: usize) -> usize {
+    (light_y << 8) | (z << 4) | x
+}
+
+/// Packs the levels into a nibble array per section, two blocks per byte with the lower block
+/// index in the low nibble, leaving out sections without any light
+fn into_arrays(levels: &[u8]) -> LightArrays {
+    let mut light = LightArrays {
+        mask: 0,
+        empty_mask: 0,
+        arrays: Vec::new(),
+    };
+    for (section, section_levels) in levels.chunks_exact(SECTION_VOLUME).enumerate() {
+        if section_levels.iter().all(|&level| level == 0) {
+            light.empty_mask |= 1 << section;
+            continue;
+        }
+        let mut array = vec![0u8; LIGHT_ARRAY_LENGTH];
+        for (byte, pair) in array.iter_mut().zip(section_levels.chunks_exact(2)) {
+            *byte = pair[0] | (pair[1] << 4);
+        }
+        light.mask |= 1 << section;
+        light.arrays.push(array);
+    }
+    light
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{AIR, GRASS_BLOCK, STONE};
+
+    /// Light level of a block in the world from the arrays of its section
+    fn level_at(light: &LightArrays, x: usize, y: usize, z: usize) -> u8 {
+        let section = y / 16 + 1;
+        if light.mask & (1 << section) == 0 {
+            return 0;
+        }
+        let array_index = (light.mask & ((1 << section) - 1)).count_ones() as usize;
+        let index = light_index(x, y % 16, z);
+        (light.arrays[array_index][index / 2] >> ((index % 2) * 4)) & 0xF
+    }
+
+    #[test]
+    fn test_sky_light_stops_at_the_ground() {
+        let mut chunk = ChunkColumn::new(0, 0, 1);
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..64 {
+                    chunk.set_block_state(x, y, z, STONE);
+                }
+                chunk.set_block_state(x, 64, z, GRASS_BLOCK);
+            }
+        }
+        // A shaft down into the stone, open to the sky
+        for y in 40..=64 {
+            chunk.set_block_state(8, y, 8, AIR);
+        }
+        // A pocket beside the shaft, two blocks away from it
+        chunk.set_block_state(9, 40, 8, AIR);
+        chunk.set_block_state(10, 40, 8, AIR);
+
+        let light = compute_sky_light(&chunk);
+        assert_eq!(level_at(&light, 0, 65, 0), MAX_LIGHT);
+        assert_eq!(level_at(&light, 0, 255, 15), MAX_LIGHT);
+        assert_eq!(level_at(&light, 0, 64, 0), 0);
+        assert_eq!(level_at(&light, 8, 40, 8), MAX_LIGHT);
+        assert_eq!(level_at(&light, 9, 40, 8), MAX_LIGHT - 1);
+        assert_eq!(level_at(&light, 10, 40, 8), MAX_LIGHT - 2);
+        // Sections fully inside the stone get no array
+        assert_eq!(light.empty_mask & 0b10, 0b10);
+        assert_eq!(light.arrays.len(), light.mask.count_ones() as usize);
+        assert_eq!(light.mask | light.empty_mask, (1 << LIGHT_SECTIONS) - 1);
+    }
+
+    #[test]
+    fn test_block_light_spreads_from_sources() {
+        let mut chunk = ChunkColumn::new(0, 0, 1);
+        for y in 0..12 {
+            for z in 0..16 {
+                chunk.set_block_state(4, y, z, STONE);
+            }
+        }
+        let torch = 1435;
+        chunk.set_block_state(8, 8, 8, torch);
+
+        let light = compute_block_light(&chunk);
+        assert_eq!(level_at(&light, 8, 8, 8), 14);
+        assert_eq!(level_at(&light, 8, 9, 8), 13);
+        assert_eq!(level_at(&light, 10, 5, 8), 9);
+        // Light only gets past the wall by going over it
+        assert_eq!(level_at(&light, 4, 8, 8), 0);
+        assert_eq!(level_at(&light, 3, 12, 8), 5);
+        assert_eq!(level_at(&light, 3, 8, 8), 1);
+        // The light reaches down into the section below the world
+        assert_eq!(light.mask, 0b111);
+
+        let packet = light_packet(&chunk, false);
+        assert_eq!(packet.sky_light_mask, 0);
+        assert_eq!(packet.block_light_mask, light.mask);
+        assert_eq!(packet.block_light_arrays, light.arrays);
+    }
+}
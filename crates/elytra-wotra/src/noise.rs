@@ -0,0 +1,166 @@
+/// Seeded 2D and 3D Perlin noise
+#[derive(Debug, Clone)]
+pub struct PerlinNoise {
+    /// Shuffled 0-255, repeated once so lookups can index past 255 without wrapping
+    permutation: [u8; 512],
+}
+
+impl PerlinNoise {
+    /// Creates the noise function for a seed. The same seed always gives the same noise.
+    pub fn new(seed: i64) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+        // Fisher-Yates shuffle driven by splitmix64
+        let mut state = seed as u64;
+        for i in (1..table.len()).rev() {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut random = state;
+            random = (random ^ (random >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            random = (random ^ (random >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            random ^= random >> 31;
+            table.swap(i, (random % (i as u64 + 1)) as usize);
+        }
+
+        Self {
+            permutation: std::array::from_fn(|i| table[i % 256]),
+        }
+    }
+
+    /// Samples the noise at a point, returning a value in roughly -1.0 to 1.0. The noise is 0 at
+    /// integer coordinates, so callers should scale their coordinates down.
+    pub fn sample(&self, x: f64, z: f64) -> f64 {
+        let (cell_x, cell_z) = (x.floor(), z.floor());
+        let (x, z) = (x - cell_x, z - cell_z);
+        let (cell_x, cell_z) = (cell_x as i64 as usize & 255, cell_z as i64 as usize & 255);
+
+        let hash = |dx: usize, dz: usize| {
+            let p = &self.permutation;
+            p[p[cell_x + dx] as usize + cell_z + dz]
+        };
+
+        let (u, v) = (fade(x), fade(z));
+        let bottom = lerp(
+            u,
+            gradient(hash(0, 0), x, z),
+            gradient(hash(1, 0), x - 1.0, z),
+        );
+        let top = lerp(
+            u,
+            gradient(hash(0, 1), x, z - 1.0),
+            gradient(hash(1, 1), x - 1.0, z - 1.0),
+        );
+        lerp(v, bottom, top)
+    }
+
+    /// Samples 3D noise at a point, returning a value in roughly -1.0 to 1.0
+    pub fn sample_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (cell_x, cell_y, cell_z) = (x.floor(), y.floor(), z.floor());
+        let (x, y, z) = (x - cell_x, y - cell_y, z - cell_z);
+        let (cell_x, cell_y, cell_z) = (
+            cell_x as i64 as usize & 255,
+            cell_y as i64 as usize & 255,
+            cell_z as i64 as usize & 255,
+        );
+
+        let hash = |dx: usize, dy: usize, dz: usize| {
+            let p = &self.permutation;
+            p[p[p[cell_x + dx] as usize + cell_y + dy] as usize + cell_z + dz]
+        };
+        let corner = |dx: usize, dy: usize, dz: usize| {
+            gradient_3d(
+                hash(dx, dy, dz),
+                x - dx as f64,
+                y - dy as f64,
+                z - dz as f64,
+            )
+        };
+
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let near = lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        );
+        let far = lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        );
+        lerp(w, near, far)
+    }
+
+    /// Sums `octaves` layers of noise, each at twice the frequency and half the amplitude of the
+    /// one before. The result is normalized back to roughly -1.0 to 1.0.
+    pub fn fractal(&self, x: f64, z: f64, octaves: u32) -> f64 {
+        let (mut total, mut amplitude, mut frequency, mut max) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves {
+            total += self.sample(x * frequency, z * frequency) * amplitude;
+            max += amplitude;
+            amplitude /= 2.0;
+            frequency *= 2.0;
+        }
+        total / max
+    }
+}
+
+/// Smoothstep curve 6t^5 - 15t^4 + 10t^3
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Dot product of the offset with one of eight gradient directions picked by the hash
+fn gradient(hash: u8, x: f64, z: f64) -> f64 {
+    match hash & 7 {
+        0 => x + z,
+        1 => x - z,
+        2 => -x + z,
+        3 => -x - z,
+        4 => x,
+        5 => -x,
+        6 => z,
+        _ => -z,
+    }
+}
+
+/// Dot product of the offset with one of the twelve cube edge directions picked by the hash
+fn gradient_3d(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let hash = hash & 15;
+    let u = if hash < 8 { x } else { y };
+    let v = match hash {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    let u = if hash & 1 == 0 { u } else { -u };
+    let v = if hash & 2 == 0 { v } else { -v };
+    u + v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_is_seeded_and_bounded() {
+        let noise = PerlinNoise::new(42);
+        let same_seed = PerlinNoise::new(42);
+        let other_seed = PerlinNoise::new(43);
+
+        let mut differs = false;
+        for i in 0..1000 {
+            let (x, z) = (i as f64 * 0.37 - 150.0, i as f64 * 0.11 + 20.0);
+            let value = noise.fractal(x, z, 4);
+            assert!((-1.0..=1.0).contains(&value));
+            assert_eq!(value, same_seed.fractal(x, z, 4));
+            differs |= value != other_seed.fractal(x, z, 4);
+        }
+        assert!(differs);
+        assert_eq!(noise.sample(3.0, -7.0), 0.0);
+        assert_eq!(noise.sample_3d(3.0, 40.0, -7.0), 0.0);
+        assert!((-1.0..=1.0).contains(&noise.sample_3d(3.5, 40.2, -7.9)));
+    }
+}
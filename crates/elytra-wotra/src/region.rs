@@ -0,0 +1,427 @@
+use crate::chunk::ChunkColumn;
+use crate::world::ChunkPos;
+use elytra_nbt::{Compression, NBTFile};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size of a sector, the unit space in a region file is allocated in
+const SECTOR_SIZE: usize = 4096;
+/// Chunks along each side of a region
+const REGION_WIDTH: i32 = 32;
+/// Sectors taken by the location and timestamp tables at the start of the file
+const HEADER_SECTORS: usize = 2;
+/// Most sectors a chunk can take, since its sector count is stored in a single byte
+const MAX_CHUNK_SECTORS: usize = 255;
+
+const COMPRESSION_GZIP: u8 = 1;
+const COMPRESSION_ZLIB: u8 = 2;
+const COMPRESSION_NONE: u8 = 3;
+
+/// Anvil region file (`.mca`) holding the chunks of a 32x32 chunk area. Each chunk is stored as
+/// compressed NBT in whole sectors, found through the location table at the start of the file.
+pub struct RegionFile {
+    file: File,
+    /// Offset in sectors in the upper 3 bytes and length in sectors in the lowest byte, per
+    /// chunk. 0 means the chunk has never been saved.
+    locations: [u32; 1024],
+    /// Whether each sector of the file holds the header or a chunk
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    /// Opens a region file, creating an empty one if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut header = vec![0u8; HEADER_SECTORS * SECTOR_SIZE];
+        let file_length = file.metadata()?.len() as usize;
+        if file_length < header.len() {
+            file.set_len(header.len() as u64)?;
+        } else {
+            file.read_exact(&mut header)?;
+        }
+
+        let mut locations = [0u32; 1024];
+        for (location, bytes) in locations.iter_mut().zip(header.chunks_exact(4)) {
+            *location = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        let sector_count = file_length.max(header.len()).div_ceil(SECTOR_SIZE);
+        let mut used_sectors = vec![false; sector_count];
+        used_sectors[..HEADER_SECTORS].fill(true);
+        for &location in &locations {
+            let (offset, length) = split_location(location);
+            if offset < HEADER_SECTORS || offset + length > sector_count {
+                continue;
+            }
+            used_sectors[offset..offset + length].fill(true);
+        }
+
+        Ok(Self {
+            file,
+            locations,
+            used_sectors,
+        })
+    }
+
+    /// Reads a chunk, or returns `None` if it has never been saved
+    pub fn read_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> io::Result<Option<NBTFile>> {
+        let (offset, length) = split_location(self.locations[location_index(chunk_x, chunk_z)]);
+        if offset == 0 {
+            return Ok(None);
+        }
+        if offset < HEADER_SECTORS || offset + length > self.used_sectors.len() {
+            return Err(invalid_data(format!(
+                "Chunk ({}, {}) points outside of the region file",
+                chunk_x, chunk_z
+            )));
+        }
+
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        let mut prefix = [0u8; 5];
+        self.file.read_exact(&mut prefix)?;
+        let data_length = u32::from_be_bytes(prefix[..4].try_into().unwrap()) as usize;
+        // The length counts the compression type byte
+        if data_length == 0 || data_length + 4 > length * SECTOR_SIZE {
+            return Err(invalid_data(format!(
+                "Chunk ({}, {}) has an invalid length of {}",
+                chunk_x, chunk_z, data_length
+            )));
+        }
+
+        let mut data = vec![0u8; data_length - 1];
+        self.file.read_exact(&mut data)?;
+        let mut reader = data.as_slice();
+        let chunk = match prefix[4] {
+            COMPRESSION_GZIP => NBTFile::read_gzip(&mut reader)?,
+            COMPRESSION_ZLIB => NBTFile::read_zlib(&mut reader)?,
+            COMPRESSION_NONE => NBTFile::read(&mut reader)?,
+            compression => {
+                return Err(invalid_data(format!(
+                    "Chunk ({}, {}) uses unknown compression type {}",
+                    chunk_x, chunk_z, compression
+                )))
+            }
+        };
+        Ok(Some(chunk))
+    }
+
+    /// Reads a chunk and converts it into a column, or returns `None` if it has never been saved
+    pub fn get_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> io::Result<Option<ChunkColumn>> {
+        match self.read_chunk(chunk_x, chunk_z)? {
+            Some(file) => ChunkColumn::from_nbt(&file.root).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Saves a column in the Anvil chunk layout, replacing any earlier copy
+    pub fn write_chunk(&mut self, chunk: &ChunkColumn) -> io::Result<()> {
+        let file = NBTFile::new(String::new(), chunk.to_nbt());
+        self.write_chunk_nbt(chunk.chunk_x, chunk.chunk_z, &file)
+    }
+
+    /// Writes chunk NBT zlib compressed, replacing any earlier copy. The sectors of the earlier
+    /// copy are freed first, so they are reused if the chunk still fits in them. Otherwise the
+    /// chunk goes in the first free run of sectors that fits, growing the file at its end.
+    pub fn write_chunk_nbt(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk: &NBTFile,
+    ) -> io::Result<()> {
+        let mut data = vec![0u8; 5];
+        chunk.write_zlib_with(&mut data, Compression::default())?;
+        let data_length = (data.len() - 4) as u32;
+        data[..4].copy_from_slice(&data_length.to_be_bytes());
+        data[4] = COMPRESSION_ZLIB;
+
+        let sectors = data.len().div_ceil(SECTOR_SIZE);
+        if sectors > MAX_CHUNK_SECTORS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Chunk ({}, {}) takes {} bytes, more than a region file can hold",
+                    chunk_x,
+                    chunk_z,
+                    data.len()
+                ),
+            ));
+        }
+        data.resize(sectors * SECTOR_SIZE, 0);
+
+        let index = location_index(chunk_x, chunk_z);
+        let (old_offset, old_length) = split_location(self.locations[index]);
+        // Entries pointing past the end of the file were never marked as used by `open`
+        if old_offset >= HEADER_SECTORS && old_offset + old_length <= self.used_sectors.len() {
+            self.used_sectors[old_offset..old_offset + old_length].fill(false);
+        }
+        let offset = self.allocate(sectors);
+
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&data)?;
+
+        self.locations[index] = ((offset as u32) << 8) | sectors as u32;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as u32);
+        self.file.seek(SeekFrom::Start((index * 4) as u64))?;
+        self.file.write_all(&self.locations[index].to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+        self.file.flush()
+    }
+
+    /// Marks the first run of `sectors` free sectors as used, growing the file if there is none,
+    /// and returns its offset
+    fn allocate(&mut self, sectors: usize) -> usize {
+        let mut run_start = HEADER_SECTORS;
+        for sector in HEADER_SECTORS..self.used_sectors.len() {
+            if self.used_sectors[sector] {
+                run_start = sector + 1;
+            } else if sector + 1 - run_start == sectors {
+                break;
+            }
+        }
+        let end = run_start + sectors;
+        if end > self.used_sectors.len() {
+            self.used_sectors.resize(end, false);
+        }
+        self.used_sectors[run_start..end].fill(true);
+        run_start
+    }
+}
+
+/// Region file the chunk at the given position is stored in, named like `r.-1.0.mca`
+pub fn region_path(dir: &Path, (chunk_x, chunk_z): ChunkPos) -> PathBuf {
+    dir.join(format!(
+        "r.{}.{}.mca",
+        chunk_x.div_euclid(REGION_WIDTH),
+        chunk_z.div_euclid(REGION_WIDTH)
+    ))
+}
+
+fn location_index(chunk_x: i32, chunk_z: i32) -> usize {
+    (chunk_x.rem_euclid(REGION_WIDTH) + chunk_z.rem_euclid(REGION_WIDTH) * REGION_WIDTH) as usize
+}
+
+/// Splits a location table entry into its offset and length in sectors
+fn split_location(location: u32) -> (usize, usize) {
+    ((location >> 8) as usize, (location & 0xFF) as usize)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{GRASS_BLOCK, OAK_SIGN, STONE};
+    use crate::chunk::{CHUNK_HEIGHT, DATA_VERSION};
+    use elytra_nbt::Tag;
+    use elytra_protocol::chunk_data::{AIR, BIOMES_LENGTH};
+
+    /// Chunk stand-in that doesn't compress into a single sector
+    fn chunk_with(seed: i64) -> NBTFile {
+        let noise = (0..2000)
+            .map(|i: i64| i.wrapping_mul(seed).wrapping_mul(0x5851_F42D_4C95_7F2D))
+            .collect();
+        NBTFile::new(
+            String::new(),
+            Tag::Compound(
+                [("Noise".to_owned(), Tag::LongArray(noise))]
+                    .into_iter()
+                    .collect(),
+            ),
+        )
+    }
+
+    fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(
+            entries
+                .into_iter()
+                .map(|(name, tag)| (name.to_owned(), tag))
+                .collect(),
+        )
+    }
+
+    /// Chunk laid out the way vanilla 1.16 saves it, with a light-only section below the world
+    fn vanilla_chunk(chunk_x: i32, chunk_z: i32) -> NBTFile {
+        let palette = ["minecraft:air", "minecraft:stone", "minecraft:grass_block"]
+            .into_iter()
+            .map(|name| {
+                compound(vec![
+                    ("Name", Tag::String(name.to_owned())),
+                    (
+                        "Properties",
+                        compound(vec![("snowy", Tag::String("false".to_owned()))]),
+                    ),
+                ])
+            })
+            .collect();
+        // 4 bits per block: the bottom layer is stone, the one above grass
+        let mut block_states = vec![0i64; 256];
+        block_states[..16].fill(0x1111_1111_1111_1111);
+        block_states[16..32].fill(0x2222_2222_2222_2222);
+
+        let sections = vec![
+            compound(vec![
+                ("Y", Tag::Byte(-1)),
+                ("SkyLight", Tag::ByteArray(vec![0; 2048])),
+            ]),
+            compound(vec![
+                ("Y", Tag::Byte(0)),
+                ("Palette", Tag::List(palette)),
+                ("BlockStates", Tag::LongArray(block_states)),
+            ]),
+        ];
+        let level = compound(vec![
+            ("xPos", Tag::Int(chunk_x)),
+            ("zPos", Tag::Int(chunk_z)),
+            ("Status", Tag::String("full".to_owned())),
+            ("Sections", Tag::List(sections)),
+            ("Biomes", Tag::IntArray(vec![1; BIOMES_LENGTH])),
+            ("TileEntities", Tag::List(Vec::new())),
+        ]);
+        NBTFile::new(
+            String::new(),
+            compound(vec![
+                ("DataVersion", Tag::Int(DATA_VERSION)),
+                ("Level", level),
+            ]),
+        )
+    }
+
+    fn read_root(region: &mut RegionFile, chunk_x: i32, chunk_z: i32) -> Option<Tag> {
+        Some(region.read_chunk(chunk_x, chunk_z).unwrap()?.root)
+    }
+
+    #[test]
+    fn test_write_and_read_chunks() {
+        let dir = std::env::temp_dir().join(format!("elytra-region-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = region_path(&dir, (-1, 33));
+        assert_eq!(path.file_name().unwrap(), "r.-1.1.mca");
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert!(region.read_chunk(-1, 33).unwrap().is_none());
+        region.write_chunk_nbt(-1, 33, &chunk_with(1)).unwrap();
+        region.write_chunk_nbt(0, 32, &chunk_with(2)).unwrap();
+        // Replacing a chunk keeps its neighbour intact
+        region.write_chunk_nbt(-1, 33, &chunk_with(3)).unwrap();
+        drop(region);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(read_root(&mut region, -1, 33), Some(chunk_with(3).root));
+        assert_eq!(read_root(&mut region, 0, 32), Some(chunk_with(2).root));
+        assert_eq!(read_root(&mut region, 5, 5), None);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len() as usize % SECTOR_SIZE,
+            0
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_overwrite_chunk_with_out_of_range_header_entry() {
+        let dir = std::env::temp_dir().join(format!("elytra-corrupt-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = region_path(&dir, (0, 0));
+
+        let mut region = RegionFile::open(&path).unwrap();
+        region.write_chunk_nbt(1, 0, &chunk_with(1)).unwrap();
+        drop(region);
+
+        // Point chunk (0, 0) far past the end of the file
+        let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.write_all(&((500u32 << 8) | 4).to_be_bytes()).unwrap();
+        drop(file);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        region.write_chunk_nbt(0, 0, &chunk_with(2)).unwrap();
+        assert_eq!(read_root(&mut region, 0, 0), Some(chunk_with(2).root));
+        assert_eq!(read_root(&mut region, 1, 0), Some(chunk_with(1).root));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_vanilla_chunk() {
+        let dir = std::env::temp_dir().join(format!("elytra-anvil-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = region_path(&dir, (3, -4));
+
+        let mut region = RegionFile::open(&path).unwrap();
+        region
+            .write_chunk_nbt(3, -4, &vanilla_chunk(3, -4))
+            .unwrap();
+        assert!(region.get_chunk(4, -4).unwrap().is_none());
+
+        let column = region.get_chunk(3, -4).unwrap().unwrap();
+        assert_eq!((column.chunk_x, column.chunk_z), (3, -4));
+        assert_eq!(column.get_block_state(0, 0, 0), STONE);
+        assert_eq!(column.get_block_state(15, 0, 15), STONE);
+        assert_eq!(column.get_block_state(7, 1, 3), GRASS_BLOCK);
+        assert_eq!(column.get_block_state(7, 2, 3), AIR);
+        assert!(column.sections[1].is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_column_and_get_it_back() {
+        let dir = std::env::temp_dir().join(format!("elytra-column-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = region_path(&dir, (0, 0));
+
+        let mut column = ChunkColumn::new(5, 6, 1);
+        for x in 0..16 {
+            for z in 0..16 {
+                column.set_block_state(x, 0, z, STONE);
+            }
+        }
+        // Enough distinct states for 8 bits per block, plus a state without a name
+        for index in 0..200 {
+            column.set_block_state(index % 16, 20 + index / 16, 3, 100 + index as u32);
+        }
+        column.set_block_state(2, 70, 2, OAK_SIGN + 4);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        region.write_chunk(&ChunkColumn::new(0, 0, 1)).unwrap();
+        region.write_chunk(&column).unwrap();
+        drop(region);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        let restored = region.get_chunk(5, 6).unwrap().unwrap();
+        for y in 0..CHUNK_HEIGHT {
+            for z in 0..16 {
+                for x in 0..16 {
+                    assert_eq!(
+                        restored.get_block_state(x, y, z),
+                        column.get_block_state(x, y, z)
+                    );
+                }
+            }
+        }
+        assert_eq!(restored.biomes, column.biomes);
+        assert_eq!(restored.to_packet(), column.to_packet());
+
+        let level = region.read_chunk(5, 6).unwrap().unwrap().root;
+        let heightmaps =
+            level.as_compound().unwrap()["Level"].as_compound().unwrap()["Heightmaps"].clone();
+        assert_eq!(heightmaps, column.to_packet().heightmaps);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
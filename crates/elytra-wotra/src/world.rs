@@ -0,0 +1,699 @@
+use crate::blocks::AIR;
+use crate::chunk::{ChunkColumn, CHUNK_HEIGHT};
+use crate::generator::WorldGenerator;
+use crate::region::{region_path, RegionFile};
+use elytra_protocol::packet::frame_packet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Chunk coordinates (x, z)
+pub type ChunkPos = (i32, i32);
+/// Block coordinates (x, y, z)
+pub type BlockPos = (i32, i32, i32);
+
+/// Length of a day-night cycle in ticks
+pub const TICKS_PER_DAY: i64 = 24000;
+/// Time of day set by `/time set day`
+pub const DAY: i64 = 1000;
+/// Time of day set by `/time set night`
+pub const NIGHT: i64 = 13000;
+
+/// Weather of a world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Weather {
+    #[default]
+    Clear,
+    Rain,
+    /// Rain with a darkened sky and lightning
+    Thunder,
+}
+
+impl Weather {
+    pub fn is_raining(self) -> bool {
+        self != Weather::Clear
+    }
+
+    /// Rain level, from 0 to 1, the client shows for this weather
+    pub fn rain_strength(self) -> f32 {
+        if self.is_raining() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Thunder level, from 0 to 1, the client shows for this weather
+    pub fn thunder_strength(self) -> f32 {
+        if self == Weather::Thunder {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Block players spawn at in a world that doesn't say otherwise
+pub const DEFAULT_SPAWN: BlockPos = (0, 64, 0);
+
+/// Largest side length the world border can have
+pub const MAX_WORLD_BORDER_DIAMETER: f64 = 59_999_968.0;
+
+/// Square around `center` that players can't leave
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBorder {
+    /// Center as (x, z)
+    pub center: (f64, f64),
+    /// Side length in blocks
+    pub diameter: f64,
+    /// Seconds before a moving border reaches a player that their screen turns red
+    pub warning_time: i32,
+    /// Distance from the border, in blocks, at which a player's screen turns red
+    pub warning_blocks: i32,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self {
+            center: (0.0, 0.0),
+            diameter: MAX_WORLD_BORDER_DIAMETER,
+            warning_time: 15,
+            warning_blocks: 5,
+        }
+    }
+}
+
+/// The loaded chunks of a world, loading missing ones from region files or generating them on
+/// demand, its spawn, its clock, its weather and its border
+pub struct World {
+    generator: Arc<dyn WorldGenerator>,
+    /// Directory of the `.mca` region files chunks are saved to and loaded from. `None` keeps the
+    /// world in memory only.
+    region_dir: Option<PathBuf>,
+    /// Only ever locked for map lookups and inserts, never across generation or an await
+    chunks: Mutex<ChunkStore>,
+    /// Woken whenever chunks leave the in-flight set, whether they were stored or given up on
+    chunks_settled: Notify,
+    spawn: Mutex<BlockPos>,
+    /// Ticks the world has run for
+    world_age: AtomicI64,
+    /// Ticks since the start of the first day. Only the remainder modulo `TICKS_PER_DAY` matters
+    /// to the client.
+    world_time: AtomicI64,
+    weather: Mutex<Weather>,
+    world_border: Mutex<WorldBorder>,
+    /// Chunk Data packets serialized so far, for telling how well the cache works
+    chunk_serializations: AtomicUsize,
+}
+
+/// Chunk Data packet of a chunk, framed and ready to send
+pub type EncodedChunk = Arc<Vec<u8>>;
+
+#[derive(Default)]
+struct ChunkStore {
+    loaded: HashMap<ChunkPos, Arc<ChunkColumn>>,
+    /// Serialized Chunk Data packets of loaded chunks, dropped when a block in the chunk changes
+    encoded: HashMap<ChunkPos, EncodedChunk>,
+    /// Chunks some caller is currently generating. Anyone else asking for them waits for that
+    /// generation instead of starting another one.
+    in_flight: HashSet<ChunkPos>,
+    /// Loaded chunks with blocks changed since they were last saved
+    dirty: HashSet<ChunkPos>,
+}
+
+impl World {
+    pub fn new(generator: impl WorldGenerator + 'static) -> Self {
+        Self {
+            generator: Arc::new(generator),
+            region_dir: None,
+            chunks: Mutex::new(ChunkStore::default()),
+            chunks_settled: Notify::new(),
+            spawn: Mutex::new(DEFAULT_SPAWN),
+            world_age: AtomicI64::new(0),
+            world_time: AtomicI64::new(0),
+            weather: Mutex::new(Weather::default()),
+            world_border: Mutex::new(WorldBorder::default()),
+            chunk_serializations: AtomicUsize::new(0),
+        }
+    }
+
+    /// Saves chunks to region files in `dir` and loads chunks found there instead of generating
+    /// them
+    pub fn with_region_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.region_dir = Some(dir.into());
+        self
+    }
+
+    /// Whether the world is superflat
+    pub fn is_flat(&self) -> bool {
+        self.generator.is_flat()
+    }
+
+    pub fn weather(&self) -> Weather {
+        *self
+            .weather
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Changes the weather and returns what it was before
+    pub fn set_weather(&self, weather: Weather) -> Weather {
+        let mut current = self
+            .weather
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *current, weather)
+    }
+
+    /// Block new players appear at
+    pub fn spawn(&self) -> BlockPos {
+        *self
+            .spawn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_spawn(&self, spawn: BlockPos) {
+        *self
+            .spawn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = spawn;
+    }
+
+    pub fn world_border(&self) -> WorldBorder {
+        *self
+            .world_border
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_world_border(&self, world_border: WorldBorder) {
+        *self
+            .world_border
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = world_border;
+    }
+
+    pub fn rain_strength(&self) -> f32 {
+        self.weather().rain_strength()
+    }
+
+    pub fn thunder_strength(&self) -> f32 {
+        self.weather().thunder_strength()
+    }
+
+    pub fn world_age(&self) -> i64 {
+        self.world_age.load(Ordering::Relaxed)
+    }
+
+    pub fn world_time(&self) -> i64 {
+        self.world_time.load(Ordering::Relaxed)
+    }
+
+    pub fn set_world_time(&self, world_time: i64) {
+        self.world_time.store(world_time, Ordering::Relaxed);
+    }
+
+    /// Moves the clock forward (or back, for negative values) and returns the new time
+    pub fn add_world_time(&self, ticks: i64) -> i64 {
+        self.world_time.fetch_add(ticks, Ordering::Relaxed) + ticks
+    }
+
+    /// Advances the world by one tick
+    pub fn tick(&self) {
+        self.world_age.fetch_add(1, Ordering::Relaxed);
+        self.world_time.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns a chunk if it has already been generated
+    pub fn get_chunk(&self, chunk_x: i32, chunk_z: i32) -> Option<Arc<ChunkColumn>> {
+        self.lock_chunks().loaded.get(&(chunk_x, chunk_z)).cloned()
+    }
+
+    /// Number of chunks currently loaded
+    pub fn loaded_chunk_count(&self) -> usize {
+        self.lock_chunks().loaded.len()
+    }
+
+    /// Whether a block at this height is inside the world
+    pub fn is_in_height_range(y: i32) -> bool {
+        (0..CHUNK_HEIGHT as i32).contains(&y)
+    }
+
+    /// Returns the block state at a position, generating its chunk if needed. Blocks above or
+    /// below the world are air.
+    pub async fn get_block_state(&self, (x, y, z): BlockPos) -> io::Result<u32> {
+        if !Self::is_in_height_range(y) {
+            return Ok(AIR);
+        }
+        let chunk = self.get_or_generate_chunk(x >> 4, z >> 4).await?;
+        Ok(chunk.get_block_state((x & 15) as usize, y as usize, (z & 15) as usize))
+    }
+
+    pub async fn set_block_state(&self, position: BlockPos, state: u32) -> io::Result<()> {
+        self.set_block_states(&[(position, state)]).await
+    }
+
+    /// Sets any number of blocks, generating the chunks they are in if needed. Blocks above or
+    /// below the world are skipped.
+    pub async fn set_block_states(&self, changes: &[(BlockPos, u32)]) -> io::Result<()> {
+        let mut positions: Vec<ChunkPos> = changes
+            .iter()
+            .map(|&((x, _, z), _)| (x >> 4, z >> 4))
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        self.get_or_generate(&positions).await?;
+
+        let mut chunks = self.lock_chunks();
+        for &((x, y, z), state) in changes {
+            if !Self::is_in_height_range(y) {
+                continue;
+            }
+            chunks.encoded.remove(&(x >> 4, z >> 4));
+            chunks.dirty.insert((x >> 4, z >> 4));
+            if let Some(chunk) = chunks.loaded.get_mut(&(x >> 4, z >> 4)) {
+                // Copies the column only if a reader, such as a chunk being sent, still holds it
+                Arc::make_mut(chunk).set_block_state(
+                    (x & 15) as usize,
+                    y as usize,
+                    (z & 15) as usize,
+                    state,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a single chunk, generating it if needed
+    pub async fn get_or_generate_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> io::Result<Arc<ChunkColumn>> {
+        let mut chunks = self.get_or_generate(&[(chunk_x, chunk_z)]).await?;
+        Ok(chunks.remove(0))
+    }
+
+    /// Returns the framed Chunk Data packets of the chunks at the given positions, in the same
+    /// order, generating chunks if needed. Packets are serialized once and reused until a block
+    /// in the chunk changes, so sending a chunk to many players costs a single serialization.
+    pub async fn get_or_encode(&self, positions: &[ChunkPos]) -> io::Result<Vec<EncodedChunk>> {
+        let chunks = self.get_or_generate(positions).await?;
+        let mut encoded_chunks = Vec::with_capacity(chunks.len());
+        for (position, chunk) in positions.iter().zip(chunks) {
+            if let Some(encoded) = self.lock_chunks().encoded.get(position) {
+                encoded_chunks.push(encoded.clone());
+                continue;
+            }
+
+            let encoded = Arc::new(frame_packet(&chunk.to_packet())?);
+            self.chunk_serializations.fetch_add(1, Ordering::Relaxed);
+            let mut store = self.lock_chunks();
+            // A block changed while serializing if the stored column was copied in the meantime
+            if store
+                .loaded
+                .get(position)
+                .is_some_and(|loaded| Arc::ptr_eq(loaded, &chunk))
+            {
+                store.encoded.insert(*position, encoded.clone());
+            }
+            encoded_chunks.push(encoded);
+        }
+        Ok(encoded_chunks)
+    }
+
+    /// Whether a chunk has changed since it was loaded or last saved
+    pub fn is_dirty(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.lock_chunks().dirty.contains(&(chunk_x, chunk_z))
+    }
+
+    /// Writes every chunk changed since it was last saved to its region file and returns how many
+    /// were written. Blocking, so async callers should run it on the blocking thread pool. Does
+    /// nothing for a world without a region directory.
+    pub fn save_dirty_chunks(&self) -> io::Result<usize> {
+        let Some(region_dir) = &self.region_dir else {
+            return Ok(0);
+        };
+        // Grouped by region so each region file is opened once
+        let mut regions: BTreeMap<PathBuf, Vec<Arc<ChunkColumn>>> = BTreeMap::new();
+        {
+            let chunks = self.lock_chunks();
+            for position in &chunks.dirty {
+                if let Some(chunk) = chunks.loaded.get(position) {
+                    regions
+                        .entry(region_path(region_dir, *position))
+                        .or_default()
+                        .push(chunk.clone());
+                }
+            }
+        }
+
+        std::fs::create_dir_all(region_dir)?;
+        let mut saved = 0;
+        for (path, chunks) in regions {
+            let mut region = RegionFile::open(path)?;
+            for chunk in chunks {
+                region.write_chunk(&chunk)?;
+
+                let mut store = self.lock_chunks();
+                let position = (chunk.chunk_x, chunk.chunk_z);
+                // A block changed while saving if the stored column was copied in the meantime
+                if store
+                    .loaded
+                    .get(&position)
+                    .is_some_and(|loaded| Arc::ptr_eq(loaded, &chunk))
+                {
+                    store.dirty.remove(&position);
+                }
+                saved += 1;
+            }
+        }
+        Ok(saved)
+    }
+
+    /// Number of Chunk Data packets serialized by [`World::get_or_encode`] so far
+    pub fn chunk_serializations(&self) -> usize {
+        self.chunk_serializations.load(Ordering::Relaxed)
+    }
+
+    /// Returns the chunks at the given positions, in the same order. Missing chunks are loaded
+    /// from their region file, or generated if they were never saved, in parallel on the blocking
+    /// thread pool. Chunks that another caller is already generating
+    /// are waited for rather than generated a second time.
+    pub async fn get_or_generate(
+        &self,
+        positions: &[ChunkPos],
+    ) -> io::Result<Vec<Arc<ChunkColumn>>> {
+        loop {
+            // Created before checking the store so that no wakeup can be missed in between
+            let chunks_settled = self.chunks_settled.notified();
+
+            let claimed = {
+                let mut chunks = self.lock_chunks();
+                if let Some(found) = positions
+                    .iter()
+                    .map(|position| chunks.loaded.get(position).cloned())
+                    .collect::<Option<Vec<_>>>()
+                {
+                    return Ok(found);
+                }
+
+                let mut claimed = Vec::new();
+                for &position in positions {
+                    if !chunks.loaded.contains_key(&position) && chunks.in_flight.insert(position) {
+                        claimed.push(position);
+                    }
+                }
+                claimed
+            };
+
+            if claimed.is_empty() {
+                chunks_settled.await;
+            } else {
+                self.generate(claimed).await?;
+            }
+        }
+    }
+
+    /// Loads or generates chunks this caller claimed in the in-flight set and stores them
+    async fn generate(&self, claimed: Vec<ChunkPos>) -> io::Result<()> {
+        // Releases the claims even if generation fails or the caller stops waiting
+        let claim = InFlightClaim {
+            world: self,
+            positions: claimed,
+        };
+
+        let tasks: Vec<_> = claim
+            .positions
+            .iter()
+            .map(|&(chunk_x, chunk_z)| {
+                let generator = self.generator.clone();
+                let region_dir = self.region_dir.clone();
+                tokio::task::spawn_blocking(move || {
+                    let saved = match &region_dir {
+                        Some(dir) => load_chunk(dir, chunk_x, chunk_z)?,
+                        None => None,
+                    };
+                    Ok::<_, io::Error>(
+                        saved.unwrap_or_else(|| generator.generate_chunk(chunk_x, chunk_z)),
+                    )
+                })
+            })
+            .collect();
+
+        let mut generated = Vec::with_capacity(tasks.len());
+        for (&position, task) in claim.positions.iter().zip(tasks) {
+            let chunk = task.await.map_err(|join_error| {
+                io::Error::other(format!(
+                    "Failed to generate chunk {:?}: {}",
+                    position, join_error
+                ))
+            })??;
+            generated.push((position, Arc::new(chunk)));
+        }
+
+        let mut chunks = self.lock_chunks();
+        for (position, chunk) in generated {
+            chunks.loaded.insert(position, chunk);
+        }
+        Ok(())
+    }
+
+    fn lock_chunks(&self) -> std::sync::MutexGuard<'_, ChunkStore> {
+        // The store is only touched by short, non-panicking critical sections
+        self.chunks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Reads a chunk from its region file in `dir`, if it has been saved
+fn load_chunk(dir: &Path, chunk_x: i32, chunk_z: i32) -> io::Result<Option<ChunkColumn>> {
+    let path = region_path(dir, (chunk_x, chunk_z));
+    if !path.exists() {
+        return Ok(None);
+    }
+    RegionFile::open(path)?.get_chunk(chunk_x, chunk_z)
+}
+
+/// Positions a caller has claimed in the in-flight set, released when dropped
+struct InFlightClaim<'a> {
+    world: &'a World,
+    positions: Vec<ChunkPos>,
+}
+
+impl Drop for InFlightClaim<'_> {
+    fn drop(&mut self) {
+        {
+            let mut chunks = self.world.lock_chunks();
+            for position in &self.positions {
+                chunks.in_flight.remove(position);
+            }
+        }
+        self.world.chunks_settled.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::FlatGenerator;
+    use elytra_nbt::Tag;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Flat generator that counts how often each chunk is generated
+    #[derive(Default)]
+    struct CountingGenerator {
+        generated: Arc<Mutex<HashMap<ChunkPos, usize>>>,
+    }
+
+    impl WorldGenerator for CountingGenerator {
+        fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> ChunkColumn {
+            *self
+                .generated
+                .lock()
+                .unwrap()
+                .entry((chunk_x, chunk_z))
+                .or_default() += 1;
+            // Slow enough that concurrent requests overlap
+            thread::sleep(Duration::from_millis(2));
+            FlatGenerator::default().generate_chunk(chunk_x, chunk_z)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_requests_generate_each_chunk_once() {
+        let generator = CountingGenerator::default();
+        let generated = generator.generated.clone();
+        let world = Arc::new(World::new(generator));
+
+        let positions: Vec<ChunkPos> = (-5..5).flat_map(|x| (-5..5).map(move |z| (x, z))).collect();
+
+        // Every task asks for the whole grid, starting at a different chunk
+        let mut tasks = Vec::new();
+        for task_index in 0..8 {
+            let world = world.clone();
+            let mut positions = positions.clone();
+            positions.rotate_left(task_index * 12);
+            tasks.push(tokio::spawn(async move {
+                let chunks = world.get_or_generate(&positions).await.unwrap();
+                for (chunk, position) in chunks.iter().zip(&positions) {
+                    assert_eq!((chunk.chunk_x, chunk.chunk_z), *position);
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let generated = generated.lock().unwrap();
+        assert_eq!(generated.len(), positions.len());
+        assert!(generated.values().all(|&count| count == 1));
+        assert_eq!(world.loaded_chunk_count(), positions.len());
+        assert!(world.lock_chunks().in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_world_time() {
+        let world = World::new(FlatGenerator::default());
+        world.tick();
+        world.tick();
+        assert_eq!((world.world_age(), world.world_time()), (2, 2));
+
+        world.set_world_time(NIGHT);
+        assert_eq!(world.add_world_time(500), NIGHT + 500);
+        world.tick();
+        assert_eq!((world.world_age(), world.world_time()), (3, NIGHT + 501));
+    }
+
+    #[test]
+    fn test_weather() {
+        let world = World::new(FlatGenerator::default());
+        assert_eq!(world.weather(), Weather::Clear);
+        assert_eq!(world.set_weather(Weather::Thunder), Weather::Clear);
+        assert_eq!(
+            (world.rain_strength(), world.thunder_strength()),
+            (1.0, 1.0)
+        );
+        assert_eq!(world.set_weather(Weather::Rain), Weather::Thunder);
+        assert_eq!(
+            (world.rain_strength(), world.thunder_strength()),
+            (1.0, 0.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_or_generate_chunk_reuses_loaded_chunk() {
+        let world = World::new(FlatGenerator::default());
+        assert!(world.get_chunk(3, 4).is_none());
+
+        let chunk = world.get_or_generate_chunk(3, 4).await.unwrap();
+        assert_eq!(chunk.get_block_state(0, 3, 0), crate::blocks::GRASS_BLOCK);
+        let again = world.get_or_generate_chunk(3, 4).await.unwrap();
+        assert!(Arc::ptr_eq(&chunk, &again));
+    }
+
+    #[tokio::test]
+    async fn test_encoded_chunks_are_cached_until_changed() {
+        let world = World::new(FlatGenerator::default());
+        let first = world.get_or_encode(&[(0, 0), (1, 0)]).await.unwrap();
+        // A second player loading the same chunks reuses the serialized packets
+        let second = world.get_or_encode(&[(0, 0), (1, 0)]).await.unwrap();
+        assert_eq!(world.chunk_serializations(), 2);
+        assert!(Arc::ptr_eq(&first[0], &second[0]));
+        assert!(Arc::ptr_eq(&first[1], &second[1]));
+
+        world.set_block_state((3, 10, 3), 1).await.unwrap();
+        let changed = world.get_or_encode(&[(0, 0), (1, 0)]).await.unwrap();
+        assert_eq!(world.chunk_serializations(), 3);
+        assert_ne!(changed[0], first[0]);
+        assert!(Arc::ptr_eq(&changed[1], &first[1]));
+    }
+
+    #[tokio::test]
+    async fn test_placed_sign_has_block_entity() {
+        use crate::blocks::{OAK_SIGN, STONE};
+
+        let world = World::new(FlatGenerator::default());
+        world.set_block_state((-13, 4, 21), OAK_SIGN).await.unwrap();
+
+        let packet = world.get_chunk(-1, 1).unwrap().to_packet();
+        assert_eq!(packet.block_entities.len(), 1);
+        let block_entity = packet.block_entities[0].as_compound().unwrap();
+        assert_eq!(
+            block_entity.get("id").and_then(Tag::as_string).unwrap(),
+            "minecraft:sign"
+        );
+        let coordinate = |name: &str| block_entity.get(name).and_then(Tag::as_i32);
+        assert_eq!(
+            (coordinate("x"), coordinate("y"), coordinate("z")),
+            (Some(-13), Some(4), Some(21))
+        );
+
+        // Replacing the sign removes its block entity
+        world.set_block_state((-13, 4, 21), STONE).await.unwrap();
+        assert!(world.get_chunk(-1, 1).unwrap().block_entities.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dirty_chunks_are_saved_and_reloaded() {
+        let dir = std::env::temp_dir().join(format!("elytra-world-save-{}", std::process::id()));
+        let world = World::new(FlatGenerator::default()).with_region_dir(&dir);
+        world.get_or_generate(&[(0, 0), (40, -3)]).await.unwrap();
+        assert!(!world.is_dirty(40, -3));
+
+        world
+            .set_block_state((645, 20, -37), crate::blocks::STONE)
+            .await
+            .unwrap();
+        assert!(world.is_dirty(40, -3));
+        assert!(!world.is_dirty(0, 0));
+
+        // Only the changed chunk is written
+        assert_eq!(world.save_dirty_chunks().unwrap(), 1);
+        assert!(!world.is_dirty(40, -3));
+        assert_eq!(world.save_dirty_chunks().unwrap(), 0);
+
+        let reloaded = World::new(FlatGenerator::default()).with_region_dir(&dir);
+        assert_eq!(
+            reloaded.get_block_state((645, 20, -37)).await.unwrap(),
+            crate::blocks::STONE
+        );
+        // Chunks that were never saved are generated
+        assert_eq!(
+            reloaded.get_block_state((0, 3, 0)).await.unwrap(),
+            crate::blocks::GRASS_BLOCK
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_block_states() {
+        let world = World::new(FlatGenerator::default());
+        let before = world.get_or_generate_chunk(-1, 0).await.unwrap();
+
+        world
+            .set_block_states(&[
+                ((-1, 10, 2), crate::blocks::STONE),
+                ((16, 0, 16), AIR),
+                ((0, 256, 0), crate::blocks::STONE),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(
+            world.get_block_state((-1, 10, 2)).await.unwrap(),
+            crate::blocks::STONE
+        );
+        assert_eq!(world.get_block_state((16, 0, 16)).await.unwrap(), AIR);
+        assert_eq!(world.get_block_state((0, 256, 0)).await.unwrap(), AIR);
+
+        // A chunk handed out earlier keeps the blocks it had
+        assert_eq!(before.get_block_state(15, 10, 2), AIR);
+        assert_eq!(world.loaded_chunk_count(), 3);
+    }
+}